@@ -1,6 +1,10 @@
 import {
+    AdmissionPolicy,
+    BufferConfiguration,
     Ciphersuite,
     ConversationConfiguration as ConversationConfigurationFfi,
+    CustomCapabilities,
+    ExporterSecretHistoryConfiguration,
     ExternalSenderKey,
     WirePolicy,
 } from "./autogenerated/core-crypto-ffi.js";
@@ -24,6 +28,26 @@ export interface ConversationConfiguration {
      * Note: encrypted handshake messages are not supported by wire-server
      */
     wirePolicy?: WirePolicy;
+    /**
+     * Which credentials can join the conversation once it is verified.
+     * Everything is admitted by default.
+     */
+    admissionPolicy?: AdmissionPolicy;
+    /**
+     * Which exporter secrets are kept once the conversation moved past the epoch they were exported in.
+     * None are kept by default.
+     */
+    exporterSecretHistory?: ExporterSecretHistoryConfiguration;
+    /**
+     * Limits of the messages and commits buffered while waiting for the commit of a future epoch.
+     * By default, 100 messages, 4 MiB and 7 days. The oldest items beyond them are evicted.
+     */
+    bufferConfiguration?: BufferConfiguration;
+    /**
+     * Application-defined extension and proposal types every member has to support. None by default.
+     * Members, the creator included, have to register them in the `customCapabilities` of their client.
+     */
+    requiredCapabilities?: CustomCapabilities;
 }
 
 export function conversationConfigurationToFfi(
@@ -33,6 +57,10 @@ export function conversationConfigurationToFfi(
         cc.ciphersuite,
         cc.externalSenders,
         cc.keyRotationSpan,
-        cc.wirePolicy
+        cc.wirePolicy,
+        cc.admissionPolicy,
+        cc.exporterSecretHistory,
+        cc.bufferConfiguration,
+        cc.requiredCapabilities
     );
 }
@@ -52,7 +52,12 @@ export {
 export { CoreCryptoContext } from "./CoreCryptoContext";
 
 export {
+    BufferedItem,
+    BufferedItemKind,
     BuildMetadata,
+    EvictionReason,
+    MemberCredentialUpdate,
+    MembershipChanges,
     WireIdentity,
     X509Identity,
     setLogger,
@@ -63,17 +68,22 @@ export {
     CoreCrypto,
 } from "./CoreCryptoInstance";
 export type {
+    BufferObserver,
     CoreCryptoDeferredParams,
     CoreCryptoParams,
     CoreCryptoLogger,
+    E2eiStatusObserver,
     EpochObserver,
     HistoryObserver,
+    MembershipObserver,
+    ReInitObserver,
 } from "./CoreCryptoInstance";
 
 export {
     CredentialType,
     WirePolicy,
     GroupInfoEncryptionType,
+    KeyPackageExpiry,
     RatchetTreeType,
     DeviceStatus,
     WelcomeBundle,
@@ -87,6 +97,7 @@ export type {
     CommitBundle,
     DecryptedMessage,
     HistorySecret,
+    KeyPackageInventoryEntry,
 } from "./CoreCryptoMLS";
 
 export { E2eiEnrollment, E2eiConversationState } from "./CoreCryptoE2EI";
@@ -101,11 +112,17 @@ export type { ProteusAutoPrekeyBundle } from "./CoreCryptoProteus";
 
 export {
     AcmeChallenge,
+    AdmissionAction,
+    AdmissionPolicy,
+    BufferConfiguration,
     Ciphersuite,
     ciphersuiteFromU16,
     ciphersuiteDefault,
     ClientId,
+    CustomCapabilities,
     CustomConfiguration,
+    CustomExtension,
+    ExporterSecretHistoryConfiguration,
     openDatabase,
     Database,
     DatabaseKey,
@@ -3,11 +3,13 @@ import {
     ClientId,
     CoreCryptoContext as CoreCryptoContextFfi,
     CustomConfiguration,
+    CustomExtension,
     WireIdentity,
     ConversationId,
     KeyPackage,
     Welcome,
     SecretKey,
+    WirePolicy,
 } from "./autogenerated/core-crypto-ffi";
 import * as CoreCryptoFfiTypes from "./autogenerated/core-crypto-ffi.d";
 
@@ -16,6 +18,8 @@ import {
     CredentialType,
     type DecryptedMessage,
     decryptedMessageFromFfi,
+    type KeyPackageInventoryEntry,
+    keyPackageInventoryEntryFromFfi,
     WelcomeBundle,
 } from "./CoreCryptoMLS";
 
@@ -156,6 +160,33 @@ export class CoreCryptoContext {
         return cs;
     }
 
+    /**
+     * Returns the effective wire policy of a conversation, i.e. which of its handshake messages are encrypted
+     *
+     * @returns the wire policy of the conversation
+     */
+    async conversationWirePolicy(
+        conversationId: ConversationId
+    ): Promise<WirePolicy> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.conversation_wire_policy(conversationId)
+        );
+    }
+
+    /**
+     * Returns the application-defined extensions currently in the group context of a conversation
+     *
+     * @param conversationId - The ID of the conversation
+     * @returns the custom extensions of the conversation
+     */
+    async conversationCustomExtensions(
+        conversationId: ConversationId
+    ): Promise<CustomExtension[]> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.conversation_custom_extensions(conversationId)
+        );
+    }
+
     /**
      * Wipes and destroys the local storage of a given conversation / MLS group
      *
@@ -252,8 +283,22 @@ export class CoreCryptoContext {
         welcomeMessage: Welcome,
         configuration: Partial<CustomConfiguration> = {}
     ): Promise<WelcomeBundle> {
-        const { keyRotationSpan, wirePolicy } = configuration || {};
-        const config = new CustomConfiguration(keyRotationSpan, wirePolicy);
+        const {
+            keyRotationSpan,
+            wirePolicy,
+            admissionPolicy,
+            exporterSecretHistory,
+            bufferConfiguration,
+            requiredCapabilities,
+        } = configuration || {};
+        const config = new CustomConfiguration(
+            keyRotationSpan,
+            wirePolicy,
+            admissionPolicy,
+            exporterSecretHistory,
+            bufferConfiguration,
+            requiredCapabilities
+        );
         return await CoreCryptoError.asyncMapErr(
             this.#ctx.process_welcome_message(welcomeMessage, config)
         );
@@ -317,6 +362,93 @@ export class CoreCryptoContext {
         return kps.map((kp) => kp.copyBytes());
     }
 
+    /**
+     * Sets the lifetime of the KeyPackages generated from now on. X509 KeyPackages never outlive their certificate.
+     *
+     * @param ciphersuite - of the KeyPackages
+     * @param credentialType - of the KeyPackages
+     * @param lifetimeSecs - lifetime of the KeyPackages, in seconds
+     */
+    async setKeyPackageLifetime(
+        ciphersuite: Ciphersuite,
+        credentialType: CredentialType,
+        lifetimeSecs: number
+    ): Promise<void> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.set_key_package_lifetime(
+                ciphersuite,
+                credentialType,
+                BigInt(lifetimeSecs)
+            )
+        );
+    }
+
+    /**
+     * Counts the KeyPackages in store per ciphersuite, credential type and expiry.
+     *
+     * @param expiringWithinSecs - KeyPackages expiring within this many seconds are counted apart, so that they can be replaced before they expire
+     * @returns The count of KeyPackages for each ciphersuite, credential type and expiry
+     */
+    async keyPackageInventory(
+        expiringWithinSecs: number
+    ): Promise<KeyPackageInventoryEntry[]> {
+        const inventory = await CoreCryptoError.asyncMapErr(
+            this.#ctx.key_package_inventory(BigInt(expiringWithinSecs))
+        );
+        return inventory.map(keyPackageInventoryEntryFromFfi);
+    }
+
+    /**
+     * Marks these KeyPackages as uploaded to the backend, see {@link keyPackageInventory}
+     *
+     * @param keyPackages - TLS-serialized KeyPackages which were uploaded
+     */
+    async markKeyPackagesUploaded(keyPackages: Uint8Array[]): Promise<void> {
+        const kps = keyPackages.map((bytes) => new KeyPackage(bytes));
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.mark_key_packages_uploaded(kps)
+        );
+    }
+
+    /**
+     * Returns the last resort KeyPackage, generating it if there is none.
+     * Unlike other KeyPackages, it is kept once used by a Welcome, so that the backend can hand it out once it ran out of KeyPackages.
+     *
+     * @param ciphersuite - of the KeyPackage
+     * @param credentialType - of the KeyPackage
+     * @returns The TLS-serialized last resort KeyPackage
+     */
+    async lastResortKeyPackage(
+        ciphersuite: Ciphersuite,
+        credentialType: CredentialType
+    ): Promise<Uint8Array> {
+        const kp = await CoreCryptoError.asyncMapErr(
+            this.#ctx.last_resort_key_package(ciphersuite, credentialType)
+        );
+        return kp.copyBytes();
+    }
+
+    /**
+     * Replaces the last resort KeyPackage with a new one, see {@link lastResortKeyPackage}.
+     * Welcomes sent to the previous one cannot be processed anymore.
+     *
+     * @param ciphersuite - of the KeyPackage
+     * @param credentialType - of the KeyPackage
+     * @returns The TLS-serialized new last resort KeyPackage
+     */
+    async rotateLastResortKeyPackage(
+        ciphersuite: Ciphersuite,
+        credentialType: CredentialType
+    ): Promise<Uint8Array> {
+        const kp = await CoreCryptoError.asyncMapErr(
+            this.#ctx.rotate_last_resort_key_package(
+                ciphersuite,
+                credentialType
+            )
+        );
+        return kp.copyBytes();
+    }
+
     /**
      * Adds new clients to a conversation, assuming the current client has the right to add new clients to the conversation.
      *
@@ -383,6 +515,25 @@ export class CoreCryptoContext {
         );
     }
 
+    /**
+     * Replaces the application-defined extensions of a conversation, keeping all its other group
+     * context extensions as they are. Every member has to support their types.
+     *
+     * Sends the corresponding commit via {@link MlsTransport.sendCommitBundle}
+     * and merges it if the call is successful.
+     *
+     * @param conversationId - The ID of the conversation
+     * @param customExtensions - The new custom extensions of the conversation
+     */
+    async updateCustomExtensions(
+        conversationId: ConversationId,
+        customExtensions: CustomExtension[]
+    ): Promise<void> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.update_custom_extensions(conversationId, customExtensions)
+        );
+    }
+
     /**
      * "Apply" to join a group through its GroupInfo.
      *
@@ -402,8 +553,22 @@ export class CoreCryptoContext {
         credentialType: CredentialType,
         configuration: Partial<CustomConfiguration> = {}
     ): Promise<WelcomeBundle> {
-        const { keyRotationSpan, wirePolicy } = configuration || {};
-        const config = new CustomConfiguration(keyRotationSpan, wirePolicy);
+        const {
+            keyRotationSpan,
+            wirePolicy,
+            admissionPolicy,
+            exporterSecretHistory,
+            bufferConfiguration,
+            requiredCapabilities,
+        } = configuration || {};
+        const config = new CustomConfiguration(
+            keyRotationSpan,
+            wirePolicy,
+            admissionPolicy,
+            exporterSecretHistory,
+            bufferConfiguration,
+            requiredCapabilities
+        );
         return await CoreCryptoError.asyncMapErr(
             this.#ctx.join_by_external_commit(groupInfo, config, credentialType)
         );
@@ -445,6 +610,34 @@ export class CoreCryptoContext {
         );
     }
 
+    /**
+     * Derives a new key from the group, domain-separated by a label and a context
+     *
+     * @param conversationId - The group's ID
+     * @param label - identifies what the key is used for. Must not be empty nor start with the
+     * reserved `core-crypto/` prefix
+     * @param context - arbitrary bytes bound to the derived key
+     * @param keyLength - the length of the key to be derived. If the value is higher than the
+     * bounds of `u16` or the context hash * 255, an error will be returned
+     *
+     * @returns A `Uint8Array` representing the derived key
+     */
+    async exportSecretKeyWithLabel(
+        conversationId: ConversationId,
+        label: string,
+        context: Uint8Array,
+        keyLength: number
+    ): Promise<SecretKey> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.export_secret_key_with_label(
+                conversationId,
+                label,
+                context,
+                keyLength
+            )
+        );
+    }
+
     /**
      * Returns the raw public key of the single external sender present in this group.
      * This should be used to initialize a subconversation
@@ -878,6 +1071,19 @@ export class CoreCryptoContext {
         return crlRegistrationFromFfi(reg);
     }
 
+    /**
+     * Re-evaluates the end-to-end identity status of every conversation as if it were `at`, i.e. considering the
+     * certificates expiring before it as expired. Changes since the last evaluation are reported to the
+     * E2EI status observer; does nothing when no observer is registered.
+     *
+     * @param at - seconds since the UNIX epoch
+     */
+    async e2eiReevaluateStatus(at: number): Promise<void> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#ctx.e2ei_reevaluate_status(BigInt(at))
+        );
+    }
+
     /**
      * Creates an update commit which replaces your leaf containing basic credentials with a leaf node containing x509 credentials in the conversation.
      *
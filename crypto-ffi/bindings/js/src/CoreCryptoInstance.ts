@@ -16,26 +16,41 @@
 
 import * as CoreCryptoFfiTypes from "./autogenerated/core-crypto-ffi.d";
 export {
+    BufferedItem,
+    BufferedItemKind,
     BuildMetadata,
+    EvictionReason,
+    MemberCredentialUpdate,
+    MembershipChanges,
     WireIdentity,
     X509Identity,
 } from "./autogenerated/core-crypto-ffi.d";
 
 import {
+    BufferedItem,
+    BufferObserver as BufferObserverFfi,
     build_metadata,
     Ciphersuite,
     ClientId,
     CoreCryptoFfi,
     CoreCryptoLogger as CoreCryptoLoggerFfi,
+    DeviceStatus,
+    E2eiStatusObserver as E2eiStatusObserverFfi,
+    EvictionReason,
     EpochObserver as EpochObserverFfi,
     HistoryObserver as HistoryObserverFfi,
     HistorySecret as HistorySecretFfi,
+    MembershipChanges,
+    MembershipObserver as MembershipObserverFfi,
+    ReInitObserver as ReInitObserverFfi,
     version as version_ffi,
     WireIdentity,
     DatabaseKey,
     ConversationId,
+    CustomCapabilities,
     set_logger,
     set_max_log_level,
+    WirePolicy,
 } from "./autogenerated/core-crypto-ffi";
 
 import { CoreCryptoError, ErrorType } from "./CoreCryptoError";
@@ -47,6 +62,7 @@ import {
 } from "./CoreCryptoMLS";
 
 import { CoreCryptoContext } from "./CoreCryptoContext";
+import { E2eiConversationState, normalizeEnum } from "./CoreCryptoE2EI";
 
 import { safeBigintToNumber } from "./Conversions";
 
@@ -69,6 +85,10 @@ export interface CoreCryptoDeferredParams {
      * This **must** be exactly 32 bytes
      */
     entropySeed?: Uint8Array;
+    /**
+     * Application-defined extension and proposal types advertised by the key packages and leaf nodes of this client
+     */
+    customCapabilities?: CustomCapabilities;
 }
 
 /**
@@ -115,11 +135,138 @@ class EpochObserverShim {
     }
 }
 
+export interface MembershipObserver {
+    membershipChanged(
+        conversationId: ConversationId,
+        changes: MembershipChanges
+    ): Promise<void>;
+}
+
+class MembershipObserverShim {
+    private inner: MembershipObserver;
+
+    constructor(inner: MembershipObserver) {
+        this.inner = inner;
+    }
+
+    // what Rust sends us
+    async membershipChanged(
+        conversationId: ConversationId,
+        changes: MembershipChanges
+    ): Promise<void> {
+        // JS-ism: we launch a new task by simply not awaiting; no explicit "spawn"
+        return this.inner.membershipChanged(conversationId, changes);
+    }
+}
+
+export interface BufferObserver {
+    bufferedItemEvicted(
+        conversationId: ConversationId,
+        item: BufferedItem,
+        reason: EvictionReason
+    ): Promise<void>;
+}
+
+class BufferObserverShim {
+    private inner: BufferObserver;
+
+    constructor(inner: BufferObserver) {
+        this.inner = inner;
+    }
+
+    // what Rust sends us
+    async bufferedItemEvicted(
+        conversationId: ConversationId,
+        item: BufferedItem,
+        reason: EvictionReason
+    ): Promise<void> {
+        // JS-ism: we launch a new task by simply not awaiting; no explicit "spawn"
+        return this.inner.bufferedItemEvicted(conversationId, item, reason);
+    }
+}
+
+export interface ReInitObserver {
+    conversationReinitialized(
+        oldConversationId: ConversationId,
+        newConversationId: ConversationId
+    ): Promise<void>;
+}
+
+class ReInitObserverShim {
+    private inner: ReInitObserver;
+
+    constructor(inner: ReInitObserver) {
+        this.inner = inner;
+    }
+
+    // what Rust sends us
+    async conversationReinitialized(
+        oldConversationId: ConversationId,
+        newConversationId: ConversationId
+    ): Promise<void> {
+        // JS-ism: we launch a new task by simply not awaiting; no explicit "spawn"
+        return this.inner.conversationReinitialized(
+            oldConversationId,
+            newConversationId
+        );
+    }
+}
+
+export interface E2eiStatusObserver {
+    deviceStatusChanged(
+        conversationId: ConversationId,
+        clientId: ClientId,
+        status: DeviceStatus
+    ): Promise<void>;
+    conversationStateChanged(
+        conversationId: ConversationId,
+        state: E2eiConversationState
+    ): Promise<void>;
+}
+
+class E2eiStatusObserverShim {
+    private inner: E2eiStatusObserver;
+
+    constructor(inner: E2eiStatusObserver) {
+        this.inner = inner;
+    }
+
+    // what Rust sends us
+    async deviceStatusChanged(
+        conversationId: ConversationId,
+        clientId: ClientId,
+        status: DeviceStatus
+    ): Promise<void> {
+        // JS-ism: we launch a new task by simply not awaiting; no explicit "spawn"
+        return this.inner.deviceStatusChanged(conversationId, clientId, status);
+    }
+
+    async conversationStateChanged(
+        conversationId: ConversationId,
+        state: number
+    ): Promise<void> {
+        return this.inner.conversationStateChanged(
+            conversationId,
+            normalizeEnum(E2eiConversationState, state)
+        );
+    }
+}
+
 export interface HistoryObserver {
     historyClientCreated(
         conversationId: ConversationId,
         secret: HistorySecret
     ): Promise<void>;
+    historyClientRemoved?(
+        conversationId: ConversationId,
+        clientId: ClientId
+    ): Promise<void>;
+    historyClientReplaced?(
+        conversationId: ConversationId,
+        previous: ClientId,
+        replacement: ClientId
+    ): Promise<void>;
+    historySharingDisabled?(conversationId: ConversationId): Promise<void>;
 }
 
 class HistoryObserverShim {
@@ -137,6 +284,31 @@ class HistoryObserverShim {
         // JS-ism: we launch a new task by simply not awaiting; no explicit "spawn"
         return this.inner.historyClientCreated(conversationId, secret);
     }
+
+    async historyClientRemoved(
+        conversationId: ConversationId,
+        clientId: ClientId
+    ): Promise<void> {
+        return this.inner.historyClientRemoved?.(conversationId, clientId);
+    }
+
+    async historyClientReplaced(
+        conversationId: ConversationId,
+        previous: ClientId,
+        replacement: ClientId
+    ): Promise<void> {
+        return this.inner.historyClientReplaced?.(
+            conversationId,
+            previous,
+            replacement
+        );
+    }
+
+    async historySharingDisabled(
+        conversationId: ConversationId
+    ): Promise<void> {
+        return this.inner.historySharingDisabled?.(conversationId);
+    }
 }
 
 function historySecretIntoFfi(secret: HistorySecret): HistorySecretFfi {
@@ -263,6 +435,7 @@ export class CoreCrypto {
         ciphersuites,
         entropySeed,
         nbKeyPackage,
+        customCapabilities,
     }: CoreCryptoParams): Promise<CoreCrypto> {
         return new this(
             await CoreCryptoError.asyncMapErr(
@@ -272,7 +445,8 @@ export class CoreCrypto {
                     clientId,
                     ciphersuites,
                     entropySeed,
-                    nbKeyPackage
+                    nbKeyPackage,
+                    customCapabilities
                 )
             )
         );
@@ -289,9 +463,15 @@ export class CoreCrypto {
         databaseName,
         key,
         entropySeed,
+        customCapabilities,
     }: CoreCryptoDeferredParams): Promise<CoreCrypto> {
         const cc = await CoreCryptoError.asyncMapErr(
-            CoreCryptoFfi.deferred_init(databaseName, key, entropySeed)
+            CoreCryptoFfi.deferred_init(
+                databaseName,
+                key,
+                entropySeed,
+                customCapabilities
+            )
         );
         return new this(cc);
     }
@@ -430,6 +610,19 @@ export class CoreCrypto {
         return cs;
     }
 
+    /**
+     * See {@link CoreCryptoContext.conversationWirePolicy}.
+     *
+     * @returns the wire policy of the conversation
+     */
+    async conversationWirePolicy(
+        conversationId: ConversationId
+    ): Promise<WirePolicy> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.conversation_wire_policy(conversationId)
+        );
+    }
+
     /**
      * See {@link CoreCryptoContext.clientPublicKey}.
      *
@@ -464,6 +657,32 @@ export class CoreCrypto {
         );
     }
 
+    /**
+     * See {@link CoreCryptoContext.exportSecretKeyWithLabel}.
+     *
+     * @param conversationId - The group's ID
+     * @param label - identifies what the key is used for
+     * @param context - arbitrary bytes bound to the derived key
+     * @param keyLength - the length of the key to be derived
+     *
+     * @returns A `Uint8Array` representing the derived key
+     */
+    async exportSecretKeyWithLabel(
+        conversationId: ConversationId,
+        label: string,
+        context: Uint8Array,
+        keyLength: number
+    ): Promise<Uint8Array> {
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.export_secret_key_with_label(
+                conversationId,
+                label,
+                context,
+                keyLength
+            )
+        );
+    }
+
     /**
      * Check if history sharing is enabled, i.e., if any of the conversation members have a {@link ClientId} starting
      * with the history client id prefix.
@@ -686,7 +905,86 @@ export class CoreCrypto {
     }
 
     /**
-     * Registers a history observer, which will then be notified every time a history client is created.
+     * Registers a membership observer, which will then be notified every time a merged commit adds or removes
+     * clients of a conversation, or replaces the credential of a member.
+     *
+     * @param membershipObserver must conform to the {@link MembershipObserver} interface
+     * @returns nothing
+     */
+    async registerMembershipObserver(
+        membershipObserver: MembershipObserver
+    ): Promise<void> {
+        const shim = new MembershipObserverShim(membershipObserver);
+        const ffi = new MembershipObserverFfi(shim, shim.membershipChanged);
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.register_membership_observer(ffi)
+        );
+    }
+
+    /**
+     * Registers a buffer observer, which will then be notified every time a message or commit buffered
+     * for a future epoch is evicted.
+     *
+     * @param bufferObserver must conform to the {@link BufferObserver} interface
+     * @returns nothing
+     */
+    async registerBufferObserver(
+        bufferObserver: BufferObserver
+    ): Promise<void> {
+        const shim = new BufferObserverShim(bufferObserver);
+        const ffi = new BufferObserverFfi(shim, shim.bufferedItemEvicted);
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.register_buffer_observer(ffi)
+        );
+    }
+
+    /**
+     * Registers a reinit observer, which will then be notified every time we become a member of the
+     * conversation replacing a reinitialized one.
+     *
+     * @param reinitObserver must conform to the {@link ReInitObserver} interface
+     * @returns nothing
+     */
+    async registerReInitObserver(
+        reinitObserver: ReInitObserver
+    ): Promise<void> {
+        const shim = new ReInitObserverShim(reinitObserver);
+        const ffi = new ReInitObserverFfi(
+            shim,
+            shim.conversationReinitialized
+        );
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.register_reinit_observer(ffi)
+        );
+    }
+
+    /**
+     * Registers an E2EI status observer, which will then be notified every time the certificate of a valid member
+     * of a conversation expires or is revoked, and every time the end-to-end identity state of a conversation changes.
+     *
+     * Statuses are re-evaluated when a commit is merged, when a CRL is registered and when
+     * {@link CoreCryptoContext.e2eiReevaluateStatus} is called.
+     *
+     * @param e2eiStatusObserver must conform to the {@link E2eiStatusObserver} interface
+     * @returns nothing
+     */
+    async registerE2eiStatusObserver(
+        e2eiStatusObserver: E2eiStatusObserver
+    ): Promise<void> {
+        const shim = new E2eiStatusObserverShim(e2eiStatusObserver);
+        const ffi = new E2eiStatusObserverFfi(
+            shim,
+            shim.deviceStatusChanged,
+            shim.conversationStateChanged
+        );
+        return await CoreCryptoError.asyncMapErr(
+            this.#cc.register_e2ei_status_observer(ffi)
+        );
+    }
+
+    /**
+     * Registers a history observer, which will then be notified every time a history client is created,
+     * removed or replaced, and every time history sharing is disabled.
      *
      * @param historyObserver must conform to the {@link HistoryObserver} interface
      * @returns nothing
@@ -695,7 +993,13 @@ export class CoreCrypto {
         historyObserver: HistoryObserver
     ): Promise<void> {
         const shim = new HistoryObserverShim(historyObserver);
-        const ffi = new HistoryObserverFfi(shim, shim.historyClientCreated);
+        const ffi = new HistoryObserverFfi(
+            shim,
+            shim.historyClientCreated,
+            shim.historyClientRemoved,
+            shim.historyClientReplaced,
+            shim.historySharingDisabled
+        );
         return await CoreCryptoError.asyncMapErr(
             this.#cc.register_history_observer(ffi)
         );
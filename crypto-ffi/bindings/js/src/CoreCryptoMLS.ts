@@ -1,10 +1,14 @@
 import { safeBigintToNumber } from "./Conversions";
 import {
     BufferedDecryptedMessage as BufferedDecryptedMessageFfi,
+    Ciphersuite,
     CommitBundle as CommitBundleFfi,
     CredentialType,
+    CustomExtension,
     DecryptedMessage as DecryptedMessageFfi,
     DeviceStatus,
+    KeyPackageExpiry,
+    KeyPackageInventoryEntry as KeyPackageInventoryEntryFfi,
     MlsGroupInfoEncryptionType as GroupInfoEncryptionType,
     MlsRatchetTreeType as RatchetTreeType,
     MlsTransport as MlsTransportFfi,
@@ -23,11 +27,60 @@ export {
     CredentialType,
     DeviceStatus,
     GroupInfoEncryptionType,
+    KeyPackageExpiry,
     RatchetTreeType,
     WelcomeBundle,
     WirePolicy,
 };
 
+/**
+ * How many KeyPackages of a ciphersuite and credential type are in the keystore
+ */
+export interface KeyPackageInventoryEntry {
+    /**
+     * Ciphersuite of the KeyPackages
+     *
+     * @readonly
+     */
+    ciphersuite: Ciphersuite;
+    /**
+     * Credential type of the KeyPackages
+     *
+     * @readonly
+     */
+    credentialType: CredentialType;
+    /**
+     * How close to their expiry the KeyPackages are
+     *
+     * @readonly
+     */
+    expiry: KeyPackageExpiry;
+    /**
+     * Number of KeyPackages
+     *
+     * @readonly
+     */
+    count: number;
+    /**
+     * Number of KeyPackages marked as uploaded
+     *
+     * @readonly
+     */
+    uploaded: number;
+}
+
+export function keyPackageInventoryEntryFromFfi(
+    entry: KeyPackageInventoryEntryFfi
+): KeyPackageInventoryEntry {
+    return {
+        ciphersuite: entry.ciphersuite,
+        credentialType: entry.credentialType,
+        expiry: entry.expiry,
+        count: safeBigintToNumber(entry.count),
+        uploaded: safeBigintToNumber(entry.uploaded),
+    };
+}
+
 /**
  * Alias for proposal reference. It is a byte array of size 16.
  */
@@ -144,6 +197,11 @@ export interface DecryptedMessage {
      * New CRL distribution points that appeared by the introduction of a new credential
      */
     crlNewDistributionPoints?: string[];
+    /**
+     * Only set when the decrypted message is a commit replacing the group context extensions.
+     * Contains the application-defined extensions of the new group context.
+     */
+    customExtensions?: CustomExtension[];
 }
 
 export function decryptedMessageFromFfi(
@@ -190,6 +248,10 @@ export interface BufferedDecryptedMessage {
      * see {@link DecryptedMessage.crlNewDistributionPoints}
      */
     crlNewDistributionPoints?: string[];
+    /**
+     * see {@link DecryptedMessage.customExtensions}
+     */
+    customExtensions?: CustomExtension[];
 }
 
 export function bufferedDecryptedMessageFromFfi(
@@ -205,6 +267,7 @@ export function bufferedDecryptedMessageFromFfi(
         hasEpochChanged: m.hasEpochChanged,
         identity: m.identity,
         crlNewDistributionPoints: m.crlNewDistributionPoints,
+        customExtensions: m.customExtensions,
     };
 }
 
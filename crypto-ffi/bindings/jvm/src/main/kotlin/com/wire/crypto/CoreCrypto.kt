@@ -18,12 +18,17 @@ fun CoreCryptoFfi.lift() = CoreCrypto(this)
  */
 class CoreCrypto(private val cc: CoreCryptoFfi) {
     companion object {
-        /** Opens an existing core crypto client or creates a new one if one doesn't exist at the `keystore` path */
+        /**
+         * Opens an existing core crypto client or creates a new one if one doesn't exist at the `keystore` path
+         *
+         * @param customCapabilities application-defined extension and proposal types advertised by this client
+         */
         suspend operator fun invoke(
             keystore: String,
-            databaseKey: DatabaseKey
+            databaseKey: DatabaseKey,
+            customCapabilities: CustomCapabilities? = null
         ) =
-            CoreCrypto(coreCryptoDeferredInit(keystore, databaseKey, null))
+            CoreCrypto(coreCryptoDeferredInit(keystore, databaseKey, null, customCapabilities))
 
         /**
          * Instantiate a history client.
@@ -101,7 +106,94 @@ class CoreCrypto(private val cc: CoreCryptoFfi) {
     }
 
     /**
-     * Register a History Observer which will be notified every time a new history client is created.
+     * Register a Membership Observer which will be notified every time a merged commit adds or removes clients of a
+     * conversation, or replaces the credential of a member.
+     *
+     * This function should be called 0 or 1 times in the lifetime of CoreCrypto, regardless of the number of transactions.
+     */
+    suspend fun registerMembershipObserver(scope: CoroutineScope, membershipObserver: MembershipObserver) {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        val observerIndirector = object : MembershipObserver {
+            override suspend fun membershipChanged(conversationId: ConversationId, changes: MembershipChanges) {
+                scope.launch { membershipObserver.membershipChanged(conversationId, changes) }
+            }
+        }
+        return cc.registerMembershipObserver(observerIndirector)
+    }
+
+    /**
+     * Register a Buffer Observer which will be notified every time a message or commit buffered for a future epoch
+     * is evicted.
+     *
+     * This function should be called 0 or 1 times in the lifetime of CoreCrypto, regardless of the number of transactions.
+     */
+    suspend fun registerBufferObserver(scope: CoroutineScope, bufferObserver: BufferObserver) {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        val observerIndirector = object : BufferObserver {
+            override suspend fun bufferedItemEvicted(
+                conversationId: ConversationId,
+                item: BufferedItem,
+                reason: EvictionReason
+            ) {
+                scope.launch { bufferObserver.bufferedItemEvicted(conversationId, item, reason) }
+            }
+        }
+        return cc.registerBufferObserver(observerIndirector)
+    }
+
+    /**
+     * Register a ReInit Observer which will be notified every time we become a member of the conversation replacing
+     * a reinitialized one.
+     *
+     * This function should be called 0 or 1 times in the lifetime of CoreCrypto, regardless of the number of transactions.
+     */
+    suspend fun registerReInitObserver(scope: CoroutineScope, reinitObserver: ReInitObserver) {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        val observerIndirector = object : ReInitObserver {
+            override suspend fun conversationReinitialized(
+                oldConversationId: ConversationId,
+                newConversationId: ConversationId
+            ) {
+                scope.launch { reinitObserver.conversationReinitialized(oldConversationId, newConversationId) }
+            }
+        }
+        return cc.registerReinitObserver(observerIndirector)
+    }
+
+    /**
+     * Register an E2EI Status Observer which will be notified every time the certificate of a valid member of a
+     * conversation expires or is revoked, and every time the end-to-end identity state of a conversation changes.
+     *
+     * This function should be called 0 or 1 times in the lifetime of CoreCrypto, regardless of the number of transactions.
+     */
+    suspend fun registerE2eiStatusObserver(scope: CoroutineScope, e2eiStatusObserver: E2eiStatusObserver) {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        val observerIndirector = object : E2eiStatusObserver {
+            override suspend fun deviceStatusChanged(
+                conversationId: ConversationId,
+                clientId: ClientId,
+                status: DeviceStatus
+            ) {
+                scope.launch { e2eiStatusObserver.deviceStatusChanged(conversationId, clientId, status) }
+            }
+
+            override suspend fun conversationStateChanged(
+                conversationId: ConversationId,
+                state: E2eiConversationState
+            ) {
+                scope.launch { e2eiStatusObserver.conversationStateChanged(conversationId, state) }
+            }
+        }
+        return cc.registerE2eiStatusObserver(observerIndirector)
+    }
+
+    /**
+     * Register a History Observer which will be notified every time a history client is created, removed or replaced,
+     * and every time history sharing is disabled.
      *
      * This function should be called 0 or 1 times in the lifetime of CoreCrypto, regardless of the number of transactions.
      */
@@ -115,6 +207,22 @@ class CoreCrypto(private val cc: CoreCryptoFfi) {
             ) {
                 scope.launch { historyObserver.historyClientCreated(conversationId, secret) }
             }
+
+            override suspend fun historyClientRemoved(conversationId: ConversationId, clientId: ClientId) {
+                scope.launch { historyObserver.historyClientRemoved(conversationId, clientId) }
+            }
+
+            override suspend fun historyClientReplaced(
+                conversationId: ConversationId,
+                previous: ClientId,
+                replacement: ClientId
+            ) {
+                scope.launch { historyObserver.historyClientReplaced(conversationId, previous, replacement) }
+            }
+
+            override suspend fun historySharingDisabled(conversationId: ConversationId) {
+                scope.launch { historyObserver.historySharingDisabled(conversationId) }
+            }
         }
         return cc.registerHistoryObserver(observerIndirector)
     }
@@ -164,6 +164,47 @@ class MLSTest : HasMockDeliveryService() {
         ).isEqualTo(200.toULong())
     }
 
+    @Test
+    fun keyPackageInventory_should_count_the_key_packages_marked_as_uploaded() = runTest {
+        val (alice) = newClients(this@MLSTest, genClientId())
+        val kps = alice.transaction { ctx -> ctx.clientKeypackagesShort(10U) }
+        alice.transaction { ctx -> ctx.markKeyPackagesUploaded(kps.take(3)) }
+
+        val inventory = alice.transaction { ctx -> ctx.keyPackageInventory(0UL) }
+        assertThat(inventory).hasSize(1)
+        assertThat(inventory.first().expiry).isEqualTo(KeyPackageExpiry.VALID)
+        assertThat(inventory.first().count).isEqualTo(10UL)
+        assertThat(inventory.first().uploaded).isEqualTo(3UL)
+    }
+
+    @Test
+    fun setKeyPackageLifetime_should_apply_to_new_key_packages() = runTest {
+        val (alice) = newClients(this@MLSTest, genClientId())
+        alice.transaction { ctx -> ctx.setKeyPackageLifetime(CIPHERSUITE_DEFAULT, CREDENTIAL_TYPE_DEFAULT, 60UL) }
+        alice.transaction { ctx -> ctx.clientKeypackagesShort(5U) }
+
+        val inventory = alice.transaction { ctx -> ctx.keyPackageInventory(3600UL) }
+        val expiringSoon = inventory.filter { it.expiry == KeyPackageExpiry.EXPIRING_SOON }.sumOf { it.count }
+        assertThat(expiringSoon).isEqualTo(4UL)
+    }
+
+    @Test
+    fun lastResortKeyPackage_should_be_kept_until_rotated() = runTest {
+        val (alice) = newClients(this@MLSTest, genClientId())
+        val kp = alice.transaction { ctx -> ctx.lastResortKeyPackage(CIPHERSUITE_DEFAULT, CREDENTIAL_TYPE_DEFAULT) }
+        assertThat(
+            alice.transaction { ctx -> ctx.lastResortKeyPackage(CIPHERSUITE_DEFAULT, CREDENTIAL_TYPE_DEFAULT) }
+        ).isEqualTo(kp)
+
+        val rotated = alice.transaction { ctx ->
+            ctx.rotateLastResortKeyPackage(CIPHERSUITE_DEFAULT, CREDENTIAL_TYPE_DEFAULT)
+        }
+        assertThat(rotated).isNotEqualTo(kp)
+        assertThat(
+            alice.transaction { ctx -> ctx.lastResortKeyPackage(CIPHERSUITE_DEFAULT, CREDENTIAL_TYPE_DEFAULT) }
+        ).isEqualTo(rotated)
+    }
+
     @Test
     fun given_new_conversation_when_calling_conversationEpoch_should_return_epoch_0() = runTest {
         val (alice) = newClients(this@MLSTest, genClientId())
@@ -427,6 +468,16 @@ class MLSTest : HasMockDeliveryService() {
                 ) {
                     observedEvents.add(HistorySecretEvent(conversationId, secret.clientId))
                 }
+
+                override suspend fun historyClientRemoved(conversationId: ConversationId, clientId: ClientId) {}
+
+                override suspend fun historyClientReplaced(
+                    conversationId: ConversationId,
+                    previous: ClientId,
+                    replacement: ClientId
+                ) {}
+
+                override suspend fun historySharingDisabled(conversationId: ConversationId) {}
             }
             val bobObserver = Observer()
             val aliceObserver = Observer()
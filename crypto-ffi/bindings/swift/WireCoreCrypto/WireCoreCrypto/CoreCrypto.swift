@@ -39,7 +39,53 @@ public protocol CoreCryptoProtocol {
     func registerEpochObserver(_ epochObserver: EpochObserver) async throws
 
     ///
-    /// Register a History Observer which will be notified every time a new history secret is created locally.
+    /// Register a Membership Observer which will be notified every time a merged commit adds or removes
+    /// clients of a conversation, or replaces the credential of a member.
+    ///
+    /// - Parameter membershipObserver: membership observer to register
+    ///
+    /// This function should be called 0 or 1 times in the lifetime of CoreCrypto,
+    /// regardless of the number of transactions.
+    ///
+    func registerMembershipObserver(_ membershipObserver: MembershipObserver) async throws
+
+    ///
+    /// Register a Buffer Observer which will be notified every time a message or commit buffered
+    /// for a future epoch is evicted.
+    ///
+    /// - Parameter bufferObserver: buffer observer to register
+    ///
+    /// This function should be called 0 or 1 times in the lifetime of CoreCrypto,
+    /// regardless of the number of transactions.
+    ///
+    func registerBufferObserver(_ bufferObserver: BufferObserver) async throws
+
+    ///
+    /// Register a ReInit Observer which will be notified every time we become a member of the conversation
+    /// replacing a reinitialized one.
+    ///
+    /// - Parameter reinitObserver: reinit observer to register
+    ///
+    /// This function should be called 0 or 1 times in the lifetime of CoreCrypto,
+    /// regardless of the number of transactions.
+    ///
+    func registerReInitObserver(_ reinitObserver: ReInitObserver) async throws
+
+    ///
+    /// Register an E2EI Status Observer which will be notified every time the certificate of a valid member
+    /// of a conversation expires or is revoked, and every time the end-to-end identity state of a conversation
+    /// changes.
+    ///
+    /// - Parameter e2eiStatusObserver: E2EI status observer to register
+    ///
+    /// This function should be called 0 or 1 times in the lifetime of CoreCrypto,
+    /// regardless of the number of transactions.
+    ///
+    func registerE2eiStatusObserver(_ e2eiStatusObserver: E2eiStatusObserver) async throws
+
+    ///
+    /// Register a History Observer which will be notified every time a new history secret is created locally,
+    /// every time a history client is removed or replaced, and every time history sharing is disabled.
     ///
     /// - Parameter historyObserver: history observer to register
     ///
@@ -71,13 +117,17 @@ public final class CoreCrypto: CoreCryptoProtocol {
     ///
     /// - Parameter keystorePath: path to the encrypted key store
     /// - Parameter key: secret key to unlock the encrypted key store
+    /// - Parameter customCapabilities: application-defined extension and proposal types advertised by this client
     ///
-    public convenience init(keystorePath: String, key: DatabaseKey) async throws {
+    public convenience init(
+        keystorePath: String, key: DatabaseKey, customCapabilities: CustomCapabilities? = nil
+    ) async throws {
         let coreCrypto =
             try await WireCoreCryptoUniffi.coreCryptoDeferredInit(
                 path: keystorePath,
                 key: key,
-                entropySeed: nil
+                entropySeed: nil,
+                customCapabilities: customCapabilities
             )
         self.init(coreCrypto, keystorePath: FilePath(stringLiteral: keystorePath))
     }
@@ -116,6 +166,34 @@ public final class CoreCrypto: CoreCryptoProtocol {
             epochObserver: EpochObserverIndirector(epochObserver))
     }
 
+    public func registerMembershipObserver(_ membershipObserver: MembershipObserver) async throws {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        try await coreCrypto.registerMembershipObserver(
+            membershipObserver: MembershipObserverIndirector(membershipObserver))
+    }
+
+    public func registerBufferObserver(_ bufferObserver: BufferObserver) async throws {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        try await coreCrypto.registerBufferObserver(
+            bufferObserver: BufferObserverIndirector(bufferObserver))
+    }
+
+    public func registerReInitObserver(_ reinitObserver: ReInitObserver) async throws {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        try await coreCrypto.registerReinitObserver(
+            reinitObserver: ReInitObserverIndirector(reinitObserver))
+    }
+
+    public func registerE2eiStatusObserver(_ e2eiStatusObserver: E2eiStatusObserver) async throws {
+        // we want to wrap the observer here to provide async indirection, so that no matter what
+        // the observer that makes its way to the Rust side of things doesn't end up blocking
+        try await coreCrypto.registerE2eiStatusObserver(
+            e2eiStatusObserver: E2eiStatusObserverIndirector(e2eiStatusObserver))
+    }
+
     public func registerHistoryObserver(_ historyObserver: HistoryObserver) async throws {
         // we want to wrap the observer here to provide async indirection, so that no matter what
         // the observer that makes its way to the Rust side of things doesn't end up blocking
@@ -144,6 +222,97 @@ final class EpochObserverIndirector: EpochObserver {
 
 }
 
+final class MembershipObserverIndirector: MembershipObserver {
+
+    let membershipObserver: MembershipObserver
+
+    init(_ membershipObserver: MembershipObserver) {
+        self.membershipObserver = membershipObserver
+    }
+
+    func membershipChanged(conversationId: ConversationId, changes: MembershipChanges) async throws {
+        Task {
+            try await membershipObserver.membershipChanged(
+                conversationId: conversationId, changes: changes)
+        }
+    }
+}
+
+final class BufferObserverIndirector: BufferObserver {
+
+    let bufferObserver: BufferObserver
+
+    init(_ bufferObserver: BufferObserver) {
+        self.bufferObserver = bufferObserver
+    }
+
+    func bufferedItemEvicted(
+        conversationId: ConversationId, item: BufferedItem, reason: EvictionReason
+    ) async throws {
+        Task {
+            try await bufferObserver.bufferedItemEvicted(
+                conversationId: conversationId, item: item, reason: reason)
+        }
+    }
+}
+
+final class ReInitObserverIndirector: ReInitObserver {
+
+    let reinitObserver: ReInitObserver
+
+    init(_ reinitObserver: ReInitObserver) {
+        self.reinitObserver = reinitObserver
+    }
+
+    func conversationReinitialized(
+        oldConversationId: ConversationId, newConversationId: ConversationId
+    ) async throws {
+        Task {
+            try await reinitObserver.conversationReinitialized(
+                oldConversationId: oldConversationId, newConversationId: newConversationId)
+        }
+    }
+}
+
+final class E2eiStatusObserverIndirector: E2eiStatusObserver {
+
+    let e2eiStatusObserver: E2eiStatusObserver
+
+    init(_ e2eiStatusObserver: E2eiStatusObserver) {
+        self.e2eiStatusObserver = e2eiStatusObserver
+    }
+
+    func deviceStatusChanged(
+        conversationId: ConversationId, clientId: ClientId, status: DeviceStatus
+    ) async throws {
+        Task {
+            try await e2eiStatusObserver.deviceStatusChanged(
+                conversationId: conversationId, clientId: clientId, status: status)
+        }
+    }
+
+    func conversationStateChanged(
+        conversationId: ConversationId, state: E2eiConversationState
+    ) async throws {
+        Task {
+            try await e2eiStatusObserver.conversationStateChanged(
+                conversationId: conversationId, state: state)
+        }
+    }
+}
+
+/// The lifecycle events of history clients are optional to observe.
+extension HistoryObserver {
+
+    public func historyClientRemoved(conversationId: ConversationId, clientId: ClientId) async throws {}
+
+    public func historyClientReplaced(
+        conversationId: ConversationId, previous: ClientId, replacement: ClientId
+    ) async throws {}
+
+    public func historySharingDisabled(conversationId: ConversationId) async throws {}
+}
+
 final class HistoryObserverIndirector: HistoryObserver {
 
     let historyObserver: HistoryObserver
@@ -158,6 +327,28 @@ final class HistoryObserverIndirector: HistoryObserver {
                 conversationId: conversationId, secret: secret)
         }
     }
+
+    func historyClientRemoved(conversationId: ConversationId, clientId: ClientId) async throws {
+        Task {
+            try await historyObserver.historyClientRemoved(
+                conversationId: conversationId, clientId: clientId)
+        }
+    }
+
+    func historyClientReplaced(
+        conversationId: ConversationId, previous: ClientId, replacement: ClientId
+    ) async throws {
+        Task {
+            try await historyObserver.historyClientReplaced(
+                conversationId: conversationId, previous: previous, replacement: replacement)
+        }
+    }
+
+    func historySharingDisabled(conversationId: ConversationId) async throws {
+        Task {
+            try await historyObserver.historySharingDisabled(conversationId: conversationId)
+        }
+    }
 }
 
 final actor TransactionExecutor<Result>: WireCoreCryptoUniffi.CoreCryptoCommand {
@@ -4,9 +4,12 @@ use std::time::Duration;
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use core_crypto::prelude::MlsCustomConfiguration;
+use core_crypto::prelude::{
+    MlsAdmissionAction, MlsAdmissionPolicy, MlsBufferConfiguration, MlsCustomConfiguration,
+    MlsExporterSecretHistoryConfiguration,
+};
 
-use crate::{Ciphersuite, core_crypto_context::mls::ExternalSenderKeyMaybeArc};
+use crate::{Ciphersuite, CustomCapabilities, core_crypto_context::mls::ExternalSenderKeyMaybeArc};
 
 /// See [core_crypto::prelude::MlsWirePolicy]
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -19,6 +22,8 @@ pub enum WirePolicy {
     Plaintext = 1,
     /// Handshake messages are always encrypted
     Ciphertext = 2,
+    /// Commits are never encrypted, proposals always are
+    Mixed = 3,
 }
 
 impl From<core_crypto::prelude::MlsWirePolicy> for WirePolicy {
@@ -26,6 +31,7 @@ impl From<core_crypto::prelude::MlsWirePolicy> for WirePolicy {
         match value {
             core_crypto::prelude::MlsWirePolicy::Plaintext => Self::Plaintext,
             core_crypto::prelude::MlsWirePolicy::Ciphertext => Self::Ciphertext,
+            core_crypto::prelude::MlsWirePolicy::Mixed => Self::Mixed,
         }
     }
 }
@@ -35,14 +41,178 @@ impl From<WirePolicy> for core_crypto::prelude::MlsWirePolicy {
         match value {
             WirePolicy::Plaintext => core_crypto::prelude::MlsWirePolicy::Plaintext,
             WirePolicy::Ciphertext => core_crypto::prelude::MlsWirePolicy::Ciphertext,
+            WirePolicy::Mixed => core_crypto::prelude::MlsWirePolicy::Mixed,
         }
     }
 }
 
-/// see [core_crypto::prelude::MlsCustomConfiguration]
+/// See [core_crypto::prelude::MlsAdmissionAction]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Enum))]
+#[repr(u8)]
+pub enum AdmissionAction {
+    /// Admit the credential
+    #[default]
+    Allow = 1,
+    /// Admit the credential, logging a warning
+    Warn = 2,
+    /// Refuse the credential, failing the operation
+    Reject = 3,
+}
+
+impl From<AdmissionAction> for MlsAdmissionAction {
+    fn from(value: AdmissionAction) -> Self {
+        match value {
+            AdmissionAction::Allow => Self::Allow,
+            AdmissionAction::Warn => Self::Warn,
+            AdmissionAction::Reject => Self::Reject,
+        }
+    }
+}
+
+/// See [core_crypto::prelude::MlsAdmissionPolicy]
 #[derive(Debug, Default, Clone, Copy)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct AdmissionPolicy {
+    /// What to do with a X509 credential whose certificate has expired
+    pub expired: AdmissionAction,
+    /// What to do with a X509 credential whose certificate has been revoked
+    pub revoked: AdmissionAction,
+    /// What to do with a Basic credential
+    pub basic: AdmissionAction,
+}
+
+impl From<AdmissionPolicy> for MlsAdmissionPolicy {
+    fn from(policy: AdmissionPolicy) -> Self {
+        Self {
+            expired: policy.expired.into(),
+            revoked: policy.revoked.into(),
+            basic: policy.basic.into(),
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl AdmissionPolicy {
+    /// Construct an `AdmissionPolicy` from its parts.
+    #[wasm_bindgen(constructor)]
+    pub fn new(expired: AdmissionAction, revoked: AdmissionAction, basic: AdmissionAction) -> Self {
+        Self {
+            expired,
+            revoked,
+            basic,
+        }
+    }
+}
+
+/// See [core_crypto::prelude::MlsExporterSecretHistoryConfiguration]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct ExporterSecretHistoryConfiguration {
+    /// For how many past epochs secrets are kept. `0` disables the history.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "maxPastEpochs"))]
+    pub max_past_epochs: u32,
+    /// Labels, as passed to `export_secret_key_with_label` with an empty context, for which secrets are kept
+    pub labels: Vec<String>,
+    /// Length of the kept secrets, 32 bytes by default
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "keyLength"))]
+    #[cfg_attr(not(target_family = "wasm"), uniffi(default = None))]
+    pub key_length: Option<u32>,
+}
+
+impl From<ExporterSecretHistoryConfiguration> for MlsExporterSecretHistoryConfiguration {
+    fn from(cfg: ExporterSecretHistoryConfiguration) -> Self {
+        Self {
+            max_past_epochs: cfg.max_past_epochs as usize,
+            labels: cfg.labels,
+            key_length: cfg
+                .key_length
+                .map(|key_length| key_length as usize)
+                .unwrap_or(Self::DEFAULT_KEY_LENGTH),
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl ExporterSecretHistoryConfiguration {
+    /// Construct an `ExporterSecretHistoryConfiguration` from its parts.
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_past_epochs: u32, labels: Vec<String>, key_length: Option<u32>) -> Self {
+        Self {
+            max_past_epochs,
+            labels,
+            key_length,
+        }
+    }
+}
+
+/// See [core_crypto::prelude::MlsBufferConfiguration]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct BufferConfiguration {
+    /// How many messages of future epochs are kept. There is at most one buffered commit.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "maxMessages"))]
+    pub max_messages: u32,
+    /// How many bytes the buffered messages and commit can take altogether
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "maxBytes"))]
+    pub max_bytes: u32,
+    /// For how many seconds buffered messages and commits are kept
+    #[cfg(target_family = "wasm")]
+    #[wasm_bindgen(js_name = "maxAge")]
+    pub max_age: u32,
+    /// For how long buffered messages and commits are kept
+    #[cfg(not(target_family = "wasm"))]
+    pub max_age: Duration,
+}
+
+impl From<BufferConfiguration> for MlsBufferConfiguration {
+    fn from(cfg: BufferConfiguration) -> Self {
+        #[cfg(target_family = "wasm")]
+        let max_age = std::time::Duration::from_secs(cfg.max_age.into());
+
+        #[cfg(not(target_family = "wasm"))]
+        let max_age = cfg.max_age;
+
+        Self {
+            max_messages: cfg.max_messages as usize,
+            max_bytes: cfg.max_bytes as usize,
+            max_age,
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl BufferConfiguration {
+    /// Construct a `BufferConfiguration` from its parts.
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_messages: u32, max_bytes: u32, max_age: u32) -> Self {
+        Self {
+            max_messages,
+            max_bytes,
+            max_age,
+        }
+    }
+}
+
+/// see [core_crypto::prelude::MlsCustomConfiguration]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
 pub struct CustomConfiguration {
     ///  Duration in seconds after which we will automatically force a self-update commit
     ///  Note: This isn't currently implemented
@@ -59,6 +229,30 @@ pub struct CustomConfiguration {
     /// Note: encrypted handshake messages are not supported by wire-server
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "wirePolicy"))]
     pub wire_policy: Option<WirePolicy>,
+
+    /// Which credentials can join the conversation once it is verified. Everything is admitted by default.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "admissionPolicy"))]
+    #[cfg_attr(not(target_family = "wasm"), uniffi(default = None))]
+    pub admission_policy: Option<AdmissionPolicy>,
+
+    /// Which exporter secrets are kept once the conversation moved past the epoch they were exported in.
+    /// None are kept by default.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "exporterSecretHistory"))]
+    #[cfg_attr(not(target_family = "wasm"), uniffi(default = None))]
+    pub exporter_secret_history: Option<ExporterSecretHistoryConfiguration>,
+
+    /// Limits of the messages and commits buffered while waiting for the commit of a future epoch. By
+    /// default, 100 messages, 4 MiB and 7 days. The oldest items beyond them are evicted.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "bufferConfiguration"))]
+    #[cfg_attr(not(target_family = "wasm"), uniffi(default = None))]
+    pub buffer_configuration: Option<BufferConfiguration>,
+
+    /// Application-defined extension and proposal types every member has to support. None by default.
+    ///
+    /// Members, the creator included, have to register them in the custom capabilities of their session.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "requiredCapabilities"))]
+    #[cfg_attr(not(target_family = "wasm"), uniffi(default = None))]
+    pub required_capabilities: Option<CustomCapabilities>,
 }
 
 impl From<CustomConfiguration> for MlsCustomConfiguration {
@@ -73,10 +267,27 @@ impl From<CustomConfiguration> for MlsCustomConfiguration {
         let key_rotation_span = cfg.key_rotation_span;
 
         let wire_policy = cfg.wire_policy.map(WirePolicy::into).unwrap_or_default();
+        let admission_policy = cfg.admission_policy.map(AdmissionPolicy::into).unwrap_or_default();
+        let exporter_secret_history = cfg
+            .exporter_secret_history
+            .map(ExporterSecretHistoryConfiguration::into)
+            .unwrap_or_default();
+        let buffer = cfg
+            .buffer_configuration
+            .map(BufferConfiguration::into)
+            .unwrap_or_default();
+        let required_capabilities = cfg
+            .required_capabilities
+            .map(CustomCapabilities::into)
+            .unwrap_or_default();
 
         Self {
             key_rotation_span,
             wire_policy,
+            admission_policy,
+            exporter_secret_history,
+            buffer,
+            required_capabilities,
             ..Default::default()
         }
     }
@@ -87,10 +298,21 @@ impl From<CustomConfiguration> for MlsCustomConfiguration {
 impl CustomConfiguration {
     /// Construct a `CustomConfiguration` from its parts.
     #[wasm_bindgen(constructor)]
-    pub fn new(key_rotation_span: Option<u32>, wire_policy: Option<WirePolicy>) -> Self {
+    pub fn new(
+        key_rotation_span: Option<u32>,
+        wire_policy: Option<WirePolicy>,
+        admission_policy: Option<AdmissionPolicy>,
+        exporter_secret_history: Option<ExporterSecretHistoryConfiguration>,
+        buffer_configuration: Option<BufferConfiguration>,
+        required_capabilities: Option<CustomCapabilities>,
+    ) -> Self {
         Self {
             key_rotation_span,
             wire_policy,
+            admission_policy,
+            exporter_secret_history,
+            buffer_configuration,
+            required_capabilities,
         }
     }
 }
@@ -127,6 +349,10 @@ impl ConversationConfiguration {
         external_senders: Option<Vec<ExternalSenderKeyMaybeArc>>,
         key_rotation_span: Option<u32>,
         wire_policy: Option<WirePolicy>,
+        admission_policy: Option<AdmissionPolicy>,
+        exporter_secret_history: Option<ExporterSecretHistoryConfiguration>,
+        buffer_configuration: Option<BufferConfiguration>,
+        required_capabilities: Option<CustomCapabilities>,
     ) -> crate::CoreCryptoResult<ConversationConfiguration> {
         let external_senders = external_senders.unwrap_or_default();
         Ok(Self {
@@ -135,6 +361,10 @@ impl ConversationConfiguration {
             custom: CustomConfiguration {
                 key_rotation_span,
                 wire_policy,
+                admission_policy,
+                exporter_secret_history,
+                buffer_configuration,
+                required_capabilities,
             },
         })
     }
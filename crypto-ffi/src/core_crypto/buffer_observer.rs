@@ -0,0 +1,324 @@
+use async_trait::async_trait;
+#[cfg(target_family = "wasm")]
+use js_sys::Promise;
+#[cfg(target_family = "wasm")]
+use log::kv;
+use std::sync::Arc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(target_family = "wasm")]
+use crate::ConversationId;
+#[cfg(not(target_family = "wasm"))]
+use crate::ConversationIdMaybeArc;
+use crate::{CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, conversation_id_coerce_maybe_arc};
+use ::core_crypto::prelude::ConversationId as InternalConversationId;
+use obfuscate::Obfuscated;
+
+/// See [core_crypto::prelude::MlsBufferedItemKind]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Enum))]
+#[repr(u8)]
+pub enum BufferedItemKind {
+    /// An application message, or a proposal, of a future epoch
+    Message = 1,
+    /// A commit referencing proposals we did not receive yet
+    Commit = 2,
+}
+
+impl From<core_crypto::prelude::MlsBufferedItemKind> for BufferedItemKind {
+    fn from(value: core_crypto::prelude::MlsBufferedItemKind) -> Self {
+        match value {
+            core_crypto::prelude::MlsBufferedItemKind::Message => Self::Message,
+            core_crypto::prelude::MlsBufferedItemKind::Commit => Self::Commit,
+        }
+    }
+}
+
+/// See [core_crypto::prelude::MlsEvictionReason]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Enum))]
+#[repr(u8)]
+pub enum EvictionReason {
+    /// The item has been buffered for too long
+    TooOld = 1,
+    /// The conversation buffers too many messages
+    TooMany = 2,
+    /// The buffered items of the conversation take too much space
+    TooLarge = 3,
+}
+
+impl From<core_crypto::prelude::MlsEvictionReason> for EvictionReason {
+    fn from(value: core_crypto::prelude::MlsEvictionReason) -> Self {
+        match value {
+            core_crypto::prelude::MlsEvictionReason::TooOld => Self::TooOld,
+            core_crypto::prelude::MlsEvictionReason::TooMany => Self::TooMany,
+            core_crypto::prelude::MlsEvictionReason::TooLarge => Self::TooLarge,
+        }
+    }
+}
+
+/// A message buffered for a future epoch, see [core_crypto::prelude::MlsBufferedItem]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct BufferedItem {
+    /// Identifies the item in the buffer; this is the SHA-256 hash of the message
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub id: Vec<u8>,
+    /// Whether this is a commit or another message
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub kind: BufferedItemKind,
+    /// The epoch the message was sent in
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub epoch: u64,
+    /// Size of the message in bytes
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub size: u64,
+    /// UNIX timestamp (in seconds) of when the message got buffered, if known
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = receivedAt))]
+    pub received_at: Option<u64>,
+}
+
+impl From<core_crypto::prelude::MlsBufferedItem> for BufferedItem {
+    fn from(item: core_crypto::prelude::MlsBufferedItem) -> Self {
+        Self {
+            id: item.id,
+            kind: item.kind.into(),
+            epoch: item.epoch,
+            size: item.size as u64,
+            received_at: item.received_at,
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum BufferedItemEvictedReportingError {
+    #[error("panic or otherwise unexpected error from foreign code")]
+    Ffi(#[from] uniffi::UnexpectedUniFFICallbackError),
+}
+
+/// A `BufferObserver` is notified whenever a message or commit buffered for a future epoch is evicted
+/// because the buffer of its conversation exceeded its limits.
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export(with_foreign)]
+#[async_trait]
+pub trait BufferObserver: Send + Sync {
+    /// This function will be called every time a buffered item has been evicted. It will never be
+    /// decrypted; the application may want to fetch it from the delivery service again later.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this interface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    ///
+    /// Though the signature includes an error type, that error is only present because
+    /// it is required by `uniffi` in order to handle panics. This function should suppress
+    /// and ignore internal errors instead of propagating them, to the maximum extent possible.
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        item: BufferedItem,
+        reason: EvictionReason,
+    ) -> Result<(), BufferedItemEvictedReportingError>;
+}
+
+/// This shim bridges the public `BufferObserver` interface with the internal one defined by `core-crypto`.
+///
+/// The orphan rule prevents us from just tying the two traits together directly.
+#[cfg(not(target_family = "wasm"))]
+struct ObserverShim(Arc<dyn BufferObserver>);
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl core_crypto::mls::BufferObserver for ObserverShim {
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: InternalConversationId,
+        item: core_crypto::prelude::MlsBufferedItem,
+        reason: core_crypto::prelude::MlsEvictionReason,
+    ) {
+        if let Err(err) = self
+            .0
+            .buffered_item_evicted(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                item.into(),
+                reason.into(),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = log::kv::Value::from_dyn_error(&err);
+                "caught an error when attempting to notify the buffer observer of an evicted item"
+            );
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export]
+impl CoreCryptoFfi {
+    /// Add a buffer observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a buffer observer already exists, this will return an error.
+    pub async fn register_buffer_observer(&self, buffer_observer: Arc<dyn BufferObserver>) -> CoreCryptoResult<()> {
+        let shim = Arc::new(ObserverShim(buffer_observer));
+        self.inner
+            .register_buffer_observer(shim)
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+/// A `BufferObserver` is notified whenever a message or commit buffered for a future epoch is evicted
+/// because the buffer of its conversation exceeded its limits.
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+#[derive(derive_more::Debug)]
+#[debug("BufferObserver")]
+pub struct BufferObserver {
+    this_context: JsValue,
+    buffered_item_evicted: js_sys::Function,
+}
+
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Send for BufferObserver {}
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Sync for BufferObserver {}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl BufferObserver {
+    /// Create a new Buffer Observer.
+    ///
+    /// This function should be hidden on the JS side of things! The JS bindings should have an `interface BufferObserver`
+    /// which has the method defined, and the bindings themselves should destructure an instance implementing that
+    /// interface appropriately to construct this.
+    ///
+    /// - `this_context` is the instance itself, which will be bound to `this` within the function bodies
+    /// - `buffered_item_evicted`: A function of the form
+    ///   `(conversation_id: ConversationId, item: BufferedItem, reason: EvictionReason) -> Promise<void>`.
+    ///   Called every time a buffered item has been evicted.
+    #[wasm_bindgen(constructor)]
+    pub fn new(this_context: JsValue, buffered_item_evicted: js_sys::Function) -> CoreCryptoResult<Self> {
+        // we can't do much type-checking here unfortunately, but we can at least validate that the incoming functions have the right length
+        if buffered_item_evicted.length() != 3 {
+            return Err(CoreCryptoError::ad_hoc(format!(
+                "`buffered_item_evicted` must accept 3 arguments but accepts {}",
+                buffered_item_evicted.length()
+            )));
+        }
+        Ok(Self {
+            this_context,
+            buffered_item_evicted,
+        })
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl BufferObserver {
+    /// Call the JS `buffered_item_evicted` function
+    ///
+    /// This blocks if the JS side of things blocks.
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: ConversationId,
+        item: BufferedItem,
+        reason: EvictionReason,
+    ) -> Result<(), JsValue> {
+        let promise = self
+            .buffered_item_evicted
+            .call3(
+                &self.this_context,
+                &conversation_id.into(),
+                &item.into(),
+                &reason.into(),
+            )?
+            .dyn_into::<Promise>()?;
+        // we don't actually care what the result of executing the notification promise is; we'll ignore it if it exists
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[async_trait(?Send)]
+impl core_crypto::mls::BufferObserver for BufferObserver {
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: InternalConversationId,
+        item: core_crypto::prelude::MlsBufferedItem,
+        reason: core_crypto::prelude::MlsEvictionReason,
+    ) {
+        if let Err(err) = self
+            .buffered_item_evicted(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                item.into(),
+                reason.into(),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = LoggableJsValue(err);
+                "caught an error when attempting to notify the buffer observer of an evicted item"
+            );
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CoreCryptoFfi {
+    /// Add a buffer observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a client's lifetime.
+    /// If called when a buffer observer already exists, this will return an error.
+    pub async fn register_buffer_observer(&self, buffer_observer: BufferObserver) -> CoreCryptoResult<()> {
+        self.inner
+            .register_buffer_observer(Arc::new(buffer_observer))
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+struct LoggableJsValue(JsValue);
+
+#[cfg(target_family = "wasm")]
+impl kv::ToValue for LoggableJsValue {
+    fn to_value(&self) -> kv::Value<'_> {
+        // can't get a borrowed str from `JsValue`, so can't directly
+        // convert into a string; oh well; fallback should catch it
+        if let Some(f) = self.0.as_f64() {
+            return f.into();
+        }
+        if let Some(b) = self.0.as_bool() {
+            return b.into();
+        }
+        if self.0.is_null() || self.0.is_undefined() {
+            return kv::Value::null();
+        }
+        kv::Value::from_debug(&self.0)
+    }
+}
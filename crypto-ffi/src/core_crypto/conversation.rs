@@ -3,7 +3,8 @@ use core_crypto::{RecursiveError, mls::conversation::Conversation as _};
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    Ciphersuite, ClientId, CoreCryptoFfi, CoreCryptoResult, bytes_wrapper::bytes_wrapper, client_id::ClientIdMaybeArc,
+    Ciphersuite, ClientId, CoreCryptoFfi, CoreCryptoResult, WirePolicy, bytes_wrapper::bytes_wrapper,
+    client_id::ClientIdMaybeArc,
 };
 
 bytes_wrapper!(
@@ -42,6 +43,18 @@ impl CoreCryptoFfi {
         Ok(Ciphersuite::from(core_crypto::prelude::CiphersuiteName::from(cs)))
     }
 
+    /// See [core_crypto::mls::conversation::Conversation::wire_policy]
+    pub async fn conversation_wire_policy(&self, conversation_id: &ConversationId) -> CoreCryptoResult<WirePolicy> {
+        let wire_policy = self
+            .inner
+            .get_raw_conversation(conversation_id)
+            .await
+            .map_err(RecursiveError::mls_client("getting raw conversation by id"))?
+            .wire_policy()
+            .await?;
+        Ok(wire_policy.into())
+    }
+
     /// See [core_crypto::prelude::Session::conversation_exists]
     pub async fn conversation_exists(&self, conversation_id: &ConversationId) -> CoreCryptoResult<bool> {
         self.inner
@@ -91,6 +104,23 @@ impl CoreCryptoFfi {
             .map_err(Into::into)
     }
 
+    /// See [core_crypto::mls::conversation::Conversation::export_secret_key_with_label]
+    pub async fn export_secret_key_with_label(
+        &self,
+        conversation_id: &ConversationId,
+        label: String,
+        context: Vec<u8>,
+        key_length: u32,
+    ) -> CoreCryptoResult<Vec<u8>> {
+        self.inner
+            .get_raw_conversation(conversation_id)
+            .await
+            .map_err(RecursiveError::mls_client("getting raw conversation"))?
+            .export_secret_key_with_label(&label, &context, key_length as usize)
+            .await
+            .map_err(Into::into)
+    }
+
     /// See [core_crypto::mls::conversation::Conversation::is_history_sharing_enabled]
     pub async fn is_history_sharing_enabled(&self, conversation_id: &ConversationId) -> CoreCryptoResult<bool> {
         let conversation = self
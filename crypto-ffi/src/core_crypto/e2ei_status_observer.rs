@@ -0,0 +1,327 @@
+use async_trait::async_trait;
+#[cfg(target_family = "wasm")]
+use js_sys::Promise;
+#[cfg(target_family = "wasm")]
+use log::kv;
+use std::sync::Arc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(target_family = "wasm")]
+use crate::ConversationId;
+#[cfg(not(target_family = "wasm"))]
+use crate::ConversationIdMaybeArc;
+#[cfg(not(target_family = "wasm"))]
+use crate::client_id::ClientIdMaybeArc;
+use crate::{
+    ClientId, CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, DeviceStatus, E2eiConversationState,
+    conversation_id_coerce_maybe_arc,
+};
+use ::core_crypto::prelude::{ClientId as InternalClientId, ConversationId as InternalConversationId};
+use obfuscate::Obfuscated;
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum E2eiStatusChangedReportingError {
+    #[error("panic or otherwise unexpected error from foreign code")]
+    Ffi(#[from] uniffi::UnexpectedUniFFICallbackError),
+}
+
+/// An `E2eiStatusObserver` is notified whenever the end-to-end identity status of a device, or of a
+/// whole conversation, changes.
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export(with_foreign)]
+#[async_trait]
+pub trait E2eiStatusObserver: Send + Sync {
+    /// This function will be called every time the certificate of a valid member of a conversation
+    /// expires or is revoked.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this interface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    ///
+    /// Though the signature includes an error type, that error is only present because
+    /// it is required by `uniffi` in order to handle panics. This function should suppress
+    /// and ignore internal errors instead of propagating them, to the maximum extent possible.
+    async fn device_status_changed(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        client_id: ClientIdMaybeArc,
+        status: DeviceStatus,
+    ) -> Result<(), E2eiStatusChangedReportingError>;
+
+    /// This function will be called every time the end-to-end identity state of a conversation changes.
+    ///
+    /// The same restrictions as for `device_status_changed` apply.
+    async fn conversation_state_changed(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        state: E2eiConversationState,
+    ) -> Result<(), E2eiStatusChangedReportingError>;
+}
+
+/// This shim bridges the public `E2eiStatusObserver` interface with the internal one defined by `core-crypto`.
+///
+/// The orphan rule prevents us from just tying the two traits together directly.
+#[cfg(not(target_family = "wasm"))]
+struct ObserverShim(Arc<dyn E2eiStatusObserver>);
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl core_crypto::mls::E2eiStatusObserver for ObserverShim {
+    async fn device_status_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        client_id: InternalClientId,
+        status: core_crypto::prelude::DeviceStatus,
+    ) {
+        if let Err(err) = self
+            .0
+            .device_status_changed(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                ClientId::from_cc(client_id),
+                status.into(),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = log::kv::Value::from_dyn_error(&err);
+                "caught an error when attempting to notify the e2ei status observer of a device status change"
+            );
+        }
+    }
+
+    async fn conversation_state_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        state: core_crypto::prelude::E2eiConversationState,
+    ) {
+        if let Err(err) = self
+            .0
+            .conversation_state_changed(conversation_id_coerce_maybe_arc(&conversation_id), state.into())
+            .await
+        {
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = log::kv::Value::from_dyn_error(&err);
+                "caught an error when attempting to notify the e2ei status observer of a conversation state change"
+            );
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export]
+impl CoreCryptoFfi {
+    /// Add an E2EI status observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when an E2EI status observer already exists, this will return an error.
+    pub async fn register_e2ei_status_observer(
+        &self,
+        e2ei_status_observer: Arc<dyn E2eiStatusObserver>,
+    ) -> CoreCryptoResult<()> {
+        let shim = Arc::new(ObserverShim(e2ei_status_observer));
+        self.inner
+            .register_e2ei_status_observer(shim)
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+/// An `E2eiStatusObserver` is notified whenever the end-to-end identity status of a device, or of a
+/// whole conversation, changes.
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+#[derive(derive_more::Debug)]
+#[debug("E2eiStatusObserver")]
+pub struct E2eiStatusObserver {
+    this_context: JsValue,
+    device_status_changed: js_sys::Function,
+    conversation_state_changed: js_sys::Function,
+}
+
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Send for E2eiStatusObserver {}
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Sync for E2eiStatusObserver {}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl E2eiStatusObserver {
+    /// Create a new E2EI Status Observer.
+    ///
+    /// This function should be hidden on the JS side of things! The JS bindings should have an `interface E2eiStatusObserver`
+    /// which has the methods defined, and the bindings themselves should destructure an instance implementing that
+    /// interface appropriately to construct this.
+    ///
+    /// - `this_context` is the instance itself, which will be bound to `this` within the function bodies
+    /// - `device_status_changed`: A function of the form
+    ///   `(conversation_id: ConversationId, client_id: ClientId, status: DeviceStatus) -> Promise<void>`.
+    ///   Called every time the certificate of a valid member of a conversation expires or is revoked.
+    /// - `conversation_state_changed`: A function of the form
+    ///   `(conversation_id: ConversationId, state: E2eiConversationState) -> Promise<void>`.
+    ///   Called every time the end-to-end identity state of a conversation changes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        this_context: JsValue,
+        device_status_changed: js_sys::Function,
+        conversation_state_changed: js_sys::Function,
+    ) -> CoreCryptoResult<Self> {
+        // we can't do much type-checking here unfortunately, but we can at least validate that the incoming functions have the right length
+        if device_status_changed.length() != 3 {
+            return Err(CoreCryptoError::ad_hoc(format!(
+                "`device_status_changed` must accept 3 arguments but accepts {}",
+                device_status_changed.length()
+            )));
+        }
+        if conversation_state_changed.length() != 2 {
+            return Err(CoreCryptoError::ad_hoc(format!(
+                "`conversation_state_changed` must accept 2 arguments but accepts {}",
+                conversation_state_changed.length()
+            )));
+        }
+        Ok(Self {
+            this_context,
+            device_status_changed,
+            conversation_state_changed,
+        })
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl E2eiStatusObserver {
+    /// Call the JS `device_status_changed` function
+    ///
+    /// This blocks if the JS side of things blocks.
+    async fn device_status_changed(
+        &self,
+        conversation_id: ConversationId,
+        client_id: ClientId,
+        status: DeviceStatus,
+    ) -> Result<(), JsValue> {
+        let promise = self
+            .device_status_changed
+            .call3(
+                &self.this_context,
+                &conversation_id.into(),
+                &client_id.into(),
+                &status.into(),
+            )?
+            .dyn_into::<Promise>()?;
+        // we don't actually care what the result of executing the notification promise is; we'll ignore it if it exists
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+
+    /// Call the JS `conversation_state_changed` function
+    ///
+    /// This blocks if the JS side of things blocks.
+    async fn conversation_state_changed(
+        &self,
+        conversation_id: ConversationId,
+        state: E2eiConversationState,
+    ) -> Result<(), JsValue> {
+        let promise = self
+            .conversation_state_changed
+            .call2(&self.this_context, &conversation_id.into(), &state.into())?
+            .dyn_into::<Promise>()?;
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[async_trait(?Send)]
+impl core_crypto::mls::E2eiStatusObserver for E2eiStatusObserver {
+    async fn device_status_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        client_id: InternalClientId,
+        status: core_crypto::prelude::DeviceStatus,
+    ) {
+        if let Err(err) = self
+            .device_status_changed(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                ClientId::from_cc(client_id),
+                status.into(),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = LoggableJsValue(err);
+                "caught an error when attempting to notify the e2ei status observer of a device status change"
+            );
+        }
+    }
+
+    async fn conversation_state_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        state: core_crypto::prelude::E2eiConversationState,
+    ) {
+        if let Err(err) = self
+            .conversation_state_changed(conversation_id_coerce_maybe_arc(&conversation_id), state.into())
+            .await
+        {
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = LoggableJsValue(err);
+                "caught an error when attempting to notify the e2ei status observer of a conversation state change"
+            );
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CoreCryptoFfi {
+    /// Add an E2EI status observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a client's lifetime.
+    /// If called when an E2EI status observer already exists, this will return an error.
+    pub async fn register_e2ei_status_observer(
+        &self,
+        e2ei_status_observer: E2eiStatusObserver,
+    ) -> CoreCryptoResult<()> {
+        self.inner
+            .register_e2ei_status_observer(Arc::new(e2ei_status_observer))
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+struct LoggableJsValue(JsValue);
+
+#[cfg(target_family = "wasm")]
+impl kv::ToValue for LoggableJsValue {
+    fn to_value(&self) -> kv::Value<'_> {
+        // can't get a borrowed str from `JsValue`, so can't directly
+        // convert into a string; oh well; fallback should catch it
+        if let Some(f) = self.0.as_f64() {
+            return f.into();
+        }
+        if let Some(b) = self.0.as_bool() {
+            return b.into();
+        }
+        if self.0.is_null() || self.0.is_undefined() {
+            return kv::Value::null();
+        }
+        kv::Value::from_debug(&self.0)
+    }
+}
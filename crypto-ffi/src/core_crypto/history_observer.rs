@@ -10,11 +10,13 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_family = "wasm")]
 use wasm_bindgen_futures::JsFuture;
 
+#[cfg(not(target_family = "wasm"))]
+use crate::client_id::ClientIdMaybeArc;
 use crate::{
-    ConversationIdMaybeArc, CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, HistorySecret,
+    ClientId, ConversationIdMaybeArc, CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, HistorySecret,
     conversation_id_coerce_maybe_arc,
 };
-use core_crypto::prelude::ConversationId;
+use core_crypto::prelude::{ClientId as CcClientId, ConversationId};
 use obfuscate::Obfuscated;
 
 #[cfg(not(target_family = "wasm"))]
@@ -25,7 +27,8 @@ pub enum NewHistoryClientReportingError {
     Ffi(#[from] uniffi::UnexpectedUniFFICallbackError),
 }
 
-/// An `HistoryObserver` is notified whenever a new history client is created.
+/// An `HistoryObserver` is notified whenever a new history client is created, and whenever a commit,
+/// whether ours or another member's, removes or replaces a history client.
 #[cfg(not(target_family = "wasm"))]
 #[uniffi::export(with_foreign)]
 #[async_trait]
@@ -49,6 +52,34 @@ pub trait HistoryObserver: Send + Sync {
         conversation_id: ConversationIdMaybeArc,
         secret: HistorySecret,
     ) -> Result<(), NewHistoryClientReportingError>;
+
+    /// This function will be called every time a history client is removed from a conversation.
+    ///
+    /// The same restrictions as for [HistoryObserver::history_client_created] apply.
+    async fn history_client_removed(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        client_id: ClientIdMaybeArc,
+    ) -> Result<(), NewHistoryClientReportingError>;
+
+    /// This function will be called every time a commit replaces the history client of a conversation,
+    /// i.e. removes `previous` and adds `replacement`.
+    ///
+    /// The same restrictions as for [HistoryObserver::history_client_created] apply.
+    async fn history_client_replaced(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        previous: ClientIdMaybeArc,
+        replacement: ClientIdMaybeArc,
+    ) -> Result<(), NewHistoryClientReportingError>;
+
+    /// This function will be called every time a commit removes the last history client of a conversation.
+    ///
+    /// The same restrictions as for [HistoryObserver::history_client_created] apply.
+    async fn history_sharing_disabled(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+    ) -> Result<(), NewHistoryClientReportingError>;
 }
 
 /// This shim bridges the public `HistoryObserver` interface with the internal one defined by `core-crypto`.
@@ -88,6 +119,60 @@ impl core_crypto::mls::HistoryObserver for ObserverShim {
             );
         }
     }
+
+    async fn history_client_removed(&self, conversation_id: ConversationId, client_id: CcClientId) {
+        let result = self
+            .0
+            .history_client_removed(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                ClientId::from_cc(client_id),
+            )
+            .await;
+        log_notification_error(&conversation_id, result, "a removed history client");
+    }
+
+    async fn history_client_replaced(
+        &self,
+        conversation_id: ConversationId,
+        previous: CcClientId,
+        replacement: CcClientId,
+    ) {
+        let result = self
+            .0
+            .history_client_replaced(
+                conversation_id_coerce_maybe_arc(&conversation_id),
+                ClientId::from_cc(previous),
+                ClientId::from_cc(replacement),
+            )
+            .await;
+        log_notification_error(&conversation_id, result, "a replaced history client");
+    }
+
+    async fn history_sharing_disabled(&self, conversation_id: ConversationId) {
+        let result = self
+            .0
+            .history_sharing_disabled(conversation_id_coerce_maybe_arc(&conversation_id))
+            .await;
+        log_notification_error(&conversation_id, result, "disabled history sharing");
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn log_notification_error(
+    conversation_id: &ConversationId,
+    result: Result<(), NewHistoryClientReportingError>,
+    event: &'static str,
+) {
+    if let Err(err) = result {
+        // we don't _care_ if an error is thrown by the notification function, per se,
+        // but this would probably be useful information for downstream debugging efforts
+        log::warn!(
+            conversation_id = Obfuscated::from(conversation_id),
+            err = log::kv::Value::from_dyn_error(&err),
+            event = event;
+            "caught an error when attempting to notify the history observer"
+        );
+    }
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -106,7 +191,8 @@ impl CoreCryptoFfi {
     }
 }
 
-/// An `HistoryObserver` is notified whenever a new history client is created.
+/// An `HistoryObserver` is notified whenever a new history client is created, and whenever a commit,
+/// whether ours or another member's, removes or replaces a history client.
 #[cfg(target_family = "wasm")]
 #[wasm_bindgen]
 #[derive(derive_more::Debug)]
@@ -114,6 +200,9 @@ impl CoreCryptoFfi {
 pub struct HistoryObserver {
     this_context: JsValue,
     history_client_created: js_sys::Function,
+    history_client_removed: js_sys::Function,
+    history_client_replaced: js_sys::Function,
+    history_sharing_disabled: js_sys::Function,
 }
 
 #[cfg(target_family = "wasm")]
@@ -136,18 +225,44 @@ impl HistoryObserver {
     /// - `history_client_created`: A function of the form `(conversation_id: ConversationId, secret: HistorySecret) -> Promise<void>`.
     ///
     ///   Called every time a history client is created.
+    /// - `history_client_removed`: A function of the form `(conversation_id: ConversationId, client_id: ClientId) -> Promise<void>`.
+    ///
+    ///   Called every time a history client is removed from a conversation.
+    /// - `history_client_replaced`: A function of the form
+    ///   `(conversation_id: ConversationId, previous: ClientId, replacement: ClientId) -> Promise<void>`.
+    ///
+    ///   Called every time a commit replaces the history client of a conversation.
+    /// - `history_sharing_disabled`: A function of the form `(conversation_id: ConversationId) -> Promise<void>`.
+    ///
+    ///   Called every time a commit removes the last history client of a conversation.
     #[wasm_bindgen(constructor)]
-    pub fn new(this_context: JsValue, history_client_created: js_sys::Function) -> CoreCryptoResult<Self> {
+    pub fn new(
+        this_context: JsValue,
+        history_client_created: js_sys::Function,
+        history_client_removed: js_sys::Function,
+        history_client_replaced: js_sys::Function,
+        history_sharing_disabled: js_sys::Function,
+    ) -> CoreCryptoResult<Self> {
         // we can't do much type-checking here unfortunately, but we can at least validate that the incoming functions have the right length
-        if history_client_created.length() != 2 {
-            return Err(CoreCryptoError::ad_hoc(format!(
-                "`history_client_created` must accept 2 arguments but accepts {}",
-                history_client_created.length()
-            )));
+        for (name, function, expected) in [
+            ("history_client_created", &history_client_created, 2),
+            ("history_client_removed", &history_client_removed, 2),
+            ("history_client_replaced", &history_client_replaced, 3),
+            ("history_sharing_disabled", &history_sharing_disabled, 1),
+        ] {
+            if function.length() != expected {
+                return Err(CoreCryptoError::ad_hoc(format!(
+                    "`{name}` must accept {expected} arguments but accepts {}",
+                    function.length()
+                )));
+            }
         }
         Ok(Self {
             this_context,
             history_client_created,
+            history_client_removed,
+            history_client_replaced,
+            history_sharing_disabled,
         })
     }
 }
@@ -173,6 +288,34 @@ impl HistoryObserver {
         JsFuture::from(promise).await?;
         Ok(())
     }
+
+    /// Call one of the JS lifecycle functions, logging any error
+    ///
+    /// This blocks if the JS side of things blocks.
+    async fn notify(
+        &self,
+        function: &js_sys::Function,
+        conversation_id: &ConversationId,
+        args: &[JsValue],
+        event: &'static str,
+    ) {
+        let args = args.iter().collect::<js_sys::Array>();
+        let result = async {
+            let promise = function.apply(&self.this_context, &args)?.dyn_into::<Promise>()?;
+            JsFuture::from(promise).await
+        }
+        .await;
+        if let Err(err) = result {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(conversation_id),
+                err = LoggableJsValue(err),
+                event = event;
+                "caught an error when attempting to notify the history observer"
+            );
+        }
+    }
 }
 
 #[cfg(target_family = "wasm")]
@@ -203,6 +346,51 @@ impl core_crypto::mls::HistoryObserver for HistoryObserver {
             );
         }
     }
+
+    async fn history_client_removed(&self, conversation_id: ConversationId, client_id: CcClientId) {
+        let args = [
+            conversation_id_coerce_maybe_arc(&conversation_id).into(),
+            ClientId::from_cc(client_id).into(),
+        ];
+        self.notify(
+            &self.history_client_removed,
+            &conversation_id,
+            &args,
+            "a removed history client",
+        )
+        .await;
+    }
+
+    async fn history_client_replaced(
+        &self,
+        conversation_id: ConversationId,
+        previous: CcClientId,
+        replacement: CcClientId,
+    ) {
+        let args = [
+            conversation_id_coerce_maybe_arc(&conversation_id).into(),
+            ClientId::from_cc(previous).into(),
+            ClientId::from_cc(replacement).into(),
+        ];
+        self.notify(
+            &self.history_client_replaced,
+            &conversation_id,
+            &args,
+            "a replaced history client",
+        )
+        .await;
+    }
+
+    async fn history_sharing_disabled(&self, conversation_id: ConversationId) {
+        let args = [conversation_id_coerce_maybe_arc(&conversation_id).into()];
+        self.notify(
+            &self.history_sharing_disabled,
+            &conversation_id,
+            &args,
+            "disabled history sharing",
+        )
+        .await;
+    }
 }
 
 #[cfg(target_family = "wasm")]
@@ -0,0 +1,285 @@
+use async_trait::async_trait;
+#[cfg(target_family = "wasm")]
+use js_sys::Promise;
+#[cfg(target_family = "wasm")]
+use log::kv;
+use std::sync::Arc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(target_family = "wasm")]
+use crate::ConversationId;
+#[cfg(not(target_family = "wasm"))]
+use crate::ConversationIdMaybeArc;
+use crate::{
+    ClientId, CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, WireIdentity, client_id::ClientIdMaybeArc,
+    conversation_id_coerce_maybe_arc,
+};
+use ::core_crypto::prelude::ConversationId as InternalConversationId;
+use obfuscate::Obfuscated;
+
+/// A member whose credential was replaced by a merged commit
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct MemberCredentialUpdate {
+    /// The member whose credential was replaced
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = clientId))]
+    pub client_id: ClientIdMaybeArc,
+    /// The identity of the member, as claimed by its new credential
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub identity: WireIdentity,
+}
+
+/// The changes of membership brought by a merged commit
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct MembershipChanges {
+    /// Clients added to the conversation
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub added: Vec<ClientIdMaybeArc>,
+    /// Clients removed from the conversation
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly))]
+    pub removed: Vec<ClientIdMaybeArc>,
+    /// Members whose credential was replaced
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = credentialUpdates))]
+    pub credential_updates: Vec<MemberCredentialUpdate>,
+}
+
+impl From<core_crypto::prelude::MembershipChanges> for MembershipChanges {
+    fn from(changes: core_crypto::prelude::MembershipChanges) -> Self {
+        Self {
+            added: changes.added.into_iter().map(ClientId::from_cc).collect(),
+            removed: changes.removed.into_iter().map(ClientId::from_cc).collect(),
+            credential_updates: changes
+                .credential_updates
+                .into_iter()
+                .map(|update| MemberCredentialUpdate {
+                    client_id: ClientId::from_cc(update.client_id),
+                    identity: update.identity.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum MembershipChangedReportingError {
+    #[error("panic or otherwise unexpected error from foreign code")]
+    Ffi(#[from] uniffi::UnexpectedUniFFICallbackError),
+}
+
+/// A `MembershipObserver` is notified whenever a merged commit changes the members of a conversation.
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export(with_foreign)]
+#[async_trait]
+pub trait MembershipObserver: Send + Sync {
+    /// This function will be called every time a commit, whether ours or another member's, adds or
+    /// removes clients, or replaces the credential of a member.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this interface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    ///
+    /// Though the signature includes an error type, that error is only present because
+    /// it is required by `uniffi` in order to handle panics. This function should suppress
+    /// and ignore internal errors instead of propagating them, to the maximum extent possible.
+    async fn membership_changed(
+        &self,
+        conversation_id: ConversationIdMaybeArc,
+        changes: MembershipChanges,
+    ) -> Result<(), MembershipChangedReportingError>;
+}
+
+/// This shim bridges the public `MembershipObserver` interface with the internal one defined by `core-crypto`.
+///
+/// The orphan rule prevents us from just tying the two traits together directly.
+#[cfg(not(target_family = "wasm"))]
+struct ObserverShim(Arc<dyn MembershipObserver>);
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl core_crypto::mls::MembershipObserver for ObserverShim {
+    async fn membership_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        changes: core_crypto::prelude::MembershipChanges,
+    ) {
+        if let Err(err) = self
+            .0
+            .membership_changed(conversation_id_coerce_maybe_arc(&conversation_id), changes.into())
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = log::kv::Value::from_dyn_error(&err);
+                "caught an error when attempting to notify the membership observer of a membership change"
+            );
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export]
+impl CoreCryptoFfi {
+    /// Add a membership observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a membership observer already exists, this will return an error.
+    pub async fn register_membership_observer(
+        &self,
+        membership_observer: Arc<dyn MembershipObserver>,
+    ) -> CoreCryptoResult<()> {
+        let shim = Arc::new(ObserverShim(membership_observer));
+        self.inner
+            .register_membership_observer(shim)
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+/// A `MembershipObserver` is notified whenever a merged commit changes the members of a conversation.
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+#[derive(derive_more::Debug)]
+#[debug("MembershipObserver")]
+pub struct MembershipObserver {
+    this_context: JsValue,
+    membership_changed: js_sys::Function,
+}
+
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Send for MembershipObserver {}
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Sync for MembershipObserver {}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl MembershipObserver {
+    /// Create a new Membership Observer.
+    ///
+    /// This function should be hidden on the JS side of things! The JS bindings should have an `interface MembershipObserver`
+    /// which has the method defined, and the bindings themselves should destructure an instance implementing that
+    /// interface appropriately to construct this.
+    ///
+    /// - `this_context` is the instance itself, which will be bound to `this` within the function bodies
+    /// - `membership_changed`: A function of the form
+    ///   `(conversation_id: ConversationId, changes: MembershipChanges) -> Promise<void>`.
+    ///   Called every time a merged commit changes the members of a conversation.
+    #[wasm_bindgen(constructor)]
+    pub fn new(this_context: JsValue, membership_changed: js_sys::Function) -> CoreCryptoResult<Self> {
+        // we can't do much type-checking here unfortunately, but we can at least validate that the incoming functions have the right length
+        if membership_changed.length() != 2 {
+            return Err(CoreCryptoError::ad_hoc(format!(
+                "`membership_changed` must accept 2 arguments but accepts {}",
+                membership_changed.length()
+            )));
+        }
+        Ok(Self {
+            this_context,
+            membership_changed,
+        })
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl MembershipObserver {
+    /// Call the JS `membership_changed` function
+    ///
+    /// This blocks if the JS side of things blocks.
+    ///
+    /// This is extracted as its own function instead of being implemented inline within the
+    /// `impl MembershipObserver for MembershipObserver` block mostly to consolidate error-handling.
+    async fn membership_changed(
+        &self,
+        conversation_id: ConversationId,
+        changes: MembershipChanges,
+    ) -> Result<(), JsValue> {
+        let promise = self
+            .membership_changed
+            .call2(&self.this_context, &conversation_id.into(), &changes.into())?
+            .dyn_into::<Promise>()?;
+        // we don't actually care what the result of executing the notification promise is; we'll ignore it if it exists
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[async_trait(?Send)]
+impl core_crypto::mls::MembershipObserver for MembershipObserver {
+    async fn membership_changed(
+        &self,
+        conversation_id: InternalConversationId,
+        changes: core_crypto::prelude::MembershipChanges,
+    ) {
+        if let Err(err) = self
+            .membership_changed(conversation_id_coerce_maybe_arc(&conversation_id), changes.into())
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                conversation_id = Obfuscated::from(&conversation_id),
+                err = LoggableJsValue(err);
+                "caught an error when attempting to notify the membership observer of a membership change"
+            );
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CoreCryptoFfi {
+    /// Add a membership observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a client's lifetime.
+    /// If called when a membership observer already exists, this will return an error.
+    pub async fn register_membership_observer(&self, membership_observer: MembershipObserver) -> CoreCryptoResult<()> {
+        self.inner
+            .register_membership_observer(Arc::new(membership_observer))
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+struct LoggableJsValue(JsValue);
+
+#[cfg(target_family = "wasm")]
+impl kv::ToValue for LoggableJsValue {
+    fn to_value(&self) -> kv::Value<'_> {
+        // can't get a borrowed str from `JsValue`, so can't directly
+        // convert into a string; oh well; fallback should catch it
+        if let Some(f) = self.0.as_f64() {
+            return f.into();
+        }
+        if let Some(b) = self.0.as_bool() {
+            return b.into();
+        }
+        if self.0.is_null() || self.0.is_undefined() {
+            return kv::Value::null();
+        }
+        kv::Value::from_debug(&self.0)
+    }
+}
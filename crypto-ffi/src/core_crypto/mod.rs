@@ -1,20 +1,24 @@
+pub(crate) mod buffer_observer;
 mod client;
 pub(crate) mod command;
 pub(crate) mod conversation;
 pub(crate) mod e2ei;
+pub(crate) mod e2ei_status_observer;
 pub(crate) mod epoch_observer;
 pub(crate) mod history_observer;
 pub(crate) mod logger;
+pub(crate) mod membership_observer;
 pub(crate) mod mls_transport;
 mod proteus;
 mod randomness;
+pub(crate) mod reinit_observer;
 
 use core_crypto::prelude::{Session, SessionConfig, ValidatedSessionConfig};
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    CoreCryptoError, CoreCryptoResult,
+    CoreCryptoError, CoreCryptoResult, CustomCapabilities,
     ciphersuite::Ciphersuites,
     client_id::ClientIdMaybeArc,
     database::{DatabaseKeyMaybeArc, ToCc as _},
@@ -52,7 +56,7 @@ pub struct CoreCryptoFfi {
 ///
 /// See [Session::try_new]
 #[cfg(not(target_family = "wasm"))]
-#[uniffi::export]
+#[uniffi::export(default(custom_capabilities = None))]
 pub async fn core_crypto_new(
     path: String,
     key: DatabaseKeyMaybeArc,
@@ -60,6 +64,7 @@ pub async fn core_crypto_new(
     ciphersuites: Ciphersuites,
     entropy_seed: Option<EntropySeed>,
     nb_key_package: Option<u32>,
+    custom_capabilities: Option<CustomCapabilities>,
 ) -> CoreCryptoResult<CoreCryptoFfi> {
     CoreCryptoFfi::new(
         path,
@@ -68,6 +73,7 @@ pub async fn core_crypto_new(
         Some(ciphersuites),
         entropy_seed,
         nb_key_package,
+        custom_capabilities,
     )
     .await
 }
@@ -77,13 +83,14 @@ pub async fn core_crypto_new(
 /// Similar to [`core_crypto_new`] but defers MLS initialization. It can be initialized later
 /// with [core_crypto::transaction_context::TransactionContext::mls_init].
 #[cfg(not(target_family = "wasm"))]
-#[uniffi::export]
+#[uniffi::export(default(custom_capabilities = None))]
 pub async fn core_crypto_deferred_init(
     path: String,
     key: DatabaseKeyMaybeArc,
     entropy_seed: Option<EntropySeed>,
+    custom_capabilities: Option<CustomCapabilities>,
 ) -> CoreCryptoResult<CoreCryptoFfi> {
-    CoreCryptoFfi::deferred_init_impl(path, key, entropy_seed).await
+    CoreCryptoFfi::deferred_init_impl(path, key, entropy_seed, custom_capabilities).await
 }
 
 impl CoreCryptoFfi {
@@ -95,6 +102,7 @@ impl CoreCryptoFfi {
         ciphersuites: Option<Ciphersuites>,
         entropy_seed: Option<EntropySeed>,
         nb_key_packages: Option<u32>,
+        custom_capabilities: Option<CustomCapabilities>,
     ) -> CoreCryptoResult<Self> {
         let nb_key_packages = nb_key_packages
             .map(usize::try_from)
@@ -108,6 +116,7 @@ impl CoreCryptoFfi {
             .ciphersuites(ciphersuites.unwrap_or_default().into_iter().map(Into::into))
             .external_entropy_opt(entropy_seed.as_deref())
             .nb_key_packages(nb_key_packages)
+            .custom_capabilities(custom_capabilities.map(Into::into).unwrap_or_default())
             .build()
             .validate()?;
         Self::from_config(configuration).await
@@ -117,12 +126,14 @@ impl CoreCryptoFfi {
         path: String,
         key: DatabaseKeyMaybeArc,
         entropy_seed: Option<EntropySeed>,
+        custom_capabilities: Option<CustomCapabilities>,
     ) -> CoreCryptoResult<Self> {
         let entropy_seed = entropy_seed.map(entropy_seed_map);
         let configuration = SessionConfig::builder()
             .persistent(&path)
             .database_key(key.to_cc())
             .external_entropy_opt(entropy_seed.as_deref())
+            .custom_capabilities(custom_capabilities.map(Into::into).unwrap_or_default())
             .build()
             .validate()?;
         CoreCryptoFfi::from_config(configuration).await
@@ -150,8 +161,18 @@ impl CoreCryptoFfi {
         ciphersuites: Option<Ciphersuites>,
         entropy_seed: Option<EntropySeed>,
         nb_key_package: Option<u32>,
+        custom_capabilities: Option<CustomCapabilities>,
     ) -> CoreCryptoResult<Self> {
-        Self::new(path, key, client_id, ciphersuites, entropy_seed, nb_key_package).await
+        Self::new(
+            path,
+            key,
+            client_id,
+            ciphersuites,
+            entropy_seed,
+            nb_key_package,
+            custom_capabilities,
+        )
+        .await
     }
 
     /// Asynchronously instantiate CC, deferring MLS initialization. MLS can be initialized later
@@ -160,8 +181,9 @@ impl CoreCryptoFfi {
         path: String,
         key: DatabaseKeyMaybeArc,
         entropy_seed: Option<Box<[u8]>>,
+        custom_capabilities: Option<CustomCapabilities>,
     ) -> CoreCryptoResult<CoreCryptoFfi> {
-        CoreCryptoFfi::deferred_init_impl(path, key, entropy_seed).await
+        CoreCryptoFfi::deferred_init_impl(path, key, entropy_seed, custom_capabilities).await
     }
 
     /// See [Session::close]
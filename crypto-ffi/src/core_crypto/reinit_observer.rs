@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+#[cfg(target_family = "wasm")]
+use js_sys::Promise;
+#[cfg(target_family = "wasm")]
+use log::kv;
+use std::sync::Arc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(target_family = "wasm")]
+use crate::ConversationId;
+#[cfg(not(target_family = "wasm"))]
+use crate::ConversationIdMaybeArc;
+use crate::{CoreCryptoError, CoreCryptoFfi, CoreCryptoResult, conversation_id_coerce_maybe_arc};
+use ::core_crypto::prelude::ConversationId as InternalConversationId;
+use obfuscate::Obfuscated;
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum ReInitReportingError {
+    #[error("panic or otherwise unexpected error from foreign code")]
+    Ffi(#[from] uniffi::UnexpectedUniFFICallbackError),
+}
+
+/// A `ReInitObserver` is notified when the migration of a conversation to a new one, e.g. with another
+/// ciphersuite, has finished.
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export(with_foreign)]
+#[async_trait]
+pub trait ReInitObserver: Send + Sync {
+    /// This function will be called once we are a member of the conversation replacing a reinitialized
+    /// one: when we created it, or when we joined it from its welcome message.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this interface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    ///
+    /// Though the signature includes an error type, that error is only present because
+    /// it is required by `uniffi` in order to handle panics. This function should suppress
+    /// and ignore internal errors instead of propagating them, to the maximum extent possible.
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: ConversationIdMaybeArc,
+        new_conversation_id: ConversationIdMaybeArc,
+    ) -> Result<(), ReInitReportingError>;
+}
+
+/// This shim bridges the public `ReInitObserver` interface with the internal one defined by `core-crypto`.
+///
+/// The orphan rule prevents us from just tying the two traits together directly.
+#[cfg(not(target_family = "wasm"))]
+struct ObserverShim(Arc<dyn ReInitObserver>);
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl core_crypto::mls::ReInitObserver for ObserverShim {
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: InternalConversationId,
+        new_conversation_id: InternalConversationId,
+    ) {
+        if let Err(err) = self
+            .0
+            .conversation_reinitialized(
+                conversation_id_coerce_maybe_arc(&old_conversation_id),
+                conversation_id_coerce_maybe_arc(&new_conversation_id),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                old_conversation_id = Obfuscated::from(&old_conversation_id),
+                new_conversation_id = Obfuscated::from(&new_conversation_id),
+                err = log::kv::Value::from_dyn_error(&err);
+                "caught an error when attempting to notify the reinit observer of a reinitialized conversation"
+            );
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[uniffi::export]
+impl CoreCryptoFfi {
+    /// Add a reinit observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a reinit observer already exists, this will return an error.
+    pub async fn register_reinit_observer(&self, reinit_observer: Arc<dyn ReInitObserver>) -> CoreCryptoResult<()> {
+        let shim = Arc::new(ObserverShim(reinit_observer));
+        self.inner
+            .register_reinit_observer(shim)
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+/// A `ReInitObserver` is notified when the migration of a conversation to a new one, e.g. with another
+/// ciphersuite, has finished.
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+#[derive(derive_more::Debug)]
+#[debug("ReInitObserver")]
+pub struct ReInitObserver {
+    this_context: JsValue,
+    conversation_reinitialized: js_sys::Function,
+}
+
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Send for ReInitObserver {}
+#[cfg(target_family = "wasm")]
+// SAFETY: we promise that we're only ever using this in a single-threaded context
+unsafe impl Sync for ReInitObserver {}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl ReInitObserver {
+    /// Create a new ReInit Observer.
+    ///
+    /// This function should be hidden on the JS side of things! The JS bindings should have an `interface ReInitObserver`
+    /// which has the method defined, and the bindings themselves should destructure an instance implementing that
+    /// interface appropriately to construct this.
+    ///
+    /// - `this_context` is the instance itself, which will be bound to `this` within the function bodies
+    /// - `conversation_reinitialized`: A function of the form
+    ///   `(old_conversation_id: Uint8Array, new_conversation_id: Uint8Array) -> Promise<void>`.
+    ///   Called once we are a member of the conversation replacing a reinitialized one.
+    #[wasm_bindgen(constructor)]
+    pub fn new(this_context: JsValue, conversation_reinitialized: js_sys::Function) -> CoreCryptoResult<Self> {
+        // we can't do much type-checking here unfortunately, but we can at least validate that the incoming functions have the right length
+        if conversation_reinitialized.length() != 2 {
+            return Err(CoreCryptoError::ad_hoc(format!(
+                "`conversation_reinitialized` must accept 2 arguments but accepts {}",
+                conversation_reinitialized.length()
+            )));
+        }
+        Ok(Self {
+            this_context,
+            conversation_reinitialized,
+        })
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl ReInitObserver {
+    /// Call the JS `conversation_reinitialized` function
+    ///
+    /// This blocks if the JS side of things blocks.
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: ConversationId,
+        new_conversation_id: ConversationId,
+    ) -> Result<(), JsValue> {
+        let promise = self
+            .conversation_reinitialized
+            .call2(
+                &self.this_context,
+                &old_conversation_id.into(),
+                &new_conversation_id.into(),
+            )?
+            .dyn_into::<Promise>()?;
+        // we don't actually care what the result of executing the notification promise is; we'll ignore it if it exists
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[async_trait(?Send)]
+impl core_crypto::mls::ReInitObserver for ReInitObserver {
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: InternalConversationId,
+        new_conversation_id: InternalConversationId,
+    ) {
+        if let Err(err) = self
+            .conversation_reinitialized(
+                conversation_id_coerce_maybe_arc(&old_conversation_id),
+                conversation_id_coerce_maybe_arc(&new_conversation_id),
+            )
+            .await
+        {
+            // we don't _care_ if an error is thrown by the notification function, per se,
+            // but this would probably be useful information for downstream debugging efforts
+            log::warn!(
+                old_conversation_id = Obfuscated::from(&old_conversation_id),
+                new_conversation_id = Obfuscated::from(&new_conversation_id),
+                err = LoggableJsValue(err);
+                "caught an error when attempting to notify the reinit observer of a reinitialized conversation"
+            );
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CoreCryptoFfi {
+    /// Add a reinit observer to this client.
+    ///
+    /// This function should be called 0 or 1 times in a client's lifetime.
+    /// If called when a reinit observer already exists, this will return an error.
+    pub async fn register_reinit_observer(&self, reinit_observer: ReInitObserver) -> CoreCryptoResult<()> {
+        self.inner
+            .register_reinit_observer(Arc::new(reinit_observer))
+            .await
+            .map_err(CoreCryptoError::generic())
+    }
+}
+
+#[cfg(target_family = "wasm")]
+struct LoggableJsValue(JsValue);
+
+#[cfg(target_family = "wasm")]
+impl kv::ToValue for LoggableJsValue {
+    fn to_value(&self) -> kv::Value<'_> {
+        // can't get a borrowed str from `JsValue`, so can't directly
+        // convert into a string; oh well; fallback should catch it
+        if let Some(f) = self.0.as_f64() {
+            return f.into();
+        }
+        if let Some(b) = self.0.as_bool() {
+            return b.into();
+        }
+        if self.0.is_null() || self.0.is_undefined() {
+            return kv::Value::null();
+        }
+        kv::Value::from_debug(&self.0)
+    }
+}
@@ -108,6 +108,15 @@ impl CoreCryptoContext {
             .map_err(Into::into)
     }
 
+    /// See [core_crypto::transaction_context::TransactionContext::e2ei_reevaluate_status]
+    pub async fn e2ei_reevaluate_status(&self, at: u64) -> CoreCryptoResult<()> {
+        self.inner
+            .e2ei_reevaluate_status(at)
+            .await
+            .map_err(Into::<TransactionError>::into)
+            .map_err(Into::into)
+    }
+
     /// See [core_crypto::transaction_context::TransactionContext::e2ei_mls_init_only]
     pub async fn e2ei_mls_init_only(
         &self,
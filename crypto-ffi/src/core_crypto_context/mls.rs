@@ -10,8 +10,8 @@ use wasm_bindgen::prelude::*;
 
 use crate::{
     Ciphersuite, ClientId, ConversationConfiguration, ConversationId, CoreCryptoContext, CoreCryptoError,
-    CoreCryptoResult, CredentialType, CustomConfiguration, DecryptedMessage, WelcomeBundle,
-    bytes_wrapper::bytes_wrapper, ciphersuite::Ciphersuites, client_id::ClientIdMaybeArc,
+    CoreCryptoResult, CredentialType, CustomConfiguration, CustomExtension, DecryptedMessage, KeyPackageInventoryEntry,
+    WelcomeBundle, WirePolicy, bytes_wrapper::bytes_wrapper, ciphersuite::Ciphersuites, client_id::ClientIdMaybeArc,
     crl::NewCrlDistributionPoints,
 };
 
@@ -103,6 +103,27 @@ impl CoreCryptoContext {
         Ok(Ciphersuite::from(core_crypto::prelude::CiphersuiteName::from(cs)))
     }
 
+    /// See [core_crypto::mls::conversation::Conversation::wire_policy]
+    pub async fn conversation_wire_policy(&self, conversation_id: &ConversationId) -> CoreCryptoResult<WirePolicy> {
+        let wire_policy = self.inner.conversation(conversation_id).await?.wire_policy().await?;
+        Ok(wire_policy.into())
+    }
+
+    /// See [core_crypto::mls::conversation::Conversation::custom_extensions]
+    pub async fn conversation_custom_extensions(
+        &self,
+        conversation_id: &ConversationId,
+    ) -> CoreCryptoResult<Vec<CustomExtension>> {
+        let conversation = self.inner.conversation(conversation_id).await?;
+        let custom_extensions = conversation
+            .custom_extensions()
+            .await
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        Ok(custom_extensions)
+    }
+
     /// See [core_crypto::prelude::Session::conversation_exists]
     pub async fn conversation_exists(&self, conversation_id: &ConversationId) -> CoreCryptoResult<bool> {
         self.inner
@@ -137,6 +158,22 @@ impl CoreCryptoContext {
             .map_err(Into::into)
     }
 
+    /// See [core_crypto::mls::conversation::Conversation::export_secret_key_with_label]
+    pub async fn export_secret_key_with_label(
+        &self,
+        conversation_id: &ConversationId,
+        label: String,
+        context: Vec<u8>,
+        key_length: u32,
+    ) -> CoreCryptoResult<SecretKey> {
+        let conversation = self.inner.conversation(conversation_id).await?;
+        conversation
+            .export_secret_key_with_label(&label, &context, key_length as usize)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
     /// See [core_crypto::mls::conversation::Conversation::get_external_sender]
     pub async fn get_external_sender(&self, conversation_id: &ConversationId) -> CoreCryptoResult<ExternalSenderKey> {
         let conversation = self.inner.conversation(conversation_id).await?;
@@ -186,6 +223,85 @@ impl CoreCryptoContext {
         Ok(count.try_into().unwrap_or(0))
     }
 
+    /// See [core_crypto::transaction_context::TransactionContext::set_key_package_lifetime]
+    pub async fn set_key_package_lifetime(
+        &self,
+        ciphersuite: Ciphersuite,
+        credential_type: CredentialType,
+        lifetime_secs: u64,
+    ) -> CoreCryptoResult<()> {
+        self.inner
+            .set_key_package_lifetime(
+                ciphersuite.into(),
+                credential_type.into(),
+                std::time::Duration::from_secs(lifetime_secs),
+            )
+            .await
+            .map_err(RecursiveError::transaction("setting keypackage lifetime"))?;
+        Ok(())
+    }
+
+    /// See [core_crypto::transaction_context::TransactionContext::key_package_inventory]
+    pub async fn key_package_inventory(
+        &self,
+        expiring_within_secs: u64,
+    ) -> CoreCryptoResult<Vec<KeyPackageInventoryEntry>> {
+        let inventory = self
+            .inner
+            .key_package_inventory(std::time::Duration::from_secs(expiring_within_secs))
+            .await
+            .map_err(RecursiveError::transaction("taking key package inventory"))?;
+
+        Ok(inventory.into_iter().map(Into::into).collect())
+    }
+
+    /// See [core_crypto::transaction_context::TransactionContext::mark_raw_key_packages_uploaded]
+    pub async fn mark_key_packages_uploaded(&self, key_packages: Vec<KeyPackageMaybeArc>) -> CoreCryptoResult<()> {
+        self.inner
+            .mark_raw_key_packages_uploaded(key_packages.iter().map(|kp| kp.as_slice()))
+            .await
+            .map_err(RecursiveError::transaction("marking key packages as uploaded"))?;
+        Ok(())
+    }
+
+    /// See [core_crypto::transaction_context::TransactionContext::last_resort_key_package]
+    pub async fn last_resort_key_package(
+        &self,
+        ciphersuite: Ciphersuite,
+        credential_type: CredentialType,
+    ) -> CoreCryptoResult<KeyPackageMaybeArc> {
+        let kp = self
+            .inner
+            .last_resort_key_package(ciphersuite.into(), credential_type.into())
+            .await
+            .map_err(RecursiveError::transaction("getting last resort keypackage"))?;
+
+        kp.tls_serialize_detached()
+            .map(key_package_coerce_maybe_arc)
+            .map_err(core_crypto::mls::conversation::Error::tls_serialize("keypackage"))
+            .map_err(RecursiveError::mls_conversation("serializing keypackage"))
+            .map_err(Into::into)
+    }
+
+    /// See [core_crypto::transaction_context::TransactionContext::rotate_last_resort_key_package]
+    pub async fn rotate_last_resort_key_package(
+        &self,
+        ciphersuite: Ciphersuite,
+        credential_type: CredentialType,
+    ) -> CoreCryptoResult<KeyPackageMaybeArc> {
+        let kp = self
+            .inner
+            .rotate_last_resort_key_package(ciphersuite.into(), credential_type.into())
+            .await
+            .map_err(RecursiveError::transaction("rotating last resort keypackage"))?;
+
+        kp.tls_serialize_detached()
+            .map(key_package_coerce_maybe_arc)
+            .map_err(core_crypto::mls::conversation::Error::tls_serialize("keypackage"))
+            .map_err(RecursiveError::mls_conversation("serializing keypackage"))
+            .map_err(Into::into)
+    }
+
     /// See [core_crypto::transaction_context::TransactionContext::new_conversation]
     pub async fn create_conversation(
         &self,
@@ -283,6 +399,19 @@ impl CoreCryptoContext {
         conversation.commit_pending_proposals().await.map_err(Into::into)
     }
 
+    /// See [core_crypto::mls::conversation::ConversationGuard::update_custom_extensions]
+    pub async fn update_custom_extensions(
+        &self,
+        conversation_id: &ConversationId,
+        custom_extensions: Vec<CustomExtension>,
+    ) -> CoreCryptoResult<()> {
+        let mut conversation = self.inner.conversation(conversation_id).await?;
+        conversation
+            .update_custom_extensions(custom_extensions.into_iter().map(Into::into).collect())
+            .await
+            .map_err(Into::into)
+    }
+
     /// See [core_crypto::mls::conversation::ConversationGuard::wipe]
     pub async fn wipe_conversation(&self, conversation_id: &ConversationId) -> CoreCryptoResult<()> {
         let mut conversation = self.inner.conversation(conversation_id).await?;
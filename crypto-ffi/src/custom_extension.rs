@@ -0,0 +1,86 @@
+use core_crypto::prelude::{MlsCustomCapabilities, MlsCustomExtension};
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// See [core_crypto::prelude::MlsCustomExtension]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct CustomExtension {
+    /// Type of the extension, in the range reserved for private use (`0xF000..=0xFFFF`)
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "extensionType"))]
+    pub extension_type: u16,
+    /// Payload of the extension, opaque to CoreCrypto
+    pub data: Vec<u8>,
+}
+
+impl From<CustomExtension> for MlsCustomExtension {
+    fn from(extension: CustomExtension) -> Self {
+        Self {
+            extension_type: extension.extension_type,
+            data: extension.data,
+        }
+    }
+}
+
+impl From<MlsCustomExtension> for CustomExtension {
+    fn from(extension: MlsCustomExtension) -> Self {
+        Self {
+            extension_type: extension.extension_type,
+            data: extension.data,
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CustomExtension {
+    /// Construct a `CustomExtension` from its parts.
+    #[wasm_bindgen(constructor)]
+    pub fn new(extension_type: u16, data: Vec<u8>) -> Self {
+        Self { extension_type, data }
+    }
+}
+
+/// See [core_crypto::prelude::MlsCustomCapabilities]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    target_family = "wasm",
+    wasm_bindgen(getter_with_clone),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct CustomCapabilities {
+    /// Extension types, in the range reserved for private use (`0xF000..=0xFFFF`)
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "extensionTypes"))]
+    pub extension_types: Vec<u16>,
+    /// Proposal types, in the range reserved for private use (`0xF000..=0xFFFF`)
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "proposalTypes"))]
+    pub proposal_types: Vec<u16>,
+}
+
+impl From<CustomCapabilities> for MlsCustomCapabilities {
+    fn from(capabilities: CustomCapabilities) -> Self {
+        Self {
+            extension_types: capabilities.extension_types,
+            proposal_types: capabilities.proposal_types,
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl CustomCapabilities {
+    /// Construct a `CustomCapabilities` from its parts.
+    #[wasm_bindgen(constructor)]
+    pub fn new(extension_types: Vec<u16>, proposal_types: Vec<u16>) -> Self {
+        Self {
+            extension_types,
+            proposal_types,
+        }
+    }
+}
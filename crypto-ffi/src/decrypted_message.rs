@@ -6,7 +6,7 @@ use core_crypto::prelude::{MlsBufferedConversationDecryptMessage, MlsConversatio
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    ClientId, CoreCryptoError, CoreCryptoResult, WireIdentity, client_id::ClientIdMaybeArc,
+    ClientId, CoreCryptoError, CoreCryptoResult, CustomExtension, WireIdentity, client_id::ClientIdMaybeArc,
     crl::NewCrlDistributionPoints,
 };
 
@@ -52,6 +52,11 @@ pub struct DecryptedMessage {
     /// New CRL distribution points that appeared by the introduction of a new credential
     #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = crlNewDistributionPoints))]
     pub crl_new_distribution_points: NewCrlDistributionPoints,
+    /// Only set when the decrypted message is a commit replacing the group context extensions.
+    ///
+    /// Contains the application-defined extensions of the new group context.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = customExtensions))]
+    pub custom_extensions: Option<Vec<CustomExtension>>,
 }
 
 impl TryFrom<MlsConversationDecryptMessage> for DecryptedMessage {
@@ -77,6 +82,9 @@ impl TryFrom<MlsConversationDecryptMessage> for DecryptedMessage {
             identity: from.identity.into(),
             buffered_messages,
             crl_new_distribution_points: from.crl_new_distribution_points.into(),
+            custom_extensions: from
+                .custom_extensions
+                .map(|extensions| extensions.into_iter().map(Into::into).collect()),
         })
     }
 }
@@ -117,6 +125,11 @@ pub struct BufferedDecryptedMessage {
     /// New CRL distribution points that appeared by the introduction of a new credential
     #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = crlNewDistributionPoints))]
     pub crl_new_distribution_points: NewCrlDistributionPoints,
+    /// Only set when the decrypted message is a commit replacing the group context extensions.
+    ///
+    /// Contains the application-defined extensions of the new group context.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(readonly, js_name = customExtensions))]
+    pub custom_extensions: Option<Vec<CustomExtension>>,
 }
 
 impl TryFrom<MlsBufferedConversationDecryptMessage> for BufferedDecryptedMessage {
@@ -132,6 +145,9 @@ impl TryFrom<MlsBufferedConversationDecryptMessage> for BufferedDecryptedMessage
             has_epoch_changed: from.has_epoch_changed,
             identity: from.identity.into(),
             crl_new_distribution_points: from.crl_new_distribution_points.into(),
+            custom_extensions: from
+                .custom_extensions
+                .map(|extensions| extensions.into_iter().map(Into::into).collect()),
         })
     }
 }
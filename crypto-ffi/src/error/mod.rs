@@ -102,7 +102,7 @@ mod tests {
         // we shouldn't be able to create a SQLite DB in `/root` unless we are running this test as root
         // Don't do that!
         let key = crate::DatabaseKey::from_cc(core_crypto_keystore::DatabaseKey::generate());
-        let result = CoreCryptoFfi::new("/root/asdf".into(), key, None, None, None, None).await;
+        let result = CoreCryptoFfi::new("/root/asdf".into(), key, None, None, None, None, None).await;
         assert!(
             result.is_err(),
             "result must be an error in order to verify that something was logged"
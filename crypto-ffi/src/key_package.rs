@@ -0,0 +1,59 @@
+use core_crypto::prelude::{MlsKeyPackageExpiry, MlsKeyPackageInventoryEntry};
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Ciphersuite, CredentialType};
+
+/// How close to their expiry key packages are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Enum))]
+#[repr(u8)]
+pub enum KeyPackageExpiry {
+    /// The key packages have expired, they will be pruned
+    Expired = 1,
+    /// The key packages expire within the requested span
+    ExpiringSoon = 2,
+    /// The key packages are valid for longer than the requested span, or have no lifetime
+    Valid = 3,
+}
+
+impl From<MlsKeyPackageExpiry> for KeyPackageExpiry {
+    fn from(value: MlsKeyPackageExpiry) -> Self {
+        match value {
+            MlsKeyPackageExpiry::Expired => Self::Expired,
+            MlsKeyPackageExpiry::ExpiringSoon => Self::ExpiringSoon,
+            MlsKeyPackageExpiry::Valid => Self::Valid,
+        }
+    }
+}
+
+/// How many key packages of a ciphersuite and credential type are in the keystore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen, derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(target_family = "wasm"), derive(uniffi::Record))]
+pub struct KeyPackageInventoryEntry {
+    /// Ciphersuite of the key packages
+    pub ciphersuite: Ciphersuite,
+    /// Credential type of the key packages
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = "credentialType"))]
+    pub credential_type: CredentialType,
+    /// How close to their expiry the key packages are
+    pub expiry: KeyPackageExpiry,
+    /// Number of key packages
+    pub count: u64,
+    /// Number of key packages marked as uploaded
+    pub uploaded: u64,
+}
+
+impl From<MlsKeyPackageInventoryEntry> for KeyPackageInventoryEntry {
+    fn from(value: MlsKeyPackageInventoryEntry) -> Self {
+        Self {
+            ciphersuite: value.ciphersuite.into(),
+            credential_type: value.credential_type.into(),
+            expiry: value.expiry.into(),
+            count: value.count.try_into().unwrap_or(u64::MAX),
+            uploaded: value.uploaded.try_into().unwrap_or(u64::MAX),
+        }
+    }
+}
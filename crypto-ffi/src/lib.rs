@@ -18,12 +18,14 @@ mod core_crypto;
 mod core_crypto_context;
 mod credential_type;
 mod crl;
+mod custom_extension;
 mod database;
 mod decrypted_message;
 mod e2ei;
 mod ephemeral;
 mod error;
 mod identity;
+mod key_package;
 mod metadata;
 mod proteus;
 
@@ -33,22 +35,30 @@ pub use bundles::{
 };
 pub use ciphersuite::{Ciphersuite, ciphersuite_default, ciphersuite_from_u16};
 pub use client_id::ClientId;
-pub use configuration::{ConversationConfiguration, CustomConfiguration, WirePolicy};
+pub use configuration::{
+    AdmissionAction, AdmissionPolicy, BufferConfiguration, ConversationConfiguration, CustomConfiguration,
+    ExporterSecretHistoryConfiguration, WirePolicy,
+};
 pub use core_crypto::conversation::ConversationId;
 pub(crate) use core_crypto::conversation::{ConversationIdMaybeArc, conversation_id_coerce_maybe_arc};
 pub(crate) use core_crypto::e2ei::identities::UserIdentities;
 pub use core_crypto::{
     CoreCryptoFfi,
+    buffer_observer::{BufferObserver, BufferedItem, BufferedItemKind, EvictionReason},
     command::CoreCryptoCommand,
+    e2ei_status_observer::E2eiStatusObserver,
     epoch_observer::EpochObserver,
     logger::{CoreCryptoLogLevel, CoreCryptoLogger, set_logger, set_max_log_level},
+    membership_observer::{MemberCredentialUpdate, MembershipChanges, MembershipObserver},
     mls_transport::{MlsTransport, MlsTransportData, MlsTransportResponse},
+    reinit_observer::ReInitObserver,
 };
 #[cfg(not(target_family = "wasm"))]
 pub use core_crypto::{command::transaction_helper::TransactionHelper, core_crypto_deferred_init, core_crypto_new};
 pub use core_crypto_context::CoreCryptoContext;
 pub use credential_type::CredentialType;
 pub use crl::CrlRegistration;
+pub use custom_extension::{CustomCapabilities, CustomExtension};
 pub use database::{Database, DatabaseKey, migrate_database_key_type_to_bytes, open_database, update_database_key};
 pub use decrypted_message::{BufferedDecryptedMessage, DecryptedMessage};
 pub use e2ei::{
@@ -65,4 +75,5 @@ pub use identity::{
     wire::{DeviceStatus, WireIdentity},
     x509::X509Identity,
 };
+pub use key_package::{KeyPackageExpiry, KeyPackageInventoryEntry};
 pub use metadata::{BuildMetadata, build_metadata, version};
@@ -20,11 +20,17 @@ impl KeyStoreEntityFlattened {
         let Self {
             collection_name,
             struct_name,
+            blob_column_names,
             ..
         } = self;
 
         // Identical for both wasm and non-wasm
         quote! {
+            impl #struct_name {
+                /// Fields encrypted in IndexedDB, i.e. the non-optional blob columns
+                pub(crate) const ENCRYPTED_FIELDS: &'static [&'static str] = &[#(#blob_column_names),*];
+            }
+
             #[cfg_attr(target_family = "wasm", async_trait::async_trait(?Send))]
             #[cfg_attr(not(target_family = "wasm"), async_trait::async_trait)]
             impl crate::entities::EntityBase for #struct_name {
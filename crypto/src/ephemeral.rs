@@ -41,6 +41,19 @@ pub struct HistorySecret {
     /// Client id of the associated history client
     pub client_id: ClientId,
     pub(crate) key_package: KeyPackageSecretEncapsulation,
+    /// The era started by adding the history client, once it has been added
+    #[serde(default)]
+    pub(crate) era: Option<HistoryEra>,
+}
+
+/// The history-sharing era of a [HistorySecret]. It travels along with the secret, so that any member
+/// receiving it can archive it, see [crate::history_archive].
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct HistoryEra {
+    /// Epoch starting the era, i.e. the one the history client joined in
+    pub(crate) epoch: u64,
+    /// TLS-serialized welcome adding the history client to the conversation
+    pub(crate) welcome: Vec<u8>,
 }
 
 impl Obfuscate for HistorySecret {
@@ -48,6 +61,7 @@ impl Obfuscate for HistorySecret {
         f.debug_struct("HistorySecret")
             .field("client_id", &self.client_id)
             .field("key_package", &Obfuscated::from(&self.key_package))
+            .field("era", &self.era.as_ref().map(|era| era.epoch))
             .finish()
     }
 }
@@ -112,7 +126,11 @@ pub(crate) async fn generate_history_secret(ciphersuite: MlsCiphersuite) -> Resu
 
     // we don't need to finish the transaction here--the point of the ephemeral CC was that no mutations would be saved there
 
-    Ok(HistorySecret { client_id, key_package })
+    Ok(HistorySecret {
+        client_id,
+        key_package,
+        era: None,
+    })
 }
 
 pub(crate) fn is_history_client(client_id: &ClientId) -> bool {
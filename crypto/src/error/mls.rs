@@ -103,6 +103,15 @@ pub enum MlsErrorKind {
     MlsUpdateExtensionsError(
         #[from] openmls::prelude::UpdateExtensionsError<core_crypto_keystore::CryptoKeystoreError>,
     ),
+    /// OpenMLS proposal error
+    #[error(transparent)]
+    MlsProposalError(#[from] openmls::prelude::ProposalError<core_crypto_keystore::CryptoKeystoreError>),
+    /// OpenMLS pre-shared key error
+    #[error(transparent)]
+    MlsPskError(#[from] openmls::prelude::PskError),
+    /// OpenMLS invalid extension error
+    #[error(transparent)]
+    MlsInvalidExtensionError(#[from] openmls::prelude::InvalidExtensionError),
     /// OpenMLS LeafNode validation error
     #[error(transparent)]
     MlsLeafNodeValidationError(#[from] openmls::prelude::LeafNodeValidationError),
@@ -38,6 +38,9 @@ pub enum Error {
     /// Invalid history secret
     #[error("Invalid history secret: {0}")]
     InvalidHistorySecret(&'static str),
+    /// No history secret was archived for the era of a message of this epoch
+    #[error("No history secret was archived for the era of epoch {0}")]
+    HistorySecretNotArchived(u64),
     /// An external MLS operation failed
     #[error(transparent)]
     Mls(#[from] MlsError),
@@ -37,7 +37,7 @@ impl GroupStoreEntity for MlsConversation {
             return Ok(None);
         };
 
-        let conversation = Self::from_serialized_state(store_value.state.clone(), store_value.parent_id.clone())
+        let conversation = Self::from_serialized_state(&store_value)
             .map_err(RecursiveError::mls_conversation("deserializing mls conversation"))?;
         // If the conversation is not active, pretend it doesn't exist
         Ok(conversation.group.is_active().then_some(conversation))
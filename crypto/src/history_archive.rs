@@ -0,0 +1,309 @@
+//! Archive of the history secrets of conversations, see [crate::ephemeral].
+//!
+//! A history-enabled conversation goes through many history-sharing eras, each with its own history
+//! client. Archiving the secret of every era, along with the welcome adding its history client to the
+//! conversation, lets a new device instantiate the history client of each era and decrypt the whole
+//! backlog of the conversation.
+//!
+//! The archive lives in the keystore, hence is encrypted at rest like any other key material.
+
+use std::collections::{BTreeMap, btree_map::Entry};
+
+use core_crypto_keystore::entities::MlsHistorySecret;
+use mls_crypto_provider::CryptoKeystore;
+use openmls::prelude::{MlsMessageIn, MlsMessageInBody};
+use tls_codec::Deserialize as _;
+
+use crate::{
+    CoreCrypto, Error, KeystoreError, MlsError, RecursiveError, Result,
+    mls::conversation::{Conversation as _, ConversationWithMls as _},
+    prelude::{ClientId, ConversationId, HistorySecret, MlsCustomConfiguration},
+    transaction_context::{self, TransactionContext},
+};
+
+/// The secret of a history-sharing era of a conversation, see [TransactionContext::history_secret_archive]
+pub struct ArchivedHistorySecret {
+    /// Epoch starting the era, i.e. the one the history client joined in
+    pub epoch: u64,
+    /// Secret of the history client of the era
+    pub secret: HistorySecret,
+    /// TLS-serialized welcome adding the history client to the conversation
+    pub welcome: Vec<u8>,
+}
+
+impl ArchivedHistorySecret {
+    fn from_entity(entity: &MlsHistorySecret) -> Result<Self> {
+        let epoch = entity.epoch().ok_or(Error::InvalidHistorySecret(
+            "malformed epoch of an archived history secret",
+        ))?;
+        let secret = serde_json::from_slice(&entity.secret).map_err(MlsError::wrap("deserializing history secret"))?;
+        Ok(Self {
+            epoch,
+            secret,
+            welcome: entity.welcome.clone(),
+        })
+    }
+}
+
+/// A message of the backlog of a conversation, see [TransactionContext::replay_history]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayedMessage {
+    /// Epoch the message was sent in
+    pub epoch: u64,
+    /// Decrypted payload of application messages, none for handshake messages
+    pub app_msg: Option<Vec<u8>>,
+    /// [ClientId] of the sender of application messages
+    pub sender_client_id: Option<ClientId>,
+}
+
+/// Archives the secret of a history-sharing era of a conversation, under the era it belongs to
+pub(crate) async fn archive(keystore: &CryptoKeystore, conversation_id: &[u8], secret: &HistorySecret) -> Result<()> {
+    let era = secret.era.as_ref().ok_or(Error::InvalidHistorySecret(
+        "the history client of the secret has not been added yet",
+    ))?;
+    let welcome = era.welcome.clone();
+    let epoch = era.epoch;
+    let secret = serde_json::to_vec(secret).map_err(MlsError::wrap("serializing history secret"))?;
+    keystore
+        .save(MlsHistorySecret::new(conversation_id, epoch, secret, welcome))
+        .await
+        .map_err(KeystoreError::wrap("archiving history secret"))?;
+    Ok(())
+}
+
+/// Removes every history secret archived for this conversation
+pub(crate) async fn delete_archive(keystore: &CryptoKeystore, conversation_id: &[u8]) -> Result<()> {
+    for entity in find_archive(keystore, conversation_id).await? {
+        keystore
+            .remove::<MlsHistorySecret, _>(&entity.id)
+            .await
+            .map_err(KeystoreError::wrap("removing archived history secret"))?;
+    }
+    Ok(())
+}
+
+/// The history secrets archived for this conversation, ordered by epoch
+async fn find_archive(keystore: &CryptoKeystore, conversation_id: &[u8]) -> Result<Vec<MlsHistorySecret>> {
+    keystore
+        .find_history_secrets_by_conversation_id(conversation_id)
+        .await
+        .map_err(KeystoreError::wrap("finding archived history secrets"))
+        .map_err(Into::into)
+}
+
+fn message_epoch(message: &[u8]) -> Result<u64> {
+    let message =
+        MlsMessageIn::tls_deserialize_exact(message).map_err(MlsError::wrap("deserializing replayed message"))?;
+    match message.body_as_ref() {
+        MlsMessageInBody::PublicMessage(message) => Ok(message.epoch().as_u64()),
+        MlsMessageInBody::PrivateMessage(message) => Ok(message.epoch().as_u64()),
+        _ => Err(Error::InvalidHistorySecret(
+            "only handshake and application messages can be replayed",
+        )),
+    }
+}
+
+/// Instantiates the history client of an era and joins the conversation with it
+async fn join_era(archived: ArchivedHistorySecret, custom_cfg: MlsCustomConfiguration) -> Result<TransactionContext> {
+    let history_client = CoreCrypto::history_client(archived.secret).await?;
+    let context = history_client
+        .new_transaction()
+        .await
+        .map_err(RecursiveError::transaction("creating new transaction"))?;
+    let welcome = MlsMessageIn::tls_deserialize_exact(&archived.welcome)
+        .map_err(MlsError::wrap("deserializing archived welcome"))?;
+    context
+        .process_welcome_message(welcome, custom_cfg)
+        .await
+        .map_err(RecursiveError::transaction("joining history-sharing era"))?;
+    Ok(context)
+}
+
+impl TransactionContext {
+    /// Archives the secret of a history-sharing era of a conversation, so that [Self::replay_history] can
+    /// decrypt its messages.
+    ///
+    /// The secrets of the eras started by this client are archived automatically. The other members archive
+    /// the secret they receive along with the commit adding its history client: it carries the epoch
+    /// starting its era and the welcome adding the history client.
+    pub async fn archive_history_secret(
+        &self,
+        conversation_id: &ConversationId,
+        secret: &HistorySecret,
+    ) -> transaction_context::Result<()> {
+        archive(&self.keystore().await?, conversation_id, secret)
+            .await
+            .map_err(RecursiveError::root("archiving history secret"))
+            .map_err(Into::into)
+    }
+
+    /// The history secrets archived for this conversation, ordered by epoch
+    pub async fn history_secret_archive(
+        &self,
+        conversation_id: &ConversationId,
+    ) -> transaction_context::Result<Vec<ArchivedHistorySecret>> {
+        let archive = find_archive(&self.keystore().await?, conversation_id)
+            .await
+            .map_err(RecursiveError::root("finding archived history secrets"))?;
+        archive
+            .iter()
+            .map(ArchivedHistorySecret::from_entity)
+            .collect::<Result<_>>()
+            .map_err(RecursiveError::root("reading archived history secrets"))
+            .map_err(Into::into)
+    }
+
+    /// Decrypts a batch of messages of this conversation with the history clients of their eras, as
+    /// found in the archive. Messages are returned in the order they were given.
+    ///
+    /// Messages have to be given in the order they were sent, commits included, starting from the
+    /// beginning of the era of the first message: history clients have to process the commits of
+    /// their era to decrypt the messages of the epochs that follow.
+    pub async fn replay_history(
+        &self,
+        conversation_id: &ConversationId,
+        messages: Vec<Vec<u8>>,
+    ) -> transaction_context::Result<Vec<ReplayedMessage>> {
+        let archive = self
+            .history_secret_archive(conversation_id)
+            .await?
+            .into_iter()
+            .map(|archived| (archived.epoch, archived))
+            .collect::<BTreeMap<_, _>>();
+        // the conversation may be unknown locally, e.g. when replaying from a fresh device
+        let custom_cfg = match self.conversation(conversation_id).await {
+            Ok(conversation) => {
+                let conversation = conversation.conversation().await;
+                conversation.configuration.custom.clone()
+            }
+            Err(_) => MlsCustomConfiguration::default(),
+        };
+
+        let mut history_clients = BTreeMap::new();
+        let replayed =
+            Self::replay_messages(conversation_id, messages, archive, custom_cfg, &mut history_clients).await;
+
+        // The history clients only live in memory, but their transactions still have to be closed
+        for history_client in history_clients.into_values() {
+            let closed = if replayed.is_ok() {
+                history_client.finish().await
+            } else {
+                history_client.abort().await
+            };
+            closed.map_err(RecursiveError::transaction("closing transaction of history client"))?;
+        }
+        replayed
+    }
+
+    /// Decrypts each message with the history client of its era, instantiating it from the `archive` on
+    /// first use
+    async fn replay_messages(
+        conversation_id: &ConversationId,
+        messages: Vec<Vec<u8>>,
+        mut archive: BTreeMap<u64, ArchivedHistorySecret>,
+        custom_cfg: MlsCustomConfiguration,
+        history_clients: &mut BTreeMap<u64, TransactionContext>,
+    ) -> transaction_context::Result<Vec<ReplayedMessage>> {
+        let mut replayed = Vec::with_capacity(messages.len());
+        for message in messages {
+            let epoch = message_epoch(&message).map_err(RecursiveError::root("reading epoch of replayed message"))?;
+            // the era of a message is the last one starting before it
+            let era = history_clients
+                .keys()
+                .chain(archive.keys())
+                .copied()
+                .filter(|start| *start <= epoch)
+                .max()
+                .ok_or(Error::HistorySecretNotArchived(epoch))
+                .map_err(RecursiveError::root("finding era of replayed message"))?;
+            let history_client = match history_clients.entry(era) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let Some(archived) = archive.remove(&era) else {
+                        return Err(RecursiveError::root("finding era of replayed message")(
+                            Error::HistorySecretNotArchived(epoch),
+                        )
+                        .into());
+                    };
+                    let history_client = join_era(archived, custom_cfg.clone())
+                        .await
+                        .map_err(RecursiveError::root("instantiating history client"))?;
+                    entry.insert(history_client)
+                }
+            };
+
+            let decrypted = history_client
+                .conversation(conversation_id)
+                .await?
+                .decrypt_message(&message)
+                .await
+                .map_err(RecursiveError::mls_conversation("replaying message"))?;
+            replayed.push(ReplayedMessage {
+                epoch,
+                app_msg: decrypted.app_msg,
+                sender_client_id: decrypted.sender_client_id,
+            });
+        }
+        Ok(replayed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mls::conversation::Conversation as _, test_utils::*};
+
+    #[apply(all_cred_cipher)]
+    async fn can_replay_history_of_several_eras(case: TestContext) {
+        let [alice, bob, carol] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case
+                .create_conversation([&alice, &bob, &carol])
+                .await
+                .enable_history_sharing_notify()
+                .await;
+            let id = conversation.id().clone();
+            let first_epoch = conversation.guard().await.epoch().await;
+
+            let first = conversation.guard().await.encrypt_message(b"first era").await.unwrap();
+            // removing a member starts a new era
+            let conversation = conversation.remove_notify(&carol).await;
+            let remove_commit = alice.mls_transport().await.latest_commit().await;
+            let second = conversation.guard().await.encrypt_message(b"second era").await.unwrap();
+            let messages = vec![first, remove_commit.to_bytes().unwrap(), second];
+
+            // alice started both eras, hence archived their secrets
+            let archive = alice.transaction.history_secret_archive(&id).await.unwrap();
+            assert_eq!(
+                archive.iter().map(|archived| archived.epoch).collect::<Vec<_>>(),
+                vec![first_epoch, first_epoch + 1]
+            );
+            // the secrets alice sent along with her commits
+            let sent_secrets = alice.history_observer().await.observed_history_clients().await;
+
+            let alice_id = alice.get_client_id().await;
+            let expected = vec![
+                (first_epoch, Some(b"first era".to_vec()), Some(alice_id.clone())),
+                (first_epoch, None, None),
+                (first_epoch + 1, Some(b"second era".to_vec()), Some(alice_id)),
+            ];
+            let summarize = |replayed: Vec<ReplayedMessage>| {
+                replayed
+                    .into_iter()
+                    .map(|message| (message.epoch, message.app_msg, message.sender_client_id))
+                    .collect::<Vec<_>>()
+            };
+            let replayed = alice.transaction.replay_history(&id, messages.clone()).await.unwrap();
+            assert_eq!(summarize(replayed), expected);
+
+            // bob cannot replay anything until he archives the secrets he received
+            assert!(bob.transaction.replay_history(&id, messages.clone()).await.is_err());
+            for (_, secret) in sent_secrets {
+                bob.transaction.archive_history_secret(&id, &secret).await.unwrap();
+            }
+            let replayed = bob.transaction.replay_history(&id, messages).await.unwrap();
+            assert_eq!(summarize(replayed), expected);
+        })
+        .await
+    }
+}
@@ -33,6 +33,7 @@ pub mod proteus;
 
 mod ephemeral;
 mod group_store;
+mod history_archive;
 pub mod transaction_context;
 
 mod build_metadata;
@@ -64,29 +65,46 @@ pub mod prelude {
         },
         ephemeral::{HISTORY_CLIENT_ID_PREFIX, HistorySecret},
         error::{Error, KeystoreError, LeafError, MlsError, ProteusError, RecursiveError},
+        history_archive::{ArchivedHistorySecret, ReplayedMessage},
         mls::{
             ciphersuite::MlsCiphersuite,
             conversation::{
-                ConversationId, MlsConversation,
+                ConversationId, MlsAdmissionViolation, MlsConversation, RESERVED_EXPORTER_LABEL_PREFIX,
                 commit::MlsCommitBundle,
-                config::{MlsConversationConfiguration, MlsCustomConfiguration, MlsWirePolicy},
-                conversation_guard::decrypt::{MlsBufferedConversationDecryptMessage, MlsConversationDecryptMessage},
+                config::{
+                    MlsAdmissionAction, MlsAdmissionPolicy, MlsBufferConfiguration, MlsConversationConfiguration,
+                    MlsCustomCapabilities, MlsCustomConfiguration, MlsExporterSecretHistoryConfiguration,
+                    MlsWirePolicy,
+                },
+                conversation_guard::MlsBatchCommitBuilder,
+                conversation_guard::decrypt::{
+                    MlsBufferedConversationDecryptMessage, MlsConversationDecryptMessage,
+                    buffer_limits::{MlsBufferedItem, MlsBufferedItemKind, MlsEvictionReason},
+                },
+                custom_extension::MlsCustomExtension,
                 group_info::{GroupInfoPayload, MlsGroupInfoBundle, MlsGroupInfoEncryptionType, MlsRatchetTreeType},
                 proposal::MlsProposalBundle,
+                reinit::MlsReInit,
                 welcome::WelcomeBundle,
             },
             credential::{typ::MlsCredentialType, x509::CertificateBundle},
-            proposal::{MlsProposal, MlsProposalRef},
+            proposal::{MlsPendingProposal, MlsProposal, MlsProposalRef, MlsProposalType},
             session::{
                 Session,
                 config::{SessionConfig, ValidatedSessionConfig},
                 id::ClientId,
                 identifier::ClientIdentifier,
-                key_package::INITIAL_KEYING_MATERIAL_COUNT,
+                key_package::{INITIAL_KEYING_MATERIAL_COUNT, MlsKeyPackageExpiry, MlsKeyPackageInventoryEntry},
                 *,
             },
         },
-        transaction_context::e2e_identity::conversation_state::E2eiConversationState,
+        transaction_context::{
+            e2e_identity::{
+                conversation_state::E2eiConversationState,
+                expiry_report::{CertificateExpiryReport, ExpiringCredential, ExpiringMemberCertificate},
+            },
+            psk::RESERVED_PSK_ID_PREFIX,
+        },
     };
 }
 
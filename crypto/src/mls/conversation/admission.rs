@@ -0,0 +1,295 @@
+//! Enforcement of the [MlsAdmissionPolicy] of verified conversations.
+
+use mls_crypto_provider::MlsCryptoProvider;
+use openmls::prelude::Credential;
+use openmls_traits::OpenMlsCryptoProvider as _;
+use wire_e2e_identity::prelude::x509::revocation::PkiEnvironment;
+
+use super::{Error, Result};
+use crate::{
+    mls::credential::ext::CredentialExt as _,
+    prelude::{
+        ClientId, DeviceStatus, E2eiConversationState, MlsAdmissionAction, MlsAdmissionPolicy, MlsCiphersuite,
+        MlsConversation, MlsCredentialType, Session,
+    },
+};
+
+/// Why a credential cannot join a verified conversation, see [MlsAdmissionPolicy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum MlsAdmissionViolation {
+    /// The certificate of the X509 credential has expired
+    #[display("its certificate has expired")]
+    Expired,
+    /// The certificate of the X509 credential has been revoked
+    #[display("its certificate has been revoked")]
+    Revoked,
+    /// The credential is a Basic one
+    #[display("it is a basic credential")]
+    Basic,
+}
+
+impl MlsAdmissionViolation {
+    /// What is wrong with this credential, if anything
+    fn of(credential: &Credential, ciphersuite: MlsCiphersuite, env: Option<&PkiEnvironment>) -> Option<Self> {
+        match credential.mls_credential() {
+            openmls::prelude::MlsCredentialType::Basic(_) => Some(Self::Basic),
+            openmls::prelude::MlsCredentialType::X509(certificate) => {
+                // credentials whose identity cannot be read are left to the credential validation
+                match certificate.extract_identity(ciphersuite, env).ok()?.status {
+                    DeviceStatus::Valid => None,
+                    DeviceStatus::Expired => Some(Self::Expired),
+                    DeviceStatus::Revoked => Some(Self::Revoked),
+                }
+            }
+        }
+    }
+}
+
+impl MlsAdmissionPolicy {
+    fn action(&self, violation: MlsAdmissionViolation) -> MlsAdmissionAction {
+        match violation {
+            MlsAdmissionViolation::Expired => self.expired,
+            MlsAdmissionViolation::Revoked => self.revoked,
+            MlsAdmissionViolation::Basic => self.basic,
+        }
+    }
+
+    /// Checks the credentials about to join a conversation having these members, in order. Only
+    /// verified conversations are subject to the policy.
+    ///
+    /// History clients are exempt: their Basic credential neither prevents the conversation from being
+    /// verified, nor is it subject to the policy.
+    pub(crate) async fn enforce<'a>(
+        &self,
+        ciphersuite: MlsCiphersuite,
+        members: impl Iterator<Item = &'a Credential>,
+        newcomers: impl IntoIterator<Item = &'a Credential>,
+        env: Option<&PkiEnvironment>,
+    ) -> Result<()> {
+        if *self == Self::default() {
+            return Ok(());
+        }
+        let members = members.filter(|credential| !is_history_client(credential));
+        let state = Session::compute_conversation_state(ciphersuite, members, MlsCredentialType::X509, env).await;
+        if state != E2eiConversationState::Verified {
+            return Ok(());
+        }
+
+        for (index, credential) in newcomers.into_iter().enumerate() {
+            if is_history_client(credential) {
+                continue;
+            }
+            let Some(violation) = MlsAdmissionViolation::of(credential, ciphersuite, env) else {
+                continue;
+            };
+            let client_id = ClientId::from(credential.identity());
+            match self.action(violation) {
+                MlsAdmissionAction::Allow => {}
+                MlsAdmissionAction::Warn => log::warn!(
+                    client_id:? = client_id,
+                    violation:% = violation;
+                    "Admitting a credential in a verified conversation"
+                ),
+                MlsAdmissionAction::Reject => {
+                    return Err(Error::CredentialNotAdmitted {
+                        index,
+                        client_id,
+                        violation,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_history_client(credential: &Credential) -> bool {
+    crate::ephemeral::is_history_client(&ClientId::from(credential.identity()))
+}
+
+impl MlsConversation {
+    /// Checks the credentials about to be added to this conversation against its [MlsAdmissionPolicy]
+    pub(crate) async fn enforce_admission_policy<'a>(
+        &self,
+        backend: &MlsCryptoProvider,
+        newcomers: impl IntoIterator<Item = &'a Credential>,
+    ) -> Result<()> {
+        let authentication_service = backend.authentication_service();
+        authentication_service.refresh_time_of_interest().await;
+        let authentication_service = authentication_service.borrow().await;
+        self.configuration
+            .custom
+            .admission_policy
+            .enforce(
+                self.ciphersuite(),
+                self.group.members_credentials(),
+                newcomers,
+                authentication_service.as_ref(),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn should_reject_revoked_credentials_in_verified_conversation() {
+        let mut case = TestContext::default_x509();
+        case.cfg.custom.admission_policy.revoked = MlsAdmissionAction::Reject;
+        let [alice_user_id, bob_user_id, rupert_user_id] = [(); 3].map(|_| uuid::Uuid::new_v4());
+        let [alice_client_id] = case.x509_client_ids_for_user(&alice_user_id);
+        let [bob_client_id] = case.x509_client_ids_for_user(&bob_user_id);
+        let [rupert_client_id] = case.x509_client_ids_for_user(&rupert_user_id);
+        let [alice, bob, rupert] = case
+            .sessions_x509_with_client_ids_and_revocation(
+                [alice_client_id, bob_client_id, rupert_client_id],
+                &[rupert_user_id.to_string()],
+            )
+            .await;
+
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let rupert_id = rupert.get_client_id().await;
+            let key_packages = vec![bob.rand_key_package(&case).await, rupert.rand_key_package(&case).await];
+            let error = conversation.guard().await.add_members(key_packages).await.unwrap_err();
+            assert!(matches!(
+                error,
+                Error::CredentialNotAdmitted { index: 1, client_id, violation: MlsAdmissionViolation::Revoked }
+                    if client_id == rupert_id
+            ));
+            assert_eq!(conversation.member_count().await, 1);
+
+            // valid credentials are still admitted
+            let conversation = conversation.invite_notify([&bob]).await;
+            assert_eq!(conversation.member_count().await, 2);
+        })
+        .await
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn should_enforce_policy_on_batches_and_proposals() {
+        let mut case = TestContext::default_x509();
+        case.cfg.custom.admission_policy.revoked = MlsAdmissionAction::Reject;
+        let [alice_user_id, bob_user_id, rupert_user_id] = [(); 3].map(|_| uuid::Uuid::new_v4());
+        let [alice_client_id] = case.x509_client_ids_for_user(&alice_user_id);
+        let [bob_client_id] = case.x509_client_ids_for_user(&bob_user_id);
+        let [rupert_client_id] = case.x509_client_ids_for_user(&rupert_user_id);
+        let [alice, bob, rupert] = case
+            .sessions_x509_with_client_ids_and_revocation(
+                [alice_client_id, bob_client_id, rupert_client_id],
+                &[rupert_user_id.to_string()],
+            )
+            .await;
+
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let rupert_id = rupert.get_client_id().await;
+            let error = conversation
+                .guard()
+                .await
+                .batch_commit()
+                .add_members([bob.rand_key_package(&case).await, rupert.rand_key_package(&case).await])
+                .commit()
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                Error::CredentialNotAdmitted { index: 1, ref client_id, violation: MlsAdmissionViolation::Revoked }
+                    if *client_id == rupert_id
+            ));
+            assert_eq!(conversation.member_count().await, 1);
+            assert!(!conversation.has_pending_proposals().await);
+
+            let error = alice
+                .transaction
+                .new_add_proposal(conversation.id(), rupert.rand_key_package(&case).await.into())
+                .await
+                .unwrap_err();
+            assert!(innermost_source_matches!(
+                error,
+                Error::CredentialNotAdmitted {
+                    index: 0,
+                    violation: MlsAdmissionViolation::Revoked,
+                    ..
+                }
+            ));
+            assert!(!conversation.has_pending_proposals().await);
+        })
+        .await
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn should_exempt_history_clients() {
+        let mut case = TestContext::default_x509();
+        case.cfg.custom.admission_policy.basic = MlsAdmissionAction::Reject;
+        let ([alice], [bob]) = case.sessions_mixed_credential_types().await;
+
+        Box::pin(async move {
+            // the history client has a basic credential
+            let conversation = case.create_conversation([&alice]).await;
+            let conversation = conversation.enable_history_sharing_notify().await;
+            assert_eq!(conversation.member_count().await, 2);
+
+            // and does not prevent the conversation from being verified
+            let key_package = bob.rand_key_package_of_type(&case, MlsCredentialType::Basic).await;
+            let error = conversation
+                .guard()
+                .await
+                .add_members(vec![key_package])
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                Error::CredentialNotAdmitted {
+                    violation: MlsAdmissionViolation::Basic,
+                    ..
+                }
+            ));
+        })
+        .await
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn should_apply_policy_to_basic_credentials() {
+        let mut case = TestContext::default_x509();
+        case.cfg.custom.admission_policy.basic = MlsAdmissionAction::Reject;
+        let ([alice], [bob]) = case.sessions_mixed_credential_types().await;
+
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let key_package = bob.rand_key_package_of_type(&case, MlsCredentialType::Basic).await;
+            let error = conversation
+                .guard()
+                .await
+                .add_members(vec![key_package.clone()])
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                Error::CredentialNotAdmitted {
+                    index: 0,
+                    violation: MlsAdmissionViolation::Basic,
+                    ..
+                }
+            ));
+
+            // a warning does not prevent the credential from joining
+            {
+                let mut guard = conversation.guard().await;
+                guard
+                    .conversation_mut()
+                    .await
+                    .configuration
+                    .custom
+                    .admission_policy
+                    .basic = MlsAdmissionAction::Warn;
+                guard.add_members(vec![key_package]).await.unwrap();
+            }
+            assert_eq!(conversation.member_count().await, 2);
+        })
+        .await
+    }
+}
@@ -425,6 +425,155 @@ mod tests {
         }
     }
 
+    mod update_group_context_extensions {
+        use super::*;
+        use openmls::prelude::{Credential, Extension, ExtensionType, Extensions, ExternalSender};
+        use openmls_traits::{OpenMlsCryptoProvider as _, crypto::OpenMlsCrypto as _};
+        use std::sync::Arc;
+
+        async fn with_new_external_sender(
+            case: &TestContext,
+            conversation: &TestConversation<'_>,
+        ) -> (Extensions, Vec<u8>) {
+            let (_sk, pk) = conversation
+                .actor()
+                .transaction
+                .mls_provider()
+                .await
+                .unwrap()
+                .crypto()
+                .signature_key_gen(case.signature_scheme())
+                .unwrap();
+            let external_sender = ExternalSender::new(
+                SignaturePublicKey::from(pk.clone()),
+                Credential::new_basic(b"ds".to_vec()),
+            );
+
+            let guard = conversation.guard().await;
+            let inner = guard.conversation().await;
+            let extensions = inner
+                .group
+                .group_context_extensions()
+                .iter()
+                .filter(|extension| extension.extension_type() != ExtensionType::ExternalSenders)
+                .cloned()
+                .chain([Extension::ExternalSenders(vec![external_sender])])
+                .collect();
+            (Extensions::from_vec(extensions).unwrap(), pk)
+        }
+
+        #[apply(all_cred_cipher)]
+        async fn should_rotate_external_senders(case: TestContext) {
+            let [alice, bob] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob]).await;
+                let epoch = conversation.guard().await.epoch().await;
+                let (extensions, external_sender) = with_new_external_sender(&case, &conversation).await;
+
+                let conversation = conversation.update_group_context_extensions_notify(extensions).await;
+
+                assert_eq!(conversation.guard().await.epoch().await, epoch + 1);
+                for session in [&alice, &bob] {
+                    let ds_key = conversation
+                        .guard_of(session)
+                        .await
+                        .get_external_sender()
+                        .await
+                        .unwrap();
+                    assert_eq!(ds_key, external_sender);
+                }
+                assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+            })
+            .await;
+        }
+
+        #[apply(all_cred_cipher)]
+        async fn should_recommit_extensions_on_retry(case: TestContext) {
+            let [alice, bob] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob]).await;
+                let (extensions, external_sender) = with_new_external_sender(&case, &conversation).await;
+
+                // Bob's commit is accepted by the DS before Alice's
+                let commit = conversation.acting_as(&bob).await.update().await;
+                let intermediate_commit = commit.message();
+                let retry_provider = Arc::new(
+                    CoreCryptoTransportRetrySuccessProvider::default().with_intermediate_commits(
+                        alice.clone(),
+                        &[intermediate_commit],
+                        commit.conversation().id(),
+                    ),
+                );
+                alice.replace_transport(retry_provider.clone()).await;
+
+                let conversation = commit.finish().update_group_context_extensions_notify(extensions).await;
+
+                assert_eq!(retry_provider.retry_count().await, 1);
+                assert_eq!(retry_provider.success_count().await, 1);
+                for session in [&alice, &bob] {
+                    let ds_key = conversation
+                        .guard_of(session)
+                        .await
+                        .get_external_sender()
+                        .await
+                        .unwrap();
+                    assert_eq!(ds_key, external_sender);
+                }
+                assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+            })
+            .await;
+        }
+
+        #[apply(all_cred_cipher)]
+        async fn should_remove_extensions_on_retry(case: TestContext) {
+            let [alice, bob] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob]).await;
+                let (extensions, _) = with_new_external_sender(&case, &conversation).await;
+                let conversation = conversation.update_group_context_extensions_notify(extensions).await;
+                let without_external_senders = {
+                    let guard = conversation.guard().await;
+                    let inner = guard.conversation().await;
+                    let extensions = inner
+                        .group
+                        .group_context_extensions()
+                        .iter()
+                        .filter(|extension| extension.extension_type() != ExtensionType::ExternalSenders)
+                        .cloned()
+                        .collect();
+                    Extensions::from_vec(extensions).unwrap()
+                };
+
+                // Bob's commit is accepted by the DS before Alice's
+                let commit = conversation.acting_as(&bob).await.update().await;
+                let intermediate_commit = commit.message();
+                let retry_provider = Arc::new(
+                    CoreCryptoTransportRetrySuccessProvider::default().with_intermediate_commits(
+                        alice.clone(),
+                        &[intermediate_commit],
+                        commit.conversation().id(),
+                    ),
+                );
+                alice.replace_transport(retry_provider.clone()).await;
+
+                let conversation = commit
+                    .finish()
+                    .update_group_context_extensions_notify(without_external_senders)
+                    .await;
+
+                assert_eq!(retry_provider.retry_count().await, 1);
+                for session in [&alice, &bob] {
+                    let guard = conversation.guard_of(session).await;
+                    let inner = guard.conversation().await;
+                    let extensions = inner.group.group_context_extensions();
+                    assert!(extensions.external_senders().is_none());
+                }
+                assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+            })
+            .await;
+        }
+    }
+
     mod commit_pending_proposals {
         use super::*;
 
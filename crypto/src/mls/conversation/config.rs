@@ -5,8 +5,9 @@
 
 use mls_crypto_provider::MlsCryptoProvider;
 use openmls::prelude::{
-    Capabilities, Credential, CredentialType, ExternalSender, OpenMlsSignaturePublicKey,
-    PURE_CIPHERTEXT_WIRE_FORMAT_POLICY, PURE_PLAINTEXT_WIRE_FORMAT_POLICY, ProtocolVersion,
+    Capabilities, ContentType, Credential, CredentialType, ExtensionType, ExternalSender,
+    MIXED_CIPHERTEXT_WIRE_FORMAT_POLICY, MIXED_PLAINTEXT_WIRE_FORMAT_POLICY, OpenMlsSignaturePublicKey,
+    PURE_CIPHERTEXT_WIRE_FORMAT_POLICY, PURE_PLAINTEXT_WIRE_FORMAT_POLICY, ProposalType, ProtocolVersion,
     RequiredCapabilitiesExtension, SenderRatchetConfiguration, WireFormatPolicy,
 };
 use openmls_traits::{
@@ -15,6 +16,7 @@ use openmls_traits::{
     types::{Ciphersuite, SignatureScheme},
 };
 use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 use wire_e2e_identity::prelude::parse_json_jwk;
 
 use super::Result;
@@ -62,22 +64,43 @@ impl MlsConversationConfiguration {
         Ciphersuite::MLS_256_DHKEMP521_AES256GCM_SHA512_P521,
     ];
 
-    /// Not used at the moment
-    const NUMBER_RESUMPTION_PSK: usize = 1;
+    /// Number of epochs, the current one included, whose resumption secret is kept so that it can be
+    /// injected as a pre-shared key. Only the current one is, to keep as few secrets of past epochs as
+    /// possible in the group state.
+    pub(crate) const NUMBER_RESUMPTION_PSK: usize = 1;
 
     /// Generates an `MlsGroupConfig` from this configuration
     #[inline(always)]
     pub fn as_openmls_default_configuration(&self) -> Result<openmls::group::MlsGroupConfig> {
+        self.as_openmls_configuration(Self::default_leaf_capabilities())
+    }
+
+    /// Generates an `MlsGroupConfig` from this configuration, with our own leaf node advertising
+    /// the given capabilities
+    pub(crate) fn as_openmls_configuration(
+        &self,
+        leaf_capabilities: Capabilities,
+    ) -> Result<openmls::group::MlsGroupConfig> {
+        self.as_openmls_configuration_with_wire_format(leaf_capabilities, self.custom.wire_policy.into())
+    }
+
+    /// Generates an `MlsGroupConfig` from this configuration, overriding the wire format policy of
+    /// [MlsCustomConfiguration::wire_policy]
+    pub(crate) fn as_openmls_configuration_with_wire_format(
+        &self,
+        leaf_capabilities: Capabilities,
+        wire_format_policy: WireFormatPolicy,
+    ) -> Result<openmls::group::MlsGroupConfig> {
         let crypto_config = openmls::prelude::CryptoConfig {
             version: Self::DEFAULT_PROTOCOL_VERSION,
             ciphersuite: self.ciphersuite.into(),
         };
         Ok(openmls::group::MlsGroupConfig::builder()
-            .wire_format_policy(self.custom.wire_policy.into())
+            .wire_format_policy(wire_format_policy)
             .max_past_epochs(MAX_PAST_EPOCHS)
             .padding_size(Self::PADDING_SIZE)
             .number_of_resumption_psks(Self::NUMBER_RESUMPTION_PSK)
-            .leaf_capabilities(Self::default_leaf_capabilities())
+            .leaf_capabilities(leaf_capabilities)
             .required_capabilities(self.default_required_capabilities())
             .sender_ratchet_configuration(SenderRatchetConfiguration::new(
                 self.custom.out_of_order_tolerance,
@@ -91,17 +114,27 @@ impl MlsConversationConfiguration {
 
     /// Default capabilities for every generated [openmls::prelude::KeyPackage]
     pub fn default_leaf_capabilities() -> Capabilities {
+        Self::leaf_capabilities(&MlsCustomCapabilities::default())
+    }
+
+    /// Default capabilities, additionally advertising the given application-defined types
+    pub fn leaf_capabilities(custom: &MlsCustomCapabilities) -> Capabilities {
         Capabilities::new(
             Some(&[Self::DEFAULT_PROTOCOL_VERSION]),
             Some(Self::DEFAULT_SUPPORTED_CIPHERSUITES),
-            Some(&[]),
-            Some(&[]),
+            Some(&custom.openmls_extension_types()),
+            Some(&custom.openmls_proposal_types()),
             Some(Self::DEFAULT_SUPPORTED_CREDENTIALS),
         )
     }
 
     fn default_required_capabilities(&self) -> RequiredCapabilitiesExtension {
-        RequiredCapabilitiesExtension::new(&[], &[], Self::DEFAULT_SUPPORTED_CREDENTIALS)
+        let required = &self.custom.required_capabilities;
+        RequiredCapabilitiesExtension::new(
+            &required.openmls_extension_types(),
+            &required.openmls_proposal_types(),
+            Self::DEFAULT_SUPPORTED_CREDENTIALS,
+        )
     }
 
     /// This expects a raw json serialized JWK. It works with any Signature scheme
@@ -140,8 +173,8 @@ impl MlsConversationConfiguration {
 /// The configuration parameters for a group/conversation which are not handled natively by openmls
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MlsCustomConfiguration {
-    // TODO: Not implemented yet. Tracking issue: WPB-9609
-    /// Duration in seconds after which we will automatically force a self_update commit
+    /// Duration after which our own leaf node should be updated with a self_update commit.
+    /// See [crate::transaction_context::TransactionContext::conversations_due_for_key_rotation].
     pub key_rotation_span: Option<std::time::Duration>,
     /// Defines if handshake messages are encrypted or not
     pub wire_policy: MlsWirePolicy,
@@ -152,6 +185,20 @@ pub struct MlsCustomConfiguration {
     /// How many application messages can be skipped. Use this when the Delivery Service can drop
     /// application messages
     pub maximum_forward_distance: u32,
+    /// Exporter secrets to keep once the group moved past the epoch they were exported in
+    #[serde(default)]
+    pub exporter_secret_history: MlsExporterSecretHistoryConfiguration,
+    /// Application-defined types every member has to support. Only used when creating the
+    /// conversation, after which they are part of its group context. All of them must be in
+    /// [MlsCustomCapabilities::PRIVATE_USE_RANGE].
+    #[serde(default)]
+    pub required_capabilities: MlsCustomCapabilities,
+    /// Limits of what is buffered while waiting for the commit of a future epoch
+    #[serde(default)]
+    pub buffer: MlsBufferConfiguration,
+    /// Which credentials can be added to the conversation, or join it, once it is verified
+    #[serde(default)]
+    pub admission_policy: MlsAdmissionPolicy,
 }
 
 impl Default for MlsCustomConfiguration {
@@ -161,10 +208,134 @@ impl Default for MlsCustomConfiguration {
             key_rotation_span: Default::default(),
             out_of_order_tolerance: OUT_OF_ORDER_TOLERANCE,
             maximum_forward_distance: MAXIMUM_FORWARD_DISTANCE,
+            exporter_secret_history: Default::default(),
+            required_capabilities: Default::default(),
+            buffer: Default::default(),
+            admission_policy: Default::default(),
+        }
+    }
+}
+
+/// Which exporter secrets are kept in the keystore once the group moved past the epoch they were
+/// exported in. See [crate::mls::conversation::Conversation::exporter_secret_at_epoch].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlsExporterSecretHistoryConfiguration {
+    /// For how many past epochs secrets are kept. `0` disables the history.
+    pub max_past_epochs: usize,
+    /// Labels, as passed to [crate::mls::conversation::Conversation::export_secret_key_with_label]
+    /// with an empty context, for which secrets are kept
+    pub labels: Vec<String>,
+    /// Length of the kept secrets
+    pub key_length: usize,
+}
+
+impl MlsExporterSecretHistoryConfiguration {
+    /// Length of the kept secrets unless configured otherwise
+    pub const DEFAULT_KEY_LENGTH: usize = 32;
+}
+
+impl Default for MlsExporterSecretHistoryConfiguration {
+    fn default() -> Self {
+        Self {
+            max_past_epochs: 0,
+            labels: Vec::new(),
+            key_length: Self::DEFAULT_KEY_LENGTH,
+        }
+    }
+}
+
+/// Limits of the messages and commits buffered for a conversation, see
+/// [crate::transaction_context::TransactionContext::buffered_items].
+///
+/// When exceeded, the oldest items are evicted and reported to the
+/// [crate::mls::BufferObserver].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlsBufferConfiguration {
+    /// How many messages of future epochs are kept. There is at most one buffered commit.
+    pub max_messages: usize,
+    /// How many bytes the buffered messages and commit can take altogether
+    pub max_bytes: usize,
+    /// For how long buffered messages and commits are kept
+    pub max_age: std::time::Duration,
+}
+
+impl Default for MlsBufferConfiguration {
+    fn default() -> Self {
+        Self {
+            max_messages: 100,
+            max_bytes: 4 * 1024 * 1024,
+            max_age: std::time::Duration::from_secs(7 * 24 * 60 * 60),
         }
     }
 }
 
+/// Which credentials can be added to a conversation, or join it by external commit, while all its
+/// members have a valid X509 credential, i.e. while its
+/// [crate::prelude::E2eiConversationState] is `Verified`.
+///
+/// Conversations which are not verified admit any credential. History clients are exempt from the
+/// policy. See [crate::mls::conversation::Error::CredentialNotAdmitted].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlsAdmissionPolicy {
+    /// What to do with a X509 credential whose certificate has expired
+    pub expired: MlsAdmissionAction,
+    /// What to do with a X509 credential whose certificate has been revoked
+    pub revoked: MlsAdmissionAction,
+    /// What to do with a Basic credential
+    pub basic: MlsAdmissionAction,
+}
+
+/// What to do with a credential which would make a verified conversation not verified anymore, see
+/// [MlsAdmissionPolicy]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MlsAdmissionAction {
+    /// Admit the credential
+    #[default]
+    Allow,
+    /// Admit the credential, logging a warning
+    Warn,
+    /// Refuse the credential, failing the operation
+    Reject,
+}
+
+/// Application-defined extension and proposal types, on top of the ones defined by RFC 9420.
+///
+/// Register them with [crate::prelude::SessionConfig::custom_capabilities] to advertise them in
+/// key packages and leaf nodes, and require them with [MlsCustomConfiguration::required_capabilities].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlsCustomCapabilities {
+    /// Extension types, see [crate::prelude::MlsCustomExtension]
+    pub extension_types: Vec<u16>,
+    /// Proposal types
+    pub proposal_types: Vec<u16>,
+}
+
+impl MlsCustomCapabilities {
+    /// Application-defined types have to be in the range reserved for private use
+    /// (see <https://www.rfc-editor.org/rfc/rfc9420.html#section-17.1>)
+    pub const PRIVATE_USE_RANGE: RangeInclusive<u16> = 0xF000..=0xFFFF;
+
+    pub(crate) fn openmls_extension_types(&self) -> Vec<ExtensionType> {
+        self.extension_types.iter().copied().map(ExtensionType::from).collect()
+    }
+
+    pub(crate) fn openmls_proposal_types(&self) -> Vec<ProposalType> {
+        self.proposal_types.iter().copied().map(ProposalType::from).collect()
+    }
+
+    /// The first type outside of [Self::PRIVATE_USE_RANGE], along with its kind, if any
+    pub(crate) fn find_invalid_type(&self) -> Option<(&'static str, u16)> {
+        [("extension", &self.extension_types), ("proposal", &self.proposal_types)]
+            .into_iter()
+            .find_map(|(kind, types)| {
+                types
+                    .iter()
+                    .find(|value| !Self::PRIVATE_USE_RANGE.contains(value))
+                    .map(|&value| (kind, value))
+            })
+    }
+}
+
 /// Wrapper over [WireFormatPolicy](openmls::prelude::WireFormatPolicy)
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -174,6 +345,22 @@ pub enum MlsWirePolicy {
     Plaintext = 1,
     /// Handshake messages are always encrypted
     Ciphertext = 2,
+    /// Commits are never encrypted, so that the Delivery Service can validate them, while
+    /// proposals are always encrypted
+    Mixed = 3,
+}
+
+impl MlsWirePolicy {
+    /// Whether handshake messages of the given content type have to be encrypted under this policy
+    pub fn encrypts(&self, content_type: ContentType) -> bool {
+        match (self, content_type) {
+            (_, ContentType::Application) => true,
+            (Self::Plaintext, _) => false,
+            (Self::Ciphertext, _) => true,
+            (Self::Mixed, ContentType::Proposal) => true,
+            (Self::Mixed, ContentType::Commit) => false,
+        }
+    }
 }
 
 impl From<MlsWirePolicy> for WireFormatPolicy {
@@ -181,6 +368,27 @@ impl From<MlsWirePolicy> for WireFormatPolicy {
         match policy {
             MlsWirePolicy::Ciphertext => PURE_CIPHERTEXT_WIRE_FORMAT_POLICY,
             MlsWirePolicy::Plaintext => PURE_PLAINTEXT_WIRE_FORMAT_POLICY,
+            // OpenMLS frames all outgoing handshake messages the same way, see
+            // [crate::prelude::MlsConversation::encrypt_outgoing_proposals]
+            MlsWirePolicy::Mixed => MIXED_PLAINTEXT_WIRE_FORMAT_POLICY,
+        }
+    }
+}
+
+impl TryFrom<WireFormatPolicy> for MlsWirePolicy {
+    type Error = super::Error;
+
+    /// Both framings OpenMLS is switched between under [MlsWirePolicy::Mixed] are recognized, see
+    /// [crate::prelude::MlsConversation::encrypt_outgoing_proposals]
+    fn try_from(policy: WireFormatPolicy) -> Result<Self> {
+        if policy == PURE_CIPHERTEXT_WIRE_FORMAT_POLICY {
+            Ok(MlsWirePolicy::Ciphertext)
+        } else if policy == PURE_PLAINTEXT_WIRE_FORMAT_POLICY {
+            Ok(MlsWirePolicy::Plaintext)
+        } else if policy == MIXED_PLAINTEXT_WIRE_FORMAT_POLICY || policy == MIXED_CIPHERTEXT_WIRE_FORMAT_POLICY {
+            Ok(MlsWirePolicy::Mixed)
+        } else {
+            Err(super::Error::UnknownWireFormatPolicy(policy))
         }
     }
 }
@@ -0,0 +1,551 @@
+//! Combining several operations in a single commit.
+
+use std::collections::HashSet;
+
+use openmls::prelude::{KeyPackageIn, PreSharedKeyId};
+
+use super::{ConversationGuard, history_sharing::HistoryClientUpdateOutcome};
+use crate::{
+    MlsError, MlsTransportResponse, RecursiveError,
+    e2e_identity::NewCrlDistributionPoints,
+    mls::conversation::{Conversation as _, ConversationWithMls as _, Error, Result, commit::MlsCommitBundle},
+    prelude::{ClientId, MlsProposalRef},
+};
+
+/// How many times [MlsBatchCommitBuilder::commit] stages and sends the batch, when other commits keep
+/// being accepted by the delivery service first
+const BATCH_COMMIT_ATTEMPTS: usize = 3;
+
+/// Collects the operations of a commit created by [ConversationGuard::batch_commit].
+///
+/// Nothing happens until [Self::commit] is called.
+#[derive(Debug)]
+#[must_use = "a batch commit does nothing until `commit()` is called"]
+pub struct MlsBatchCommitBuilder<'a> {
+    guard: &'a mut ConversationGuard,
+    key_packages: Vec<KeyPackageIn>,
+    removed_clients: Vec<ClientId>,
+    update: bool,
+    psks: Vec<PreSharedKeyId>,
+}
+
+impl ConversationGuard {
+    /// Starts building a single commit which can add and remove members, update our own key material and
+    /// inject pre-shared keys at once. This spares the other members to process one commit per operation.
+    pub fn batch_commit(&mut self) -> MlsBatchCommitBuilder<'_> {
+        MlsBatchCommitBuilder {
+            guard: self,
+            key_packages: Vec::new(),
+            removed_clients: Vec::new(),
+            update: false,
+            psks: Vec::new(),
+        }
+    }
+}
+
+impl MlsBatchCommitBuilder<'_> {
+    /// Adds the clients owning these key packages to the conversation
+    pub fn add_members(mut self, key_packages: impl IntoIterator<Item = KeyPackageIn>) -> Self {
+        self.key_packages.extend(key_packages);
+        self
+    }
+
+    /// Removes these clients from the conversation
+    pub fn remove_members(mut self, clients: impl IntoIterator<Item = ClientId>) -> Self {
+        self.removed_clients.extend(clients);
+        self
+    }
+
+    /// Updates our own leaf node, like [ConversationGuard::update_key_material] does
+    pub fn update_key_material(mut self) -> Self {
+        self.update = true;
+        self
+    }
+
+    /// Injects the pre-shared key with this id into the key schedule. The secret has to be present in
+    /// the keystore of every member.
+    pub fn psk(mut self, psk_id: PreSharedKeyId) -> Self {
+        self.psks.push(psk_id);
+        self
+    }
+
+    /// Creates the commit, sends it via [crate::MlsTransport] and merges it once accepted.
+    ///
+    /// The proposals of the batch are part of the commit itself. Proposals already pending in the
+    /// conversation are only committed along when the batch solely updates our key material.
+    ///
+    /// When the delivery service asks to retry because it accepted other commits first, the whole batch
+    /// is staged and committed again in the new epoch. Clients removed by those commits in the meantime
+    /// are not removed again.
+    ///
+    /// Returns the CRL distribution points of the added key packages which were not known yet.
+    ///
+    /// # Errors
+    /// When the batch is empty, or when one of the clients to remove is not a member of the
+    /// conversation. In both cases nothing has been staged. When the commit cannot be sent or is
+    /// rejected by the delivery service, the proposals of the batch are discarded.
+    /// [Error::BatchCommitNotMerged] when the delivery service keeps accepting other commits first.
+    pub async fn commit(self) -> Result<NewCrlDistributionPoints> {
+        let Self {
+            guard,
+            key_packages,
+            removed_clients,
+            update,
+            psks,
+        } = self;
+
+        if key_packages.is_empty() && removed_clients.is_empty() && !update && psks.is_empty() {
+            return Err(Error::CallerError("a batch commit needs at least one operation"));
+        }
+        guard.ensure_no_pending_commit().await?;
+
+        let mut removed_clients = removed_clients;
+        for attempt in 0..BATCH_COMMIT_ATTEMPTS {
+            if attempt > 0 {
+                // the commits accepted in the meantime may have removed some of them already
+                removed_clients = guard.retain_members(removed_clients).await;
+                if key_packages.is_empty() && removed_clients.is_empty() && !update && psks.is_empty() {
+                    return Ok(None.into());
+                }
+            }
+
+            let mut staged = Vec::new();
+            let result = guard
+                .batch_commit_inner(
+                    key_packages.clone(),
+                    removed_clients.clone(),
+                    update,
+                    psks.clone(),
+                    &mut staged,
+                )
+                .await;
+            let (crl_new_distribution_points, commit) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    guard.discard_staged_proposals(staged).await?;
+                    return Err(e);
+                }
+            };
+
+            // The proposals were never sent: once the commit is not merged, no other member could resolve them
+            match guard.send_and_merge_batch_commit(commit).await {
+                Ok(true) => return Ok(crl_new_distribution_points),
+                // Renewing the commit would drop its pre-shared keys and our update: stage it all again instead
+                Ok(false) => guard.discard_own_pending_proposals().await?,
+                Err(e) => {
+                    guard.discard_staged_proposals(staged).await?;
+                    return Err(e);
+                }
+            }
+        }
+        Err(Error::BatchCommitNotMerged {
+            attempts: BATCH_COMMIT_ATTEMPTS,
+        })
+    }
+}
+
+impl ConversationGuard {
+    async fn batch_commit_inner(
+        &mut self,
+        key_packages: Vec<KeyPackageIn>,
+        removed_clients: Vec<ClientId>,
+        update: bool,
+        psks: Vec<PreSharedKeyId>,
+        staged: &mut Vec<MlsProposalRef>,
+    ) -> Result<(NewCrlDistributionPoints, MlsCommitBundle)> {
+        let session = &self.session().await?;
+        let backend = &self.crypto_provider().await?;
+        let mut conversation = self.conversation_mut().await;
+
+        let removed_members = removed_clients
+            .into_iter()
+            .map(|client_id| {
+                conversation
+                    .group
+                    .members()
+                    .find(|member| member.credential.identity() == client_id.as_slice())
+                    .map(|member| member.index)
+                    .ok_or(Error::ClientNotFound(client_id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Stage everything as proposals of our own, so that they are validated like any other, then commit
+        // them inline: their messages are never sent, so the other members could not resolve references to them
+        let mut crl_new_distribution_points = HashSet::new();
+        for (index, key_package) in key_packages.into_iter().enumerate() {
+            let proposal = conversation
+                .propose_add_member(session, backend, key_package)
+                .await
+                .map_err(|e| match e {
+                    // report which of the batch's key packages was refused
+                    Error::CredentialNotAdmitted {
+                        client_id, violation, ..
+                    } => Error::CredentialNotAdmitted {
+                        index,
+                        client_id,
+                        violation,
+                    },
+                    e => e,
+                })?;
+            staged.push(proposal.proposal_ref);
+            crl_new_distribution_points.extend(proposal.crl_new_distribution_points);
+        }
+        for member in removed_members {
+            let proposal = conversation.propose_remove_member(session, backend, member).await?;
+            staged.push(proposal.proposal_ref);
+        }
+        for psk_id in psks {
+            let proposal = conversation.propose_external_psk(session, backend, psk_id).await?;
+            staged.push(proposal.proposal_ref);
+        }
+
+        let inline_proposals = conversation
+            .group
+            .pending_proposals()
+            .filter(|proposal| {
+                staged
+                    .iter()
+                    .any(|proposal_ref| proposal_ref.to_bytes() == proposal.proposal_reference().as_slice())
+            })
+            .map(|proposal| proposal.proposal().clone())
+            .collect::<Vec<_>>();
+        drop(conversation);
+
+        let commit = match self.commit_inline_proposals(inline_proposals).await? {
+            Some(commit) => {
+                // our leaf node is renewed by the update path of the commit, which must not be silently missing
+                if update && !self.pending_commit_has_update_path().await {
+                    self.clear_pending_commit().await?;
+                    return Err(Error::MlsGroupInvalidState(
+                        "the batch commit has no update path to update our key material",
+                    ));
+                }
+                commit
+            }
+            // only our key material has to be updated
+            None => self.update_key_material_inner(None, None).await?,
+        };
+
+        let crl_new_distribution_points =
+            (!crl_new_distribution_points.is_empty()).then_some(crl_new_distribution_points);
+        Ok((crl_new_distribution_points.into(), commit))
+    }
+
+    /// Sends the commit of a batch via [crate::MlsTransport] and merges it once accepted.
+    ///
+    /// Unlike [Self::send_and_merge_commit], the commit is not renewed when the delivery service asks to
+    /// retry after other commits were accepted: returns `false` instead, and the batch has to be staged again.
+    async fn send_and_merge_batch_commit(&mut self, commit: MlsCommitBundle) -> Result<bool> {
+        if self.update_history_client().await? == HistoryClientUpdateOutcome::CommitSentAndMerged {
+            return Ok(true);
+        }
+
+        let transport = self.transport().await?;
+        let epoch_before_sending = self.epoch().await;
+        loop {
+            match transport
+                .send_commit_bundle(commit.clone())
+                .await
+                .map_err(RecursiveError::root("sending commit bundle"))?
+            {
+                MlsTransportResponse::Success => {
+                    self.merge_commit().await?;
+                    return Ok(true);
+                }
+                MlsTransportResponse::Abort { reason } => {
+                    self.clear_pending_commit().await?;
+                    return Err(Error::MessageRejected { reason });
+                }
+                // No intermediate commit has been processed, e.g. on network failure: send the same commit again
+                MlsTransportResponse::Retry if self.epoch().await == epoch_before_sending => continue,
+                // Merging the commits accepted first has discarded our pending commit
+                MlsTransportResponse::Retry => return Ok(false),
+            }
+        }
+    }
+
+    /// The clients among these which are still members of the conversation
+    async fn retain_members(&self, clients: Vec<ClientId>) -> Vec<ClientId> {
+        let conversation = self.conversation().await;
+        clients
+            .into_iter()
+            .filter(|client_id| {
+                conversation
+                    .group
+                    .members()
+                    .any(|member| member.credential.identity() == client_id.as_slice())
+            })
+            .collect()
+    }
+
+    /// Removes the proposals renewed from a batch commit when merging the commits accepted instead of it
+    async fn discard_own_pending_proposals(&mut self) -> Result<()> {
+        let renewed = self
+            .conversation()
+            .await
+            .self_pending_proposals()
+            .map(|proposal| MlsProposalRef::from(proposal.proposal_reference().as_slice().to_vec()))
+            .collect();
+        self.discard_staged_proposals(renewed).await
+    }
+
+    async fn pending_commit_has_update_path(&self) -> bool {
+        self.conversation()
+            .await
+            .group
+            .pending_commit()
+            .is_some_and(|commit| commit.get_update_path_leaf_node().is_some())
+    }
+
+    /// Removes the proposals staged by a batch commit which could not be created or merged. The ones
+    /// already dropped along with their epoch are skipped.
+    async fn discard_staged_proposals(&mut self, staged: Vec<MlsProposalRef>) -> Result<()> {
+        let keystore = self.crypto_provider().await?.keystore();
+        let mut conversation = self.conversation_mut().await;
+        let staged = staged
+            .into_iter()
+            .filter(|proposal_ref| {
+                conversation
+                    .group
+                    .pending_proposals()
+                    .any(|proposal| proposal_ref.to_bytes() == proposal.proposal_reference().as_slice())
+            })
+            .collect::<Vec<_>>();
+        if staged.is_empty() {
+            return Ok(());
+        }
+        for proposal_ref in &staged {
+            conversation
+                .group
+                .remove_pending_proposal(&keystore, proposal_ref)
+                .await
+                .map_err(MlsError::wrap("removing staged proposal"))?;
+        }
+        conversation.persist_group_when_changed(&keystore, true).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mls::conversation::{Conversation as _, ConversationWithMls as _};
+    use crate::test_utils::*;
+
+    #[apply(all_cred_cipher)]
+    async fn should_add_remove_and_update_in_a_single_commit(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+            let encryption_key = conversation.encryption_public_key().await;
+            let key_package = charlie.rand_key_package(&case).await;
+
+            conversation
+                .guard()
+                .await
+                .batch_commit()
+                .add_members([key_package])
+                .remove_members([bob.get_client_id().await])
+                .update_key_material()
+                .commit()
+                .await
+                .unwrap();
+
+            // Everything was sent through the transport as a single commit
+            let commit = alice.mls_transport().await.latest_commit_bundle().await;
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 1);
+            assert_ne!(conversation.encryption_public_key().await, encryption_key);
+
+            let decrypted = conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert!(!decrypted.is_active);
+
+            let welcome = commit.welcome.expect("a member was added");
+            charlie
+                .transaction
+                .process_welcome_message(welcome.into(), case.custom_cfg())
+                .await
+                .unwrap();
+            assert_eq!(conversation.guard_of(&charlie).await.epoch().await, epoch + 1);
+            assert!(conversation.can_talk(&alice, &charlie).await);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn remaining_members_should_process_batch_commit(case: TestContext) {
+        let [alice, bob, charlie, debbie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob, &charlie]).await;
+            let epoch = conversation.guard().await.epoch().await;
+            let encryption_key = conversation.encryption_public_key().await;
+            let key_package = debbie.rand_key_package(&case).await;
+
+            conversation
+                .guard()
+                .await
+                .batch_commit()
+                .add_members([key_package])
+                .update_key_material()
+                .commit()
+                .await
+                .unwrap();
+            assert_ne!(conversation.encryption_public_key().await, encryption_key);
+
+            // Bob never received the proposals of the batch, they have to be part of the commit itself
+            let commit = alice.mls_transport().await.latest_commit_bundle().await;
+            for member in [&bob, &charlie] {
+                let decrypted = conversation
+                    .guard_of(member)
+                    .await
+                    .decrypt_message(commit.commit.to_bytes().unwrap())
+                    .await
+                    .unwrap();
+                assert!(decrypted.is_active);
+                assert_eq!(conversation.guard_of(member).await.epoch().await, epoch + 1);
+            }
+
+            debbie
+                .transaction
+                .process_welcome_message(commit.welcome.unwrap().into(), case.custom_cfg())
+                .await
+                .unwrap();
+            assert!(conversation.can_talk(&alice, &bob).await);
+            assert!(conversation.can_talk(&bob, &debbie).await);
+            assert!(conversation.can_talk(&charlie, &debbie).await);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_reject_invalid_batches_without_staging(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+
+            let mut guard = conversation.guard().await;
+            let error = guard.batch_commit().commit().await.unwrap_err();
+            assert!(matches!(error, Error::CallerError(_)));
+
+            let key_package = charlie.rand_key_package(&case).await;
+            let error = guard
+                .batch_commit()
+                .add_members([key_package])
+                .remove_members([charlie.get_client_id().await])
+                .commit()
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::ClientNotFound(_)));
+
+            assert_eq!(guard.epoch().await, epoch);
+            assert_eq!(guard.conversation().await.group.pending_proposals().count(), 0);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_commit_whole_batch_again_on_retry(case: TestContext) {
+        const PSK_ID: &[u8] = b"guest link";
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob, &charlie]).await;
+            // Charlie lacks the pre-shared key, so cannot process a commit injecting it
+            for session in [&alice, &bob] {
+                session
+                    .transaction
+                    .store_external_psk(case.ciphersuite(), PSK_ID.to_vec(), b"secret shared out-of-band")
+                    .await
+                    .unwrap();
+            }
+            let epoch = conversation.guard().await.epoch().await;
+            let encryption_key = conversation.encryption_public_key().await;
+
+            // Bob's commit is accepted by the DS before Alice's
+            let commit = conversation.acting_as(&bob).await.update().await;
+            let intermediate_commit = commit.message();
+            let retry_provider = std::sync::Arc::new(
+                CoreCryptoTransportRetrySuccessProvider::default().with_intermediate_commits(
+                    alice.clone(),
+                    &[intermediate_commit],
+                    commit.conversation().id(),
+                ),
+            );
+            alice.replace_transport(retry_provider.clone()).await;
+            let conversation = commit.notify_member(&charlie).await.finish();
+
+            let psk_id = conversation
+                .guard()
+                .await
+                .pre_shared_key_id(openmls::prelude::Psk::External(openmls::prelude::ExternalPsk::new(
+                    PSK_ID.to_vec(),
+                )))
+                .await
+                .unwrap();
+            conversation
+                .guard()
+                .await
+                .batch_commit()
+                .psk(psk_id)
+                .update_key_material()
+                .commit()
+                .await
+                .unwrap();
+            assert_eq!(retry_provider.retry_count().await, 1);
+
+            // the commit accepted after the retry still contains the pre-shared key and the update
+            let commit = alice.mls_transport().await.latest_commit().await;
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 2);
+            assert_ne!(conversation.encryption_public_key().await, encryption_key);
+            assert!(
+                conversation
+                    .guard_of(&charlie)
+                    .await
+                    .decrypt_message(commit.to_bytes().unwrap())
+                    .await
+                    .is_err()
+            );
+            conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert!(conversation.can_talk(&alice, &bob).await);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_discard_staged_proposals_when_rejected(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+            alice
+                .replace_transport(std::sync::Arc::<CoreCryptoTransportAbortProvider>::default())
+                .await;
+
+            let key_package = charlie.rand_key_package(&case).await;
+            let mut guard = conversation.guard().await;
+            let error = guard
+                .batch_commit()
+                .add_members([key_package])
+                .update_key_material()
+                .commit()
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::MessageRejected { .. }));
+
+            assert_eq!(guard.epoch().await, epoch);
+            assert!(guard.conversation().await.group.pending_commit().is_none());
+            assert_eq!(guard.conversation().await.group.pending_proposals().count(), 0);
+        })
+        .await
+    }
+}
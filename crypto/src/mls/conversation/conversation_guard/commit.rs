@@ -1,6 +1,6 @@
 //! The methods in this module all produce or handle commits.
 
-use openmls::prelude::{KeyPackageIn, LeafNode};
+use openmls::prelude::{Extension, Extensions, KeyPackageIn, LeafNode};
 
 use crate::mls::conversation::{Conversation as _, ConversationWithMls as _, Error};
 use crate::mls::credential::CredentialBundle;
@@ -12,11 +12,15 @@ use crate::{
         conversation::{ConversationGuard, Result, commit::MlsCommitBundle},
         credential::crl::{extract_crl_uris_from_credentials, get_new_crl_distribution_points},
     },
-    prelude::ClientId,
+    prelude::{ClientId, MlsCustomExtension},
 };
 
 use super::history_sharing::HistoryClientUpdateOutcome;
 
+/// How many times [ConversationGuard::update_group_context_extensions] commits the extensions, when
+/// other commits keep being accepted by the delivery service first
+const GROUP_CONTEXT_EXTENSIONS_ATTEMPTS: usize = 3;
+
 /// What to do with a commit after it has been sent via [crate::MlsTransport].
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum TransportedCommitPolicy {
@@ -123,6 +127,10 @@ impl ConversationGuard {
             .await
             .map_err(RecursiveError::mls_credential("getting new crl distribution points"))?;
 
+        conversation
+            .enforce_admission_policy(&backend, key_packages.iter().map(|kp| kp.credential()))
+            .await?;
+
         let (commit, welcome, group_info) = conversation
             .group
             .add_members(&backend, signer, key_packages)
@@ -270,6 +278,92 @@ impl ConversationGuard {
         })
     }
 
+    /// Replaces the group context extensions of the conversation, for example to rotate the external
+    /// senders, change the required capabilities or add application-defined extensions. Pending
+    /// proposals will be committed as well.
+    ///
+    /// The provided extensions replace the current ones entirely, so the ones which should be kept
+    /// have to be part of `extensions` too.
+    ///
+    /// Fails with [Error::GroupContextExtensionsNotApplied] when the delivery service keeps accepting
+    /// other commits first.
+    pub async fn update_group_context_extensions(&mut self, extensions: Extensions) -> Result<()> {
+        for _ in 0..GROUP_CONTEXT_EXTENSIONS_ATTEMPTS {
+            let commit = self.update_group_context_extensions_inner(extensions.clone()).await?;
+            self.send_and_merge_commit(commit).await?;
+
+            // Unlike other proposals, group context extensions are not renewed when the delivery service
+            // asked us to retry because another commit was accepted first. In that case, commit them again.
+            if self.has_group_context_extensions(&extensions).await {
+                return Ok(());
+            }
+        }
+        Err(Error::GroupContextExtensionsNotApplied {
+            attempts: GROUP_CONTEXT_EXTENSIONS_ATTEMPTS,
+        })
+    }
+
+    /// Replaces the application-defined extensions of the conversation (see [MlsCustomExtension]),
+    /// keeping all other group context extensions as they are.
+    ///
+    /// Every member has to advertise the extension types in their leaf node capabilities.
+    pub async fn update_custom_extensions(&mut self, custom_extensions: Vec<MlsCustomExtension>) -> Result<()> {
+        let extensions = self
+            .conversation()
+            .await
+            .group
+            .group_context_extensions()
+            .iter()
+            .filter(|extension| !matches!(extension, Extension::Unknown(..)))
+            .cloned()
+            .chain(custom_extensions.into_iter().map(Extension::from))
+            .collect::<Vec<_>>();
+        let extensions =
+            Extensions::from_vec(extensions).map_err(MlsError::wrap("building group context extensions"))?;
+        self.update_group_context_extensions(extensions).await
+    }
+
+    pub(crate) async fn update_group_context_extensions_inner(
+        &mut self,
+        extensions: Extensions,
+    ) -> Result<MlsCommitBundle> {
+        self.ensure_no_pending_commit().await?;
+        let backend = &self.crypto_provider().await?;
+        let credential = self.credential_bundle().await?;
+        let signer = credential.signature_key();
+        let mut conversation = self.conversation_mut().await;
+
+        let (commit, welcome, group_info) = conversation
+            .group
+            .update_extensions(backend, signer, extensions)
+            .await
+            .map_err(MlsError::wrap("group update extensions"))?;
+
+        let group_info = Self::group_info(group_info)?;
+
+        conversation
+            .persist_group_when_changed(&backend.keystore(), false)
+            .await?;
+
+        Ok(MlsCommitBundle {
+            welcome,
+            commit,
+            group_info,
+            encrypted_message: None,
+        })
+    }
+
+    /// Whether the group context extensions are exactly `extensions`, in any order
+    async fn has_group_context_extensions(&self, extensions: &Extensions) -> bool {
+        let conversation = self.conversation().await;
+        let current = conversation.group.group_context_extensions();
+        // extensions are unique by type, so same length and inclusion means equality
+        current.iter().count() == extensions.iter().count()
+            && extensions
+                .iter()
+                .all(|extension| current.iter().any(|applied| applied == extension))
+    }
+
     /// Commits all pending proposals of the group
     pub async fn commit_pending_proposals(&mut self) -> Result<()> {
         self.ensure_no_pending_commit().await?;
@@ -0,0 +1,436 @@
+//! Messages and commits of future epochs are buffered until they can be processed (see
+//! [super::buffer_messages] and [super::buffer_commit]). Should the epoch they wait for never arrive, they
+//! would pile up forever; this module keeps the buffer of each conversation within the limits of its
+//! [crate::prelude::MlsBufferConfiguration] and lets the application inspect and drop buffered items.
+
+use core_crypto_keystore::{
+    connection::FetchFromDatabase as _,
+    entities::{MlsBufferedCommit, MlsPendingMessage},
+};
+use log::{info, warn};
+use obfuscate::Obfuscated;
+use openmls::framing::{MlsMessageIn, MlsMessageInBody};
+use sha2::{Digest as _, Sha256};
+use tls_codec::Deserialize as _;
+
+use super::Result;
+use crate::KeystoreError;
+use crate::mls::conversation::{ConversationGuard, ConversationWithMls as _, Error};
+
+/// What kind of message has been buffered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MlsBufferedItemKind {
+    /// An application message, or a proposal, of a future epoch
+    Message,
+    /// A commit referencing proposals we did not receive yet
+    Commit,
+}
+
+/// A message waiting in the buffer of a conversation, see [crate::transaction_context::TransactionContext::buffered_items]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsBufferedItem {
+    /// Identifies the item in the buffer; this is the SHA-256 hash of the message
+    pub id: Vec<u8>,
+    /// Whether this is a commit or another message
+    pub kind: MlsBufferedItemKind,
+    /// The epoch the message was sent in
+    pub epoch: u64,
+    /// Size of the message in bytes
+    pub size: usize,
+    /// UNIX timestamp (in seconds) of when the message got buffered. Unknown for messages buffered by
+    /// earlier versions.
+    pub received_at: Option<u64>,
+}
+
+/// Why a buffered item has been evicted, see [crate::mls::BufferObserver]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MlsEvictionReason {
+    /// The item has been buffered for longer than [crate::prelude::MlsBufferConfiguration::max_age]
+    TooOld,
+    /// The conversation buffers more than [crate::prelude::MlsBufferConfiguration::max_messages]
+    TooMany,
+    /// The conversation buffers more than [crate::prelude::MlsBufferConfiguration::max_bytes]
+    TooLarge,
+}
+
+impl MlsBufferedItem {
+    fn new(kind: MlsBufferedItemKind, message: &[u8], received_at: Option<u64>) -> Result<Self> {
+        let parsed = MlsMessageIn::tls_deserialize(&mut &*message).map_err(Error::tls_deserialize("mls message in"))?;
+        let epoch = match parsed.body_as_ref() {
+            MlsMessageInBody::PublicMessage(m) => m.epoch(),
+            MlsMessageInBody::PrivateMessage(m) => m.epoch(),
+            _ => return Err(Error::InappropriateMessageBodyType),
+        };
+        Ok(Self {
+            id: Sha256::digest(message).to_vec(),
+            kind,
+            epoch: epoch.as_u64(),
+            size: message.len(),
+            received_at,
+        })
+    }
+}
+
+/// The buffer of a conversation as stored in the keystore
+struct Buffer {
+    messages: Vec<(MlsBufferedItem, MlsPendingMessage)>,
+    commit: Option<MlsBufferedItem>,
+}
+
+impl Buffer {
+    /// All items, oldest first. Those of unknown age come first.
+    fn items(&self) -> Vec<MlsBufferedItem> {
+        let mut items = self
+            .messages
+            .iter()
+            .map(|(item, _)| item.clone())
+            .chain(self.commit.clone())
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| item.received_at);
+        items
+    }
+
+    /// Removes the item with this id, if buffered
+    fn take(&mut self, item_id: &[u8]) -> Option<MlsBufferedItem> {
+        if self.commit.as_ref().is_some_and(|commit| commit.id == item_id) {
+            return self.commit.take();
+        }
+        let index = self.messages.iter().position(|(item, _)| item.id == item_id)?;
+        Some(self.messages.remove(index).0)
+    }
+}
+
+impl ConversationGuard {
+    /// Loads the buffer of this conversation. Records which cannot be parsed would never be processed
+    /// nor evicted, hence are deleted right away.
+    async fn load_buffer(&self) -> Result<Buffer> {
+        let keystore = self.crypto_provider().await?.keystore();
+        let conversation_id = self.conversation().await.id().clone();
+        let mut messages = Vec::new();
+        let mut unparsable_messages = false;
+        for message in keystore
+            .find_pending_messages_by_conversation_id(&conversation_id)
+            .await
+            .map_err(KeystoreError::wrap("finding buffered messages"))?
+        {
+            match MlsBufferedItem::new(MlsBufferedItemKind::Message, &message.message, message.received_at()) {
+                Ok(item) => messages.push((item, message)),
+                Err(e) => {
+                    warn!(
+                        group_id = Obfuscated::from(&conversation_id), error:% = e;
+                        "Deleting a buffered message which cannot be parsed"
+                    );
+                    unparsable_messages = true;
+                }
+            }
+        }
+        let mut commit = None;
+        let mut unparsable_commit = false;
+        if let Some(buffered_commit) = keystore
+            .find::<MlsBufferedCommit>(&conversation_id)
+            .await
+            .map_err(KeystoreError::wrap("finding buffered commit"))?
+        {
+            match MlsBufferedItem::new(
+                MlsBufferedItemKind::Commit,
+                buffered_commit.commit_data(),
+                buffered_commit.received_at(),
+            ) {
+                Ok(item) => commit = Some(item),
+                Err(e) => {
+                    warn!(
+                        group_id = Obfuscated::from(&conversation_id), error:% = e;
+                        "Deleting a buffered commit which cannot be parsed"
+                    );
+                    unparsable_commit = true;
+                }
+            }
+        }
+        let buffer = Buffer { messages, commit };
+        if unparsable_messages || unparsable_commit {
+            self.store_buffer(&buffer, unparsable_messages, unparsable_commit)
+                .await?;
+        }
+        Ok(buffer)
+    }
+
+    /// Persists the buffer after items have been taken out of it
+    async fn store_buffer(&self, buffer: &Buffer, messages_taken: bool, commit_taken: bool) -> Result<()> {
+        let keystore = self.crypto_provider().await?.keystore();
+        let conversation = self.conversation().await;
+        if messages_taken {
+            // pending messages can only be removed all at once
+            keystore
+                .remove_pending_messages_by_conversation_id(conversation.id())
+                .await
+                .map_err(KeystoreError::wrap("removing buffered messages"))?;
+            for (_, message) in &buffer.messages {
+                keystore
+                    .save(message.clone())
+                    .await
+                    .map_err(KeystoreError::wrap("saving buffered message"))?;
+            }
+        }
+        if commit_taken {
+            keystore
+                .remove::<MlsBufferedCommit, _>(conversation.id())
+                .await
+                .map_err(KeystoreError::wrap("removing buffered commit"))?;
+        }
+        Ok(())
+    }
+
+    /// Evicts buffered items until the buffer fits the limits of the configuration, and reports them to
+    /// the [crate::mls::BufferObserver].
+    ///
+    /// Expired items are evicted first. Then the oldest messages are evicted while there are too many,
+    /// and finally the oldest items while they take too much space.
+    pub(super) async fn enforce_buffer_limits(&self) -> Result<()> {
+        let limits = self.conversation().await.configuration.custom.buffer.clone();
+        let now = crate::mls::unix_timestamp();
+        let mut buffer = self.load_buffer().await?;
+        let items = buffer.items();
+
+        let mut evicted = Vec::new();
+        let mut kept = Vec::with_capacity(items.len());
+        for item in items {
+            // items of unknown age are never considered expired
+            let expired = item
+                .received_at
+                .is_some_and(|received_at| now.saturating_sub(received_at) > limits.max_age.as_secs());
+            if expired {
+                evicted.push((item, MlsEvictionReason::TooOld));
+            } else {
+                kept.push(item);
+            }
+        }
+
+        let is_message = |item: &MlsBufferedItem| item.kind == MlsBufferedItemKind::Message;
+        let mut message_count = kept.iter().filter(|item| is_message(item)).count();
+        while message_count > limits.max_messages {
+            let Some(index) = kept.iter().position(is_message) else {
+                break;
+            };
+            evicted.push((kept.remove(index), MlsEvictionReason::TooMany));
+            message_count -= 1;
+        }
+
+        let mut size = kept.iter().map(|item| item.size).sum::<usize>();
+        while size > limits.max_bytes {
+            let item = kept.remove(0);
+            size -= item.size;
+            evicted.push((item, MlsEvictionReason::TooLarge));
+        }
+
+        if evicted.is_empty() {
+            return Ok(());
+        }
+
+        let conversation_id = self.conversation().await.id().clone();
+        info!(group_id = Obfuscated::from(&conversation_id); "Evicting {} buffered items", evicted.len());
+        for (item, _) in &evicted {
+            buffer.take(&item.id);
+        }
+        let messages_taken = evicted.iter().any(|(item, _)| is_message(item));
+        let commit_taken = evicted.iter().any(|(item, _)| !is_message(item));
+        self.store_buffer(&buffer, messages_taken, commit_taken).await?;
+
+        let session = self.session().await?;
+        for (item, reason) in evicted {
+            session
+                .notify_buffered_item_evicted(conversation_id.clone(), item, reason)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// The messages and the commit of future epochs buffered for this conversation, oldest first
+    pub async fn buffered_items(&self) -> Result<Vec<MlsBufferedItem>> {
+        Ok(self.load_buffer().await?.items())
+    }
+
+    /// Drops the buffered item with this id. It will not be processed once the conversation reaches its
+    /// epoch.
+    ///
+    /// # Errors
+    /// When no item with this id is buffered for this conversation
+    pub async fn drop_buffered_item(&self, item_id: &[u8]) -> Result<()> {
+        let mut buffer = self.load_buffer().await?;
+        let item = buffer.take(item_id).ok_or(Error::BufferedItemNotFound)?;
+        let is_message = item.kind == MlsBufferedItemKind::Message;
+        self.store_buffer(&buffer, is_message, !is_message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::MlsBufferConfiguration;
+    use crate::test_utils::*;
+
+    /// Returns messages Alice sends in an epoch Bob has not reached yet, since Bob did not merge the
+    /// commit creating it
+    async fn future_messages<'a>(
+        conversation: TestConversation<'a>,
+        [alice, bob]: [&SessionContext; 2],
+        count: usize,
+    ) -> (TestConversation<'a>, Vec<Vec<u8>>) {
+        let conversation = conversation
+            .acting_as(bob)
+            .await
+            .update_unmerged()
+            .await
+            .notify_member(alice)
+            .await
+            .finish();
+        let mut messages = Vec::with_capacity(count);
+        for i in 0..count {
+            let message = conversation
+                .guard()
+                .await
+                .encrypt_message(format!("message {i}"))
+                .await
+                .unwrap();
+            messages.push(message);
+        }
+        (conversation, messages)
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_evict_oldest_messages_beyond_count_limit(mut case: TestContext) {
+        case.cfg.custom.buffer = MlsBufferConfiguration {
+            max_messages: 2,
+            ..Default::default()
+        };
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let observer = TestBufferObserver::new();
+            bob.session()
+                .await
+                .register_buffer_observer(observer.clone())
+                .await
+                .unwrap();
+
+            let (conversation, messages) = future_messages(conversation, [&alice, &bob], 3).await;
+            for message in &messages {
+                let result = conversation.guard_of(&bob).await.decrypt_message(message).await;
+                assert!(matches!(result.unwrap_err(), Error::BufferedFutureMessage { .. }));
+            }
+
+            let items = conversation.guard_of(&bob).await.buffered_items().await.unwrap();
+            assert_eq!(items.len(), 2);
+            assert!(items.iter().all(|item| item.kind == MlsBufferedItemKind::Message));
+
+            let evictions = observer.observed_evictions().await;
+            let [(conversation_id, item, reason)] = &evictions[..] else {
+                panic!("exactly one message should have been evicted");
+            };
+            assert_eq!(conversation_id, conversation.id());
+            assert_eq!(*reason, MlsEvictionReason::TooMany);
+            assert!(!items.contains(item));
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_evict_messages_beyond_size_limit(mut case: TestContext) {
+        case.cfg.custom.buffer = MlsBufferConfiguration {
+            max_bytes: 1,
+            ..Default::default()
+        };
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let observer = TestBufferObserver::new();
+            bob.session()
+                .await
+                .register_buffer_observer(observer.clone())
+                .await
+                .unwrap();
+
+            let (conversation, messages) = future_messages(conversation, [&alice, &bob], 1).await;
+            let result = conversation.guard_of(&bob).await.decrypt_message(&messages[0]).await;
+            assert!(matches!(result.unwrap_err(), Error::BufferedFutureMessage { .. }));
+
+            let evictions = observer.observed_evictions().await;
+            let [(_, item, reason)] = &evictions[..] else {
+                panic!("the message should have been evicted");
+            };
+            assert_eq!(*reason, MlsEvictionReason::TooLarge);
+            assert_eq!(item.size, messages[0].len());
+            assert!(
+                conversation
+                    .guard_of(&bob)
+                    .await
+                    .buffered_items()
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_list_and_drop_buffered_items(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard_of(&bob).await.epoch().await;
+
+            let (conversation, messages) = future_messages(conversation, [&alice, &bob], 2).await;
+            for message in &messages {
+                let result = conversation.guard_of(&bob).await.decrypt_message(message).await;
+                assert!(matches!(result.unwrap_err(), Error::BufferedFutureMessage { .. }));
+            }
+
+            let items = bob.transaction.buffered_items(conversation.id()).await.unwrap();
+            assert_eq!(items.len(), 2);
+            assert!(items.iter().all(|item| item.epoch == epoch + 1));
+            assert!(items.iter().all(|item| item.received_at.is_some()));
+
+            let dropped = &items[0];
+            bob.transaction
+                .drop_buffered_item(conversation.id(), &dropped.id)
+                .await
+                .unwrap();
+            let remaining = bob.transaction.buffered_items(conversation.id()).await.unwrap();
+            assert_eq!(remaining, vec![items[1].clone()]);
+
+            let result = bob.transaction.drop_buffered_item(conversation.id(), &dropped.id).await;
+            assert!(result.is_err());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_delete_unparsable_buffered_messages(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+
+            let (conversation, messages) = future_messages(conversation, [&alice, &bob], 1).await;
+            let result = conversation.guard_of(&bob).await.decrypt_message(&messages[0]).await;
+            assert!(matches!(result.unwrap_err(), Error::BufferedFutureMessage { .. }));
+            bob.transaction
+                .keystore()
+                .await
+                .unwrap()
+                .save(MlsPendingMessage {
+                    foreign_id: conversation.id().clone(),
+                    message: b"not an mls message".to_vec(),
+                    received_at: None,
+                })
+                .await
+                .unwrap();
+
+            // the unparsable message neither prevents listing the buffer nor stays in it
+            let items = conversation.guard_of(&bob).await.buffered_items().await.unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].size, messages[0].len());
+            assert_eq!(bob.transaction.count_entities().await.pending_messages, 1);
+        })
+        .await
+    }
+}
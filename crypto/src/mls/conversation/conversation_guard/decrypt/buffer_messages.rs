@@ -29,6 +29,7 @@ impl ConversationGuard {
         let pending_msg = MlsPendingMessage {
             foreign_id: conversation.id().clone(),
             message: message.as_ref().to_vec(),
+            received_at: Some(crate::mls::unix_timestamp().to_be_bytes().to_vec()),
         };
         keystore
             .save::<MlsPendingMessage>(pending_msg)
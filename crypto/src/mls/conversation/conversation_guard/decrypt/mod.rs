@@ -9,6 +9,7 @@
 //! | 1+ pend. Proposal | ✅              | ✅              |
 
 mod buffer_commit;
+pub(crate) mod buffer_limits;
 pub(crate) mod buffer_messages;
 
 use super::{ConversationGuard, Result};
@@ -19,7 +20,7 @@ use crate::mls::credential::crl::{
     extract_crl_uris_from_proposals, extract_crl_uris_from_update_path, get_new_crl_distribution_points,
 };
 use crate::mls::credential::ext::CredentialExt as _;
-use crate::prelude::{ClientId, E2eiConversationState, Session};
+use crate::prelude::{ClientId, E2eiConversationState, MlsConversation, MlsCustomExtension, Session};
 use crate::prelude::{MlsProposalBundle, WireIdentity};
 use crate::{MlsError, RecursiveError};
 use log::{debug, info};
@@ -63,6 +64,9 @@ pub struct MlsConversationDecryptMessage {
     pub buffered_messages: Option<Vec<MlsBufferedConversationDecryptMessage>>,
     /// New CRL distribution points that appeared by the introduction of a new credential
     pub crl_new_distribution_points: NewCrlDistributionPoints,
+    /// Only set when the decrypted message is a commit replacing the group context extensions.
+    /// Contains the application-defined extensions of the new group context.
+    pub custom_extensions: Option<Vec<MlsCustomExtension>>,
 }
 
 /// Type safe recursion of [MlsConversationDecryptMessage]
@@ -85,6 +89,8 @@ pub struct MlsBufferedConversationDecryptMessage {
     pub identity: WireIdentity,
     /// see [MlsConversationDecryptMessage]
     pub crl_new_distribution_points: NewCrlDistributionPoints,
+    /// see [MlsConversationDecryptMessage]
+    pub custom_extensions: Option<Vec<MlsCustomExtension>>,
 }
 
 impl From<MlsConversationDecryptMessage> for MlsBufferedConversationDecryptMessage {
@@ -100,6 +106,7 @@ impl From<MlsConversationDecryptMessage> for MlsBufferedConversationDecryptMessa
             has_epoch_changed: from.has_epoch_changed,
             identity: from.identity,
             crl_new_distribution_points: from.crl_new_distribution_points,
+            custom_extensions: from.custom_extensions,
         }
     }
 }
@@ -142,11 +149,13 @@ impl ConversationGuard {
         // bytes; here, we do.
         if let Err(Error::BufferedFutureMessage { message_epoch }) = decrypt_message_result {
             self.buffer_future_message(message.as_ref()).await?;
+            self.enforce_buffer_limits().await?;
             let conversation = self.conversation().await;
             info!(group_id = Obfuscated::from(conversation.id()); "Buffered future message from epoch {message_epoch}");
         }
         if let Err(Error::BufferedCommit) = decrypt_message_result {
             self.buffer_commit(message).await?;
+            self.enforce_buffer_limits().await?;
         }
 
         let decrypt_message = decrypt_message_result?;
@@ -239,6 +248,7 @@ impl ConversationGuard {
                     identity,
                     buffered_messages: None,
                     crl_new_distribution_points: None.into(),
+                    custom_extensions: None,
                 }
             }
             ProcessedMessageContent::ProposalMessage(proposal) => {
@@ -303,6 +313,7 @@ impl ConversationGuard {
                     identity,
                     buffered_messages: None,
                     crl_new_distribution_points,
+                    custom_extensions: None,
                 }
             }
             ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
@@ -353,11 +364,33 @@ impl ConversationGuard {
 
                 let removed_members = Self::members_at_indices(removed_indices, conversation.group());
 
+                let replaces_extensions = staged_commit
+                    .queued_proposals()
+                    .any(|p| matches!(p.proposal(), Proposal::GroupContextExtensions(_)));
+                let reinit = MlsConversation::reinit_of(&staged_commit)?;
+                let previous_history_clients = conversation.history_client_ids();
+                let previous_members = client
+                    .has_membership_observer()
+                    .await
+                    .then(|| conversation.members_with_key());
+                conversation.baseline_e2ei_status(client, backend).await;
+
+                if conversation.updates_own_leaf(&staged_commit, false) {
+                    conversation.last_self_update = Some(crate::mls::unix_timestamp());
+                }
+
                 conversation
                     .group
                     .merge_staged_commit(backend, *staged_commit.clone())
                     .await
                     .map_err(MlsError::wrap("merge staged commit"))?;
+                conversation.retain_exporter_secrets(backend).await?;
+                if let Some(reinit) = reinit {
+                    conversation.record_reinit(backend, &reinit).await?;
+                }
+
+                let custom_extensions = replaces_extensions
+                    .then(|| MlsCustomExtension::from_extensions(conversation.group.group_context_extensions()));
 
                 let added_members = conversation
                     .group
@@ -394,6 +427,23 @@ impl ConversationGuard {
                     "Epoch advanced"
                 );
                 client.notify_epoch_changed(conversation.id.clone(), epoch).await;
+                client
+                    .notify_history_clients_changed(
+                        conversation.id.clone(),
+                        previous_history_clients,
+                        conversation.history_client_ids(),
+                    )
+                    .await;
+                if let Some(previous_members) = previous_members {
+                    // the commit is merged already, so failing to report it must not fail decrypting it
+                    match conversation.membership_changes(&previous_members, backend).await {
+                        Ok(changes) => client.notify_membership_changed(conversation.id.clone(), changes).await,
+                        Err(e) => {
+                            log::warn!(error:% = e; "Failed to compute the membership changes of a merged commit")
+                        }
+                    }
+                }
+                conversation.reevaluate_e2ei_status(client, backend, None).await;
 
                 // we still support the `has_epoch_changed` field, though we'll remove it later
                 #[expect(deprecated)]
@@ -407,6 +457,7 @@ impl ConversationGuard {
                     identity,
                     buffered_messages,
                     crl_new_distribution_points,
+                    custom_extensions,
                 }
             }
             ProcessedMessageContent::ExternalJoinProposalMessage(proposal) => {
@@ -436,6 +487,7 @@ impl ConversationGuard {
                     identity,
                     buffered_messages: None,
                     crl_new_distribution_points,
+                    custom_extensions: None,
                 }
             }
         };
@@ -476,6 +528,7 @@ impl ConversationGuard {
                 );
             }
         };
+        conversation.ensure_wire_policy_respected(&protocol_message)?;
         Ok(ParsedMessage {
             is_duplicate,
             protocol_message,
@@ -1,9 +1,11 @@
 use std::collections::HashSet;
 
 use itertools::{Either, Itertools as _};
+use tls_codec::Serialize as _;
 
 use crate::{
     RecursiveError,
+    ephemeral::HistoryEra,
     mls::conversation::{Conversation as _, ConversationWithMls, conversation_guard::commit::TransportedCommitPolicy},
     prelude::{HistorySecret, MlsCommitBundle},
 };
@@ -39,11 +41,29 @@ impl ConversationGuard {
     async fn send_new_history_client_commit(
         &mut self,
         mut commit: MlsCommitBundle,
-        history_secret: HistorySecret,
+        mut history_secret: HistorySecret,
     ) -> Result<()> {
         // Merge the commit locally so that we can encrypt the history secret with the new state.
         self.merge_commit().await?;
 
+        // Archive the secret of the era we're starting, so that its backlog can be replayed later. The era
+        // travels along with the secret, so that the other members can archive it too.
+        if let Some(welcome) = commit.welcome.as_ref() {
+            let welcome = welcome
+                .tls_serialize_detached()
+                .map_err(Error::tls_serialize("history client welcome"))?;
+            let epoch = self.epoch().await;
+            history_secret.era = Some(HistoryEra { epoch, welcome });
+            let conversation_id = self.conversation().await.id().clone();
+            crate::history_archive::archive(
+                &self.crypto_provider().await?.keystore(),
+                &conversation_id,
+                &history_secret,
+            )
+            .await
+            .map_err(RecursiveError::root("archiving history secret"))?;
+        }
+
         // Wrap and encrypt the history secret
         let transportable_history_secret = self
             .transport()
@@ -13,11 +13,16 @@ use crate::{
     KeystoreError, LeafError, RecursiveError, group_store::GroupStoreValue, prelude::MlsGroupInfoBundle,
     transaction_context::TransactionContext,
 };
+mod batch_commit;
 mod commit;
 pub(crate) mod decrypt;
 mod encrypt;
 mod history_sharing;
 mod merge;
+mod psk;
+mod reinit;
+
+pub use batch_commit::MlsBatchCommitBuilder;
 
 /// A Conversation Guard wraps a `GroupStoreValue<MlsConversation>`.
 ///
@@ -75,6 +80,7 @@ impl ConversationGuard {
     /// KeyStore errors, such as IO
     pub async fn wipe(&mut self) -> Result<()> {
         let provider = self.crypto_provider().await?;
+        let session = self.session().await?;
         let mut group_store = self
             .central_context
             .mls_groups()
@@ -88,6 +94,10 @@ impl ConversationGuard {
             .await
             .map_err(KeystoreError::wrap("deleting mls group"))?;
         let _ = group_store.remove(conversation.id());
+        session
+            .forget_e2ei_status(conversation.id())
+            .await
+            .map_err(RecursiveError::mls_client("forgetting e2ei status"))?;
         Ok(())
     }
 
@@ -145,20 +155,20 @@ pub mod test_utils {
     impl ConversationGuard {
         /// Replaces the MLS group in memory with the one from keystore.
         pub async fn drop_and_restore(&mut self) {
-            use core_crypto_keystore::CryptoKeystoreMls as _;
+            use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::PersistedMlsGroup};
             let context = self.context().await.unwrap();
             let inner = self.conversation().await;
             let id = inner.id();
 
-            let (parent_id, group) = context
+            let persisted = context
                 .keystore()
                 .await
                 .unwrap()
-                .mls_groups_restore()
+                .find::<PersistedMlsGroup>(id)
                 .await
-                .map(|mut groups| groups.remove(id.as_slice()).unwrap())
+                .unwrap()
                 .unwrap();
-            let group = MlsConversation::from_serialized_state(group, parent_id).unwrap();
+            let group = MlsConversation::from_serialized_state(&persisted).unwrap();
             context.mls_groups().await.unwrap().insert(id.clone(), group);
         }
     }
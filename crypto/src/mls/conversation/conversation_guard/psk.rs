@@ -0,0 +1,441 @@
+//! Injecting pre-shared keys into the key schedule of a conversation.
+//!
+//! RFC 9420 also uses resumption pre-shared keys to branch a conversation, with the usage `branch`,
+//! which OpenMLS cannot inject. A branch is instead linked to its conversation by storing the resumption
+//! secret of the epoch it was created in as an external pre-shared key, see [ConversationGuard::branch].
+//! This pre-shared key is only stored while the branch is created or joined.
+
+use mls_crypto_provider::MlsCryptoProvider;
+use openmls::prelude::{
+    ExternalPsk, KeyPackageIn, MlsMessageIn, PreSharedKeyId, Psk, ResumptionPsk, ResumptionPskUsage,
+};
+use openmls_traits::OpenMlsCryptoProvider as _;
+
+use super::ConversationGuard;
+use crate::{
+    MlsError, RecursiveError,
+    e2e_identity::NewCrlDistributionPoints,
+    mls::conversation::{Conversation as _, ConversationWithMls as _, Error, Result},
+    prelude::{
+        ConversationId, MlsConversation, MlsConversationConfiguration, MlsCustomConfiguration, RESERVED_PSK_ID_PREFIX,
+        WelcomeBundle,
+    },
+    transaction_context::psk::{delete_external_psk, write_external_psk},
+};
+
+impl ConversationGuard {
+    /// Creates a commit injecting the external pre-shared key stored under this id with
+    /// [crate::transaction_context::TransactionContext::store_external_psk], sends it via
+    /// [crate::MlsTransport] and merges it once accepted.
+    ///
+    /// Members which don't know the secret cannot process the commit. When the delivery service accepts
+    /// other commits first, the pre-shared key is injected by a new commit in the next epoch, see
+    /// [super::batch_commit::MlsBatchCommitBuilder::commit].
+    pub async fn inject_external_psk(&mut self, psk_id: Vec<u8>) -> Result<()> {
+        let psk_id = self.pre_shared_key_id(Psk::External(ExternalPsk::new(psk_id))).await?;
+        self.batch_commit().psk(psk_id).commit().await?;
+        Ok(())
+    }
+
+    /// Like [Self::inject_external_psk], but injects the resumption secret of a past epoch of this
+    /// conversation. This proves to the other members that we were part of the conversation back then.
+    ///
+    /// Only the resumption secret of the current epoch is kept, see
+    /// [MlsConversationConfiguration::NUMBER_RESUMPTION_PSK]. Hence this fails with
+    /// [Error::ResumptionPskNotRetained] as well when the delivery service accepts other commits first.
+    pub async fn inject_resumption_psk(&mut self, epoch: u64) -> Result<()> {
+        if !self.retains_resumption_psk(epoch).await {
+            return Err(Error::ResumptionPskNotRetained(epoch));
+        }
+        let group_id = self.conversation().await.group.group_id().clone();
+        let psk = Psk::Resumption(ResumptionPsk::new(
+            ResumptionPskUsage::Application,
+            group_id,
+            epoch.into(),
+        ));
+        let psk_id = self.pre_shared_key_id(psk).await?;
+        match self.batch_commit().psk(psk_id).commit().await {
+            Ok(_) => Ok(()),
+            // the commits accepted first moved the conversation past the epoch of the secret
+            Err(_) if !self.retains_resumption_psk(epoch).await => Err(Error::ResumptionPskNotRetained(epoch)),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn retains_resumption_psk(&self, epoch: u64) -> bool {
+        let current_epoch = self.epoch().await;
+        let oldest_epoch = current_epoch.saturating_sub(MlsConversationConfiguration::NUMBER_RESUMPTION_PSK as u64 - 1);
+        (oldest_epoch..=current_epoch).contains(&epoch)
+    }
+
+    /// Creates the conversation `new_id` as a branch of this one, with the given configuration, and adds
+    /// the owners of `key_packages` to it. They have to be members of this conversation, and join the
+    /// branch with [Self::join_branch] while it is still at its current epoch.
+    ///
+    /// # Errors
+    /// [Error::CallerError] when the configuration is of another ciphersuite, since a branch keeps the
+    /// ciphersuite of its conversation.
+    pub async fn branch(
+        &mut self,
+        new_id: ConversationId,
+        config: MlsConversationConfiguration,
+        key_packages: Vec<KeyPackageIn>,
+    ) -> Result<NewCrlDistributionPoints> {
+        if config.ciphersuite != self.ciphersuite().await {
+            return Err(Error::CallerError(
+                "a branch must keep the ciphersuite of its conversation",
+            ));
+        }
+        let backend = self.crypto_provider().await?;
+        let (link_psk_id, credential_type) = {
+            let conversation = self.conversation().await;
+            let epoch = conversation.group.epoch().as_u64();
+            let link_psk_id = conversation.store_branch_psk(&backend, epoch).await?;
+            (link_psk_id, conversation.own_credential_type()?)
+        };
+
+        let context = self.context().await?;
+        let result = async {
+            context
+                .new_conversation(&new_id, credential_type, config)
+                .await
+                .map_err(RecursiveError::transaction("creating the branch"))?;
+            let mut branch = context
+                .conversation(&new_id)
+                .await
+                .map_err(RecursiveError::transaction("getting the branch"))?;
+            let psk_id = branch
+                .pre_shared_key_id(Psk::External(ExternalPsk::new(link_psk_id.clone())))
+                .await?;
+            branch
+                .batch_commit()
+                .add_members(key_packages)
+                .psk(psk_id)
+                .commit()
+                .await
+        }
+        .await;
+        delete_external_psk(&backend, link_psk_id).await?;
+        result
+    }
+
+    /// Joins a branch of this conversation, created with [Self::branch], from its welcome
+    pub async fn join_branch(
+        &mut self,
+        welcome: MlsMessageIn,
+        custom_cfg: MlsCustomConfiguration,
+    ) -> Result<WelcomeBundle> {
+        let backend = self.crypto_provider().await?;
+        let link_psk_id = {
+            let conversation = self.conversation().await;
+            let epoch = conversation.group.epoch().as_u64();
+            conversation.store_branch_psk(&backend, epoch).await?
+        };
+        let result = self
+            .context()
+            .await?
+            .process_welcome_message(welcome, custom_cfg)
+            .await
+            .map_err(RecursiveError::transaction("joining the branch"))
+            .map_err(Into::into);
+        delete_external_psk(&backend, link_psk_id).await?;
+        result
+    }
+
+    /// The id of a pre-shared key to inject, with a fresh nonce
+    pub(super) async fn pre_shared_key_id(&self, psk: Psk) -> Result<PreSharedKeyId> {
+        let backend = self.crypto_provider().await?;
+        let ciphersuite = self.ciphersuite().await;
+        PreSharedKeyId::new(ciphersuite.into(), backend.rand(), psk)
+            .map_err(MlsError::wrap("creating pre-shared key id"))
+            .map_err(Into::into)
+    }
+}
+
+impl MlsConversation {
+    /// Stores the resumption secret of `epoch` as the external pre-shared key linking a branch created
+    /// in that epoch, and returns the id of this pre-shared key. It must be deleted once the branch is
+    /// created or joined.
+    async fn store_branch_psk(&self, backend: &MlsCryptoProvider, epoch: u64) -> Result<Vec<u8>> {
+        let secret = self.resumption_secret(epoch)?;
+        let psk_id = [
+            RESERVED_PSK_ID_PREFIX,
+            b"branch/",
+            &epoch.to_be_bytes(),
+            self.id.as_slice(),
+        ]
+        .concat();
+        write_external_psk::<Error>(backend, self.ciphersuite(), psk_id.clone(), &secret).await?;
+        Ok(psk_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mls::conversation::Conversation as _;
+    use crate::test_utils::*;
+
+    const PSK_ID: &[u8] = b"guest link";
+    const PSK: &[u8] = b"secret shared out-of-band";
+
+    #[apply(all_cred_cipher)]
+    async fn should_inject_external_psk(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            for session in [&alice, &bob] {
+                session
+                    .transaction
+                    .store_external_psk(case.ciphersuite(), PSK_ID.to_vec(), PSK)
+                    .await
+                    .unwrap();
+            }
+
+            let epoch = conversation.guard().await.epoch().await;
+            conversation
+                .guard()
+                .await
+                .inject_external_psk(PSK_ID.to_vec())
+                .await
+                .unwrap();
+            let commit = alice.mls_transport().await.latest_commit().await;
+            conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(conversation.guard_of(&bob).await.epoch().await, epoch + 1);
+            assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_inject_external_psk_again_on_retry(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob, &charlie]).await;
+            for session in [&alice, &bob] {
+                session
+                    .transaction
+                    .store_external_psk(case.ciphersuite(), PSK_ID.to_vec(), PSK)
+                    .await
+                    .unwrap();
+            }
+            let epoch = conversation.guard().await.epoch().await;
+
+            // Bob's commit is accepted by the DS before Alice's
+            let commit = conversation.acting_as(&bob).await.update().await;
+            let intermediate_commit = commit.message();
+            let retry_provider = std::sync::Arc::new(
+                CoreCryptoTransportRetrySuccessProvider::default().with_intermediate_commits(
+                    alice.clone(),
+                    &[intermediate_commit],
+                    commit.conversation().id(),
+                ),
+            );
+            alice.replace_transport(retry_provider.clone()).await;
+            let conversation = commit.notify_member(&charlie).await.finish();
+
+            conversation
+                .guard()
+                .await
+                .inject_external_psk(PSK_ID.to_vec())
+                .await
+                .unwrap();
+            assert_eq!(retry_provider.retry_count().await, 1);
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 2);
+
+            // Charlie lacks the secret: the commit sent after the retry injects it
+            let commit = alice.mls_transport().await.latest_commit().await;
+            assert!(
+                conversation
+                    .guard_of(&charlie)
+                    .await
+                    .decrypt_message(commit.to_bytes().unwrap())
+                    .await
+                    .is_err()
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_inject_resumption_psk_of_epoch_left_on_retry(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+
+            // Bob's commit is accepted by the DS before Alice's
+            let commit = conversation.acting_as(&bob).await.update().await;
+            let intermediate_commit = commit.message();
+            let retry_provider = std::sync::Arc::new(
+                CoreCryptoTransportRetrySuccessProvider::default().with_intermediate_commits(
+                    alice.clone(),
+                    &[intermediate_commit],
+                    commit.conversation().id(),
+                ),
+            );
+            alice.replace_transport(retry_provider.clone()).await;
+            let conversation = commit.finish();
+
+            let error = conversation
+                .guard()
+                .await
+                .inject_resumption_psk(epoch)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::ResumptionPskNotRetained(e) if e == epoch));
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 1);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_store_external_psk_with_reserved_id(case: TestContext) {
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let psk_id = [RESERVED_PSK_ID_PREFIX, b"branch/"].concat();
+            let result = alice
+                .transaction
+                .store_external_psk(case.ciphersuite(), psk_id, PSK)
+                .await;
+            assert!(matches!(
+                result.unwrap_err(),
+                crate::transaction_context::Error::CallerError(_)
+            ));
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_process_commit_with_unknown_psk(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            alice
+                .transaction
+                .store_external_psk(case.ciphersuite(), PSK_ID.to_vec(), PSK)
+                .await
+                .unwrap();
+
+            conversation
+                .guard()
+                .await
+                .inject_external_psk(PSK_ID.to_vec())
+                .await
+                .unwrap();
+            let commit = alice.mls_transport().await.latest_commit().await;
+            let result = conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await;
+            assert!(result.is_err());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_branch_conversation(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let branch_id = conversation_id();
+
+            let key_packages = vec![bob.rand_key_package(&case).await, charlie.rand_key_package(&case).await];
+            conversation
+                .guard()
+                .await
+                .branch(branch_id.clone(), case.cfg.clone(), key_packages)
+                .await
+                .unwrap();
+            let welcome = alice.mls_transport().await.latest_welcome_message().await;
+
+            // Charlie is not a member of the conversation and lacks the secret linking the branch to it
+            let result = charlie
+                .transaction
+                .process_welcome_message(welcome.clone().into(), case.custom_cfg())
+                .await;
+            assert!(result.is_err());
+
+            conversation
+                .guard_of(&bob)
+                .await
+                .join_branch(welcome.into(), case.custom_cfg())
+                .await
+                .unwrap();
+            let branch = TestConversation::new_from_existing(&case, branch_id, [&alice, &bob]).await;
+            assert!(branch.can_talk(&alice, &bob).await);
+
+            // the secrets linking the branch are not kept once it is created and joined
+            for session in [&alice, &bob] {
+                assert_eq!(session.transaction.count_entities().await.psk_bundle, 0);
+                assert_eq!(session.transaction.count_entities().await.external_psk, 0);
+            }
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_join_branch_in_later_epoch(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+
+            let key_packages = vec![bob.rand_key_package(&case).await];
+            conversation
+                .guard()
+                .await
+                .branch(conversation_id(), case.cfg.clone(), key_packages)
+                .await
+                .unwrap();
+            let welcome = alice.mls_transport().await.latest_welcome_message().await;
+
+            // the resumption secret of the epoch the branch was created in is gone
+            let conversation = conversation.acting_as(&bob).await.advance_epoch().await;
+            let result = conversation
+                .guard_of(&bob)
+                .await
+                .join_branch(welcome.into(), case.custom_cfg())
+                .await;
+            assert!(result.is_err());
+            assert_eq!(bob.transaction.count_entities().await.psk_bundle, 0);
+            assert_eq!(bob.transaction.count_entities().await.external_psk, 0);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_inject_resumption_psk_of_current_epoch(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+
+            conversation.guard().await.inject_resumption_psk(epoch).await.unwrap();
+            let commit = alice.mls_transport().await.latest_commit().await;
+            conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+
+            // the commit moved the conversation to the next epoch
+            let error = conversation
+                .guard()
+                .await
+                .inject_resumption_psk(epoch)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::ResumptionPskNotRetained(e) if e == epoch));
+        })
+        .await
+    }
+}
@@ -0,0 +1,229 @@
+//! Migrating a conversation to a new one, see [crate::mls::conversation::reinit].
+
+use openmls::prelude::{ExternalPsk, KeyPackageIn, Psk};
+
+use super::ConversationGuard;
+use crate::{
+    RecursiveError,
+    e2e_identity::NewCrlDistributionPoints,
+    mls::conversation::{ConversationWithMls as _, Error, Result},
+    prelude::{ConversationId, MlsConversationConfiguration, MlsReInit},
+};
+
+impl ConversationGuard {
+    /// Reinitializes this conversation into a new one, e.g. to migrate it to another ciphersuite.
+    ///
+    /// This commits a ReInit proposal to this conversation, then creates the conversation `new_id` with
+    /// the given configuration and adds the owners of `key_packages` to it. They join it from the welcome
+    /// once they have processed the ReInit commit. The [crate::mls::ReInitObserver] of each member is
+    /// notified once they are part of the new conversation.
+    ///
+    /// The key packages have to be of the new ciphersuite. The old conversation is left as is; it is up
+    /// to the application to wipe it.
+    ///
+    /// Can be called again with the same arguments when creating the new conversation failed after the
+    /// ReInit commit had been merged.
+    pub async fn reinit(
+        &mut self,
+        new_id: ConversationId,
+        config: MlsConversationConfiguration,
+        key_packages: Vec<KeyPackageIn>,
+    ) -> Result<NewCrlDistributionPoints> {
+        let old_id = self.conversation().await.id().clone();
+        let reinit = MlsReInit {
+            new_id: new_id.clone(),
+            ciphersuite: config.ciphersuite,
+        };
+        let keystore = self.crypto_provider().await?.keystore();
+
+        match MlsReInit::find(&keystore, &old_id).await? {
+            Some(merged) if merged != reinit => return Err(Error::AlreadyReInitialized),
+            Some(_) => {}
+            None => {
+                self.ensure_no_pending_commit().await?;
+                let commit = self
+                    .commit_inline_proposals(vec![reinit.to_proposal()?])
+                    .await?
+                    .ok_or(Error::MlsGroupInvalidState("no commit for the ReInit proposal"))?;
+                self.send_and_merge_commit(commit).await?;
+                if MlsReInit::find(&keystore, &old_id).await?.is_none() {
+                    return Err(Error::ReInitNotMerged);
+                }
+            }
+        }
+
+        let context = self.context().await?;
+        let credential_type = self.conversation().await.own_credential_type()?;
+        context
+            .new_conversation(&new_id, credential_type, config)
+            .await
+            .map_err(RecursiveError::transaction("creating the reinitialized conversation"))?;
+        let mut new_conversation = context
+            .conversation(&new_id)
+            .await
+            .map_err(RecursiveError::transaction("getting the reinitialized conversation"))?;
+        let psk_id = new_conversation
+            .pre_shared_key_id(Psk::External(ExternalPsk::new(MlsReInit::link_psk_id(&old_id))))
+            .await?;
+        let crl_new_distribution_points = new_conversation
+            .batch_commit()
+            .add_members(key_packages)
+            .psk(psk_id)
+            .commit()
+            .await?;
+        MlsReInit::delete_link_psk(&self.crypto_provider().await?, &old_id).await?;
+
+        self.session()
+            .await?
+            .notify_conversation_reinitialized(old_id, new_id)
+            .await;
+        Ok(crl_new_distribution_points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mls::conversation::Conversation as _;
+    use crate::test_utils::*;
+
+    #[apply(all_cred_cipher)]
+    async fn should_reinitialize_conversation(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let old_id = conversation.id().clone();
+            let new_id = conversation_id();
+
+            let observers = [TestReInitObserver::new(), TestReInitObserver::new()];
+            for (session, observer) in [&alice, &bob].into_iter().zip(&observers) {
+                session
+                    .session()
+                    .await
+                    .register_reinit_observer(observer.clone())
+                    .await
+                    .unwrap();
+            }
+
+            let key_package = bob.rand_key_package(&case).await;
+            conversation
+                .guard()
+                .await
+                .reinit(new_id.clone(), case.cfg.clone(), vec![key_package])
+                .await
+                .unwrap();
+            let [.., reinit_commit, commit] = &alice.mls_transport().await.commit_bundles().await[..] else {
+                panic!("the ReInit commit and the commit adding Bob should have been sent");
+            };
+            assert!(alice.transaction.conversation_exists(&new_id).await.unwrap());
+
+            // Bob has to merge the ReInit commit to know the secret linking both conversations
+            conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(reinit_commit.commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            let expected = MlsReInit {
+                new_id: new_id.clone(),
+                ciphersuite: case.ciphersuite(),
+            };
+            for session in [&alice, &bob] {
+                let reinit = session.transaction.conversation_reinit(&old_id).await.unwrap();
+                assert_eq!(reinit, Some(expected.clone()));
+            }
+            assert!(observers[1].observed_reinits().await.is_empty());
+
+            let welcome = commit.welcome.clone().expect("bob was added to the new conversation");
+            bob.transaction
+                .process_welcome_message(welcome.into(), case.custom_cfg())
+                .await
+                .unwrap();
+
+            for observer in &observers {
+                assert_eq!(
+                    observer.observed_reinits().await,
+                    vec![(old_id.clone(), new_id.clone())]
+                );
+            }
+            TestConversation::new_from_existing(&case, new_id, [&alice, &bob]).await;
+
+            // the secret linking both conversations is not kept once the new one is created and joined
+            for session in [&alice, &bob] {
+                assert_eq!(session.transaction.count_entities().await.psk_bundle, 0);
+                assert_eq!(session.transaction.count_entities().await.external_psk, 0);
+            }
+
+            // the conversation cannot be reinitialized into yet another one
+            let error = conversation
+                .guard()
+                .await
+                .reinit(conversation_id(), case.cfg.clone(), vec![])
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::AlreadyReInitialized));
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_join_reinitialized_conversation_without_link(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let new_id = conversation_id();
+
+            let key_package = bob.rand_key_package(&case).await;
+            conversation
+                .guard()
+                .await
+                .reinit(new_id.clone(), case.cfg.clone(), vec![key_package])
+                .await
+                .unwrap();
+            let commit = alice.mls_transport().await.latest_commit_bundle().await;
+
+            // Bob never processed the ReInit commit, hence cannot prove being a member of the old conversation
+            let welcome = commit.welcome.expect("bob was added to the new conversation");
+            let result = bob
+                .transaction
+                .process_welcome_message(welcome.into(), case.custom_cfg())
+                .await;
+            assert!(result.is_err());
+            assert!(!bob.transaction.conversation_exists(&new_id).await.unwrap());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_delete_link_when_wiping_reinitialized_conversation(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+
+            let key_package = bob.rand_key_package(&case).await;
+            conversation
+                .guard()
+                .await
+                .reinit(conversation_id(), case.cfg.clone(), vec![key_package])
+                .await
+                .unwrap();
+            let [.., reinit_commit, _] = &alice.mls_transport().await.commit_bundles().await[..] else {
+                panic!("the ReInit commit and the commit adding Bob should have been sent");
+            };
+            conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(reinit_commit.commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(bob.transaction.count_entities().await.psk_bundle, 1);
+            assert_eq!(bob.transaction.count_entities().await.external_psk, 1);
+
+            // Bob never joins the new conversation
+            conversation.guard_of(&bob).await.wipe().await.unwrap();
+            assert_eq!(bob.transaction.count_entities().await.psk_bundle, 0);
+            assert_eq!(bob.transaction.count_entities().await.external_psk, 0);
+        })
+        .await
+    }
+}
@@ -0,0 +1,171 @@
+//! Application-defined group context extensions.
+//!
+//! Their types have to be registered with [crate::prelude::SessionConfig::custom_capabilities], so that
+//! our key packages and leaf nodes advertise them. A conversation can then require them with
+//! [crate::prelude::MlsCustomConfiguration::required_capabilities].
+
+use openmls::prelude::{Extension, Extensions, UnknownExtension};
+
+/// An application-defined group context extension, for example to store per-conversation metadata
+/// inside the group state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsCustomExtension {
+    /// Type of the extension, see [crate::prelude::MlsCustomCapabilities::PRIVATE_USE_RANGE]
+    pub extension_type: u16,
+    /// Payload of the extension, opaque to CoreCrypto
+    pub data: Vec<u8>,
+}
+
+impl MlsCustomExtension {
+    /// Extracts the application-defined extensions out of the given ones
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Vec<Self> {
+        extensions
+            .iter()
+            .filter_map(|extension| match extension {
+                Extension::Unknown(extension_type, UnknownExtension(data)) => Some(Self {
+                    extension_type: *extension_type,
+                    data: data.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl From<MlsCustomExtension> for Extension {
+    fn from(extension: MlsCustomExtension) -> Self {
+        Extension::Unknown(extension.extension_type, UnknownExtension(extension.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openmls::prelude::{ExtensionType, ProposalType};
+
+    use super::*;
+    use crate::mls::conversation::{Conversation as _, ConversationWithMls as _};
+    use crate::prelude::MlsCustomCapabilities;
+    use crate::test_utils::*;
+
+    const NAME_HASH_EXTENSION: u16 = 0xF0A1;
+    const FLAGS_PROPOSAL: u16 = 0xF0A2;
+
+    fn custom_capabilities() -> MlsCustomCapabilities {
+        MlsCustomCapabilities {
+            extension_types: vec![NAME_HASH_EXTENSION],
+            proposal_types: vec![FLAGS_PROPOSAL],
+        }
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_advertise_and_require_custom_capabilities(mut case: TestContext) {
+        case.custom_capabilities = custom_capabilities();
+        case.cfg.custom.required_capabilities = custom_capabilities();
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let key_package = bob.get_one_key_package(&case).await;
+            let capabilities = key_package.leaf_node().capabilities();
+            assert_eq!(capabilities.extensions(), &[ExtensionType::from(NAME_HASH_EXTENSION)]);
+            assert_eq!(capabilities.proposals(), &[ProposalType::from(FLAGS_PROPOSAL)]);
+
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let guard = conversation.guard().await;
+            let inner = guard.conversation().await;
+            let required = inner.group.group_context_extensions().required_capabilities().unwrap();
+            assert_eq!(required.extension_types(), &[ExtensionType::from(NAME_HASH_EXTENSION)]);
+            assert_eq!(required.proposal_types(), &[ProposalType::from(FLAGS_PROPOSAL)]);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_advertise_custom_capabilities_after_joining_by_welcome(mut case: TestContext) {
+        case.custom_capabilities = custom_capabilities();
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+
+            // Bob's new leaf node is built from the configuration he joined with
+            let conversation = conversation.acting_as(&bob).await.update_notify().await;
+            let guard = conversation.guard_of(&bob).await;
+            let inner = guard.conversation().await;
+            let capabilities = inner.group.own_leaf().unwrap().capabilities();
+            assert_eq!(capabilities.extensions(), &[ExtensionType::from(NAME_HASH_EXTENSION)]);
+            assert_eq!(capabilities.proposals(), &[ProposalType::from(FLAGS_PROPOSAL)]);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_reject_required_capabilities_outside_private_use_range(mut case: TestContext) {
+        case.cfg.custom.required_capabilities = MlsCustomCapabilities {
+            extension_types: vec![NAME_HASH_EXTENSION, 0x0005],
+            proposal_types: vec![],
+        };
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let id = conversation_id();
+            let error = alice
+                .transaction
+                .new_conversation(&id, case.credential_type, case.cfg.clone())
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                crate::transaction_context::Error::Recursive(crate::RecursiveError::MlsConversation { source, .. })
+                    if matches!(*source, crate::mls::conversation::Error::InvalidCustomType {
+                        kind: "extension",
+                        value: 0x0005
+                    })
+            ));
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_surface_custom_extensions_of_decrypted_commit(mut case: TestContext) {
+        case.custom_capabilities = custom_capabilities();
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            assert!(conversation.guard().await.custom_extensions().await.is_empty());
+
+            let extension = MlsCustomExtension {
+                extension_type: NAME_HASH_EXTENSION,
+                data: b"conversation name hash".to_vec(),
+            };
+            conversation
+                .guard()
+                .await
+                .update_custom_extensions(vec![extension.clone()])
+                .await
+                .unwrap();
+            let commit = alice.mls_transport().await.latest_commit().await;
+
+            let decrypted = conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(decrypted.custom_extensions, Some(vec![extension.clone()]));
+
+            for session in [&alice, &bob] {
+                let extensions = conversation.guard_of(session).await.custom_extensions().await;
+                assert_eq!(extensions, vec![extension.clone()]);
+            }
+
+            // Commits which leave the group context extensions untouched don't report any
+            let commit = conversation.acting_as(&alice).await.update().await;
+            let decrypted = commit
+                .conversation()
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.message().to_bytes().unwrap())
+                .await
+                .unwrap();
+            assert!(decrypted.custom_extensions.is_none());
+        })
+        .await
+    }
+}
@@ -4,16 +4,16 @@ impl MlsConversation {
     /// Replaces the MLS group in memory with the one from keystore.
     /// see [crate::durable]
     pub async fn drop_and_restore(&mut self, backend: &mls_crypto_provider::MlsCryptoProvider) {
-        use core_crypto_keystore::CryptoKeystoreMls as _;
+        use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::PersistedMlsGroup};
 
         let group_id = self.group.group_id();
-        let (parent_id, group) = backend
+        let persisted = backend
             .keystore()
-            .mls_groups_restore()
+            .find::<PersistedMlsGroup>(group_id.as_slice())
             .await
-            .map(|mut groups| groups.remove(group_id.as_slice()).unwrap())
+            .unwrap()
             .unwrap();
-        let group = MlsConversation::from_serialized_state(group, parent_id).unwrap();
+        let group = MlsConversation::from_serialized_state(&persisted).unwrap();
         *self = group;
     }
 }
@@ -0,0 +1,99 @@
+//! Evaluation of the end-to-end identity status of a conversation and of its members, reported to the
+//! [crate::mls::E2eiStatusObserver].
+
+use std::collections::HashMap;
+
+use mls_crypto_provider::MlsCryptoProvider;
+use openmls_traits::OpenMlsCryptoProvider as _;
+
+use super::{MlsConversation, Result};
+use crate::{
+    RecursiveError,
+    mls::{credential::ext::CredentialExt as _, session::E2eiStatusSnapshot},
+    prelude::{ClientId, DeviceStatus, E2eiConversationState, MlsCredentialType, Session},
+};
+
+impl MlsConversation {
+    /// The end-to-end identity status of this conversation and of its members having a X509 credential.
+    ///
+    /// When `at` (in seconds since the UNIX epoch) is given, certificates expiring before it are
+    /// considered expired. Members whose identity cannot be extracted are left out.
+    pub(crate) async fn e2ei_status_snapshot(
+        &self,
+        backend: &MlsCryptoProvider,
+        at: Option<u64>,
+    ) -> Result<E2eiStatusSnapshot> {
+        let authentication_service = backend.authentication_service();
+        authentication_service.refresh_time_of_interest().await;
+        let authentication_service = authentication_service.borrow().await;
+        let env = authentication_service.as_ref();
+
+        let mut devices = HashMap::new();
+        for (id, credential) in self.members_with_key() {
+            if credential.credential.is_basic() {
+                continue;
+            }
+            let Ok(identity) = credential.extract_identity(self.ciphersuite(), env) else {
+                continue;
+            };
+            let mut status = identity.status;
+            if let Some(at) = at
+                && status == DeviceStatus::Valid
+                && Session::certificate_expiry(&credential.credential)
+                    .map_err(RecursiveError::mls_client("getting certificate expiry"))?
+                    .is_some_and(|not_after| not_after <= at)
+            {
+                status = DeviceStatus::Expired;
+            }
+            devices.insert(ClientId::from(id), status);
+        }
+
+        let mut state = Session::compute_conversation_state(
+            self.ciphersuite(),
+            self.group.members_credentials(),
+            MlsCredentialType::X509,
+            env,
+        )
+        .await;
+        if state == E2eiConversationState::Verified && devices.values().any(|status| *status != DeviceStatus::Valid) {
+            state = E2eiConversationState::NotVerified;
+        }
+        Ok(E2eiStatusSnapshot { state, devices })
+    }
+
+    /// Records the end-to-end identity status of this conversation, if not done yet, so that its next
+    /// evaluation can be compared to it. Only done when an observer is registered.
+    pub(crate) async fn baseline_e2ei_status(&self, client: &Session, backend: &MlsCryptoProvider) {
+        match client.has_e2ei_status(&self.id).await {
+            Ok(false) => self.reevaluate_e2ei_status(client, backend, None).await,
+            Ok(true) => {}
+            Err(e) => log::warn!(error:% = e; "Failed to read the end-to-end identity status of a conversation"),
+        }
+    }
+
+    /// Evaluates the end-to-end identity status of this conversation, notifying the observer of the
+    /// changes since its last evaluation. Only done when an observer is registered.
+    ///
+    /// When `at` is given, the status as of then is only compared with the last evaluation, which it
+    /// does not replace. Failing to evaluate the status is only logged, so that observing it never
+    /// makes an operation fail.
+    pub(crate) async fn reevaluate_e2ei_status(&self, client: &Session, backend: &MlsCryptoProvider, at: Option<u64>) {
+        if !client.has_e2ei_status_observer().await {
+            return;
+        }
+        let snapshot = match self.e2ei_status_snapshot(backend, at).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                log::warn!(error:% = e; "Failed to evaluate the end-to-end identity status of a conversation");
+                return;
+            }
+        };
+        let result = match at {
+            Some(_) => client.compare_e2ei_status(self.id.clone(), snapshot).await,
+            None => client.update_e2ei_status(self.id.clone(), snapshot).await,
+        };
+        if let Err(e) = result {
+            log::warn!(error:% = e; "Failed to record the end-to-end identity status of a conversation");
+        }
+    }
+}
@@ -42,6 +42,8 @@ pub enum Error {
         "You tried to join with an external commit but did not merge it yet. We will reapply this message for you when you merge your external commit"
     )]
     BufferedForPendingConversation,
+    #[error("No buffered message or commit has this id")]
+    BufferedItemNotFound,
     #[error("Incoming message is from an epoch too far in the future to buffer.")]
     UnbufferedFarFutureMessage,
     #[error("The received commit is deemed stale and is from an older epoch.")]
@@ -60,6 +62,8 @@ pub enum Error {
     MissingExternalSenderExtension,
     #[error("Couldn't find pending proposal {0}")]
     PendingProposalNotFound(crate::mls::proposal::MlsProposalRef),
+    #[error("Couldn't find client {0:?} in the conversation")]
+    ClientNotFound(crate::prelude::ClientId),
     #[error("Couldn't find pending commit")]
     PendingCommitNotFound,
     #[error(
@@ -74,6 +78,40 @@ pub enum Error {
     ProposalVariantCannotBeRenewed,
     #[error("caller error: {0}")]
     CallerError(&'static str),
+    #[error("Required application-defined {kind} type {value:#06x} is outside of the range reserved for private use")]
+    InvalidCustomType { kind: &'static str, value: u16 },
+    #[error("The exporter label must not be empty")]
+    EmptyExporterLabel,
+    #[error("The exporter label \"{0}\" is reserved for internal use")]
+    ReservedExporterLabel(String),
+    #[error("No exporter secret with label \"{label}\" is kept for epoch {epoch}")]
+    ExporterSecretNotRetained { epoch: u64, label: String },
+    #[error("The resumption secret of epoch {0} is not kept anymore")]
+    ResumptionPskNotRetained(u64),
+    #[error("The conversation has already been reinitialized into another one")]
+    AlreadyReInitialized,
+    #[error(
+        "The ReInit commit has not been merged, because the delivery service rejected it in favor of another commit"
+    )]
+    ReInitNotMerged,
+    #[error(
+        "The group context extensions were not applied after {attempts} commits, because the delivery service kept accepting other commits first"
+    )]
+    GroupContextExtensionsNotApplied { attempts: usize },
+    #[error(
+        "The batch commit was not merged after {attempts} attempts, because the delivery service kept accepting other commits first"
+    )]
+    BatchCommitNotMerged { attempts: usize },
+    #[error(
+        "The {policy:?} wire policy of the conversation does not allow {content_type:?} messages with encrypted: {encrypted}"
+    )]
+    WirePolicyViolation {
+        policy: super::config::MlsWirePolicy,
+        content_type: openmls::prelude::ContentType,
+        encrypted: bool,
+    },
+    #[error("The wire format policy of the group, {0:?}, matches none of the wire policies")]
+    UnknownWireFormatPolicy(openmls::prelude::WireFormatPolicy),
     /// This happens when the DS cannot flag KeyPackages as claimed or not. In this scenario, a client
     /// requests their old KeyPackages to be deleted but one has already been claimed by another client to create a Welcome.
     /// In that case the only solution is that the client receiving such a Welcome tries to join the group
@@ -82,6 +120,15 @@ pub enum Error {
         "Although this Welcome seems valid, the local KeyPackage it references has already been deleted locally. Join this group with an external commit"
     )]
     OrphanWelcome,
+    #[error(
+        "The credential of key package {index} ({client_id:?}) cannot join this verified conversation: {violation}"
+    )]
+    CredentialNotAdmitted {
+        /// Index of the key package among the ones given, 0 when joining by external commit
+        index: usize,
+        client_id: crate::prelude::ClientId,
+        violation: super::admission::MlsAdmissionViolation,
+    },
     #[error("Serializing {item} for TLS")]
     TlsSerialize {
         item: &'static str,
@@ -0,0 +1,201 @@
+//! Exporter secrets are only reachable while the group is in the epoch they were exported in. To
+//! still be able to derive the keys of payloads protected in a past epoch, the secrets of the labels
+//! listed in [MlsExporterSecretHistoryConfiguration] are kept in the keystore for a bounded number
+//! of epochs.
+//!
+//! [MlsExporterSecretHistoryConfiguration]: crate::prelude::MlsExporterSecretHistoryConfiguration
+
+use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::MlsExporterSecret};
+use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
+
+use super::{Error, Result, validate_exporter_label};
+use crate::{KeystoreError, prelude::MlsConversation};
+
+impl MlsConversation {
+    /// Stores the secrets of the current epoch and drops those of epochs which fell out of the
+    /// configured window. Must be called whenever the group enters a new epoch.
+    pub(crate) async fn retain_exporter_secrets(&self, backend: &MlsCryptoProvider) -> Result<()> {
+        let history = &self.configuration.custom.exporter_secret_history;
+        if history.max_past_epochs == 0 {
+            return Ok(());
+        }
+
+        let keystore = backend.keystore();
+        let epoch = self.group.epoch().as_u64();
+        // labels we would refuse to export explicitly must not be retrievable through the history either
+        for label in history
+            .labels
+            .iter()
+            .filter(|label| validate_exporter_label(label).is_ok())
+        {
+            let secret = self.export_secret(backend, label, &[], history.key_length)?;
+            keystore
+                .save(MlsExporterSecret::new(&self.id, epoch, label, secret))
+                .await
+                .map_err(KeystoreError::wrap("saving exporter secret"))?;
+        }
+
+        let oldest_kept_epoch = epoch.saturating_sub(history.max_past_epochs as u64);
+        for stale in self
+            .find_exporter_secrets(&keystore)
+            .await?
+            .into_iter()
+            .filter(|secret| secret.epoch().is_none_or(|kept_epoch| kept_epoch < oldest_kept_epoch))
+        {
+            keystore
+                .remove::<MlsExporterSecret, _>(&stale.id)
+                .await
+                .map_err(KeystoreError::wrap("removing stale exporter secret"))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn exporter_secret_at_epoch(
+        &self,
+        backend: &MlsCryptoProvider,
+        epoch: u64,
+        label: &str,
+    ) -> Result<Vec<u8>> {
+        let history = &self.configuration.custom.exporter_secret_history;
+        let not_retained = || Error::ExporterSecretNotRetained {
+            epoch,
+            label: label.to_owned(),
+        };
+        if history.max_past_epochs == 0 || !history.labels.iter().any(|kept| kept == label) {
+            return Err(not_retained());
+        }
+
+        if epoch == self.group.epoch().as_u64() {
+            return self.export_secret(backend, label, &[], history.key_length);
+        }
+
+        backend
+            .keystore()
+            .find::<MlsExporterSecret>(&MlsExporterSecret::make_id(&self.id, epoch, label))
+            .await
+            .map_err(KeystoreError::wrap("finding exporter secret"))?
+            .map(|entity| entity.secret.clone())
+            .ok_or_else(not_retained)
+    }
+
+    /// Removes every exporter secret kept for this conversation
+    pub(crate) async fn delete_exporter_secrets(&self, keystore: &CryptoKeystore) -> Result<()> {
+        for secret in self.find_exporter_secrets(keystore).await? {
+            keystore
+                .remove::<MlsExporterSecret, _>(&secret.id)
+                .await
+                .map_err(KeystoreError::wrap("removing exporter secret"))?;
+        }
+        Ok(())
+    }
+
+    async fn find_exporter_secrets(&self, keystore: &CryptoKeystore) -> Result<Vec<MlsExporterSecret>> {
+        keystore
+            .find_exporter_secrets_by_conversation_id(&self.id)
+            .await
+            .map_err(KeystoreError::wrap("finding exporter secrets of conversation"))
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mls::conversation::{Conversation as _, Error};
+    use crate::prelude::MlsExporterSecretHistoryConfiguration;
+    use crate::test_utils::*;
+
+    const LABEL: &str = "media";
+
+    fn keep_media_secrets(case: &mut TestContext, max_past_epochs: usize) {
+        case.cfg.custom.exporter_secret_history = MlsExporterSecretHistoryConfiguration {
+            max_past_epochs,
+            labels: vec![LABEL.to_string()],
+            ..Default::default()
+        };
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_keep_secrets_of_past_epochs(mut case: TestContext) {
+        keep_media_secrets(&mut case, 2);
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let epoch = conversation.guard().await.epoch().await;
+            let key_length = MlsExporterSecretHistoryConfiguration::DEFAULT_KEY_LENGTH;
+            let secret = conversation
+                .guard()
+                .await
+                .export_secret_key_with_label(LABEL, &[], key_length)
+                .await
+                .unwrap();
+
+            let conversation = conversation.advance_epoch().await;
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 1);
+
+            // both the committer and the receiver can still derive the key of the previous epoch
+            for session in [&alice, &bob] {
+                let kept = conversation
+                    .guard_of(session)
+                    .await
+                    .exporter_secret_at_epoch(epoch, LABEL)
+                    .await
+                    .unwrap();
+                assert_eq!(kept, secret);
+            }
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_prune_secrets_outside_of_window(mut case: TestContext) {
+        keep_media_secrets(&mut case, 1);
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let epoch = conversation.guard().await.epoch().await;
+
+            let conversation = conversation.advance_epoch().await.advance_epoch().await;
+            let guard = conversation.guard().await;
+            assert!(guard.exporter_secret_at_epoch(epoch + 1, LABEL).await.is_ok());
+            let error = guard.exporter_secret_at_epoch(epoch, LABEL).await.unwrap_err();
+            assert!(matches!(error, Error::ExporterSecretNotRetained { epoch: e, .. } if e == epoch));
+            // current epoch and a single past one
+            assert_eq!(alice.transaction.count_entities().await.exporter_secret, 2);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_keep_unconfigured_labels(mut case: TestContext) {
+        keep_media_secrets(&mut case, 2);
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let epoch = conversation.guard().await.epoch().await;
+            let error = conversation
+                .guard()
+                .await
+                .exporter_secret_at_epoch(epoch, "files")
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::ExporterSecretNotRetained { .. }));
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_wipe_secrets_with_conversation(mut case: TestContext) {
+        keep_media_secrets(&mut case, 2);
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let conversation = conversation.advance_epoch().await;
+            assert_eq!(alice.transaction.count_entities().await.exporter_secret, 2);
+
+            conversation.guard().await.wipe().await.unwrap();
+            assert_eq!(alice.transaction.count_entities().await.exporter_secret, 0);
+        })
+        .await
+    }
+}
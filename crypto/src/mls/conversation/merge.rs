@@ -27,11 +27,27 @@ impl MlsConversation {
         // openmls stores here all the encryption keypairs used for update proposals..
         let previous_own_leaf_nodes = self.group.own_leaf_nodes.clone();
 
+        if self
+            .group
+            .pending_commit()
+            .is_some_and(|commit| self.updates_own_leaf(commit, true))
+        {
+            self.last_self_update = Some(crate::mls::unix_timestamp());
+        }
+        let reinit = self.group.pending_commit().map(Self::reinit_of).transpose()?.flatten();
+        let previous_history_clients = self.history_client_ids();
+        let previous_members = client.has_membership_observer().await.then(|| self.members_with_key());
+        self.baseline_e2ei_status(client, backend).await;
+
         self.group
             .merge_pending_commit(backend)
             .await
             .map_err(MlsError::wrap("merging pending commit"))?;
         self.persist_group_when_changed(&backend.keystore(), false).await?;
+        self.retain_exporter_secrets(backend).await?;
+        if let Some(reinit) = reinit {
+            self.record_reinit(backend, &reinit).await?;
+        }
 
         // ..so if there's any, we clear them after the commit is merged
         for oln in &previous_own_leaf_nodes {
@@ -42,6 +58,17 @@ impl MlsConversation {
         client
             .notify_epoch_changed(self.id.clone(), self.group.epoch().as_u64())
             .await;
+        client
+            .notify_history_clients_changed(self.id.clone(), previous_history_clients, self.history_client_ids())
+            .await;
+        if let Some(previous_members) = previous_members {
+            // the commit is merged already, so failing to report it must not fail the merge
+            match self.membership_changes(&previous_members, backend).await {
+                Ok(changes) => client.notify_membership_changed(self.id.clone(), changes).await,
+                Err(e) => log::warn!(error:% = e; "Failed to compute the membership changes of a merged commit"),
+            }
+        }
+        self.reevaluate_e2ei_status(client, backend, None).await;
 
         Ok(())
     }
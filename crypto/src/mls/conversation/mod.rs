@@ -11,14 +11,14 @@
 //! | merge     | ❌           | ❌            | ✅           | ✅            |
 //! | decrypt   | ✅           | ✅            | ✅           | ✅            |
 
-use config::MlsConversationConfiguration;
-use core_crypto_keystore::CryptoKeystoreMls;
+use config::{MlsConversationConfiguration, MlsWirePolicy};
+use core_crypto_keystore::entities::PersistedMlsGroup;
 use itertools::Itertools as _;
 use log::trace;
 use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
 use openmls::{
     group::MlsGroup,
-    prelude::{Credential, CredentialWithKey, LeafNodeIndex, Proposal, SignaturePublicKey},
+    prelude::{Credential, CredentialWithKey, LeafNodeIndex, Proposal, Sender, SignaturePublicKey, StagedCommit},
 };
 use openmls_traits::OpenMlsCryptoProvider;
 use openmls_traits::types::SignatureScheme;
@@ -28,17 +28,24 @@ use std::{collections::HashSet, ops::Deref};
 use crate::{
     KeystoreError, LeafError, MlsError, RecursiveError,
     mls::Session,
-    prelude::{ClientId, E2eiConversationState, MlsCiphersuite, MlsCredentialType, WireIdentity},
+    prelude::{
+        ClientId, E2eiConversationState, MemberCredentialUpdate, MembershipChanges, MlsCiphersuite, MlsCredentialType,
+        MlsPendingProposal, WireIdentity,
+    },
 };
 
+mod admission;
 pub(crate) mod commit;
 mod commit_delay;
 pub(crate) mod config;
 pub(crate) mod conversation_guard;
+pub(crate) mod custom_extension;
 mod duplicate;
 #[cfg(test)]
 mod durability;
+mod e2ei_status;
 mod error;
+mod exporter_secret_history;
 pub(crate) mod group_info;
 mod immutable_conversation;
 pub(crate) mod merge;
@@ -46,14 +53,18 @@ mod orphan_welcome;
 mod own_commit;
 pub(crate) mod pending_conversation;
 pub(crate) mod proposal;
+pub(crate) mod reinit;
 mod renew;
 pub(crate) mod welcome;
 mod wipe;
+mod wire_policy;
 
 use crate::mls::HasSessionAndCrypto;
 use crate::mls::credential::ext::CredentialExt as _;
 use crate::prelude::user_id::UserId;
+pub use admission::MlsAdmissionViolation;
 pub use conversation_guard::ConversationGuard;
+use custom_extension::MlsCustomExtension;
 pub use error::{Error, Result};
 pub use immutable_conversation::ImmutableConversation;
 
@@ -110,6 +121,12 @@ pub trait Conversation<'a>: ConversationWithMls<'a> {
         self.conversation().await.ciphersuite()
     }
 
+    /// Returns the effective wire policy of a given conversation, i.e. which of its handshake messages
+    /// are encrypted
+    async fn wire_policy(&'a self) -> Result<MlsWirePolicy> {
+        self.conversation().await.wire_policy()
+    }
+
     /// Derives a new key from the one in the group, to be used elsewhere.
     ///
     /// # Arguments
@@ -119,15 +136,63 @@ pub trait Conversation<'a>: ConversationWithMls<'a> {
     /// # Errors
     /// OpenMls secret generation error
     async fn export_secret_key(&'a self, key_length: usize) -> Result<Vec<u8>> {
-        const EXPORTER_LABEL: &str = "exporter";
-        const EXPORTER_CONTEXT: &[u8] = &[];
         let backend = self.crypto_provider().await?;
+        self.conversation()
+            .await
+            .export_secret(&backend, DEFAULT_EXPORTER_LABEL, &[], key_length)
+    }
+
+    /// Derives a new key from the one in the group, domain-separated by the provided label and context.
+    ///
+    /// Use this instead of [Conversation::export_secret_key] whenever different features need
+    /// unrelated keys from the same epoch.
+    ///
+    /// # Arguments
+    /// * `label` - identifies what the key is used for. It must not be empty, and must neither be the
+    ///     label used by [Conversation::export_secret_key] nor start with [RESERVED_EXPORTER_LABEL_PREFIX]
+    /// * `context` - arbitrary bytes bound to the derived key
+    /// * `key_length` - the length of the key to be derived. If the value is higher than the
+    ///     bounds of `u16` or the context hash * 255, an error will be returned
+    ///
+    /// # Errors
+    /// [Error::EmptyExporterLabel], [Error::ReservedExporterLabel] or OpenMls secret generation error
+    async fn export_secret_key_with_label(&'a self, label: &str, context: &[u8], key_length: usize) -> Result<Vec<u8>> {
+        validate_exporter_label(label)?;
+        let backend = self.crypto_provider().await?;
+        self.conversation()
+            .await
+            .export_secret(&backend, label, context, key_length)
+    }
+
+    /// Returns the secret exported with `label` and an empty context in the given epoch, provided it is
+    /// kept as per [MlsCustomConfiguration::exporter_secret_history]. This allows deriving the keys of
+    /// payloads protected in an epoch the group has since moved past.
+    ///
+    /// # Errors
+    /// [Error::ExporterSecretNotRetained] if the secret is not, or no longer, kept. Label errors as in
+    /// [Conversation::export_secret_key_with_label]
+    ///
+    /// [MlsCustomConfiguration::exporter_secret_history]: crate::prelude::MlsCustomConfiguration::exporter_secret_history
+    async fn exporter_secret_at_epoch(&'a self, epoch: u64, label: &str) -> Result<Vec<u8>> {
+        validate_exporter_label(label)?;
+        let backend = self.crypto_provider().await?;
+        self.conversation()
+            .await
+            .exporter_secret_at_epoch(&backend, epoch, label)
+            .await
+    }
+
+    /// Returns the application-defined extensions currently in the group context of the conversation
+    async fn custom_extensions(&'a self) -> Vec<MlsCustomExtension> {
         let inner = self.conversation().await;
-        inner
-            .group()
-            .export_secret(&backend, EXPORTER_LABEL, EXPORTER_CONTEXT, key_length)
-            .map_err(MlsError::wrap("exporting secret key"))
-            .map_err(Into::into)
+        MlsCustomExtension::from_extensions(inner.group().group_context_extensions())
+    }
+
+    /// Lists the proposals which will be part of the next commit, e.g. to show pending changes.
+    /// Unwanted ones can be dropped with [ConversationGuard::clear_pending_proposal] before committing
+    /// the others with [ConversationGuard::commit_pending_proposals].
+    async fn pending_proposals(&'a self) -> Vec<MlsPendingProposal> {
+        self.conversation().await.pending_proposals()
     }
 
     /// Exports the clients from a conversation
@@ -261,6 +326,23 @@ pub trait Conversation<'a>: ConversationWithMls<'a> {
 
 impl<'a, T: ConversationWithMls<'a>> Conversation<'a> for T {}
 
+/// Label used by [Conversation::export_secret_key].
+const DEFAULT_EXPORTER_LABEL: &str = "exporter";
+
+/// Exporter labels starting with this prefix are reserved for internal use by CoreCrypto and
+/// are rejected by [Conversation::export_secret_key_with_label].
+pub const RESERVED_EXPORTER_LABEL_PREFIX: &str = "core-crypto/";
+
+fn validate_exporter_label(label: &str) -> Result<()> {
+    if label.is_empty() {
+        return Err(Error::EmptyExporterLabel);
+    }
+    if label == DEFAULT_EXPORTER_LABEL || label.starts_with(RESERVED_EXPORTER_LABEL_PREFIX) {
+        return Err(Error::ReservedExporterLabel(label.to_owned()));
+    }
+    Ok(())
+}
+
 /// A unique identifier for a group/conversation. The identifier must be unique within a client.
 pub type ConversationId = Vec<u8>;
 
@@ -275,7 +357,9 @@ pub struct MlsConversation {
     pub(crate) id: ConversationId,
     pub(crate) parent_id: Option<ConversationId>,
     pub(crate) group: MlsGroup,
-    configuration: MlsConversationConfiguration,
+    pub(crate) configuration: MlsConversationConfiguration,
+    /// UNIX timestamp (in seconds) of the last time our own leaf node was updated
+    pub(crate) last_self_update: Option<u64>,
 }
 
 impl MlsConversation {
@@ -297,6 +381,9 @@ impl MlsConversation {
         configuration: MlsConversationConfiguration,
         backend: &MlsCryptoProvider,
     ) -> Result<Self> {
+        if let Some((kind, value)) = configuration.custom.required_capabilities.find_invalid_type() {
+            return Err(Error::InvalidCustomType { kind, value });
+        }
         let (cs, ct) = (configuration.ciphersuite, creator_credential_type);
         let cb = author_client
             .get_most_recent_or_create_credential_bundle(backend, cs.signature_algorithm(), ct)
@@ -306,7 +393,7 @@ impl MlsConversation {
         let group = MlsGroup::new_with_group_id(
             backend,
             &cb.signature_key,
-            &configuration.as_openmls_default_configuration()?,
+            &configuration.as_openmls_configuration(author_client.leaf_capabilities())?,
             openmls::prelude::GroupId::from_slice(id.as_slice()),
             cb.to_mls_credential_with_key(),
         )
@@ -318,11 +405,13 @@ impl MlsConversation {
             group,
             parent_id: None,
             configuration,
+            last_self_update: Some(crate::mls::unix_timestamp()),
         };
 
         conversation
             .persist_group_when_changed(&backend.keystore(), true)
             .await?;
+        conversation.retain_exporter_secrets(backend).await?;
 
         Ok(conversation)
     }
@@ -340,33 +429,66 @@ impl MlsConversation {
             group,
             configuration,
             parent_id: None,
+            last_self_update: Some(crate::mls::unix_timestamp()),
         };
 
         conversation
             .persist_group_when_changed(&backend.keystore(), true)
             .await?;
+        conversation.retain_exporter_secrets(backend).await?;
 
         Ok(conversation)
     }
 
     /// Internal API: restore the conversation from a persistence-saved serialized Group State.
-    pub(crate) fn from_serialized_state(buf: Vec<u8>, parent_id: Option<ConversationId>) -> Result<Self> {
+    pub(crate) fn from_serialized_state(persisted: &PersistedMlsGroup) -> Result<Self> {
         let group: MlsGroup =
-            core_crypto_keystore::deser(&buf).map_err(KeystoreError::wrap("deserializing group state"))?;
+            core_crypto_keystore::deser(&persisted.state).map_err(KeystoreError::wrap("deserializing group state"))?;
         let id = ConversationId::from(group.group_id().as_slice());
+        let custom = persisted
+            .custom_configuration
+            .as_deref()
+            .map(serde_json::from_slice)
+            .transpose()
+            .map_err(MlsError::wrap("deserializing mls custom configuration"))?
+            .unwrap_or_default();
         let configuration = MlsConversationConfiguration {
             ciphersuite: group.ciphersuite().into(),
+            custom,
             ..Default::default()
         };
 
         Ok(Self {
             id,
             group,
-            parent_id,
+            parent_id: persisted.parent_id.clone(),
             configuration,
+            last_self_update: persisted.last_self_update(),
         })
     }
 
+    pub(crate) fn export_secret(
+        &self,
+        backend: &MlsCryptoProvider,
+        label: &str,
+        context: &[u8],
+        key_length: usize,
+    ) -> Result<Vec<u8>> {
+        self.group
+            .export_secret(backend, label, context, key_length)
+            .map_err(MlsError::wrap("exporting secret key"))
+            .map_err(Into::into)
+    }
+
+    /// The resumption secret of one of the last [MlsConversationConfiguration::NUMBER_RESUMPTION_PSK]
+    /// epochs of this conversation
+    pub(crate) fn resumption_secret(&self, epoch: u64) -> Result<Vec<u8>> {
+        self.group
+            .get_past_resumption_psk(epoch.into())
+            .map(|secret| secret.as_slice().to_vec())
+            .ok_or(Error::ResumptionPskNotRetained(epoch))
+    }
+
     /// Group/conversation id
     pub fn id(&self) -> &ConversationId {
         &self.id
@@ -386,6 +508,64 @@ impl MlsConversation {
         })
     }
 
+    /// Client ids of the history clients among the members
+    pub(crate) fn history_client_ids(&self) -> HashSet<ClientId> {
+        self.group
+            .members()
+            .map(|member| ClientId::from(member.credential.identity()))
+            .filter(crate::ephemeral::is_history_client)
+            .collect()
+    }
+
+    /// The changes of membership since `previous`, the members before merging a commit,
+    /// see [crate::mls::MembershipObserver]
+    pub(crate) async fn membership_changes(
+        &self,
+        previous: &HashMap<Vec<u8>, CredentialWithKey>,
+        backend: &MlsCryptoProvider,
+    ) -> Result<MembershipChanges> {
+        let current = self.members_with_key();
+
+        let mut removed = previous
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .collect::<Vec<_>>();
+        removed.sort();
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        for (id, credential) in &current {
+            match previous.get(id) {
+                None => added.push(id),
+                Some(previous) if previous != credential => updated.push((id, credential)),
+                Some(_) => {}
+            }
+        }
+        added.sort();
+        updated.sort_by_key(|(id, _)| *id);
+
+        let mut credential_updates = Vec::with_capacity(updated.len());
+        if !updated.is_empty() {
+            let authentication_service = backend.authentication_service();
+            authentication_service.refresh_time_of_interest().await;
+            let authentication_service = authentication_service.borrow().await;
+            for (id, credential) in updated {
+                let identity = credential
+                    .extract_identity(self.ciphersuite(), authentication_service.as_ref())
+                    .map_err(RecursiveError::mls_credential("extracting identity"))?;
+                credential_updates.push(MemberCredentialUpdate {
+                    client_id: ClientId::from(id.as_slice()),
+                    identity,
+                });
+            }
+        }
+
+        Ok(MembershipChanges {
+            added: added.into_iter().map(|id| ClientId::from(id.as_slice())).collect(),
+            removed: removed.into_iter().map(|id| ClientId::from(id.as_slice())).collect(),
+            credential_updates,
+        })
+    }
+
     /// Get actual group members and subtract pending remove proposals
     pub fn members_in_next_epoch(&self) -> Vec<ClientId> {
         let pending_removals = self.pending_removals();
@@ -432,12 +612,17 @@ impl MlsConversation {
 
     pub(crate) async fn persist_group_when_changed(&mut self, keystore: &CryptoKeystore, force: bool) -> Result<()> {
         if force || self.group.state_changed() == openmls::group::InnerState::Changed {
+            let custom_configuration =
+                serde_json::to_vec(&self.configuration.custom).map_err(MlsError::wrap("serializing custom config"))?;
             keystore
-                .mls_group_persist(
-                    &self.id,
-                    &core_crypto_keystore::ser(&self.group).map_err(KeystoreError::wrap("serializing group state"))?,
-                    self.parent_id.as_deref(),
-                )
+                .save(PersistedMlsGroup {
+                    id: self.id.clone(),
+                    state: core_crypto_keystore::ser(&self.group)
+                        .map_err(KeystoreError::wrap("serializing group state"))?,
+                    parent_id: self.parent_id.clone(),
+                    custom_configuration: Some(custom_configuration),
+                    last_self_update: self.last_self_update.map(|timestamp| timestamp.to_be_bytes().to_vec()),
+                })
                 .await
                 .map_err(KeystoreError::wrap("persisting mls group"))?;
 
@@ -447,6 +632,31 @@ impl MlsConversation {
         Ok(())
     }
 
+    /// Whether the given commit updates our own leaf node, either through its update path when we are
+    /// the committer or through one of our update proposals.
+    pub(crate) fn updates_own_leaf(&self, commit: &StagedCommit, committed_by_us: bool) -> bool {
+        let own_index = self.group.own_leaf_index();
+        (committed_by_us && commit.get_update_path_leaf_node().is_some())
+            || commit
+                .update_proposals()
+                .any(|proposal| matches!(proposal.sender(), Sender::Member(index) if *index == own_index))
+    }
+
+    /// Whether the configured [MlsCustomConfiguration::key_rotation_span] has elapsed since we last
+    /// updated our own leaf node.
+    ///
+    /// [MlsCustomConfiguration::key_rotation_span]: crate::prelude::MlsCustomConfiguration::key_rotation_span
+    pub(crate) fn is_key_rotation_due(&self, now: u64) -> bool {
+        let Some(span) = self.configuration.custom.key_rotation_span else {
+            return false;
+        };
+        // When we don't know when our leaf was last updated, be conservative and rotate
+        let Some(last_self_update) = self.last_self_update else {
+            return true;
+        };
+        now.saturating_sub(last_self_update) >= span.as_secs()
+    }
+
     pub(crate) fn own_credential_type(&self) -> Result<MlsCredentialType> {
         Ok(self
             .group
@@ -974,6 +1184,70 @@ mod tests {
             .await
         }
 
+        #[apply(all_cred_cipher)]
+        pub async fn can_export_domain_separated_secret_keys(case: TestContext) {
+            let [alice, bob] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob]).await;
+                let key_length = 32;
+
+                let alice_guard = conversation.guard().await;
+                let default_key = alice_guard.export_secret_key(key_length).await.unwrap();
+                let calls_key = alice_guard
+                    .export_secret_key_with_label("calls", b"", key_length)
+                    .await
+                    .unwrap();
+                let files_key = alice_guard
+                    .export_secret_key_with_label("files", b"", key_length)
+                    .await
+                    .unwrap();
+                let files_key_with_context = alice_guard
+                    .export_secret_key_with_label("files", b"some context", key_length)
+                    .await
+                    .unwrap();
+                assert_eq!(calls_key.len(), key_length);
+                assert_ne!(default_key, calls_key);
+                assert_ne!(calls_key, files_key);
+                assert_ne!(files_key, files_key_with_context);
+
+                // every member derives the same key
+                let bob_calls_key = conversation
+                    .guard_of(&bob)
+                    .await
+                    .export_secret_key_with_label("calls", b"", key_length)
+                    .await
+                    .unwrap();
+                assert_eq!(calls_key, bob_calls_key);
+            })
+            .await
+        }
+
+        #[apply(all_cred_cipher)]
+        pub async fn cannot_export_secret_key_with_invalid_label(case: TestContext) {
+            let [alice] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice]).await;
+                let guard = conversation.guard().await;
+
+                let error = guard.export_secret_key_with_label("", b"", 32).await.unwrap_err();
+                assert!(matches!(error, Error::EmptyExporterLabel));
+
+                let error = guard
+                    .export_secret_key_with_label(DEFAULT_EXPORTER_LABEL, b"", 32)
+                    .await
+                    .unwrap_err();
+                assert!(matches!(error, Error::ReservedExporterLabel(_)));
+
+                let reserved_label = format!("{RESERVED_EXPORTER_LABEL_PREFIX}history");
+                let error = guard
+                    .export_secret_key_with_label(&reserved_label, b"", 32)
+                    .await
+                    .unwrap_err();
+                assert!(matches!(error, Error::ReservedExporterLabel(label) if label == reserved_label));
+            })
+            .await
+        }
+
         #[apply(all_cred_cipher)]
         pub async fn cannot_export_secret_key_invalid_length(case: TestContext) {
             let [alice] = case.sessions().await;
@@ -122,6 +122,7 @@ impl MlsConversation {
             identity,
             buffered_messages: None,
             crl_new_distribution_points,
+            custom_extensions: None,
         })
     }
 }
@@ -120,6 +120,7 @@ impl PendingConversation {
         let pending_msg = MlsPendingMessage {
             foreign_id: self.id().clone(),
             message: message.as_ref().to_vec(),
+            received_at: Some(crate::mls::unix_timestamp().to_be_bytes().to_vec()),
         };
         keystore
             .save::<MlsPendingMessage>(pending_msg)
@@ -209,6 +210,7 @@ impl PendingConversation {
             identity,
             buffered_messages,
             crl_new_distribution_points,
+            custom_extensions: None,
         })
     }
 
@@ -5,7 +5,12 @@
 //! | 0 pend. Proposal       | ✅              | ❌              |
 //! | 1+ pend. Proposal      | ✅              | ❌              |
 
-use openmls::{binary_tree::LeafNodeIndex, framing::MlsMessageOut, key_packages::KeyPackageIn, prelude::LeafNode};
+use openmls::{
+    binary_tree::LeafNodeIndex,
+    framing::MlsMessageOut,
+    key_packages::KeyPackageIn,
+    prelude::{LeafNode, PreSharedKeyId, Proposal, Sender},
+};
 
 use mls_crypto_provider::MlsCryptoProvider;
 
@@ -14,12 +19,15 @@ use crate::{
     MlsError, RecursiveError,
     e2e_identity::NewCrlDistributionPoints,
     mls::credential::crl::{extract_crl_uris_from_credentials, get_new_crl_distribution_points},
-    prelude::{MlsConversation, MlsProposalRef, Session},
+    prelude::{ClientId, MlsConversation, MlsPendingProposal, MlsProposalRef, Session},
 };
 
 /// Creating proposals
 impl MlsConversation {
     /// see [openmls::group::MlsGroup::propose_add_member]
+    ///
+    /// The credential of the key package is checked against the [crate::prelude::MlsAdmissionPolicy] of
+    /// the conversation.
     #[cfg_attr(test, crate::durable)]
     pub async fn propose_add_member(
         &mut self,
@@ -33,6 +41,9 @@ impl MlsConversation {
             .map_err(|_| Error::IdentityInitializationError)?
             .signature_key;
 
+        self.enforce_admission_policy(backend, [key_package.credential()])
+            .await?;
+
         let crl_new_distribution_points = get_new_crl_distribution_points(
             backend,
             extract_crl_uris_from_credentials(std::iter::once(key_package.credential().mls_credential()))
@@ -41,11 +52,10 @@ impl MlsConversation {
         .await
         .map_err(RecursiveError::mls_credential("getting new crl distribution points"))?;
 
-        let (proposal, proposal_ref) = self
-            .group
-            .propose_add_member(backend, signer, key_package)
-            .await
-            .map_err(MlsError::wrap("propose add member"))?;
+        self.encrypt_outgoing_proposals(true)?;
+        let proposal = self.group.propose_add_member(backend, signer, key_package).await;
+        self.encrypt_outgoing_proposals(false)?;
+        let (proposal, proposal_ref) = proposal.map_err(MlsError::wrap("propose add member"))?;
         let proposal = MlsProposalBundle {
             proposal,
             proposal_ref: proposal_ref.into(),
@@ -68,15 +78,39 @@ impl MlsConversation {
             .await
             .map_err(|_| Error::IdentityInitializationError)?
             .signature_key;
-        let proposal = self
-            .group
-            .propose_remove_member(backend, signer, member)
+        self.encrypt_outgoing_proposals(true)?;
+        let proposal = self.group.propose_remove_member(backend, signer, member);
+        self.encrypt_outgoing_proposals(false)?;
+        let proposal = proposal
             .map_err(MlsError::wrap("propose remove member"))
             .map(MlsProposalBundle::from)?;
         self.persist_group_when_changed(&backend.keystore(), false).await?;
         Ok(proposal)
     }
 
+    /// see [openmls::group::MlsGroup::propose_external_psk]
+    #[cfg_attr(test, crate::durable)]
+    pub async fn propose_external_psk(
+        &mut self,
+        client: &Session,
+        backend: &MlsCryptoProvider,
+        psk_id: PreSharedKeyId,
+    ) -> Result<MlsProposalBundle> {
+        let signer = &self
+            .find_current_credential_bundle(client)
+            .await
+            .map_err(|_| Error::IdentityInitializationError)?
+            .signature_key;
+        self.encrypt_outgoing_proposals(true)?;
+        let proposal = self.group.propose_external_psk(backend, signer, psk_id);
+        self.encrypt_outgoing_proposals(false)?;
+        let proposal = proposal
+            .map_err(MlsError::wrap("propose external psk"))
+            .map(MlsProposalBundle::from)?;
+        self.persist_group_when_changed(&backend.keystore(), false).await?;
+        Ok(proposal)
+    }
+
     /// see [openmls::group::MlsGroup::propose_self_update]
     #[cfg_attr(test, crate::durable)]
     pub async fn propose_self_update(
@@ -101,23 +135,69 @@ impl MlsConversation {
             .map_err(|_| Error::IdentityInitializationError)?
             .signature_key;
 
-        let proposal = if let Some(leaf_node) = leaf_node {
-            let leaf_node_signer = &self.find_most_recent_credential_bundle(client).await?.signature_key;
+        let leaf_node_cb = if leaf_node.is_some() {
+            Some(self.find_most_recent_credential_bundle(client).await?)
+        } else {
+            None
+        };
 
+        self.encrypt_outgoing_proposals(true)?;
+        let proposal = if let Some((leaf_node, leaf_node_cb)) = leaf_node.zip(leaf_node_cb) {
             self.group
-                .propose_explicit_self_update(backend, msg_signer, leaf_node, leaf_node_signer)
+                .propose_explicit_self_update(backend, msg_signer, leaf_node, &leaf_node_cb.signature_key)
                 .await
         } else {
             self.group.propose_self_update(backend, msg_signer).await
-        }
-        .map(MlsProposalBundle::from)
-        .map_err(MlsError::wrap("proposing self update"))?;
+        };
+        self.encrypt_outgoing_proposals(false)?;
+        let proposal = proposal
+            .map(MlsProposalBundle::from)
+            .map_err(MlsError::wrap("proposing self update"))?;
 
         self.persist_group_when_changed(&backend.keystore(), false).await?;
         Ok(proposal)
     }
 }
 
+/// Inspecting proposals
+impl MlsConversation {
+    /// Lists the proposals in the proposal store, in the order in which they were created or received
+    pub(crate) fn pending_proposals(&self) -> Vec<MlsPendingProposal> {
+        let client_at = |index: LeafNodeIndex| {
+            self.group
+                .members()
+                .find(|member| member.index == index)
+                .map(|member| ClientId::from(member.credential.identity()))
+        };
+        self.group
+            .pending_proposals()
+            .map(|proposal| {
+                let joiner = match proposal.proposal() {
+                    Proposal::Add(add) => Some(ClientId::from(add.key_package().leaf_node().credential().identity())),
+                    _ => None,
+                };
+                let sender = match proposal.sender() {
+                    Sender::Member(index) => client_at(*index),
+                    Sender::NewMemberProposal => joiner.clone(),
+                    Sender::External(_) | Sender::NewMemberCommit => None,
+                };
+                let target = match proposal.proposal() {
+                    Proposal::Add(_) => joiner,
+                    Proposal::Update(_) => sender.clone(),
+                    Proposal::Remove(remove) => client_at(remove.removed()),
+                    _ => None,
+                };
+                MlsPendingProposal {
+                    proposal_ref: proposal.proposal_reference().to_owned().into(),
+                    proposal_type: proposal.proposal().into(),
+                    sender,
+                    target,
+                }
+            })
+            .collect()
+    }
+}
+
 /// Returned when a Proposal is created. Helps roll backing a local proposal
 #[derive(Debug)]
 pub struct MlsProposalBundle {
@@ -298,6 +378,71 @@ mod tests {
         }
     }
 
+    mod pending_proposals {
+        use super::*;
+        use crate::mls::conversation::Conversation as _;
+        use crate::prelude::MlsProposalType;
+
+        #[apply(all_cred_cipher)]
+        async fn should_list_pending_proposals(case: TestContext) {
+            let [alice, bob, charlie, debbie] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob, &charlie]).await;
+                assert!(conversation.guard_of(&bob).await.pending_proposals().await.is_empty());
+
+                let conversation = conversation
+                    .invite_proposal_notify(&debbie)
+                    .await
+                    .remove_proposal_notify(&charlie)
+                    .await;
+
+                let alice_id = alice.get_client_id().await;
+                let proposals = conversation.guard_of(&bob).await.pending_proposals().await;
+                let [add, remove] = proposals.try_into().unwrap();
+                assert_eq!(add.proposal_type, MlsProposalType::Add);
+                assert_eq!(add.sender.as_ref(), Some(&alice_id));
+                assert_eq!(add.target, Some(debbie.get_client_id().await));
+                assert_eq!(remove.proposal_type, MlsProposalType::Remove);
+                assert_eq!(remove.sender.as_ref(), Some(&alice_id));
+                assert_eq!(remove.target, Some(charlie.get_client_id().await));
+
+                // Both sides agree on the proposal references
+                let own_proposals = conversation.guard().await.pending_proposals().await;
+                assert_eq!(
+                    own_proposals.iter().map(|p| &p.proposal_ref).collect_vec(),
+                    [&add.proposal_ref, &remove.proposal_ref]
+                );
+
+                let mut guard = conversation.guard_of(&bob).await;
+                guard.clear_pending_proposal(remove.proposal_ref).await.unwrap();
+                assert_eq!(guard.pending_proposals().await, vec![add]);
+            })
+            .await
+        }
+
+        #[apply(all_cred_cipher)]
+        async fn should_target_sender_of_update_proposal(case: TestContext) {
+            let [alice, bob] = case.sessions().await;
+            Box::pin(async move {
+                let conversation = case.create_conversation([&alice, &bob]).await;
+                let conversation = conversation.update_proposal_notify().await;
+
+                let [update] = conversation
+                    .guard_of(&bob)
+                    .await
+                    .pending_proposals()
+                    .await
+                    .try_into()
+                    .unwrap();
+                let alice_id = alice.get_client_id().await;
+                assert_eq!(update.proposal_type, MlsProposalType::Update);
+                assert_eq!(update.sender, Some(alice_id.clone()));
+                assert_eq!(update.target, Some(alice_id));
+            })
+            .await
+        }
+    }
+
     mod delivery_semantics {
         use super::*;
 
@@ -0,0 +1,151 @@
+//! Reinitializing a conversation, e.g. to migrate it to another ciphersuite.
+//!
+//! A member commits a ReInit proposal in the old conversation, then creates the new conversation and
+//! adds the other members to it (see [crate::mls::conversation::ConversationGuard::reinit]). Every
+//! member records which conversation replaces the old one when merging the ReInit commit.
+//!
+//! RFC 9420 links both conversations with a resumption pre-shared key of usage `reinit`, which OpenMLS
+//! cannot inject. Instead, each member stores the resumption secret of the last epoch of the old
+//! conversation as an external pre-shared key when merging the ReInit commit. It is injected in the
+//! commit creating the new conversation, so that only members of the old conversation can join it, and
+//! deleted once the new conversation is created or joined, or the old one is wiped.
+
+use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::MlsConversationReInit};
+use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
+use openmls::prelude::{Extensions, GroupId, Proposal, ProtocolVersion, ReInitProposal, StagedCommit};
+use openmls_traits::types::Ciphersuite;
+use tls_codec::{Deserialize as _, Serialize as _, TlsDeserialize, TlsSerialize, TlsSize};
+
+use super::{Error, Result};
+use crate::{
+    KeystoreError, RecursiveError,
+    prelude::{ConversationId, MlsCiphersuite, MlsConversation, RESERVED_PSK_ID_PREFIX},
+    transaction_context::psk::{delete_external_psk, write_external_psk},
+};
+
+/// The content of a ReInit proposal, as defined in RFC 9420 section 12.1.5.
+///
+/// OpenMLS keeps the fields of [ReInitProposal] private and offers no way to build one, so proposals are
+/// converted through their wire format, which both share.
+#[derive(TlsSerialize, TlsDeserialize, TlsSize)]
+struct ReInitContent {
+    group_id: GroupId,
+    version: ProtocolVersion,
+    ciphersuite: Ciphersuite,
+    extensions: Extensions,
+}
+
+/// Where a reinitialized conversation continues
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsReInit {
+    /// Id of the conversation replacing the reinitialized one
+    pub new_id: ConversationId,
+    /// Ciphersuite of the new conversation
+    pub ciphersuite: MlsCiphersuite,
+}
+
+impl MlsReInit {
+    pub(crate) fn to_proposal(&self) -> Result<Proposal> {
+        let content = ReInitContent {
+            group_id: GroupId::from_slice(&self.new_id),
+            version: ProtocolVersion::default(),
+            ciphersuite: self.ciphersuite.into(),
+            extensions: Extensions::empty(),
+        };
+        let bytes = content
+            .tls_serialize_detached()
+            .map_err(Error::tls_serialize("reinit proposal content"))?;
+        let proposal =
+            ReInitProposal::tls_deserialize_exact(bytes).map_err(Error::tls_deserialize("reinit proposal"))?;
+        Ok(Proposal::ReInit(proposal))
+    }
+
+    fn from_proposal(proposal: &ReInitProposal) -> Result<Self> {
+        let bytes = proposal
+            .tls_serialize_detached()
+            .map_err(Error::tls_serialize("reinit proposal"))?;
+        let content =
+            ReInitContent::tls_deserialize_exact(bytes).map_err(Error::tls_deserialize("reinit proposal content"))?;
+        Ok(Self {
+            new_id: content.group_id.to_vec(),
+            ciphersuite: content.ciphersuite.into(),
+        })
+    }
+
+    /// Id of the external pre-shared key linking the new conversation to the reinitialized one
+    pub(crate) fn link_psk_id(old_id: &[u8]) -> Vec<u8> {
+        [RESERVED_PSK_ID_PREFIX, b"reinit/", old_id].concat()
+    }
+
+    /// Deletes the external pre-shared key linking the new conversation to the reinitialized one, once
+    /// it is no longer needed
+    pub(crate) async fn delete_link_psk(backend: &MlsCryptoProvider, old_id: &[u8]) -> Result<()> {
+        delete_external_psk(backend, Self::link_psk_id(old_id)).await?;
+        Ok(())
+    }
+
+    fn from_entity(entity: &MlsConversationReInit) -> Result<Self> {
+        let ciphersuite = entity.ciphersuite().ok_or(Error::MlsGroupInvalidState(
+            "malformed ciphersuite of a conversation reinit",
+        ))?;
+        let ciphersuite = MlsCiphersuite::try_from(ciphersuite)
+            .map_err(RecursiveError::mls("reading ciphersuite of a conversation reinit"))?;
+        Ok(Self {
+            new_id: entity.new_id.clone(),
+            ciphersuite,
+        })
+    }
+
+    /// The reinitialization of this conversation, if one was merged
+    pub(crate) async fn find(keystore: &CryptoKeystore, id: &[u8]) -> Result<Option<Self>> {
+        keystore
+            .find::<MlsConversationReInit>(id)
+            .await
+            .map_err(KeystoreError::wrap("finding conversation reinit"))?
+            .as_ref()
+            .map(Self::from_entity)
+            .transpose()
+    }
+
+    /// The id of the conversation reinitialized into the one with this id, if any
+    pub(crate) async fn find_old_id(keystore: &CryptoKeystore, new_id: &[u8]) -> Result<Option<ConversationId>> {
+        Ok(keystore
+            .find_all::<MlsConversationReInit>(Default::default())
+            .await
+            .map_err(KeystoreError::wrap("finding conversation reinits"))?
+            .into_iter()
+            .find(|reinit| reinit.new_id == new_id)
+            .map(|reinit| reinit.id.clone()))
+    }
+}
+
+impl MlsConversation {
+    /// The reinitialization carried by this commit, if any
+    pub(crate) fn reinit_of(commit: &StagedCommit) -> Result<Option<MlsReInit>> {
+        commit
+            .queued_proposals()
+            .find_map(|proposal| match proposal.proposal() {
+                Proposal::ReInit(reinit) => Some(reinit),
+                _ => None,
+            })
+            .map(MlsReInit::from_proposal)
+            .transpose()
+    }
+
+    /// Records that this conversation has been reinitialized. Must be called right after merging the
+    /// ReInit commit, since the new conversation is linked by the resumption secret of this epoch.
+    pub(crate) async fn record_reinit(&self, backend: &MlsCryptoProvider, reinit: &MlsReInit) -> Result<()> {
+        let secret = self.resumption_secret(self.group.epoch().as_u64())?;
+        write_external_psk::<Error>(backend, reinit.ciphersuite, MlsReInit::link_psk_id(&self.id), &secret).await?;
+        backend
+            .keystore()
+            .save(MlsConversationReInit::new(
+                &self.id,
+                &reinit.new_id,
+                reinit.ciphersuite.into(),
+            ))
+            .await
+            .map_err(KeystoreError::wrap("saving conversation reinit"))?;
+        Ok(())
+    }
+}
@@ -1,13 +1,14 @@
 use super::{Error, Result};
 use crate::{
-    LeafError, MlsError,
+    LeafError, MlsError, RecursiveError,
     e2e_identity::NewCrlDistributionPoints,
     group_store::GroupStore,
+    mls::session::key_package::LastResortKeyMaterial,
     prelude::{ConversationId, MlsConversation, MlsConversationConfiguration},
 };
 use core_crypto_keystore::{connection::FetchFromDatabase, entities::PersistedMlsPendingGroup};
 use mls_crypto_provider::MlsCryptoProvider;
-use openmls::prelude::{MlsGroup, Welcome};
+use openmls::prelude::{Capabilities, MlsGroup, Welcome};
 use openmls_traits::OpenMlsCryptoProvider;
 
 /// Contains everything client needs to know after decrypting an (encrypted) Welcome message
@@ -26,6 +27,7 @@ impl MlsConversation {
     /// # Arguments
     /// * `welcome` - welcome message to create the group from
     /// * `config` - group configuration
+    /// * `leaf_capabilities` - capabilities advertised by our leaf node
     /// * `backend` - the KeyStore to persist the group
     ///
     /// # Errors
@@ -33,10 +35,14 @@ impl MlsConversation {
     pub(crate) async fn from_welcome_message(
         welcome: Welcome,
         configuration: MlsConversationConfiguration,
+        leaf_capabilities: Capabilities,
         backend: &MlsCryptoProvider,
         mls_groups: &mut GroupStore<MlsConversation>,
     ) -> Result<Self> {
-        let mls_group_config = configuration.as_openmls_default_configuration()?;
+        let mls_group_config = configuration.as_openmls_configuration(leaf_capabilities)?;
+        let last_resort = LastResortKeyMaterial::find(&backend.keystore(), &welcome)
+            .await
+            .map_err(RecursiveError::mls_client("finding last resort key material"))?;
 
         let group = MlsGroup::new_from_welcome(backend, &mls_group_config, welcome, None).await;
 
@@ -46,6 +52,14 @@ impl MlsConversation {
             _ => group.map_err(MlsError::wrap("group could not be created from welcome"))?,
         };
 
+        // A last resort key package may be used by several welcomes
+        for material in last_resort {
+            material
+                .restore(&backend.keystore())
+                .await
+                .map_err(RecursiveError::mls_client("restoring last resort key material"))?;
+        }
+
         let id = ConversationId::from(group.group_id().as_slice());
         let existing_conversation = mls_groups.get_fetch(&id[..], &backend.keystore(), None).await;
         let conversation_exists = existing_conversation.ok().flatten().is_some();
@@ -1,5 +1,8 @@
 use super::Result;
-use crate::{MlsError, prelude::MlsConversation};
+use crate::{
+    MlsError, RecursiveError,
+    prelude::{MlsConversation, MlsReInit},
+};
 use mls_crypto_provider::MlsCryptoProvider;
 use openmls_traits::OpenMlsCryptoProvider;
 
@@ -18,6 +21,13 @@ impl MlsConversation {
                 .map_err(MlsError::wrap("removing pending proposal"))?;
         }
 
+        self.delete_exporter_secrets(&backend.keystore()).await?;
+        crate::history_archive::delete_archive(&backend.keystore(), self.id())
+            .await
+            .map_err(RecursiveError::root("deleting archived history secrets"))?;
+        // in case this conversation was reinitialized but the new one never created or joined
+        MlsReInit::delete_link_psk(backend, self.id()).await?;
+
         Ok(())
     }
 }
@@ -49,6 +59,7 @@ mod tests {
             assert_eq!(final_count.group, 0);
             assert_eq!(final_count.encryption_keypair, final_count.key_package);
             assert_eq!(final_count.epoch_encryption_keypair, 0);
+            assert_eq!(final_count.exporter_secret, 0);
         })
         .await
     }
@@ -0,0 +1,178 @@
+//! Enforcing the [MlsWirePolicy] of a conversation.
+//!
+//! OpenMLS frames all outgoing handshake messages the same way, and only knows whether to accept
+//! plaintext, ciphertext or both for incoming ones. [MlsWirePolicy::Mixed] however makes a difference
+//! between commits and proposals, so this is handled here.
+
+use openmls::framing::ProtocolMessage;
+use openmls::prelude::{MIXED_CIPHERTEXT_WIRE_FORMAT_POLICY, MIXED_PLAINTEXT_WIRE_FORMAT_POLICY, Sender};
+
+use super::{Error, Result};
+use crate::prelude::{MlsConversation, MlsWirePolicy};
+
+impl MlsConversation {
+    /// The effective wire policy, as stored in the group state
+    pub(crate) fn wire_policy(&self) -> Result<MlsWirePolicy> {
+        self.group.configuration().wire_format_policy().try_into()
+    }
+
+    /// Under [MlsWirePolicy::Mixed], switches OpenMLS to encrypting outgoing handshake messages while
+    /// a proposal is created, and back to plaintext right after. Does nothing under other policies.
+    pub(crate) fn encrypt_outgoing_proposals(&mut self, encrypt: bool) -> Result<()> {
+        if self.wire_policy()? != MlsWirePolicy::Mixed {
+            return Ok(());
+        }
+        let wire_format_policy = if encrypt {
+            MIXED_CIPHERTEXT_WIRE_FORMAT_POLICY
+        } else {
+            MIXED_PLAINTEXT_WIRE_FORMAT_POLICY
+        };
+        // the whole configuration is replaced, so keep advertising what our leaf node already does
+        let leaf_capabilities = self
+            .group
+            .own_leaf()
+            .ok_or(Error::MlsGroupInvalidState("own_leaf not present in group"))?
+            .capabilities()
+            .clone();
+        let configuration = self
+            .configuration
+            .as_openmls_configuration_with_wire_format(leaf_capabilities, wire_format_policy)?;
+        self.group.set_configuration(&configuration);
+        Ok(())
+    }
+
+    /// Rejects incoming handshake messages which are not framed as the wire policy requires.
+    ///
+    /// Only members can encrypt messages, so the ones of other senders, e.g. external commits or
+    /// external proposals, are always accepted in plaintext.
+    pub(crate) fn ensure_wire_policy_respected(&self, message: &ProtocolMessage) -> Result<()> {
+        let (content_type, encrypted) = match message {
+            ProtocolMessage::PrivateMessage(message) => (message.content_type(), true),
+            ProtocolMessage::PublicMessage(message) if matches!(message.sender(), Sender::Member(_)) => {
+                (message.content_type(), false)
+            }
+            ProtocolMessage::PublicMessage(_) => return Ok(()),
+        };
+        let policy = self.wire_policy()?;
+        if policy.encrypts(content_type) != encrypted {
+            return Err(Error::WirePolicyViolation {
+                policy,
+                content_type,
+                encrypted,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openmls::prelude::{ContentType, ExtensionType, MlsMessageIn, MlsMessageInBody, MlsMessageOut, ProposalType};
+    use tls_codec::Deserialize as _;
+
+    use super::*;
+    use crate::mls::conversation::{Conversation as _, ConversationWithMls as _};
+    use crate::test_utils::*;
+
+    fn is_encrypted(message: MlsMessageOut) -> bool {
+        let message = MlsMessageIn::tls_deserialize(&mut message.to_bytes().unwrap().as_slice()).unwrap();
+        matches!(message.extract(), MlsMessageInBody::PrivateMessage(_))
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_report_wire_policy(case: TestContext) {
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let mut guard = conversation.guard().await;
+            assert_eq!(guard.wire_policy().await.unwrap(), case.cfg.custom.wire_policy);
+
+            guard.drop_and_restore().await;
+            assert_eq!(guard.wire_policy().await.unwrap(), case.cfg.custom.wire_policy);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn mixed_policy_should_only_encrypt_proposals(mut case: TestContext) {
+        case.cfg.custom.wire_policy = MlsWirePolicy::Mixed;
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            assert_eq!(
+                conversation.guard().await.wire_policy().await.unwrap(),
+                MlsWirePolicy::Mixed
+            );
+
+            let proposal = conversation.update_proposal().await;
+            assert!(is_encrypted(proposal.message()));
+            let conversation = proposal.notify_members().await;
+
+            let commit = conversation.acting_as(&bob).await.commit_pending_proposals().await;
+            assert!(!is_encrypted(commit.message()));
+            let conversation = commit.notify_members().await;
+
+            assert_eq!(
+                conversation.guard().await.wire_policy().await.unwrap(),
+                MlsWirePolicy::Mixed
+            );
+            assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn mixed_policy_should_keep_custom_capabilities(mut case: TestContext) {
+        case.cfg.custom.wire_policy = MlsWirePolicy::Mixed;
+        case.custom_capabilities = crate::prelude::MlsCustomCapabilities {
+            extension_types: vec![0xF0A1],
+            proposal_types: vec![0xF0A2],
+        };
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let conversation = conversation.update_proposal_notify().await;
+            let conversation = conversation.commit_pending_proposals_notify().await;
+
+            // Alice's new leaf node is built from the configuration set while proposing
+            let guard = conversation.guard().await;
+            let inner = guard.conversation().await;
+            let capabilities = inner.group.own_leaf().unwrap().capabilities();
+            assert_eq!(capabilities.extensions(), &[ExtensionType::from(0xF0A1)]);
+            assert_eq!(capabilities.proposals(), &[ProposalType::from(0xF0A2)]);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_reject_messages_violating_wire_policy(mut case: TestContext) {
+        case.cfg.custom.wire_policy = MlsWirePolicy::Mixed;
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+
+            // Make Alice encrypt her commit, which Mixed does not allow
+            let mut guard = conversation.guard().await;
+            guard.conversation_mut().await.encrypt_outgoing_proposals(true).unwrap();
+            guard.update_key_material().await.unwrap();
+            let commit = alice.mls_transport().await.latest_commit().await;
+            assert!(is_encrypted(commit.clone()));
+
+            let error = conversation
+                .guard_of(&bob)
+                .await
+                .decrypt_message(commit.to_bytes().unwrap())
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                Error::WirePolicyViolation {
+                    policy: MlsWirePolicy::Mixed,
+                    content_type: ContentType::Commit,
+                    encrypted: true,
+                }
+            ));
+        })
+        .await
+    }
+}
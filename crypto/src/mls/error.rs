@@ -12,6 +12,8 @@ pub enum Error {
     UnknownCiphersuite,
     #[error("Malformed or empty identifier found: {0}")]
     MalformedIdentifier(&'static str),
+    #[error("Application-defined {kind} type {value:#06x} is outside of the range reserved for private use")]
+    InvalidCustomType { kind: &'static str, value: u16 },
     #[error(transparent)]
     Keystore(#[from] crate::KeystoreError),
     #[error(transparent)]
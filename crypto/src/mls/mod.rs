@@ -9,8 +9,12 @@ pub(crate) mod proposal;
 pub(crate) mod session;
 
 pub use error::{Error, Result};
+pub use session::BufferObserver;
+pub use session::E2eiStatusObserver;
 pub use session::EpochObserver;
 pub use session::HistoryObserver;
+pub use session::MembershipObserver;
+pub use session::ReInitObserver;
 
 #[cfg_attr(target_family = "wasm", async_trait::async_trait(?Send))]
 #[cfg_attr(not(target_family = "wasm"), async_trait::async_trait)]
@@ -19,6 +23,14 @@ pub(crate) trait HasSessionAndCrypto: Send {
     async fn crypto_provider(&self) -> Result<MlsCryptoProvider>;
 }
 
+/// Seconds elapsed since the UNIX epoch
+pub(crate) fn unix_timestamp() -> u64 {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::transaction_context::Error as TransactionError;
@@ -107,6 +119,29 @@ mod tests {
             assert!(matches!(config_err, mls::Error::MalformedIdentifier(msg) if msg.contains("path")));
         }
 
+        #[test]
+        fn custom_types_should_be_in_private_use_range() {
+            let config_err = SessionConfig::builder()
+                .in_memory()
+                .database_key(DatabaseKey::generate())
+                .ciphersuites([MlsCiphersuite::default()])
+                .custom_capabilities(crate::prelude::MlsCustomCapabilities {
+                    extension_types: vec![0xF000],
+                    proposal_types: vec![0x0002],
+                })
+                .build()
+                .validate()
+                .unwrap_err();
+
+            assert!(matches!(
+                config_err,
+                mls::Error::InvalidCustomType {
+                    kind: "proposal",
+                    value: 0x0002
+                }
+            ));
+        }
+
         #[macro_rules_attribute::apply(smol_macros::test)]
         async fn client_id_should_not_be_empty() {
             let mut case = TestContext::default();
@@ -1,5 +1,5 @@
 use crate::mls::ClientId;
-use openmls::prelude::{KeyPackage, hash_ref::ProposalRef};
+use openmls::prelude::{KeyPackage, Proposal, hash_ref::ProposalRef};
 
 /// Abstraction over a [openmls::prelude::hash_ref::ProposalRef] to deal with conversions
 #[derive(Debug, Clone, Eq, PartialEq, derive_more::From, derive_more::Deref, derive_more::Display)]
@@ -43,3 +43,54 @@ pub enum MlsProposal {
     /// Requests that the member with LeafNodeRef removed be removed from the group
     Remove(ClientId),
 }
+
+/// Kind of a [MlsPendingProposal]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MlsProposalType {
+    /// Adds a client to the group
+    Add,
+    /// Replaces the leaf node of the sender
+    Update,
+    /// Removes a member from the group
+    Remove,
+    /// Injects a pre-shared key into the key schedule
+    PreSharedKey,
+    /// Reinitializes the group, e.g. with another ciphersuite
+    ReInit,
+    /// Used by external commits only
+    ExternalInit,
+    /// Replaces the group context extensions
+    GroupContextExtensions,
+    /// Any other proposal type, e.g. an application-defined one
+    Other(u16),
+}
+
+impl From<&Proposal> for MlsProposalType {
+    fn from(proposal: &Proposal) -> Self {
+        match proposal {
+            Proposal::Add(_) => Self::Add,
+            Proposal::Update(_) => Self::Update,
+            Proposal::Remove(_) => Self::Remove,
+            Proposal::PreSharedKey(_) => Self::PreSharedKey,
+            Proposal::ReInit(_) => Self::ReInit,
+            Proposal::ExternalInit(_) => Self::ExternalInit,
+            Proposal::GroupContextExtensions(_) => Self::GroupContextExtensions,
+            _ => Self::Other(proposal.proposal_type().into()),
+        }
+    }
+}
+
+/// A proposal waiting in the proposal store of a conversation until it gets committed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsPendingProposal {
+    /// Identifies the proposal, e.g. to clear it with
+    /// [crate::mls::conversation::ConversationGuard::clear_pending_proposal]
+    pub proposal_ref: MlsProposalRef,
+    /// What the proposal does
+    pub proposal_type: MlsProposalType,
+    /// The client who sent the proposal. `None` when it comes from an external sender, e.g. the
+    /// delivery service
+    pub sender: Option<ClientId>,
+    /// The client added, updated or removed by the proposal. `None` for other proposal types
+    pub target: Option<ClientId>,
+}
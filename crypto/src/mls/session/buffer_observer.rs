@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::prelude::{ConversationId, MlsBufferedItem, MlsEvictionReason};
+
+use super::{Error, Result, Session};
+
+/// A `BufferObserver` is notified whenever a message or commit buffered for a future epoch is evicted
+/// because the limits of [crate::prelude::MlsBufferConfiguration] were exceeded.
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+pub trait BufferObserver: Send + Sync {
+    /// This function will be called every time a buffered item has been evicted. It will never be
+    /// decrypted; the application may want to fetch it from the delivery service again later.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this inteface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: ConversationId,
+        item: MlsBufferedItem,
+        reason: MlsEvictionReason,
+    );
+}
+
+impl Session {
+    /// Add a buffer observer to this session.
+    /// (see [BufferObserver]).
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a buffer observer already exists, this will return an error.
+    pub async fn register_buffer_observer(&self, buffer_observer: Arc<dyn BufferObserver>) -> Result<()> {
+        let mut observer_guard = self.buffer_observer.write().await;
+        if observer_guard.is_some() {
+            return Err(Error::BufferObserverAlreadyExists);
+        }
+        observer_guard.replace(buffer_observer);
+        Ok(())
+    }
+
+    /// Notify the observer that a buffered item has been evicted, if one is present.
+    pub(crate) async fn notify_buffered_item_evicted(
+        &self,
+        conversation_id: ConversationId,
+        item: MlsBufferedItem,
+        reason: MlsEvictionReason,
+    ) {
+        if let Some(observer) = self.buffer_observer.read().await.as_ref() {
+            observer.buffered_item_evicted(conversation_id, item, reason).await;
+        }
+    }
+}
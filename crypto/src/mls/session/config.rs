@@ -8,7 +8,7 @@ use crate::{
         ciphersuite::MlsCiphersuite,
         error::{Error, Result},
     },
-    prelude::{ClientId, INITIAL_KEYING_MATERIAL_COUNT},
+    prelude::{ClientId, INITIAL_KEYING_MATERIAL_COUNT, MlsCustomCapabilities},
 };
 
 /// Configuration parameters for [Session][crate::mls::session::Session]
@@ -36,14 +36,31 @@ pub struct SessionConfig<'a> {
     /// Defaults to [crate::prelude::INITIAL_KEYING_MATERIAL_COUNT].
     #[builder(default)]
     pub nb_key_packages: Option<usize>,
+    /// Application-defined extension and proposal types advertised by the key packages and leaf nodes
+    /// of this session.
+    ///
+    /// All of them must be in [MlsCustomCapabilities::PRIVATE_USE_RANGE].
+    #[builder(default)]
+    pub custom_capabilities: MlsCustomCapabilities,
 }
-impl<'a, Key, ClientId, ExternalEntropy, Ciphersuites, KPs>
-    SessionConfigBuilder<'a, ((), Key, ClientId, ExternalEntropy, Ciphersuites, KPs)>
+impl<'a, Key, ClientId, ExternalEntropy, Ciphersuites, KPs, Capabilities>
+    SessionConfigBuilder<'a, ((), Key, ClientId, ExternalEntropy, Ciphersuites, KPs, Capabilities)>
 {
     /// Use an in-memory database
     pub fn in_memory(
         self,
-    ) -> SessionConfigBuilder<'a, ((ConnectionType<'a>,), Key, ClientId, ExternalEntropy, Ciphersuites, KPs)> {
+    ) -> SessionConfigBuilder<
+        'a,
+        (
+            (ConnectionType<'a>,),
+            Key,
+            ClientId,
+            ExternalEntropy,
+            Ciphersuites,
+            KPs,
+            Capabilities,
+        ),
+    > {
         self.db_connection_type(ConnectionType::InMemory)
     }
 
@@ -51,7 +68,18 @@ impl<'a, Key, ClientId, ExternalEntropy, Ciphersuites, KPs>
     pub fn persistent(
         self,
         path: &'a str,
-    ) -> SessionConfigBuilder<'a, ((ConnectionType<'a>,), Key, ClientId, ExternalEntropy, Ciphersuites, KPs)> {
+    ) -> SessionConfigBuilder<
+        'a,
+        (
+            (ConnectionType<'a>,),
+            Key,
+            ClientId,
+            ExternalEntropy,
+            Ciphersuites,
+            KPs,
+            Capabilities,
+        ),
+    > {
         self.db_connection_type(ConnectionType::Persistent(path))
     }
 }
@@ -67,6 +95,7 @@ pub struct ValidatedSessionConfig<'a> {
     pub(super) external_entropy: Option<EntropySeed>,
     pub(super) ciphersuites: Vec<MlsCiphersuite>,
     pub(super) nb_key_packages: usize,
+    pub(super) custom_capabilities: MlsCustomCapabilities,
 }
 
 impl<'a> SessionConfig<'a> {
@@ -81,6 +110,7 @@ impl<'a> SessionConfig<'a> {
             external_entropy,
             ciphersuites,
             nb_key_packages,
+            custom_capabilities,
         } = self;
 
         if let ConnectionType::Persistent(path) = &db_connection_type
@@ -107,6 +137,10 @@ impl<'a> SessionConfig<'a> {
 
         let nb_key_packages = nb_key_packages.unwrap_or(INITIAL_KEYING_MATERIAL_COUNT);
 
+        if let Some((kind, value)) = custom_capabilities.find_invalid_type() {
+            return Err(Error::InvalidCustomType { kind, value });
+        }
+
         Ok(ValidatedSessionConfig {
             db_connection_type,
             database_key,
@@ -114,6 +148,7 @@ impl<'a> SessionConfig<'a> {
             external_entropy,
             ciphersuites,
             nb_key_packages,
+            custom_capabilities,
         })
     }
 }
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+
+use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::MlsE2eiStatus};
+
+use crate::{
+    KeystoreError, MlsError,
+    prelude::{ClientId, ConversationId, DeviceStatus, E2eiConversationState},
+};
+
+use super::{Error, Result, Session};
+
+/// An `E2eiStatusObserver` is notified whenever the end-to-end identity status of a device, or of a
+/// whole conversation, changes.
+///
+/// Statuses are re-evaluated when a commit is merged, when a CRL is registered and when
+/// [crate::transaction_context::TransactionContext::e2ei_reevaluate_status] is called. The first
+/// evaluation of a conversation only records its status, without notifying. The statuses are recorded in
+/// the keystore, and changes are only notified once the transaction they happened in is finished.
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+pub trait E2eiStatusObserver: Send + Sync {
+    /// This function will be called every time the certificate of a valid member of a conversation
+    /// expires or is revoked.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this inteface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    async fn device_status_changed(&self, conversation_id: ConversationId, client_id: ClientId, status: DeviceStatus);
+
+    /// This function will be called every time the [E2eiConversationState] of a conversation changes.
+    ///
+    /// The same restrictions as for [E2eiStatusObserver::device_status_changed] apply.
+    async fn conversation_state_changed(&self, conversation_id: ConversationId, state: E2eiConversationState);
+}
+
+/// The end-to-end identity status of a conversation and of its members at a moment T
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct E2eiStatusSnapshot {
+    pub(crate) state: E2eiConversationState,
+    pub(crate) devices: HashMap<ClientId, DeviceStatus>,
+}
+
+/// How an [E2eiStatusSnapshot] is stored in the keystore
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredE2eiStatus {
+    state: E2eiConversationState,
+    devices: Vec<(ClientId, DeviceStatus)>,
+}
+
+impl E2eiStatusSnapshot {
+    fn to_entity(&self, conversation_id: &ConversationId) -> Result<MlsE2eiStatus> {
+        let stored = StoredE2eiStatus {
+            state: self.state,
+            devices: self.devices.clone().into_iter().collect(),
+        };
+        let status = serde_json::to_vec(&stored).map_err(MlsError::wrap("serializing e2ei status"))?;
+        Ok(MlsE2eiStatus {
+            id: conversation_id.clone(),
+            status,
+        })
+    }
+
+    fn from_entity(entity: &MlsE2eiStatus) -> Result<Self> {
+        let stored = serde_json::from_slice::<StoredE2eiStatus>(&entity.status)
+            .map_err(MlsError::wrap("deserializing e2ei status"))?;
+        Ok(Self {
+            state: stored.state,
+            devices: stored.devices.into_iter().collect(),
+        })
+    }
+}
+
+/// A change of status, reported to the [E2eiStatusObserver] once the transaction causing it is committed
+#[derive(Debug, Clone)]
+pub(crate) enum E2eiStatusChange {
+    Device(ConversationId, ClientId, DeviceStatus),
+    Conversation(ConversationId, E2eiConversationState),
+}
+
+impl Session {
+    /// Add an E2EI status observer to this session.
+    /// (see [E2eiStatusObserver]).
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when an E2EI status observer already exists, this will return an error.
+    pub async fn register_e2ei_status_observer(&self, e2ei_status_observer: Arc<dyn E2eiStatusObserver>) -> Result<()> {
+        let mut observer_guard = self.e2ei_status_observer.write().await;
+        if observer_guard.is_some() {
+            return Err(Error::E2eiStatusObserverAlreadyExists);
+        }
+        observer_guard.replace(e2ei_status_observer);
+        Ok(())
+    }
+
+    /// Whether an E2EI status observer is present, so that statuses are only evaluated when needed.
+    pub(crate) async fn has_e2ei_status_observer(&self) -> bool {
+        self.e2ei_status_observer.read().await.is_some()
+    }
+
+    /// The status of this conversation recorded last, if any
+    async fn e2ei_status(&self, conversation_id: &ConversationId) -> Result<Option<E2eiStatusSnapshot>> {
+        self.crypto_provider
+            .keystore()
+            .find::<MlsE2eiStatus>(conversation_id)
+            .await
+            .map_err(KeystoreError::wrap("finding e2ei status"))?
+            .as_ref()
+            .map(E2eiStatusSnapshot::from_entity)
+            .transpose()
+    }
+
+    /// Whether the status of this conversation has already been recorded
+    pub(crate) async fn has_e2ei_status(&self, conversation_id: &ConversationId) -> Result<bool> {
+        Ok(self.e2ei_status(conversation_id).await?.is_some())
+    }
+
+    /// Record the status of a conversation in the keystore, and queue the changes since the last
+    /// recorded one for the observer, if an observer is present.
+    pub(crate) async fn update_e2ei_status(
+        &self,
+        conversation_id: ConversationId,
+        snapshot: E2eiStatusSnapshot,
+    ) -> Result<()> {
+        let previous = self.e2ei_status(&conversation_id).await?;
+        self.crypto_provider
+            .keystore()
+            .save(snapshot.to_entity(&conversation_id)?)
+            .await
+            .map_err(KeystoreError::wrap("saving e2ei status"))?;
+        if let Some(previous) = previous {
+            self.queue_e2ei_status_changes(conversation_id, &previous, &snapshot)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Queue the changes of the status of a conversation since the last recorded one for the observer,
+    /// without recording it, if an observer is present.
+    pub(crate) async fn compare_e2ei_status(
+        &self,
+        conversation_id: ConversationId,
+        snapshot: E2eiStatusSnapshot,
+    ) -> Result<()> {
+        if let Some(previous) = self.e2ei_status(&conversation_id).await? {
+            self.queue_e2ei_status_changes(conversation_id, &previous, &snapshot)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Forget the status recorded for a conversation, once it has been wiped
+    pub(crate) async fn forget_e2ei_status(&self, conversation_id: &ConversationId) -> Result<()> {
+        self.crypto_provider
+            .keystore()
+            .remove::<MlsE2eiStatus, _>(conversation_id)
+            .await
+            .map_err(KeystoreError::wrap("deleting e2ei status"))?;
+        Ok(())
+    }
+
+    async fn queue_e2ei_status_changes(
+        &self,
+        conversation_id: ConversationId,
+        previous: &E2eiStatusSnapshot,
+        snapshot: &E2eiStatusSnapshot,
+    ) {
+        let mut degraded = snapshot
+            .devices
+            .iter()
+            .filter(|(client_id, status)| {
+                **status != DeviceStatus::Valid && previous.devices.get(*client_id) == Some(&DeviceStatus::Valid)
+            })
+            .collect::<Vec<_>>();
+        degraded.sort_by(|(a, _), (b, _)| a.as_slice().cmp(b.as_slice()));
+
+        let mut changes = self.pending_e2ei_status_changes.write().await;
+        changes.extend(
+            degraded.into_iter().map(|(client_id, status)| {
+                E2eiStatusChange::Device(conversation_id.clone(), client_id.clone(), *status)
+            }),
+        );
+        if snapshot.state != previous.state {
+            changes.push(E2eiStatusChange::Conversation(conversation_id, snapshot.state));
+        }
+    }
+
+    /// Notify the observer of the status changes queued by a transaction, once it has been committed
+    pub(crate) async fn notify_e2ei_status_changes(&self) {
+        let changes = std::mem::take(&mut *self.pending_e2ei_status_changes.write().await);
+        let Some(observer) = self.e2ei_status_observer.read().await.clone() else {
+            return;
+        };
+        for change in changes {
+            match change {
+                E2eiStatusChange::Device(conversation_id, client_id, status) => {
+                    observer.device_status_changed(conversation_id, client_id, status).await
+                }
+                E2eiStatusChange::Conversation(conversation_id, state) => {
+                    observer.conversation_state_changed(conversation_id, state).await
+                }
+            }
+        }
+    }
+
+    /// Drop the status changes queued by a transaction which has not been committed
+    pub(crate) async fn discard_e2ei_status_changes(&self) {
+        self.pending_e2ei_status_changes.write().await.clear();
+    }
+}
@@ -35,6 +35,20 @@ pub enum Error {
     EpochObserverAlreadyExists,
     #[error("An HistoryHandler has already been registered; reregistration is not possible")]
     HistoryObserverAlreadyExists,
+    #[error("A ReInitObserver has already been registered; reregistration is not possible")]
+    ReInitObserverAlreadyExists,
+    #[error("A BufferObserver has already been registered; reregistration is not possible")]
+    BufferObserverAlreadyExists,
+    #[error("A MembershipObserver has already been registered; reregistration is not possible")]
+    MembershipObserverAlreadyExists,
+    #[error("An E2eiStatusObserver has already been registered; reregistration is not possible")]
+    E2eiStatusObserverAlreadyExists,
+    #[error("The keypackage was not found in the keystore")]
+    KeyPackageNotFound,
+    #[error("The lifetime of keypackages cannot exceed the limit defined in openmls")]
+    KeyPackageLifetimeTooLong,
+    #[error("The certificate of the credential has expired")]
+    ExpiredCertificate,
     #[error("Serializing {item} for TLS")]
     TlsSerialize {
         item: &'static str,
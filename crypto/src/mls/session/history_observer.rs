@@ -1,10 +1,13 @@
-use crate::prelude::{ConversationId, HistorySecret};
+use crate::prelude::{ClientId, ConversationId, HistorySecret};
 use async_trait::async_trait;
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use super::{Error, Session};
 
-/// The `HistoryObserver` will be called when updating the history client in a conversation
+/// The `HistoryObserver` will be called when updating the history client in a conversation.
+///
+/// Apart from [HistoryObserver::history_client_created], its events are raised for every merged commit
+/// changing the history clients of a conversation, whether it was created by us or by another member.
 #[cfg_attr(target_family = "wasm", async_trait(?Send))]
 #[cfg_attr(not(target_family = "wasm"), async_trait)]
 pub trait HistoryObserver: Send + Sync {
@@ -13,6 +16,25 @@ pub trait HistoryObserver: Send + Sync {
     ///
     /// The `secret` parameter contains the history client's secrets.
     async fn history_client_created(&self, conversation_id: ConversationId, secret: &HistorySecret);
+
+    /// This function will be called when a history client has been removed from the conversation without
+    /// being replaced. Does nothing by default.
+    async fn history_client_removed(&self, _conversation_id: ConversationId, _client_id: ClientId) {}
+
+    /// This function will be called when a commit replaced the history client of the conversation,
+    /// i.e. removed `previous` and added `replacement`, as happens when a member leaves the conversation.
+    /// Does nothing by default.
+    async fn history_client_replaced(
+        &self,
+        _conversation_id: ConversationId,
+        _previous: ClientId,
+        _replacement: ClientId,
+    ) {
+    }
+
+    /// This function will be called when a commit removed the last history client of the conversation.
+    /// Does nothing by default.
+    async fn history_sharing_disabled(&self, _conversation_id: ConversationId) {}
 }
 
 impl Session {
@@ -43,4 +65,90 @@ impl Session {
             handler.history_client_created(conversation_id, history_secret).await;
         }
     }
+
+    /// Notify the history handler about the history clients added or removed by a merged commit,
+    /// if one is present.
+    ///
+    /// `previous` and `current` are the history clients of the conversation before and after the commit.
+    pub(crate) async fn notify_history_clients_changed(
+        &self,
+        conversation_id: ConversationId,
+        previous: HashSet<ClientId>,
+        current: HashSet<ClientId>,
+    ) {
+        if previous == current {
+            return;
+        }
+        let Some(handler) = self.history_observer.read().await.clone() else {
+            return;
+        };
+
+        // A removed history client is replaced when the commit added another one in its stead
+        let mut added = current.difference(&previous).cloned();
+        for client_id in previous.difference(&current).cloned() {
+            match added.next() {
+                Some(replacement) => {
+                    handler
+                        .history_client_replaced(conversation_id.clone(), client_id, replacement)
+                        .await
+                }
+                None => handler.history_client_removed(conversation_id.clone(), client_id).await,
+            }
+        }
+        if current.is_empty() {
+            handler.history_sharing_disabled(conversation_id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[apply(all_cred_cipher)]
+    async fn observes_history_client_lifecycle_from_own_and_remote_commits(case: TestContext) {
+        let [alice, bob, carol] = case.sessions().await;
+        Box::pin(async move {
+            bob.setup_history_observer().await;
+            let conversation = case
+                .create_conversation([&alice, &bob, &carol])
+                .await
+                .enable_history_sharing_notify()
+                .await;
+            let id = conversation.id().clone();
+            assert!(
+                bob.history_observer().await.observed_events().await.is_empty(),
+                "adding the first history client neither removes nor replaces anything"
+            );
+
+            // removing a member replaces the history client
+            let conversation = conversation.remove_notify(&carol).await;
+            let history_clients = alice
+                .history_observer()
+                .await
+                .observed_history_clients()
+                .await
+                .into_iter()
+                .map(|(_, secret)| secret.client_id)
+                .collect::<Vec<_>>();
+            let [first, second] = history_clients.as_slice() else {
+                panic!("two history clients should have been created");
+            };
+            let replaced = vec![HistoryEvent::Replaced(id.clone(), first.clone(), second.clone())];
+            assert_eq!(alice.history_observer().await.observed_events().await, replaced);
+            assert_eq!(bob.history_observer().await.observed_events().await, replaced);
+
+            conversation.disable_history_sharing_notify().await;
+            let disabled = replaced
+                .into_iter()
+                .chain([
+                    HistoryEvent::Removed(id.clone(), second.clone()),
+                    HistoryEvent::Disabled(id.clone()),
+                ])
+                .collect::<Vec<_>>();
+            assert_eq!(alice.history_observer().await.observed_events().await, disabled);
+            assert_eq!(bob.history_observer().await.observed_events().await, disabled);
+        })
+        .await
+    }
 }
@@ -81,6 +81,18 @@ impl Identities {
         Ok(())
     }
 
+    /// The most recent credential bundle of each signature scheme having one of this credential type
+    pub(crate) fn most_recent_credential_bundles(
+        &self,
+        ct: MlsCredentialType,
+    ) -> impl Iterator<Item = (SignatureScheme, Arc<CredentialBundle>)> + '_ {
+        self.0.iter().filter_map(move |(sc, cbs)| {
+            cbs.iter()
+                .rfind(|c| ct == c.credential.credential_type().into())
+                .map(|cb| (*sc, cb.clone()))
+        })
+    }
+
     pub(crate) fn iter(&self) -> impl Iterator<Item = (SignatureScheme, Arc<CredentialBundle>)> + '_ {
         self.0.iter().flat_map(|(sc, cb)| cb.iter().map(|c| (*sc, c.clone())))
     }
@@ -101,6 +113,17 @@ impl Session {
         }
     }
 
+    /// The most recent credential bundle of each signature scheme having one of this credential type
+    pub(crate) async fn most_recent_credential_bundles(
+        &self,
+        ct: MlsCredentialType,
+    ) -> Result<Vec<(SignatureScheme, Arc<CredentialBundle>)>> {
+        match self.inner.read().await.deref() {
+            None => Err(Error::MlsNotInitialized),
+            Some(SessionInner { identities, .. }) => Ok(identities.most_recent_credential_bundles(ct).collect()),
+        }
+    }
+
     pub(crate) async fn find_credential_bundle_by_public_key(
         &self,
         sc: SignatureScheme,
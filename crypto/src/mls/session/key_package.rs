@@ -1,19 +1,22 @@
-use openmls::prelude::{Credential, CredentialWithKey, CryptoConfig, KeyPackage, KeyPackageRef, Lifetime};
+use openmls::prelude::{
+    Credential, CredentialWithKey, CryptoConfig, Extension, ExtensionType, Extensions, KeyPackage, KeyPackageRef,
+    LastResortExtension, Lifetime, Welcome,
+};
 use openmls_traits::OpenMlsCryptoProvider;
 use std::collections::{HashMap, HashSet};
 use tls_codec::{Deserialize, Serialize};
 
 use core_crypto_keystore::{
     connection::FetchFromDatabase,
-    entities::{EntityFindParams, MlsEncryptionKeyPair, MlsHpkePrivateKey, MlsKeyPackage},
+    entities::{EntityFindParams, MlsEncryptionKeyPair, MlsHpkePrivateKey, MlsKeyPackage, MlsKeyPackageLifetime},
 };
 use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
 
 use super::{Error, Result};
 use crate::{
-    KeystoreError, MlsError,
-    mls::{credential::CredentialBundle, session::SessionInner},
-    prelude::{MlsCiphersuite, MlsConversationConfiguration, MlsCredentialType, Session},
+    KeystoreError, MlsError, RecursiveError,
+    mls::credential::{CredentialBundle, ext::CredentialExt as _},
+    prelude::{MlsCiphersuite, MlsConversationConfiguration, MlsCredentialType, MlsCustomCapabilities, Session},
 };
 
 /// Default number of KeyPackages a client generates the first time it's created
@@ -27,6 +30,106 @@ pub const INITIAL_KEYING_MATERIAL_COUNT: usize = 10;
 pub(crate) const KEYPACKAGE_DEFAULT_LIFETIME: std::time::Duration =
     std::time::Duration::from_secs(60 * 60 * 24 * 28 * 3); // ~3 months
 
+/// How close to their expiry key packages are, see [Session::keypackage_inventory]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MlsKeyPackageExpiry {
+    /// The key package has expired, it will be pruned
+    Expired,
+    /// The key package expires within the requested span
+    ExpiringSoon,
+    /// The key package is valid for longer than the requested span, or has no lifetime
+    Valid,
+}
+
+impl From<core_crypto_keystore::entities::MlsKeyPackageExpiry> for MlsKeyPackageExpiry {
+    fn from(expiry: core_crypto_keystore::entities::MlsKeyPackageExpiry) -> Self {
+        use core_crypto_keystore::entities::MlsKeyPackageExpiry as KeystoreExpiry;
+        match expiry {
+            KeystoreExpiry::Expired => Self::Expired,
+            KeystoreExpiry::ExpiringSoon => Self::ExpiringSoon,
+            KeystoreExpiry::Valid => Self::Valid,
+        }
+    }
+}
+
+/// How many key packages of a ciphersuite and credential type are in the keystore, see
+/// [Session::keypackage_inventory]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsKeyPackageInventoryEntry {
+    /// Ciphersuite of the key packages
+    pub ciphersuite: MlsCiphersuite,
+    /// Credential type of the key packages
+    pub credential_type: MlsCredentialType,
+    /// How close to their expiry the key packages are
+    pub expiry: MlsKeyPackageExpiry,
+    /// Number of key packages
+    pub count: usize,
+    /// Number of key packages marked as uploaded, see [Session::mark_keypackages_uploaded]
+    pub uploaded: usize,
+}
+
+/// Key material of a last resort key package a welcome was sent to. OpenMLS deletes it when joining from
+/// the welcome, so it is put back afterwards.
+pub(crate) struct LastResortKeyMaterial {
+    key_package: MlsKeyPackage,
+    init_key: Option<MlsHpkePrivateKey>,
+    encryption_key_pair: Option<MlsEncryptionKeyPair>,
+}
+
+impl LastResortKeyMaterial {
+    /// The key material of the last resort key packages this welcome was sent to
+    pub(crate) async fn find(keystore: &CryptoKeystore, welcome: &Welcome) -> Result<Vec<Self>> {
+        let mut materials = Vec::new();
+        for secrets in welcome.secrets() {
+            let Some(key_package) = keystore
+                .find::<MlsKeyPackage>(secrets.new_member().as_slice())
+                .await
+                .map_err(KeystoreError::wrap("finding keypackage"))?
+                .filter(MlsKeyPackage::is_last_resort)
+            else {
+                continue;
+            };
+            let kp = core_crypto_keystore::deser::<KeyPackage>(&key_package.keypackage)
+                .map_err(KeystoreError::wrap("deserializing keypackage"))?;
+            let init_key = keystore
+                .find::<MlsHpkePrivateKey>(kp.hpke_init_key().as_slice())
+                .await
+                .map_err(KeystoreError::wrap("finding private key"))?;
+            let encryption_key_pair = keystore
+                .find::<MlsEncryptionKeyPair>(kp.leaf_node().encryption_key().as_slice())
+                .await
+                .map_err(KeystoreError::wrap("finding encryption keypair"))?;
+            materials.push(Self {
+                key_package,
+                init_key,
+                encryption_key_pair,
+            });
+        }
+        Ok(materials)
+    }
+
+    /// Saves the key material again, in case it got deleted
+    pub(crate) async fn restore(self, keystore: &CryptoKeystore) -> Result<()> {
+        keystore
+            .save(self.key_package)
+            .await
+            .map_err(KeystoreError::wrap("restoring last resort keypackage"))?;
+        if let Some(init_key) = self.init_key {
+            keystore
+                .save(init_key)
+                .await
+                .map_err(KeystoreError::wrap("restoring private key"))?;
+        }
+        if let Some(encryption_key_pair) = self.encryption_key_pair {
+            keystore
+                .save(encryption_key_pair)
+                .await
+                .map_err(KeystoreError::wrap("restoring encryption keypair"))?;
+        }
+        Ok(())
+    }
+}
+
 impl Session {
     /// Generates a single new keypackage
     ///
@@ -41,14 +144,45 @@ impl Session {
         cs: MlsCiphersuite,
         cb: &CredentialBundle,
     ) -> Result<KeyPackage> {
-        let guard = self.inner.read().await;
-        let SessionInner {
-            keypackage_lifetime, ..
-        } = guard.as_ref().ok_or(Error::MlsNotInitialized)?;
+        self.generate_keypackage(backend, cs, cb, false).await
+    }
+
+    async fn generate_keypackage(
+        &self,
+        backend: &MlsCryptoProvider,
+        cs: MlsCiphersuite,
+        cb: &CredentialBundle,
+        last_resort: bool,
+    ) -> Result<KeyPackage> {
+        let credential_type = MlsCredentialType::from(cb.credential.credential_type());
+        let mut lifetime = Self::keypackage_lifetime(&backend.keystore(), cs, credential_type)
+            .await?
+            .as_secs();
+        // A keypackage is unusable once its certificate has expired
+        if let Some(certificate_expiry) = Self::certificate_expiry(&cb.credential)? {
+            let remaining = certificate_expiry.saturating_sub(crate::mls::unix_timestamp());
+            if remaining == 0 {
+                return Err(Error::ExpiredCertificate);
+            }
+            lifetime = lifetime.min(remaining);
+        }
+
+        let (capabilities, extensions) = if last_resort {
+            // The leaf node has to advertise the extensions of its key package
+            let mut custom = MlsCustomCapabilities::clone(&self.custom_capabilities);
+            custom.extension_types.push(ExtensionType::LastResort.into());
+            (
+                MlsConversationConfiguration::leaf_capabilities(&custom),
+                Extensions::single(Extension::LastResort(LastResortExtension::default())),
+            )
+        } else {
+            (self.leaf_capabilities(), Extensions::empty())
+        };
 
         let keypackage = KeyPackage::builder()
-            .leaf_node_capabilities(MlsConversationConfiguration::default_leaf_capabilities())
-            .key_package_lifetime(Lifetime::new(keypackage_lifetime.as_secs()))
+            .leaf_node_capabilities(capabilities)
+            .key_package_extensions(extensions)
+            .key_package_lifetime(Lifetime::new(lifetime))
             .build(
                 CryptoConfig {
                     ciphersuite: cs.into(),
@@ -63,10 +197,106 @@ impl Session {
             )
             .await
             .map_err(KeystoreError::wrap("building keypackage"))?;
+        Self::record_keypackage_metadata(backend, &keypackage, last_resort).await?;
 
         Ok(keypackage)
     }
 
+    /// Records the metadata of a key package stored by OpenMLS, so that it can be queried for
+    async fn record_keypackage_metadata(backend: &MlsCryptoProvider, kp: &KeyPackage, last_resort: bool) -> Result<()> {
+        let kp_ref = kp
+            .hash_ref(backend.crypto())
+            .map_err(MlsError::wrap("computing keypackage hashref"))?;
+        let keystore = backend.keystore();
+        let mut stored_kp = keystore
+            .find::<MlsKeyPackage>(kp_ref.as_slice())
+            .await
+            .map_err(KeystoreError::wrap("finding keypackage"))?
+            .ok_or(Error::KeyPackageNotFound)?;
+        Self::set_keypackage_metadata(&mut stored_kp, kp)?;
+        if last_resort {
+            stored_kp.set_last_resort();
+        }
+        keystore
+            .save(stored_kp)
+            .await
+            .map_err(KeystoreError::wrap("saving keypackage metadata"))?;
+        Ok(())
+    }
+
+    fn set_keypackage_metadata(stored_kp: &mut MlsKeyPackage, kp: &KeyPackage) -> Result<()> {
+        let credential_type = MlsCredentialType::from(kp.leaf_node().credential().credential_type());
+        let expires_at = kp.leaf_node().life_time().map(|lifetime| lifetime.not_after());
+        let expires_at = match (expires_at, Self::certificate_expiry(kp.leaf_node().credential())?) {
+            (Some(expires_at), Some(certificate_expiry)) => Some(expires_at.min(certificate_expiry)),
+            (expires_at, certificate_expiry) => expires_at.or(certificate_expiry),
+        };
+        stored_kp.set_metadata(
+            MlsCiphersuite::from(kp.ciphersuite()).into(),
+            credential_type as u8,
+            expires_at,
+        );
+        Ok(())
+    }
+
+    /// When the leaf certificate of this credential expires, for X509 credentials
+    pub(crate) fn certificate_expiry(credential: &Credential) -> Result<Option<u64>> {
+        let certificate = credential
+            .parse_leaf_cert()
+            .map_err(RecursiveError::mls_credential("parsing leaf certificate"))?;
+        Ok(certificate.map(|certificate| {
+            certificate
+                .tbs_certificate
+                .validity
+                .not_after
+                .to_unix_duration()
+                .as_secs()
+        }))
+    }
+
+    /// Records the metadata of key packages stored before it was recorded. This migrates the keystore
+    /// once, when the client is loaded: every key package generated since records its metadata.
+    ///
+    /// Key packages which cannot be read are left as they are, so that they don't prevent the client
+    /// from loading. They are not part of [Self::keypackage_inventory].
+    pub(super) async fn backfill_keypackage_metadata(keystore: &CryptoKeystore) -> Result<()> {
+        let stored_kps = keystore
+            .find_key_packages_without_metadata()
+            .await
+            .map_err(KeystoreError::wrap("finding keypackages without metadata"))?;
+        for mut stored_kp in stored_kps {
+            let metadata = core_crypto_keystore::deser::<KeyPackage>(&stored_kp.keypackage)
+                .map_err(KeystoreError::wrap("deserializing keypackage"))
+                .map_err(Error::from)
+                .and_then(|kp| Self::set_keypackage_metadata(&mut stored_kp, &kp));
+            if let Err(e) = metadata {
+                log::warn!(error:% = e; "Skipping a keypackage whose metadata cannot be recorded");
+                continue;
+            }
+            keystore
+                .save(stored_kp)
+                .await
+                .map_err(KeystoreError::wrap("saving keypackage metadata"))?;
+        }
+        Ok(())
+    }
+
+    /// The key packages of this ciphersuite and credential type in the keystore, either the last resort
+    /// ones or the others
+    async fn find_keypackages(
+        keystore: &CryptoKeystore,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+        last_resort: bool,
+        params: EntityFindParams,
+    ) -> Result<Vec<MlsKeyPackage>> {
+        keystore
+            .find_key_packages(ciphersuite.into(), credential_type as u8, last_resort, params)
+            .await
+            .map_err(KeystoreError::wrap("finding keypackages"))
+            .map_err(Into::into)
+    }
+
     /// Requests `count` keying material to be present and returns
     /// a reference to it for the consumer to copy/clone.
     ///
@@ -86,17 +316,18 @@ impl Session {
     ) -> Result<Vec<KeyPackage>> {
         // Auto-prune expired keypackages on request
         self.prune_keypackages(backend, std::iter::empty()).await?;
-        use core_crypto_keystore::CryptoKeystoreMls as _;
 
-        let mut existing_kps = backend
-            .key_store()
-            .mls_fetch_keypackages::<KeyPackage>(count as u32)
-            .await.map_err(KeystoreError::wrap("fetching mls keypackages"))?
+        let params = EntityFindParams {
+            limit: Some(count as u32),
+            offset: None,
+            reverse: cfg!(not(target_family = "wasm")),
+        };
+        let mut existing_kps = Self::find_keypackages(&backend.keystore(), ciphersuite, credential_type, false, params)
+            .await?
             .into_iter()
-            // TODO: do this filtering in SQL when the schema is updated. Tracking issue: WPB-9599
-            .filter(|kp|
-                kp.ciphersuite() == ciphersuite.0 && MlsCredentialType::from(kp.leaf_node().credential().credential_type()) == credential_type)
-            .collect::<Vec<_>>();
+            .map(|kp| core_crypto_keystore::deser::<KeyPackage>(&kp.keypackage))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(KeystoreError::wrap("deserializing keypackage"))?;
 
         let kpb_count = existing_kps.len();
         let mut kps = if count > kpb_count {
@@ -135,6 +366,74 @@ impl Session {
         Ok(kps)
     }
 
+    /// Returns the last resort keypackage of this ciphersuite and credential type, generating it if there
+    /// is none or it has expired.
+    ///
+    /// Unlike other keypackages, it is kept once a Welcome consumed it, so that the delivery service can
+    /// hand it out when it ran out of keypackages (see <https://www.rfc-editor.org/rfc/rfc9420.html#section-16.8>).
+    /// It is pruned once expired.
+    pub async fn last_resort_keypackage(
+        &self,
+        backend: &MlsCryptoProvider,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<KeyPackage> {
+        let now = crate::mls::unix_timestamp();
+        let existing = Self::find_keypackages(
+            &backend.keystore(),
+            ciphersuite,
+            credential_type,
+            true,
+            Default::default(),
+        )
+        .await?
+        .into_iter()
+        .find(|kp| !matches!(kp.expires_at(), Some(expires_at) if expires_at <= now));
+        if let Some(existing) = existing {
+            return core_crypto_keystore::deser::<KeyPackage>(&existing.keypackage)
+                .map_err(KeystoreError::wrap("deserializing keypackage"))
+                .map_err(Into::into);
+        }
+        self.generate_last_resort_keypackage(backend, ciphersuite, credential_type)
+            .await
+    }
+
+    /// Replaces the last resort keypackage of this ciphersuite and credential type with a new one.
+    ///
+    /// The previous one is deleted, so Welcomes sent to it cannot be processed anymore: the new one should
+    /// replace it on the delivery service first. Conversations joined through it keep working.
+    pub async fn rotate_last_resort_keypackage(
+        &self,
+        backend: &MlsCryptoProvider,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<KeyPackage> {
+        let keystore = backend.keystore();
+        let previous =
+            Self::find_keypackages(&keystore, ciphersuite, credential_type, true, Default::default()).await?;
+        let kp = self
+            .generate_last_resort_keypackage(backend, ciphersuite, credential_type)
+            .await?;
+        for stored_kp in previous {
+            let previous_kp = core_crypto_keystore::deser::<KeyPackage>(&stored_kp.keypackage)
+                .map_err(KeystoreError::wrap("deserializing keypackage"))?;
+            Self::delete_keypackage(&keystore, &stored_kp, &previous_kp).await?;
+        }
+        Ok(kp)
+    }
+
+    async fn generate_last_resort_keypackage(
+        &self,
+        backend: &MlsCryptoProvider,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<KeyPackage> {
+        let cb = self
+            .find_most_recent_credential_bundle(ciphersuite.signature_algorithm(), credential_type)
+            .await?;
+        self.generate_keypackage(backend, ciphersuite, &cb, true).await
+    }
+
     /// Returns the count of valid, non-expired, unclaimed keypackages in store
     pub async fn valid_keypackages_count(
         &self,
@@ -142,30 +441,82 @@ impl Session {
         ciphersuite: MlsCiphersuite,
         credential_type: MlsCredentialType,
     ) -> Result<usize> {
-        let kps: Vec<MlsKeyPackage> = backend
-            .key_store()
-            .find_all(EntityFindParams::default())
-            .await
-            .map_err(KeystoreError::wrap("finding all key packages"))?;
+        let now = crate::mls::unix_timestamp();
+        let kps = Self::find_keypackages(
+            &backend.keystore(),
+            ciphersuite,
+            credential_type,
+            false,
+            Default::default(),
+        )
+        .await?;
+        Ok(kps
+            .iter()
+            .filter(|kp| !matches!(kp.expires_at(), Some(expires_at) if expires_at <= now))
+            .count())
+    }
 
-        let mut valid_count = 0;
-        for kp in kps
-            .into_iter()
-            .map(|kp| core_crypto_keystore::deser::<KeyPackage>(&kp.keypackage))
-            // TODO: do this filtering in SQL when the schema is updated. Tracking issue: WPB-9599
-            .filter(|kp| {
-                kp.as_ref()
-                    .map(|b| b.ciphersuite() == ciphersuite.0 && MlsCredentialType::from(b.leaf_node().credential().credential_type()) == credential_type)
-                    .unwrap_or_default()
-            })
-        {
-            let kp = kp.map_err(KeystoreError::wrap("counting valid keypackages"))?;
-            if !Self::is_mls_keypackage_expired(&kp) {
-                valid_count += 1;
-            }
+    /// Counts the key packages in store per ciphersuite, credential type and expiry.
+    ///
+    /// Key packages expiring within `expiring_within` are counted as [MlsKeyPackageExpiry::ExpiringSoon].
+    /// Entries are sorted, and there are none for empty buckets. Last resort key packages are not counted.
+    pub async fn keypackage_inventory(
+        &self,
+        backend: &MlsCryptoProvider,
+        expiring_within: std::time::Duration,
+    ) -> Result<Vec<MlsKeyPackageInventoryEntry>> {
+        let now = crate::mls::unix_timestamp();
+        let soon = now.saturating_add(expiring_within.as_secs());
+        let counts = backend
+            .keystore()
+            .count_key_packages(now, soon)
+            .await
+            .map_err(KeystoreError::wrap("counting key packages"))?;
+
+        let mut entries = Vec::with_capacity(counts.len());
+        for count in counts {
+            let credential_type = match count.credential_type {
+                ct if ct == MlsCredentialType::Basic as u8 => MlsCredentialType::Basic,
+                ct if ct == MlsCredentialType::X509 as u8 => MlsCredentialType::X509,
+                _ => continue,
+            };
+            let ciphersuite = MlsCiphersuite::try_from(count.ciphersuite)
+                .map_err(RecursiveError::mls("reading ciphersuite of a keypackage"))?;
+            entries.push(MlsKeyPackageInventoryEntry {
+                ciphersuite,
+                credential_type,
+                expiry: count.expiry.into(),
+                count: count.count,
+                uploaded: count.uploaded,
+            });
         }
+        Ok(entries)
+    }
 
-        Ok(valid_count)
+    /// Marks these key packages as uploaded to the delivery service, see [Self::keypackage_inventory].
+    /// Unknown key packages are ignored.
+    pub async fn mark_keypackages_uploaded(
+        &self,
+        backend: &MlsCryptoProvider,
+        refs: impl IntoIterator<Item = KeyPackageRef>,
+    ) -> Result<()> {
+        let keystore = backend.keystore();
+        let now = crate::mls::unix_timestamp();
+        for kp_ref in refs {
+            let Some(mut kp) = keystore
+                .find::<MlsKeyPackage>(kp_ref.as_slice())
+                .await
+                .map_err(KeystoreError::wrap("finding keypackage"))?
+            else {
+                continue;
+            };
+            kp.set_uploaded_at(now);
+            keystore
+                .save(kp)
+                .await
+                .map_err(KeystoreError::wrap("marking keypackage as uploaded"))?;
+        }
+        Ok(())
     }
 
     /// Checks if a given OpenMLS [`KeyPackage`] is expired by looking through its extensions,
@@ -183,6 +534,7 @@ impl Session {
     /// Warning: Despite this API being public, the caller should know what they're doing.
     /// Provided KeypackageRefs **will** be purged regardless of their expiration state, so please be wary of what you are doing if you directly call this API.
     /// This could result in still valid, uploaded keypackages being pruned from the system and thus being impossible to find when referenced in a future Welcome message.
+    /// Last resort keypackages are only pruned once expired, see [Self::rotate_last_resort_keypackage].
     pub async fn prune_keypackages(
         &self,
         backend: &MlsCryptoProvider,
@@ -242,7 +594,8 @@ impl Session {
         Ok(())
     }
 
-    /// Deletes all expired KeyPackages plus the ones in `refs`. It also deletes all associated:
+    /// Deletes all expired KeyPackages plus the ones in `refs`, unless they are last resort ones. It also
+    /// deletes all associated:
     /// * HPKE private keys
     /// * HPKE Encryption KeyPairs
     /// * Signature KeyPairs & Credentials (use [Self::prune_keypackages_and_credential])
@@ -268,29 +621,47 @@ impl Session {
             })
             .collect::<HashSet<_>>();
 
+        let now = crate::mls::unix_timestamp();
         let kp_to_delete = kps.iter().filter_map(|(store_kp, kp)| {
-            let is_expired = Self::is_mls_keypackage_expired(kp);
-            let to_delete = is_expired || refs.contains(store_kp.keypackage_ref.as_slice());
-            to_delete.then_some((kp, &store_kp.keypackage_ref))
+            // the recorded expiry also accounts for the certificate of X509 keypackages
+            let is_expired = Self::is_mls_keypackage_expired(kp)
+                || matches!(store_kp.expires_at(), Some(expires_at) if expires_at <= now);
+            let is_consumed = refs.contains(store_kp.keypackage_ref.as_slice()) && !store_kp.is_last_resort();
+            let to_delete = is_expired || is_consumed;
+            to_delete.then_some((kp, store_kp))
         });
 
         // note: we're cloning the iterator here, not the data
-        for (kp, kp_ref) in kp_to_delete.clone() {
-            keystore
-                .remove::<MlsKeyPackage, &[u8]>(kp_ref.as_slice())
-                .await
-                .map_err(KeystoreError::wrap("removing key package from keystore"))?;
-            keystore
-                .remove::<MlsHpkePrivateKey, &[u8]>(kp.hpke_init_key().as_slice())
-                .await
-                .map_err(KeystoreError::wrap("removing private key from keystore"))?;
-            keystore
-                .remove::<MlsEncryptionKeyPair, &[u8]>(kp.leaf_node().encryption_key().as_slice())
-                .await
-                .map_err(KeystoreError::wrap("removing encryption keypair from keystore"))?;
+        for (kp, store_kp) in kp_to_delete.clone() {
+            Self::delete_keypackage(keystore, store_kp, kp).await?;
         }
 
-        Ok(kp_to_delete.map(|(_, kpref)| kpref.as_slice()).collect())
+        Ok(kp_to_delete
+            .map(|(_, store_kp)| store_kp.keypackage_ref.as_slice())
+            .collect())
+    }
+
+    /// Deletes a keypackage and its key material.
+    ///
+    /// The encryption keypair of a last resort keypackage is kept: it is the leaf key of every conversation
+    /// joined through it, so it belongs to these conversations.
+    async fn delete_keypackage(keystore: &CryptoKeystore, stored_kp: &MlsKeyPackage, kp: &KeyPackage) -> Result<()> {
+        keystore
+            .remove::<MlsKeyPackage, &[u8]>(&stored_kp.keypackage_ref)
+            .await
+            .map_err(KeystoreError::wrap("removing key package from keystore"))?;
+        keystore
+            .remove::<MlsHpkePrivateKey, &[u8]>(kp.hpke_init_key().as_slice())
+            .await
+            .map_err(KeystoreError::wrap("removing private key from keystore"))?;
+        if stored_kp.is_last_resort() {
+            return Ok(());
+        }
+        keystore
+            .remove::<MlsEncryptionKeyPair, &[u8]>(kp.leaf_node().encryption_key().as_slice())
+            .await
+            .map_err(KeystoreError::wrap("removing encryption keypair from keystore"))?;
+        Ok(())
     }
 
     async fn find_all_keypackages(&self, keystore: &CryptoKeystore) -> Result<Vec<(MlsKeyPackage, KeyPackage)>> {
@@ -311,20 +682,52 @@ impl Session {
         Ok(kps)
     }
 
-    /// Allows to set the current default keypackage lifetime extension duration.
-    /// It will be embedded in the [openmls::key_packages::KeyPackage]'s [openmls::extensions::LifetimeExtension]
-    #[cfg(test)]
-    pub async fn set_keypackage_lifetime(&self, duration: std::time::Duration) -> Result<()> {
-        use std::ops::DerefMut;
-        match self.inner.write().await.deref_mut() {
-            None => Err(Error::MlsNotInitialized),
-            Some(SessionInner {
-                keypackage_lifetime, ..
-            }) => {
-                *keypackage_lifetime = duration;
-                Ok(())
-            }
+    /// Sets the lifetime of the keypackages generated from now on for this ciphersuite and credential type.
+    /// It will be embedded in the [openmls::key_packages::KeyPackage]'s [openmls::extensions::LifetimeExtension],
+    /// and is kept in the keystore.
+    ///
+    /// It cannot exceed [KEYPACKAGE_DEFAULT_LIFETIME], about 3 months: openmls rejects key packages whose
+    /// lifetime spans longer when validating them, so no one could add us with such a key package. Basic
+    /// keypackages can use that whole range, while X509 keypackages never outlive their certificate,
+    /// whatever their lifetime.
+    pub async fn set_keypackage_lifetime(
+        &self,
+        backend: &MlsCryptoProvider,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+        duration: std::time::Duration,
+    ) -> Result<()> {
+        if duration > KEYPACKAGE_DEFAULT_LIFETIME {
+            return Err(Error::KeyPackageLifetimeTooLong);
         }
+        backend
+            .keystore()
+            .save(MlsKeyPackageLifetime::new(
+                ciphersuite.into(),
+                credential_type as u8,
+                duration.as_secs(),
+            ))
+            .await
+            .map_err(KeystoreError::wrap("saving keypackage lifetime"))?;
+        Ok(())
+    }
+
+    /// The lifetime of the keypackages generated for this ciphersuite and credential type, see
+    /// [Self::set_keypackage_lifetime]
+    async fn keypackage_lifetime(
+        keystore: &CryptoKeystore,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<std::time::Duration> {
+        let id = MlsKeyPackageLifetime::make_id(ciphersuite.into(), credential_type as u8);
+        let lifetime = keystore
+            .find::<MlsKeyPackageLifetime>(&id)
+            .await
+            .map_err(KeystoreError::wrap("finding keypackage lifetime"))?
+            .and_then(|lifetime| lifetime.lifetime())
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(KEYPACKAGE_DEFAULT_LIFETIME);
+        Ok(lifetime)
     }
 }
 
@@ -337,10 +740,15 @@ mod tests {
     use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
 
     use crate::e2e_identity::enrollment::test_utils::{e2ei_enrollment, init_activation_or_rotation, noop_restore};
-    use crate::prelude::MlsConversationConfiguration;
-    use crate::prelude::key_package::INITIAL_KEYING_MATERIAL_COUNT;
+    use crate::prelude::key_package::{INITIAL_KEYING_MATERIAL_COUNT, KEYPACKAGE_DEFAULT_LIFETIME};
+    use crate::prelude::{
+        CertificateBundle, MlsConversationConfiguration, MlsCredentialType, MlsKeyPackageExpiry,
+        MlsKeyPackageInventoryEntry,
+    };
     use crate::test_utils::*;
-    use core_crypto_keystore::{ConnectionType, DatabaseKey};
+    use core_crypto_keystore::{
+        ConnectionType, DatabaseKey, connection::FetchFromDatabase as _, entities::MlsKeyPackage,
+    };
 
     use super::Session;
 
@@ -376,7 +784,7 @@ mod tests {
 
         // 1-second expiration
         session
-            .set_keypackage_lifetime(std::time::Duration::from_secs(1))
+            .set_keypackage_lifetime(&backend, cs, ct, std::time::Duration::from_secs(1))
             .await
             .unwrap();
         let kp_1s_exp = session.generate_one_keypackage(&backend, cs, ct).await.unwrap();
@@ -530,6 +938,382 @@ mod tests {
         .await
     }
 
+    #[apply(all_cred_cipher)]
+    async fn can_take_keypackage_inventory(case: TestContext) {
+        let [session_context] = case.sessions().await;
+        Box::pin(async move {
+            let transaction = &session_context.transaction;
+            let (ciphersuite, credential_type) = (case.ciphersuite(), case.credential_type);
+            let soon = std::time::Duration::from_secs(2 * 60 * 60);
+            let entry = |expiry, count, uploaded| MlsKeyPackageInventoryEntry {
+                ciphersuite,
+                credential_type,
+                expiry,
+                count,
+                uploaded,
+            };
+
+            let inventory = transaction.key_package_inventory(soon).await.unwrap();
+            assert_eq!(
+                inventory,
+                vec![entry(MlsKeyPackageExpiry::Valid, INITIAL_KEYING_MATERIAL_COUNT, 0)]
+            );
+
+            // These expire within two hours
+            transaction
+                .set_key_package_lifetime(ciphersuite, credential_type, std::time::Duration::from_secs(60 * 60))
+                .await
+                .unwrap();
+            let kps = transaction
+                .get_or_create_client_keypackages(ciphersuite, credential_type, INITIAL_KEYING_MATERIAL_COUNT + 2)
+                .await
+                .unwrap();
+            let crypto_provider = transaction.mls_provider().await.unwrap();
+            let new_kp_refs = kps[..2]
+                .iter()
+                .map(|kp| kp.hash_ref(crypto_provider.crypto()).unwrap())
+                .collect::<Vec<_>>();
+            transaction.mark_key_packages_uploaded(new_kp_refs).await.unwrap();
+
+            let inventory = transaction.key_package_inventory(soon).await.unwrap();
+            assert_eq!(
+                inventory,
+                vec![
+                    entry(MlsKeyPackageExpiry::ExpiringSoon, 2, 2),
+                    entry(MlsKeyPackageExpiry::Valid, INITIAL_KEYING_MATERIAL_COUNT, 0),
+                ]
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn backfills_keypackage_metadata_when_loading(case: TestContext) {
+        use core_crypto_keystore::entities::{EntityFindParams, MlsCredential};
+        use tls_codec::Deserialize as _;
+
+        let [session_context] = case.sessions().await;
+        Box::pin(async move {
+            let transaction = &session_context.transaction;
+            let keystore = transaction.keystore().await.unwrap();
+            let soon = std::time::Duration::from_secs(60);
+
+            // key packages stored by earlier versions lack their metadata
+            let stored_kps = keystore
+                .find_all::<MlsKeyPackage>(EntityFindParams::default())
+                .await
+                .unwrap();
+            for (i, mut stored_kp) in stored_kps.into_iter().enumerate() {
+                stored_kp.ciphersuite = None;
+                stored_kp.credential_type = None;
+                stored_kp.expires_at = None;
+                // an unreadable key package must not prevent loading the client
+                if i == 0 {
+                    stored_kp.keypackage = vec![0xff; 4];
+                }
+                keystore.save(stored_kp).await.unwrap();
+            }
+            assert!(transaction.key_package_inventory(soon).await.unwrap().is_empty());
+
+            let session = session_context.session().await;
+            let client_id = session.id().await.unwrap();
+            let credentials = keystore
+                .find_all::<MlsCredential>(EntityFindParams::default())
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|credential| {
+                    let created_at = credential.created_at;
+                    let credential =
+                        openmls::prelude::Credential::tls_deserialize(&mut credential.credential.as_slice()).unwrap();
+                    (credential, created_at)
+                })
+                .collect();
+            session.reset().await;
+            let backend = transaction.mls_provider().await.unwrap();
+            session
+                .load(
+                    &backend,
+                    &client_id,
+                    credentials,
+                    std::collections::HashSet::from([case.signature_scheme()]),
+                )
+                .await
+                .unwrap();
+
+            let inventory = transaction.key_package_inventory(soon).await.unwrap();
+            assert_eq!(
+                inventory,
+                vec![MlsKeyPackageInventoryEntry {
+                    ciphersuite: case.ciphersuite(),
+                    credential_type: case.credential_type,
+                    expiry: MlsKeyPackageExpiry::Valid,
+                    count: INITIAL_KEYING_MATERIAL_COUNT - 1,
+                    uploaded: 0,
+                }]
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn keypackage_lifetime_is_set_per_credential_type(case: TestContext) {
+        let [session_context] = case.sessions().await;
+        Box::pin(async move {
+            let transaction = &session_context.transaction;
+            let (ciphersuite, credential_type) = (case.ciphersuite(), case.credential_type);
+            let other_credential_type = match credential_type {
+                MlsCredentialType::Basic => MlsCredentialType::X509,
+                MlsCredentialType::X509 => MlsCredentialType::Basic,
+            };
+            let hour = std::time::Duration::from_secs(60 * 60);
+            transaction
+                .set_key_package_lifetime(ciphersuite, other_credential_type, hour)
+                .await
+                .unwrap();
+            transaction
+                .set_key_package_lifetime(ciphersuite, credential_type, 2 * hour)
+                .await
+                .unwrap();
+            assert!(
+                transaction
+                    .set_key_package_lifetime(ciphersuite, credential_type, 2 * KEYPACKAGE_DEFAULT_LIFETIME)
+                    .await
+                    .is_err()
+            );
+
+            let kps = transaction
+                .get_or_create_client_keypackages(ciphersuite, credential_type, INITIAL_KEYING_MATERIAL_COUNT + 1)
+                .await
+                .unwrap();
+            let crypto_provider = transaction.mls_provider().await.unwrap();
+            let kp_ref = kps[0].hash_ref(crypto_provider.crypto()).unwrap();
+            let stored_kp = crypto_provider
+                .keystore()
+                .find::<MlsKeyPackage>(kp_ref.as_slice())
+                .await
+                .unwrap()
+                .unwrap();
+            let expires_in = stored_kp.expires_at().unwrap() - crate::mls::unix_timestamp();
+            assert!(expires_in > hour.as_secs() && expires_in <= 2 * hour.as_secs());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn keypackage_lifetime_persists_across_loads(case: TestContext) {
+        use core_crypto_keystore::entities::{EntityFindParams, MlsCredential};
+        use tls_codec::Deserialize as _;
+
+        let [session_context] = case.sessions().await;
+        Box::pin(async move {
+            let transaction = &session_context.transaction;
+            let (ciphersuite, credential_type) = (case.ciphersuite(), case.credential_type);
+            let hour = std::time::Duration::from_secs(60 * 60);
+            transaction
+                .set_key_package_lifetime(ciphersuite, credential_type, hour)
+                .await
+                .unwrap();
+
+            let session = session_context.session().await;
+            let client_id = session.id().await.unwrap();
+            let keystore = transaction.keystore().await.unwrap();
+            let credentials = keystore
+                .find_all::<MlsCredential>(EntityFindParams::default())
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|credential| {
+                    let created_at = credential.created_at;
+                    let credential =
+                        openmls::prelude::Credential::tls_deserialize(&mut credential.credential.as_slice()).unwrap();
+                    (credential, created_at)
+                })
+                .collect();
+            session.reset().await;
+            let backend = transaction.mls_provider().await.unwrap();
+            session
+                .load(
+                    &backend,
+                    &client_id,
+                    credentials,
+                    std::collections::HashSet::from([case.signature_scheme()]),
+                )
+                .await
+                .unwrap();
+
+            let kp = session
+                .generate_one_keypackage(&backend, ciphersuite, credential_type)
+                .await
+                .unwrap();
+            let kp_ref = kp.hash_ref(backend.crypto()).unwrap();
+            let stored_kp = keystore
+                .find::<MlsKeyPackage>(kp_ref.as_slice())
+                .await
+                .unwrap()
+                .unwrap();
+            let expires_in = stored_kp.expires_at().unwrap() - crate::mls::unix_timestamp();
+            assert!(expires_in <= hour.as_secs());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn x509_keypackages_do_not_outlive_their_certificate(case: TestContext) {
+        if !case.is_x509() {
+            return;
+        }
+        let [session_context] = case.sessions().await;
+        Box::pin(async move {
+            let transaction = &session_context.transaction;
+            let (ciphersuite, credential_type) = (case.ciphersuite(), case.credential_type);
+            let expiration_time = std::time::Duration::from_secs(10);
+            let start = web_time::Instant::now();
+
+            let intermediate_ca = session_context.x509_chain_unchecked().find_local_intermediate_ca();
+            let cert = CertificateBundle::new_with_default_values(intermediate_ca, Some(expiration_time));
+            let crypto_provider = transaction.mls_provider().await.unwrap();
+            transaction
+                .session()
+                .await
+                .unwrap()
+                .save_new_x509_credential_bundle(&crypto_provider.keystore(), case.signature_scheme(), cert)
+                .await
+                .unwrap();
+
+            // these are generated from the new certificate
+            let kps = transaction
+                .get_or_create_client_keypackages(ciphersuite, credential_type, INITIAL_KEYING_MATERIAL_COUNT + 2)
+                .await
+                .unwrap();
+            for kp in &kps[..2] {
+                assert!(!Session::is_mls_keypackage_expired(kp));
+                let kp_ref = kp.hash_ref(crypto_provider.crypto()).unwrap();
+                let stored_kp = crypto_provider
+                    .keystore()
+                    .find::<MlsKeyPackage>(kp_ref.as_slice())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let expires_in = stored_kp.expires_at().unwrap() - crate::mls::unix_timestamp();
+                assert!(expires_in <= expiration_time.as_secs());
+            }
+            let count = transaction
+                .client_valid_key_packages_count(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_eq!(count, INITIAL_KEYING_MATERIAL_COUNT + 2);
+
+            let elapsed = start.elapsed();
+            if expiration_time > elapsed {
+                smol::Timer::after(expiration_time - elapsed + std::time::Duration::from_secs(1)).await;
+            }
+
+            let count = transaction
+                .client_valid_key_packages_count(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_eq!(count, INITIAL_KEYING_MATERIAL_COUNT);
+            // no keypackage can be generated from the expired certificate
+            assert!(
+                transaction
+                    .get_or_create_client_keypackages(ciphersuite, credential_type, INITIAL_KEYING_MATERIAL_COUNT + 1)
+                    .await
+                    .is_err()
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn last_resort_keypackage_survives_welcomes(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let (ciphersuite, credential_type) = (case.ciphersuite(), case.credential_type);
+            let crypto_provider = bob.transaction.mls_provider().await.unwrap();
+            let kp_ref = |kp: &KeyPackage| kp.hash_ref(crypto_provider.crypto()).unwrap();
+
+            let last_resort = bob
+                .transaction
+                .last_resort_key_package(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            let last_resort_ref = kp_ref(&last_resort);
+            let again = bob
+                .transaction
+                .last_resort_key_package(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_eq!(kp_ref(&again), last_resort_ref);
+            // it is not handed out with the others
+            let count = bob
+                .transaction
+                .client_valid_key_packages_count(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_eq!(count, INITIAL_KEYING_MATERIAL_COUNT);
+
+            let mut joined = Vec::new();
+            for _ in 0..2 {
+                let conversation = case.create_conversation([&alice]).await;
+                conversation
+                    .guard()
+                    .await
+                    .add_members(vec![KeyPackageIn::from(last_resort.clone())])
+                    .await
+                    .unwrap();
+                let welcome = alice.mls_transport().await.latest_welcome_message().await;
+                bob.transaction
+                    .process_welcome_message(welcome.into(), case.custom_cfg())
+                    .await
+                    .unwrap();
+                // pruning the consumed key package keeps it
+                bob.transaction
+                    .delete_keypackages([last_resort_ref.clone()])
+                    .await
+                    .unwrap();
+                joined.push(conversation.id().clone());
+            }
+
+            let rotated = bob
+                .transaction
+                .rotate_last_resort_key_package(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_ne!(kp_ref(&rotated), last_resort_ref);
+            let current = bob
+                .transaction
+                .last_resort_key_package(ciphersuite, credential_type)
+                .await
+                .unwrap();
+            assert_eq!(kp_ref(&current), kp_ref(&rotated));
+
+            // conversations joined through the previous one keep its leaf key, so bob can process commits
+            // with an update path
+            for id in joined {
+                let conversation = TestConversation::new_from_existing(&case, id, [&alice, &bob]).await;
+                let conversation = conversation.update_notify().await;
+                assert!(conversation.is_functional_and_contains([&alice, &bob]).await);
+            }
+
+            // the previous one is gone
+            let conversation = case.create_conversation([&alice]).await;
+            conversation
+                .guard()
+                .await
+                .add_members(vec![KeyPackageIn::from(last_resort)])
+                .await
+                .unwrap();
+            let welcome = alice.mls_transport().await.latest_welcome_message().await;
+            let result = bob
+                .transaction
+                .process_welcome_message(welcome.into(), case.custom_cfg())
+                .await;
+            assert!(result.is_err());
+        })
+        .await
+    }
+
     #[apply(all_cred_cipher)]
     async fn automatically_prunes_lifetime_expired_keypackages(case: TestContext) {
         let [session] = case.sessions().await;
@@ -570,7 +1354,12 @@ mod tests {
 
         // Set the keypackage expiration to be in 2 seconds
         session
-            .set_keypackage_lifetime(std::time::Duration::from_secs(10))
+            .set_keypackage_lifetime(
+                &backend,
+                case.ciphersuite(),
+                case.credential_type,
+                std::time::Duration::from_secs(10),
+            )
             .await
             .unwrap();
 
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::prelude::{ClientId, ConversationId, WireIdentity};
+
+use super::{Error, Result, Session};
+
+/// A member whose credential was replaced by a merged commit, e.g. after an E2EI certificate rotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberCredentialUpdate {
+    /// The member whose credential was replaced
+    pub client_id: ClientId,
+    /// The identity of the member, as claimed by its new credential
+    pub identity: WireIdentity,
+}
+
+/// The changes of membership brought by a merged commit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MembershipChanges {
+    /// Clients added to the conversation
+    pub added: Vec<ClientId>,
+    /// Clients removed from the conversation
+    pub removed: Vec<ClientId>,
+    /// Members whose credential was replaced
+    pub credential_updates: Vec<MemberCredentialUpdate>,
+}
+
+impl MembershipChanges {
+    /// Whether the commit left the membership untouched
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.credential_updates.is_empty()
+    }
+}
+
+/// A `MembershipObserver` is notified whenever a merged commit changes the members of a conversation.
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+pub trait MembershipObserver: Send + Sync {
+    /// This function will be called every time a commit, whether ours or another member's, adds or
+    /// removes clients, or replaces the credential of a member.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this inteface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    async fn membership_changed(&self, conversation_id: ConversationId, changes: MembershipChanges);
+}
+
+impl Session {
+    /// Add a membership observer to this session.
+    /// (see [MembershipObserver]).
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a membership observer already exists, this will return an error.
+    pub async fn register_membership_observer(&self, membership_observer: Arc<dyn MembershipObserver>) -> Result<()> {
+        let mut observer_guard = self.membership_observer.write().await;
+        if observer_guard.is_some() {
+            return Err(Error::MembershipObserverAlreadyExists);
+        }
+        observer_guard.replace(membership_observer);
+        Ok(())
+    }
+
+    /// Whether a membership observer is present, so that changes are only computed when needed.
+    pub(crate) async fn has_membership_observer(&self) -> bool {
+        self.membership_observer.read().await.is_some()
+    }
+
+    /// Notify the observer that the membership of a conversation has changed, if one is present.
+    pub(crate) async fn notify_membership_changed(&self, conversation_id: ConversationId, changes: MembershipChanges) {
+        if changes.is_empty() {
+            return;
+        }
+        if let Some(observer) = self.membership_observer.read().await.as_ref() {
+            observer.membership_changed(conversation_id, changes).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[apply(all_cred_cipher)]
+    async fn observes_own_and_remote_membership_changes(case: TestContext) {
+        let [alice, bob, carol] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let id = conversation.id().clone();
+            let observers = [TestMembershipObserver::new(), TestMembershipObserver::new()];
+            for (session, observer) in [&alice, &bob].into_iter().zip(&observers) {
+                session
+                    .session()
+                    .await
+                    .register_membership_observer(observer.clone())
+                    .await
+                    .unwrap();
+            }
+
+            // an update does not change the membership
+            let conversation = conversation.update_notify().await;
+            let conversation = conversation.invite_notify([&carol]).await;
+            conversation.remove_notify(&carol).await;
+
+            let carol_id = carol.get_client_id().await;
+            for observer in &observers {
+                let observed = observer.observed_changes().await;
+                assert_eq!(observed.len(), 2, "the update should not have been observed");
+                assert_eq!(observed[0].0, id);
+                assert_eq!(observed[0].1.added, vec![carol_id.clone()]);
+                assert!(observed[0].1.removed.is_empty());
+                assert_eq!(observed[1].1.removed, vec![carol_id.clone()]);
+                assert!(observed[1].1.added.is_empty());
+            }
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn observes_credential_updates(case: TestContext) {
+        if !case.is_x509() {
+            return;
+        }
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let observer = TestMembershipObserver::new();
+            bob.session()
+                .await
+                .register_membership_observer(observer.clone())
+                .await
+                .unwrap();
+
+            let intermediate_ca = alice.x509_chain_unchecked().find_local_intermediate_ca();
+            let cb = alice
+                .save_new_credential(&case, "new_handle", "New Name", intermediate_ca)
+                .await;
+            conversation.e2ei_rotate_notify(Some(&cb)).await;
+
+            let observed = observer.observed_changes().await;
+            assert_eq!(observed.len(), 1);
+            let changes = &observed[0].1;
+            assert!(changes.added.is_empty() && changes.removed.is_empty());
+            assert_eq!(changes.credential_updates.len(), 1);
+            let update = &changes.credential_updates[0];
+            assert_eq!(update.client_id, alice.get_client_id().await);
+            assert_eq!(update.identity.x509_identity.as_ref().unwrap().display_name, "New Name");
+        })
+        .await
+    }
+}
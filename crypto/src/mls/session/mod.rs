@@ -1,5 +1,7 @@
+mod buffer_observer;
 pub(crate) mod config;
 pub(crate) mod e2e_identity;
+mod e2ei_status_observer;
 mod epoch_observer;
 mod error;
 mod history_observer;
@@ -7,6 +9,8 @@ pub(crate) mod id;
 pub(crate) mod identifier;
 pub(crate) mod identities;
 pub(crate) mod key_package;
+mod membership_observer;
+mod reinit_observer;
 pub(crate) mod user_id;
 
 use crate::{
@@ -18,26 +22,31 @@ use crate::{
         credential::{CredentialBundle, ext::CredentialExt},
     },
     prelude::{
-        CertificateBundle, ClientId, ConversationId, HistorySecret, MlsCiphersuite, MlsCredentialType,
-        config::ValidatedSessionConfig, identifier::ClientIdentifier, key_package::KEYPACKAGE_DEFAULT_LIFETIME,
+        CertificateBundle, ClientId, ConversationId, HistorySecret, MlsCiphersuite, MlsConversationConfiguration,
+        MlsCredentialType, MlsCustomCapabilities, config::ValidatedSessionConfig, identifier::ClientIdentifier,
     },
 };
 use async_lock::RwLock;
+pub use buffer_observer::BufferObserver;
 use core_crypto_keystore::{
     CryptoKeystoreError, Database,
     connection::FetchFromDatabase,
     entities::{EntityFindParams, MlsCredential, MlsSignatureKeyPair},
 };
+pub use e2ei_status_observer::E2eiStatusObserver;
+pub(crate) use e2ei_status_observer::{E2eiStatusChange, E2eiStatusSnapshot};
 pub use epoch_observer::EpochObserver;
 pub(crate) use error::{Error, Result};
 pub use history_observer::HistoryObserver;
 use identities::Identities;
 use log::debug;
+pub use membership_observer::{MemberCredentialUpdate, MembershipChanges, MembershipObserver};
 use mls_crypto_provider::{CryptoKeystore, EntropySeed, MlsCryptoProvider};
-use openmls::prelude::{Credential, CredentialType};
+use openmls::prelude::{Capabilities, Credential, CredentialType};
 use openmls_basic_credential::SignatureKeyPair;
 use openmls_traits::{OpenMlsCryptoProvider, crypto::OpenMlsCrypto, types::SignatureScheme};
 use openmls_x509_credential::CertificateKeyPair;
+pub use reinit_observer::ReInitObserver;
 use std::collections::HashSet;
 use std::ops::Deref;
 use std::sync::Arc;
@@ -62,6 +71,18 @@ pub struct Session {
     pub(crate) epoch_observer: Arc<RwLock<Option<Arc<dyn EpochObserver + 'static>>>>,
     #[debug("HistoryObserver")]
     pub(crate) history_observer: Arc<RwLock<Option<Arc<dyn HistoryObserver + 'static>>>>,
+    #[debug("ReInitObserver")]
+    pub(crate) reinit_observer: Arc<RwLock<Option<Arc<dyn ReInitObserver + 'static>>>>,
+    #[debug("BufferObserver")]
+    pub(crate) buffer_observer: Arc<RwLock<Option<Arc<dyn BufferObserver + 'static>>>>,
+    #[debug("MembershipObserver")]
+    pub(crate) membership_observer: Arc<RwLock<Option<Arc<dyn MembershipObserver + 'static>>>>,
+    #[debug("E2eiStatusObserver")]
+    pub(crate) e2ei_status_observer: Arc<RwLock<Option<Arc<dyn E2eiStatusObserver + 'static>>>>,
+    /// End-to-end identity status changes to notify once the current transaction is committed, see
+    /// [E2eiStatusObserver]
+    pub(crate) pending_e2ei_status_changes: Arc<RwLock<Vec<E2eiStatusChange>>>,
+    pub(crate) custom_capabilities: Arc<MlsCustomCapabilities>,
 }
 
 #[cfg_attr(target_family = "wasm", async_trait::async_trait(?Send))]
@@ -80,7 +101,6 @@ impl HasSessionAndCrypto for Session {
 pub(crate) struct SessionInner {
     id: ClientId,
     pub(crate) identities: Identities,
-    keypackage_lifetime: std::time::Duration,
 }
 
 impl Session {
@@ -105,6 +125,7 @@ impl Session {
             external_entropy,
             ciphersuites,
             nb_key_packages,
+            custom_capabilities,
         }: ValidatedSessionConfig<'_>,
     ) -> crate::mls::Result<Self> {
         // Init backend (crypto + rand + keystore)
@@ -125,6 +146,12 @@ impl Session {
             transport: Arc::new(None.into()),
             epoch_observer: Arc::new(None.into()),
             history_observer: Arc::new(None.into()),
+            reinit_observer: Arc::new(None.into()),
+            buffer_observer: Arc::new(None.into()),
+            membership_observer: Arc::new(None.into()),
+            e2ei_status_observer: Arc::new(None.into()),
+            pending_e2ei_status_changes: Default::default(),
+            custom_capabilities: Arc::new(custom_capabilities),
         };
 
         let cc = CoreCrypto::from(session);
@@ -157,6 +184,12 @@ impl Session {
         Ok(cc.mls)
     }
 
+    /// Capabilities advertised by the key packages and leaf nodes of this session, including the
+    /// application-defined types registered in [crate::prelude::SessionConfig::custom_capabilities]
+    pub(crate) fn leaf_capabilities(&self) -> Capabilities {
+        MlsConversationConfiguration::leaf_capabilities(&self.custom_capabilities)
+    }
+
     /// Provide the implementation of functions to communicate with the delivery service
     /// (see [MlsTransport]).
     pub async fn provide_transport(&self, transport: Arc<dyn MlsTransport>) {
@@ -375,7 +408,6 @@ impl Session {
         self.replace_inner(SessionInner {
             id: id.into_owned(),
             identities: Identities::new(signature_schemes.len()),
-            keypackage_lifetime: KEYPACKAGE_DEFAULT_LIFETIME,
         })
         .await;
 
@@ -477,10 +509,11 @@ impl Session {
                 identities.push_credential_bundle(signature_scheme, cb).await?;
             }
         }
+        // key packages stored by earlier versions lack the metadata they are queried by
+        Self::backfill_keypackage_metadata(&backend.keystore()).await?;
         self.replace_inner(SessionInner {
             id: id.clone(),
             identities,
-            keypackage_lifetime: KEYPACKAGE_DEFAULT_LIFETIME,
         })
         .await;
         Ok(())
@@ -494,7 +527,6 @@ impl Session {
         self.replace_inner(SessionInner {
             id: history_secret.client_id.clone(),
             identities: Identities::new(0),
-            keypackage_lifetime: KEYPACKAGE_DEFAULT_LIFETIME,
         })
         .await;
 
@@ -700,9 +732,12 @@ mod tests {
         /// Count the entities
         pub async fn count_entities(&self) -> EntitiesCount {
             let keystore = self.crypto_provider.keystore();
+            let conversation_reinit = keystore.count::<MlsConversationReInit>().await.unwrap();
             let credential = keystore.count::<MlsCredential>().await.unwrap();
             let encryption_keypair = keystore.count::<MlsEncryptionKeyPair>().await.unwrap();
             let epoch_encryption_keypair = keystore.count::<MlsEpochEncryptionKeyPair>().await.unwrap();
+            let exporter_secret = keystore.count::<MlsExporterSecret>().await.unwrap();
+            let external_psk = keystore.count::<MlsExternalPsk>().await.unwrap();
             let enrollment = keystore.count::<E2eiEnrollment>().await.unwrap();
             let group = keystore.count::<PersistedMlsGroup>().await.unwrap();
             let hpke_private_key = keystore.count::<MlsHpkePrivateKey>().await.unwrap();
@@ -712,9 +747,12 @@ mod tests {
             let psk_bundle = keystore.count::<MlsPskBundle>().await.unwrap();
             let signature_keypair = keystore.count::<MlsSignatureKeyPair>().await.unwrap();
             EntitiesCount {
+                conversation_reinit,
                 credential,
                 encryption_keypair,
                 epoch_encryption_keypair,
+                exporter_secret,
+                external_psk,
                 enrollment,
                 group,
                 hpke_private_key,
@@ -751,4 +789,33 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[apply(all_cred_cipher)]
+    async fn keystore_in_use_has_no_dangling_references(case: TestContext) {
+        let [alice, bob, charlie] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let conversation = conversation.update_notify().await.enable_history_sharing_notify().await;
+            alice
+                .transaction
+                .get_or_create_client_keypackages(case.ciphersuite(), case.credential_type, 3)
+                .await
+                .unwrap();
+            // charlie is left with a pending conversation
+            let (_commit, _pending_conversation) = conversation.external_join_unmerged(&charlie).await;
+
+            for session in [&alice, &bob, &charlie] {
+                session.transaction.finish().await.unwrap();
+                let dangling = session
+                    .session
+                    .crypto_provider
+                    .keystore()
+                    .check_integrity(false)
+                    .await
+                    .unwrap();
+                assert!(dangling.is_empty(), "{dangling:?}");
+            }
+        })
+        .await
+    }
 }
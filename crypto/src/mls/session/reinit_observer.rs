@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::prelude::ConversationId;
+
+use super::{Error, Result, Session};
+
+/// A `ReInitObserver` is notified when the migration of a conversation to a new one, e.g. with
+/// another ciphersuite, has finished (see [crate::mls::conversation::ConversationGuard::reinit]).
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+pub trait ReInitObserver: Send + Sync {
+    /// This function will be called once we are a member of the conversation replacing a
+    /// reinitialized one: when we created it, or when we joined it from its welcome message.
+    ///
+    /// <div class="warning">
+    /// This function must not block! Foreign implementors of this inteface can
+    /// spawn a task indirecting the notification, or (unblocking) send the notification
+    /// on some kind of channel, or anything else, as long as the operation completes
+    /// quickly.
+    /// </div>
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: ConversationId,
+        new_conversation_id: ConversationId,
+    );
+}
+
+impl Session {
+    /// Add a reinit observer to this session.
+    /// (see [ReInitObserver]).
+    ///
+    /// This function should be called 0 or 1 times in a session's lifetime. If called
+    /// when a reinit observer already exists, this will return an error.
+    pub async fn register_reinit_observer(&self, reinit_observer: Arc<dyn ReInitObserver>) -> Result<()> {
+        let mut observer_guard = self.reinit_observer.write().await;
+        if observer_guard.is_some() {
+            return Err(Error::ReInitObserverAlreadyExists);
+        }
+        observer_guard.replace(reinit_observer);
+        Ok(())
+    }
+
+    /// Notify the observer that a conversation has been reinitialized, if one is present.
+    pub(crate) async fn notify_conversation_reinitialized(
+        &self,
+        old_conversation_id: ConversationId,
+        new_conversation_id: ConversationId,
+    ) {
+        if let Some(observer) = self.reinit_observer.read().await.as_ref() {
+            observer
+                .conversation_reinitialized(old_conversation_id, new_conversation_id)
+                .await;
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::prelude::{BufferObserver, ConversationId, MlsBufferedItem, MlsEvictionReason};
+
+pub(crate) struct TestBufferObserver(Mutex<BufferObserverInner>);
+
+#[derive(Default)]
+struct BufferObserverInner {
+    observed_evictions: Vec<(ConversationId, MlsBufferedItem, MlsEvictionReason)>,
+}
+
+impl TestBufferObserver {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Default::default()))
+    }
+
+    pub(crate) async fn observed_evictions(&self) -> Vec<(ConversationId, MlsBufferedItem, MlsEvictionReason)> {
+        self.0.lock().await.observed_evictions.clone()
+    }
+}
+
+#[cfg_attr(target_family="wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+impl BufferObserver for TestBufferObserver {
+    async fn buffered_item_evicted(
+        &self,
+        conversation_id: ConversationId,
+        item: MlsBufferedItem,
+        reason: MlsEvictionReason,
+    ) {
+        let mut guard = self.0.lock().await;
+        guard.observed_evictions.push((conversation_id, item, reason));
+    }
+}
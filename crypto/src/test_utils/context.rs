@@ -10,8 +10,7 @@ use crate::{
     },
     mls::credential::{CredentialBundle, ext::CredentialExt},
     prelude::{
-        CertificateBundle, MlsCiphersuite, MlsConversationConfiguration, MlsConversationDecryptMessage,
-        MlsCredentialType, Session, WireIdentity,
+        CertificateBundle, MlsCiphersuite, MlsConversationDecryptMessage, MlsCredentialType, Session, WireIdentity,
     },
     test_utils::{TestContext, x509::X509Certificate},
 };
@@ -55,7 +54,7 @@ impl SessionContext {
             .unwrap();
         KeyPackage::builder()
             .key_package_lifetime(lifetime)
-            .leaf_node_capabilities(MlsConversationConfiguration::default_leaf_capabilities())
+            .leaf_node_capabilities(self.session.leaf_capabilities())
             .build(
                 CryptoConfig {
                     ciphersuite: case.ciphersuite().into(),
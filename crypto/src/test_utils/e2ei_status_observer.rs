@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::prelude::{ClientId, ConversationId, DeviceStatus, E2eiConversationState, E2eiStatusObserver};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum E2eiStatusEvent {
+    Device(ConversationId, ClientId, DeviceStatus),
+    Conversation(ConversationId, E2eiConversationState),
+}
+
+pub(crate) struct TestE2eiStatusObserver(Mutex<E2eiStatusObserverInner>);
+
+#[derive(Default)]
+struct E2eiStatusObserverInner {
+    observed_events: Vec<E2eiStatusEvent>,
+}
+
+impl TestE2eiStatusObserver {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Default::default()))
+    }
+
+    pub(crate) async fn observed_events(&self) -> Vec<E2eiStatusEvent> {
+        self.0.lock().await.observed_events.clone()
+    }
+}
+
+#[cfg_attr(target_family="wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+impl E2eiStatusObserver for TestE2eiStatusObserver {
+    async fn device_status_changed(&self, conversation_id: ConversationId, client_id: ClientId, status: DeviceStatus) {
+        let mut guard = self.0.lock().await;
+        guard
+            .observed_events
+            .push(E2eiStatusEvent::Device(conversation_id, client_id, status));
+    }
+
+    async fn conversation_state_changed(&self, conversation_id: ConversationId, state: E2eiConversationState) {
+        let mut guard = self.0.lock().await;
+        guard
+            .observed_events
+            .push(E2eiStatusEvent::Conversation(conversation_id, state));
+    }
+}
@@ -3,14 +3,23 @@ use async_trait::async_trait;
 use obfuscate::Obfuscated;
 use std::sync::Arc;
 
-use crate::prelude::{ConversationId, HistoryObserver, HistorySecret};
+use crate::prelude::{ClientId, ConversationId, HistoryObserver, HistorySecret};
 
 #[derive(Debug)]
 pub(crate) struct TestHistoryObserver(Mutex<HistoryObserverInner>);
 
+/// History client lifecycle events observed after creation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HistoryEvent {
+    Removed(ConversationId, ClientId),
+    Replaced(ConversationId, ClientId, ClientId),
+    Disabled(ConversationId),
+}
+
 #[derive(Default)]
 struct HistoryObserverInner {
     observed_history_clients: Vec<(ConversationId, HistorySecret)>,
+    observed_events: Vec<HistoryEvent>,
 }
 
 impl std::fmt::Debug for HistoryObserverInner {
@@ -25,6 +34,7 @@ impl std::fmt::Debug for HistoryObserverInner {
                     .map(|(id, secret)| (Obfuscated::from(id), Obfuscated::from(secret)))
                     .collect::<Vec<_>>(),
             )
+            .field("observed_events", &self.observed_events)
             .finish()
     }
 }
@@ -38,12 +48,17 @@ impl TestHistoryObserver {
     pub(crate) async fn reset(&self) {
         let mut guard = self.0.lock().await;
         guard.observed_history_clients.clear();
+        guard.observed_events.clear();
     }
 
     #[allow(dead_code)]
     pub(crate) async fn has_changed(&self) -> bool {
         let guard = self.0.lock().await;
-        !guard.observed_history_clients.is_empty()
+        !guard.observed_history_clients.is_empty() || !guard.observed_events.is_empty()
+    }
+
+    pub(crate) async fn observed_events(&self) -> Vec<HistoryEvent> {
+        self.0.lock().await.observed_events.clone()
     }
 
     pub(crate) async fn observed_history_clients(&self) -> Vec<(ConversationId, HistorySecret)> {
@@ -71,4 +86,28 @@ impl HistoryObserver for TestHistoryObserver {
 
         guard.observed_history_clients.push((conversation_id, history_secret))
     }
+
+    async fn history_client_removed(&self, conversation_id: ConversationId, client_id: ClientId) {
+        let mut guard = self.0.lock().await;
+        guard
+            .observed_events
+            .push(HistoryEvent::Removed(conversation_id, client_id));
+    }
+
+    async fn history_client_replaced(
+        &self,
+        conversation_id: ConversationId,
+        previous: ClientId,
+        replacement: ClientId,
+    ) {
+        let mut guard = self.0.lock().await;
+        guard
+            .observed_events
+            .push(HistoryEvent::Replaced(conversation_id, previous, replacement));
+    }
+
+    async fn history_sharing_disabled(&self, conversation_id: ConversationId) {
+        let mut guard = self.0.lock().await;
+        guard.observed_events.push(HistoryEvent::Disabled(conversation_id));
+    }
 }
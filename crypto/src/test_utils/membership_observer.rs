@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::prelude::{ConversationId, MembershipChanges, MembershipObserver};
+
+pub(crate) struct TestMembershipObserver(Mutex<MembershipObserverInner>);
+
+#[derive(Default)]
+struct MembershipObserverInner {
+    observed_changes: Vec<(ConversationId, MembershipChanges)>,
+}
+
+impl TestMembershipObserver {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Default::default()))
+    }
+
+    pub(crate) async fn observed_changes(&self) -> Vec<(ConversationId, MembershipChanges)> {
+        self.0.lock().await.observed_changes.clone()
+    }
+}
+
+#[cfg_attr(target_family="wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+impl MembershipObserver for TestMembershipObserver {
+    async fn membership_changed(&self, conversation_id: ConversationId, changes: MembershipChanges) {
+        let mut guard = self.0.lock().await;
+        guard.observed_changes.push((conversation_id, changes));
+    }
+}
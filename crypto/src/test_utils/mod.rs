@@ -2,11 +2,15 @@
 // and historically have not been.
 #![allow(missing_docs)]
 
+mod buffer_observer;
 pub mod context;
+mod e2ei_status_observer;
 mod epoch_observer;
 mod error;
 mod history_observer;
+mod membership_observer;
 pub mod message;
+mod reinit_observer;
 pub mod test_context;
 mod test_conversation;
 pub mod x509;
@@ -14,9 +18,13 @@ pub mod x509;
 #[cfg(feature = "proteus")]
 pub mod proteus_utils;
 
+pub(crate) use self::buffer_observer::TestBufferObserver;
+pub(crate) use self::e2ei_status_observer::{E2eiStatusEvent, TestE2eiStatusObserver};
 pub(crate) use self::epoch_observer::TestEpochObserver;
 use self::error::Result;
-pub(crate) use self::history_observer::TestHistoryObserver;
+pub(crate) use self::history_observer::{HistoryEvent, TestHistoryObserver};
+pub(crate) use self::membership_observer::TestMembershipObserver;
+pub(crate) use self::reinit_observer::TestReInitObserver;
 pub use self::{error::Error as TestError, message::*, test_context::*, test_conversation::TestConversation};
 pub use crate::prelude::{ClientIdentifier, INITIAL_KEYING_MATERIAL_COUNT, MlsCredentialType};
 use crate::{
@@ -132,6 +140,7 @@ impl SessionContext {
             .db_connection_type(core_crypto_keystore::ConnectionType::Persistent(&db_path))
             .database_key(DatabaseKey::generate())
             .ciphersuites([context.cfg.ciphersuite])
+            .custom_capabilities(context.custom_capabilities.clone())
             .build()
             .validate()
             .unwrap();
@@ -197,6 +206,7 @@ impl SessionContext {
             .db_connection_type(core_crypto_keystore::ConnectionType::Persistent(&db_path))
             .database_key(DatabaseKey::generate())
             .ciphersuites([context.cfg.ciphersuite])
+            .custom_capabilities(context.custom_capabilities.clone())
             .build()
             .validate()
             .unwrap();
@@ -283,7 +293,7 @@ impl SessionContext {
         self.mls_transport.read().await.clone()
     }
 
-    async fn setup_history_observer(&self) {
+    pub(crate) async fn setup_history_observer(&self) {
         let new_observer = TestHistoryObserver::new();
         let new_observer_dyn = new_observer.clone() as Arc<dyn HistoryObserver>;
 
@@ -386,11 +396,15 @@ pub trait MlsTransportTestExt: MlsTransport {
     }
 
     async fn latest_message(&self) -> Vec<u8>;
+
+    /// All commit bundles accepted so far, oldest first
+    async fn commit_bundles(&self) -> Vec<MlsCommitBundle>;
 }
 
 #[derive(Debug, Default)]
 pub struct CoreCryptoTransportSuccessProvider {
     latest_commit_bundle: RwLock<Option<MlsCommitBundle>>,
+    commit_bundles: RwLock<Vec<MlsCommitBundle>>,
     latest_message: RwLock<Option<Vec<u8>>>,
 }
 
@@ -398,6 +412,7 @@ pub struct CoreCryptoTransportSuccessProvider {
 #[cfg_attr(not(target_family = "wasm"), async_trait::async_trait)]
 impl MlsTransport for CoreCryptoTransportSuccessProvider {
     async fn send_commit_bundle(&self, commit_bundle: MlsCommitBundle) -> crate::Result<MlsTransportResponse> {
+        self.commit_bundles.write().await.push(commit_bundle.clone());
         self.latest_commit_bundle.write().await.replace(commit_bundle);
         Ok(MlsTransportResponse::Success)
     }
@@ -426,6 +441,10 @@ impl MlsTransportTestExt for CoreCryptoTransportSuccessProvider {
     async fn latest_message(&self) -> Vec<u8> {
         self.latest_message.read().await.clone().expect("latest_message")
     }
+
+    async fn commit_bundles(&self) -> Vec<MlsCommitBundle> {
+        self.commit_bundles.read().await.clone()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -461,12 +480,17 @@ impl MlsTransportTestExt for CoreCryptoTransportAbortProvider {
     async fn latest_message(&self) -> Vec<u8> {
         unreachable!("abort provider never stores a message")
     }
+
+    async fn commit_bundles(&self) -> Vec<MlsCommitBundle> {
+        Vec::new()
+    }
 }
 
 /// This alternates between retry and success responses (starts with retry).
 #[derive(Debug, Default)]
 pub struct CoreCryptoTransportRetrySuccessProvider {
     latest_commit_bundle: RwLock<Option<MlsCommitBundle>>,
+    commit_bundles: RwLock<Vec<MlsCommitBundle>>,
     latest_message: RwLock<Option<Vec<u8>>>,
     just_returned_retry: RwLock<bool>,
     retry_count: RwLock<u32>,
@@ -515,6 +539,7 @@ impl MlsTransport for CoreCryptoTransportRetrySuccessProvider {
         if *just_returned_retry {
             *just_returned_retry = false;
             *self.success_count.write().await += 1;
+            self.commit_bundles.write().await.push(commit_bundle.clone());
             self.latest_commit_bundle.write().await.replace(commit_bundle);
             Ok(MlsTransportResponse::Success)
         } else {
@@ -577,4 +602,8 @@ impl MlsTransportTestExt for CoreCryptoTransportRetrySuccessProvider {
     async fn latest_message(&self) -> Vec<u8> {
         self.latest_message.read().await.clone().expect("latest_message")
     }
+
+    async fn commit_bundles(&self) -> Vec<MlsCommitBundle> {
+        self.commit_bundles.read().await.clone()
+    }
 }
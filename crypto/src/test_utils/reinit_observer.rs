@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::prelude::{ConversationId, ReInitObserver};
+
+pub(crate) struct TestReInitObserver(Mutex<ReInitObserverInner>);
+
+#[derive(Default)]
+struct ReInitObserverInner {
+    observed_reinits: Vec<(ConversationId, ConversationId)>,
+}
+
+impl TestReInitObserver {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Default::default()))
+    }
+
+    pub(crate) async fn observed_reinits(&self) -> Vec<(ConversationId, ConversationId)> {
+        self.0.lock().await.observed_reinits.clone()
+    }
+}
+
+#[cfg_attr(target_family="wasm", async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+impl ReInitObserver for TestReInitObserver {
+    async fn conversation_reinitialized(
+        &self,
+        old_conversation_id: ConversationId,
+        new_conversation_id: ConversationId,
+    ) {
+        let mut guard = self.0.lock().await;
+        guard.observed_reinits.push((old_conversation_id, new_conversation_id));
+    }
+}
@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 pub use crate::prelude::{
-    MlsCiphersuite, MlsConversationConfiguration, MlsCredentialType, MlsCustomConfiguration, MlsWirePolicy,
+    MlsCiphersuite, MlsConversationConfiguration, MlsCredentialType, MlsCustomCapabilities, MlsCustomConfiguration,
+    MlsWirePolicy,
 };
 use crate::{
     e2e_identity::id::{QualifiedE2eiClientId, WireQualifiedClientId},
@@ -79,6 +80,7 @@ pub fn all_cred_cipher(case: TestContext) {}
 pub struct TestContext {
     pub credential_type: MlsCredentialType,
     pub cfg: MlsConversationConfiguration,
+    pub custom_capabilities: MlsCustomCapabilities,
     pub transport: Arc<dyn MlsTransportTestExt>,
     #[cfg(not(target_family = "wasm"))]
     db_file: Option<(String, Arc<tempfile::TempDir>)>,
@@ -114,6 +116,7 @@ impl TestContext {
         Self {
             credential_type: MlsCredentialType::X509,
             cfg: MlsConversationConfiguration::default(),
+            custom_capabilities: MlsCustomCapabilities::default(),
             transport: Arc::<CoreCryptoTransportSuccessProvider>::default(),
             db_file: None,
         }
@@ -416,6 +419,7 @@ impl Default for TestContext {
         Self {
             credential_type: MlsCredentialType::Basic,
             cfg: MlsConversationConfiguration::default(),
+            custom_capabilities: MlsCustomCapabilities::default(),
             transport: Arc::<CoreCryptoTransportSuccessProvider>::default(),
             db_file: None,
         }
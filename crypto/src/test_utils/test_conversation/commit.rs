@@ -1,4 +1,4 @@
-use openmls::prelude::group_info::VerifiableGroupInfo;
+use openmls::prelude::{Extensions, group_info::VerifiableGroupInfo};
 
 use crate::mls::conversation::ConversationWithMls as _;
 use crate::mls::conversation::pending_conversation::PendingConversation;
@@ -135,6 +135,27 @@ impl<'a> TestConversation<'a> {
         OperationGuard::new(TestOperation::Update, commit, self, [committer_index])
     }
 
+    /// Replace the group context extensions and notify all members.
+    pub async fn update_group_context_extensions_notify(self, extensions: Extensions) -> TestConversation<'a> {
+        self.update_group_context_extensions(extensions)
+            .await
+            .notify_members()
+            .await
+    }
+
+    /// Replace the group context extensions.
+    pub async fn update_group_context_extensions(self, extensions: Extensions) -> OperationGuard<'a, Commit> {
+        self.guard()
+            .await
+            .update_group_context_extensions(extensions)
+            .await
+            .unwrap();
+        let commit = self.transport().await.latest_commit().await;
+        let committer_index = self.actor_index();
+        // Members are unchanged, so this is equivalent to an update
+        OperationGuard::new(TestOperation::Update, commit, self, [committer_index])
+    }
+
     /// Commit all proposals pending in the actor's conversation state and notify all members.
     pub async fn commit_pending_proposals_notify(self) -> TestConversation<'a> {
         self.commit_pending_proposals().await.notify_members().await
@@ -1,6 +1,7 @@
 //! This module contains the implementation of [TransactionContext::join_by_external_commit].
 
 use openmls::prelude::{MlsGroup, group_info::VerifiableGroupInfo};
+use openmls_traits::OpenMlsCryptoProvider as _;
 
 use super::{Error, Result};
 use crate::mls::conversation::pending_conversation::PendingConversation;
@@ -92,7 +93,7 @@ impl TransactionContext {
             None,
             group_info,
             &configuration
-                .as_openmls_default_configuration()
+                .as_openmls_configuration(client.leaf_capabilities())
                 .map_err(RecursiveError::mls_conversation(
                     "using configuration as openmls default configuration",
                 ))?,
@@ -116,6 +117,23 @@ impl TransactionContext {
         .await
         .map_err(RecursiveError::mls_credential("getting new crl distribution points"))?;
 
+        {
+            let own_leaf_index = group.own_leaf_index();
+            let members = group
+                .members()
+                .filter(|member| member.index != own_leaf_index)
+                .map(|member| member.credential)
+                .collect::<Vec<_>>();
+            let authentication_service = mls_provider.authentication_service();
+            authentication_service.refresh_time_of_interest().await;
+            let authentication_service = authentication_service.borrow().await;
+            custom_cfg
+                .admission_policy
+                .enforce(cs, members.iter(), [cb.credential()], authentication_service.as_ref())
+                .await
+                .map_err(RecursiveError::mls_conversation("enforcing admission policy"))?;
+        }
+
         let new_group_id = group.group_id().to_vec();
 
         let pending_conversation = PendingConversation::from_mls_group(group, custom_cfg, self.clone())
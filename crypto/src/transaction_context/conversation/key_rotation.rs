@@ -0,0 +1,117 @@
+//! Automatic key rotation, driven by [crate::prelude::MlsCustomConfiguration::key_rotation_span].
+
+use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::PersistedMlsGroup};
+
+use super::{Result, TransactionContext};
+use crate::{KeystoreError, RecursiveError, prelude::ConversationId, prelude::MlsConversation};
+
+impl TransactionContext {
+    /// Lists the conversations in which our own leaf node has not been updated for longer than their
+    /// configured [crate::prelude::MlsCustomConfiguration::key_rotation_span].
+    ///
+    /// Conversations without a key rotation span are never returned.
+    pub async fn conversations_due_for_key_rotation(&self) -> Result<Vec<ConversationId>> {
+        let now = crate::mls::unix_timestamp();
+        let persisted_groups = self
+            .keystore()
+            .await?
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .map_err(KeystoreError::wrap("finding all persisted mls groups"))?;
+
+        let mut due = Vec::new();
+        for persisted in &persisted_groups {
+            let conversation = MlsConversation::from_serialized_state(persisted)
+                .map_err(RecursiveError::mls_conversation("deserializing mls conversation"))?;
+            if conversation.group.is_active() && conversation.is_key_rotation_due(now) {
+                due.push(conversation.id.clone());
+            }
+        }
+        Ok(due)
+    }
+
+    /// Updates our key material in every conversation returned by
+    /// [Self::conversations_due_for_key_rotation]. Each commit is sent through the [crate::MlsTransport]
+    /// and merged once accepted.
+    ///
+    /// Returns the ids of the conversations in which key material was rotated.
+    pub async fn rotate_due_key_material(&self) -> Result<Vec<ConversationId>> {
+        let due = self.conversations_due_for_key_rotation().await?;
+        for id in &due {
+            self.conversation(id)
+                .await?
+                .update_key_material()
+                .await
+                .map_err(RecursiveError::mls_conversation("updating key material"))?;
+        }
+        Ok(due)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::mls::conversation::Conversation as _;
+    use crate::test_utils::*;
+
+    #[apply(all_cred_cipher)]
+    async fn should_not_rotate_without_key_rotation_span(case: TestContext) {
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let _conversation = case.create_conversation([&alice]).await;
+            let due = alice.transaction.conversations_due_for_key_rotation().await.unwrap();
+            assert!(due.is_empty());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_rotate_conversations_past_their_span(mut case: TestContext) {
+        case.cfg.custom.key_rotation_span = Some(Duration::ZERO);
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let id = conversation.id().clone();
+            let epoch = conversation.guard().await.epoch().await;
+
+            let due = alice.transaction.conversations_due_for_key_rotation().await.unwrap();
+            assert_eq!(due, vec![id.clone()]);
+
+            let rotated = alice.transaction.rotate_due_key_material().await.unwrap();
+            assert_eq!(rotated, vec![id]);
+            assert_eq!(conversation.guard().await.epoch().await, epoch + 1);
+            assert!(
+                alice
+                    .mls_transport()
+                    .await
+                    .latest_commit_bundle()
+                    .await
+                    .welcome
+                    .is_none()
+            );
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn should_persist_key_rotation_state(mut case: TestContext) {
+        const SPAN: Duration = Duration::from_secs(3600);
+        case.cfg.custom.key_rotation_span = Some(SPAN);
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let due = alice.transaction.conversations_due_for_key_rotation().await.unwrap();
+            assert!(due.is_empty());
+
+            let mut guard = conversation.guard().await;
+            guard.drop_and_restore().await;
+            let inner = guard.conversation().await;
+            let now = crate::mls::unix_timestamp();
+            assert_eq!(inner.configuration.custom.key_rotation_span, Some(SPAN));
+            assert!(!inner.is_key_rotation_due(now));
+            assert!(inner.is_key_rotation_due(now + SPAN.as_secs()));
+        })
+        .await
+    }
+}
@@ -3,6 +3,7 @@
 pub mod external_commit;
 mod external_proposal;
 pub mod external_sender;
+mod key_rotation;
 pub(crate) mod proposal;
 pub mod welcome;
 
@@ -11,7 +12,9 @@ use core_crypto_keystore::entities::PersistedMlsPendingGroup;
 
 use crate::mls::conversation::ConversationGuard;
 use crate::mls::conversation::pending_conversation::PendingConversation;
-use crate::prelude::{ConversationId, MlsConversation, MlsConversationConfiguration, MlsCredentialType};
+use crate::prelude::{
+    ConversationId, MlsBufferedItem, MlsConversation, MlsConversationConfiguration, MlsCredentialType, MlsReInit,
+};
 use crate::{KeystoreError, LeafError, RecursiveError};
 
 use super::TransactionContext;
@@ -97,4 +100,38 @@ impl TransactionContext {
             .map_err(RecursiveError::root("fetching conversation from mls groups by id"))
             .map_err(Into::into)
     }
+
+    /// Returns where the conversation with this id continues, when it has been reinitialized, e.g. to
+    /// migrate it to another ciphersuite (see [ConversationGuard::reinit]).
+    ///
+    /// This is kept after the reinitialized conversation is wiped.
+    pub async fn conversation_reinit(&self, id: &ConversationId) -> Result<Option<MlsReInit>> {
+        MlsReInit::find(&self.mls_provider().await?.keystore(), id)
+            .await
+            .map_err(RecursiveError::mls_conversation("finding conversation reinit"))
+            .map_err(Into::into)
+    }
+
+    /// The messages and the commit of future epochs buffered for the conversation with this id, oldest
+    /// first. They are processed once the conversation reaches their epoch, unless evicted before (see
+    /// [crate::prelude::MlsBufferConfiguration]).
+    pub async fn buffered_items(&self, id: &ConversationId) -> Result<Vec<MlsBufferedItem>> {
+        self.conversation(id)
+            .await?
+            .buffered_items()
+            .await
+            .map_err(RecursiveError::mls_conversation("listing buffered items"))
+            .map_err(Into::into)
+    }
+
+    /// Drops the item with this id from the buffer of the conversation with this id, see
+    /// [Self::buffered_items].
+    pub async fn drop_buffered_item(&self, id: &ConversationId, item_id: &[u8]) -> Result<()> {
+        self.conversation(id)
+            .await?
+            .drop_buffered_item(item_id)
+            .await
+            .map_err(RecursiveError::mls_conversation("dropping buffered item"))
+            .map_err(Into::into)
+    }
 }
@@ -6,7 +6,7 @@ use super::{Error, Result, TransactionContext};
 use crate::{
     RecursiveError,
     mls::credential::crl::{extract_crl_uris_from_group, get_new_crl_distribution_points},
-    prelude::{MlsConversation, MlsConversationConfiguration, MlsCustomConfiguration, WelcomeBundle},
+    prelude::{MlsConversation, MlsConversationConfiguration, MlsCustomConfiguration, MlsReInit, WelcomeBundle},
 };
 use openmls::prelude::{MlsMessageIn, MlsMessageInBody};
 use tls_codec::Deserialize as _;
@@ -65,6 +65,7 @@ impl TransactionContext {
             custom: custom_cfg,
             ..Default::default()
         };
+        let leaf_capabilities = self.session().await?.leaf_capabilities();
         let mls_provider = self
             .mls_provider()
             .await
@@ -73,10 +74,15 @@ impl TransactionContext {
             .mls_groups()
             .await
             .map_err(RecursiveError::transaction("getting mls groups"))?;
-        let conversation =
-            MlsConversation::from_welcome_message(welcome, configuration, &mls_provider, mls_groups.borrow_mut())
-                .await
-                .map_err(RecursiveError::mls_conversation("creating conversation from welcome"))?;
+        let conversation = MlsConversation::from_welcome_message(
+            welcome,
+            configuration,
+            leaf_capabilities,
+            &mls_provider,
+            mls_groups.borrow_mut(),
+        )
+        .await
+        .map_err(RecursiveError::mls_conversation("creating conversation from welcome"))?;
 
         // We wait for the group to be created then we iterate through all members
         let crl_new_distribution_points = get_new_crl_distribution_points(
@@ -89,6 +95,21 @@ impl TransactionContext {
 
         let id = conversation.id.clone();
         mls_groups.insert(id.clone(), conversation);
+        drop(mls_groups);
+
+        // joining the conversation replacing a reinitialized one completes the migration
+        let reinitialized = MlsReInit::find_old_id(&mls_provider.keystore(), &id)
+            .await
+            .map_err(RecursiveError::mls_conversation("finding reinitialized conversation"))?;
+        if let Some(old_id) = reinitialized {
+            MlsReInit::delete_link_psk(&mls_provider, &old_id)
+                .await
+                .map_err(RecursiveError::mls_conversation("deleting the reinit link"))?;
+            self.session()
+                .await?
+                .notify_conversation_reinitialized(old_id, id.clone())
+                .await;
+        }
 
         Ok(WelcomeBundle {
             id,
@@ -0,0 +1,283 @@
+//! Report of the X509 certificates about to expire, so that they can be renewed ahead of time.
+
+use std::time::Duration;
+
+use openmls::prelude::CredentialWithKey;
+use openmls_traits::{OpenMlsCryptoProvider as _, types::SignatureScheme};
+use wire_e2e_identity::prelude::x509::revocation::PkiEnvironment;
+
+use super::Result;
+use crate::{
+    RecursiveError,
+    mls::credential::ext::CredentialExt as _,
+    prelude::{
+        ClientId, ConversationId, MlsCiphersuite, MlsCredentialType, MlsKeyPackageExpiry, MlsKeyPackageInventoryEntry,
+        Session, WireIdentity,
+    },
+    transaction_context::TransactionContext,
+};
+
+/// The certificate of a conversation member which expires soon, or has already expired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringMemberCertificate {
+    /// Conversation the member belongs to
+    pub conversation_id: ConversationId,
+    /// The member
+    pub client_id: ClientId,
+    /// Expiry of the certificate, as a Unix timestamp
+    pub not_after: u64,
+    /// The identity of the member, as claimed by its credential
+    pub identity: WireIdentity,
+}
+
+/// One of our own X509 credentials, which expires soon or has already expired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringCredential {
+    /// Signature scheme of the credential
+    pub signature_scheme: SignatureScheme,
+    /// Expiry of the certificate, as a Unix timestamp
+    pub not_after: u64,
+}
+
+/// Everything which expires within a given window, see [TransactionContext::e2ei_certificate_expiry_report]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateExpiryReport {
+    /// Member certificates expiring, sorted by conversation then client
+    pub members: Vec<ExpiringMemberCertificate>,
+    /// Our most recent X509 credential of each signature scheme, when expiring
+    pub own_credentials: Vec<ExpiringCredential>,
+    /// Our key packages expiring, be it because of their lifetime or of their certificate
+    pub key_packages: Vec<MlsKeyPackageInventoryEntry>,
+}
+
+impl TransactionContext {
+    /// Scans every conversation and reports the X509 certificates of its members which expire within
+    /// `expiring_within`, or have already expired. Also reports our own credentials and key packages
+    /// which need renewal within this window, so that e.g. a rotate enrollment can be started ahead of
+    /// time.
+    pub async fn e2ei_certificate_expiry_report(&self, expiring_within: Duration) -> Result<CertificateExpiryReport> {
+        let session = self
+            .session()
+            .await
+            .map_err(RecursiveError::transaction("getting session"))?;
+        let backend = self
+            .mls_provider()
+            .await
+            .map_err(RecursiveError::transaction("getting mls provider"))?;
+        let until = crate::mls::unix_timestamp().saturating_add(expiring_within.as_secs());
+
+        let conversations = self.active_conversations().await?;
+        let authentication_service = backend.authentication_service();
+        authentication_service.refresh_time_of_interest().await;
+        let authentication_service = authentication_service.borrow().await;
+        let mut members = Vec::new();
+        for conversation in &conversations {
+            for (id, credential) in conversation.members_with_key() {
+                let member = Self::expiring_member_certificate(
+                    &conversation.id,
+                    conversation.ciphersuite(),
+                    id,
+                    &credential,
+                    authentication_service.as_ref(),
+                    until,
+                );
+                match member {
+                    Ok(Some(member)) => members.push(member),
+                    Ok(None) => {}
+                    // a single malformed credential must not prevent reporting the other members
+                    Err(e) => log::warn!(error:% = e; "Skipping a member whose certificate cannot be read"),
+                }
+            }
+        }
+        members.sort_by(|a, b| {
+            (&a.conversation_id, a.client_id.as_slice()).cmp(&(&b.conversation_id, b.client_id.as_slice()))
+        });
+
+        let mut own_credentials = Vec::new();
+        for (signature_scheme, cb) in session
+            .most_recent_credential_bundles(MlsCredentialType::X509)
+            .await
+            .map_err(RecursiveError::mls_client("finding x509 credentials"))?
+        {
+            let expiry = Session::certificate_expiry(cb.credential())
+                .map_err(RecursiveError::mls_client("getting certificate expiry"))?;
+            if let Some(not_after) = expiry.filter(|not_after| *not_after <= until) {
+                own_credentials.push(ExpiringCredential {
+                    signature_scheme,
+                    not_after,
+                });
+            }
+        }
+        own_credentials.sort_by_key(|credential| credential.signature_scheme as u16);
+
+        let key_packages = session
+            .keypackage_inventory(&backend, expiring_within)
+            .await
+            .map_err(RecursiveError::mls_client("counting key packages"))?
+            .into_iter()
+            .filter(|entry| entry.expiry != MlsKeyPackageExpiry::Valid)
+            .collect();
+
+        Ok(CertificateExpiryReport {
+            members,
+            own_credentials,
+            key_packages,
+        })
+    }
+
+    /// The certificate of a member, when it expires by `until`
+    fn expiring_member_certificate(
+        conversation_id: &ConversationId,
+        ciphersuite: MlsCiphersuite,
+        client_id: Vec<u8>,
+        credential: &CredentialWithKey,
+        env: Option<&PkiEnvironment>,
+        until: u64,
+    ) -> Result<Option<ExpiringMemberCertificate>> {
+        let Some(not_after) = Session::certificate_expiry(&credential.credential)
+            .map_err(RecursiveError::mls_client("getting certificate expiry"))?
+            .filter(|not_after| *not_after <= until)
+        else {
+            return Ok(None);
+        };
+        let identity = credential
+            .extract_identity(ciphersuite, env)
+            .map_err(RecursiveError::mls_credential("extracting identity"))?;
+        Ok(Some(ExpiringMemberCertificate {
+            conversation_id: conversation_id.clone(),
+            client_id: ClientId::from(client_id),
+            not_after,
+            identity,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use openmls::prelude::{Credential, CredentialWithKey, SignaturePublicKey};
+    use x509_cert::der::Encode as _;
+
+    use crate::{
+        e2e_identity::id::QualifiedE2eiClientId,
+        mls::conversation::ConversationWithMls as _,
+        prelude::MlsKeyPackageExpiry,
+        test_utils::{x509::CertificateParams, *},
+        transaction_context::TransactionContext,
+    };
+
+    #[apply(all_cred_cipher)]
+    async fn reports_certificates_expiring_within_window(case: TestContext) {
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            alice
+                .transaction
+                .get_or_create_client_keypackages(case.ciphersuite(), case.credential_type, 2)
+                .await
+                .unwrap();
+
+            // test certificates are valid for a day
+            let report = alice
+                .transaction
+                .e2ei_certificate_expiry_report(Duration::from_secs(60 * 60))
+                .await
+                .unwrap();
+            assert!(report.members.is_empty());
+            assert!(report.own_credentials.is_empty());
+            assert!(report.key_packages.is_empty());
+
+            let report = alice
+                .transaction
+                .e2ei_certificate_expiry_report(Duration::from_secs(2 * 24 * 60 * 60))
+                .await
+                .unwrap();
+            if !case.is_x509() {
+                assert!(report.members.is_empty());
+                assert!(report.own_credentials.is_empty());
+                assert!(report.key_packages.is_empty());
+                return;
+            }
+            let mut expected = [alice.get_client_id().await, bob.get_client_id().await];
+            expected.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+            let reported = report
+                .members
+                .iter()
+                .map(|member| {
+                    assert_eq!(&member.conversation_id, conversation.id());
+                    assert_eq!(
+                        member.identity.x509_identity.as_ref().unwrap().not_after,
+                        member.not_after
+                    );
+                    member.client_id.clone()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(reported, expected);
+            assert_eq!(report.own_credentials.len(), 1);
+            assert_eq!(report.own_credentials[0].signature_scheme, case.signature_scheme());
+            // key packages expire along with the certificate they were generated with
+            let [key_packages] = report.key_packages.as_slice() else {
+                panic!("expected a single inventory entry, got {:?}", report.key_packages);
+            };
+            assert_eq!(key_packages.ciphersuite, case.ciphersuite());
+            assert_eq!(key_packages.credential_type, MlsCredentialType::X509);
+            assert_eq!(key_packages.expiry, MlsKeyPackageExpiry::ExpiringSoon);
+            assert!(key_packages.count >= 2);
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn skips_members_whose_certificate_cannot_be_read(case: TestContext) {
+        if !case.is_x509() {
+            return;
+        }
+
+        let [alice, bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let until = crate::mls::unix_timestamp() + 2 * 24 * 60 * 60;
+
+            // a certificate without a handle, from which no identity can be extracted
+            let intermediate_ca = alice.x509_chain_unchecked().find_local_intermediate_ca();
+            let certificate = intermediate_ca.create_and_sign_end_identity(CertificateParams {
+                client_id: Some(
+                    QualifiedE2eiClientId::generate_with_domain("world.com")
+                        .try_into()
+                        .unwrap(),
+                ),
+                ..Default::default()
+            });
+            let malformed = CredentialWithKey {
+                credential: Credential::new_x509(vec![certificate.certificate.to_der().unwrap()]).unwrap(),
+                signature_key: SignaturePublicKey::from(vec![0; 32]),
+            };
+            let malformed_member = TransactionContext::expiring_member_certificate(
+                conversation.id(),
+                case.ciphersuite(),
+                b"malformed".to_vec(),
+                &malformed,
+                None,
+                until,
+            );
+            assert!(malformed_member.is_err());
+
+            // the other members of the conversation are still reported
+            let guard = conversation.guard().await;
+            let inner = guard.conversation().await;
+            for (id, credential) in inner.members_with_key() {
+                let member = TransactionContext::expiring_member_certificate(
+                    conversation.id(),
+                    case.ciphersuite(),
+                    id,
+                    &credential,
+                    None,
+                    until,
+                );
+                assert!(member.unwrap().is_some());
+            }
+        })
+        .await
+    }
+}
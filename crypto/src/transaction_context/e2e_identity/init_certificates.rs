@@ -210,17 +210,27 @@ impl TransactionContext {
             .await
             .map_err(RecursiveError::transaction("getting keystore"))?;
 
-        let dirty = ks
+        let existing_crl = ks
             .find::<E2eiCrl>(crl_dp.as_bytes())
             .await
             .ok()
             .flatten()
-            .map(|existing_crl| {
-                PkiEnvironment::decode_der_crl(existing_crl.content.clone())
-                    .map(|old_crl| old_crl.tbs_cert_list.revoked_certificates != crl.tbs_cert_list.revoked_certificates)
-            })
-            .transpose()?
-            .unwrap_or_default();
+            .map(|existing_crl| PkiEnvironment::decode_der_crl(existing_crl.content.clone()))
+            .transpose()?;
+        let dirty = existing_crl.as_ref().is_some_and(|old_crl| {
+            old_crl.tbs_cert_list.revoked_certificates != crl.tbs_cert_list.revoked_certificates
+        });
+
+        // statuses only change when the CRL revokes other certificates, in which case they are compared
+        // before and after the CRL is taken into account
+        let revokes_other_certificates =
+            dirty || (existing_crl.is_none() && crl.tbs_cert_list.revoked_certificates.is_some());
+        let observed_conversations = if revokes_other_certificates {
+            self.e2ei_observed_conversations().await?
+        } else {
+            Vec::new()
+        };
+        self.baseline_e2ei_statuses(&observed_conversations).await?;
 
         // Save DER repr in keystore
         let crl_data = E2eiCrl {
@@ -230,6 +240,7 @@ impl TransactionContext {
         ks.save(crl_data).await.map_err(KeystoreError::wrap("saving crl"))?;
 
         self.init_pki_env().await?;
+        self.reevaluate_e2ei_statuses(&observed_conversations, None).await?;
 
         Ok(CrlRegistration { expiration, dirty })
     }
@@ -3,9 +3,11 @@
 pub(crate) mod conversation_state;
 pub mod enabled;
 mod error;
+pub(crate) mod expiry_report;
 mod init_certificates;
 mod rotate;
 mod stash;
+mod status;
 
 use std::collections::{HashMap, HashSet};
 
@@ -0,0 +1,203 @@
+//! Re-evaluation of the end-to-end identity status of every conversation, see
+//! [crate::mls::E2eiStatusObserver].
+
+use core_crypto_keystore::{connection::FetchFromDatabase as _, entities::PersistedMlsGroup};
+
+use super::Result;
+use crate::{KeystoreError, RecursiveError, prelude::MlsConversation, transaction_context::TransactionContext};
+
+impl TransactionContext {
+    /// Re-evaluates the end-to-end identity status of every conversation as if it were `at` (in seconds
+    /// since the UNIX epoch), i.e. considering the certificates expiring before it as expired.
+    ///
+    /// Changes since the last evaluation are reported to the [crate::mls::E2eiStatusObserver]. The
+    /// statuses as of `at` are not recorded: the next evaluation is still compared with the current ones.
+    /// Does nothing when no observer is registered.
+    pub async fn e2ei_reevaluate_status(&self, at: u64) -> Result<()> {
+        let conversations = self.e2ei_observed_conversations().await?;
+        self.reevaluate_e2ei_statuses(&conversations, Some(at)).await
+    }
+
+    /// Records the end-to-end identity status of the conversations evaluated for the first time
+    pub(super) async fn baseline_e2ei_statuses(&self, conversations: &[MlsConversation]) -> Result<()> {
+        let session = self
+            .session()
+            .await
+            .map_err(RecursiveError::transaction("getting session"))?;
+        let backend = self
+            .mls_provider()
+            .await
+            .map_err(RecursiveError::transaction("getting mls provider"))?;
+        for conversation in conversations {
+            conversation.baseline_e2ei_status(&session, &backend).await;
+        }
+        Ok(())
+    }
+
+    /// Re-evaluates the end-to-end identity status of the conversations, reporting the changes
+    pub(super) async fn reevaluate_e2ei_statuses(
+        &self,
+        conversations: &[MlsConversation],
+        at: Option<u64>,
+    ) -> Result<()> {
+        let session = self
+            .session()
+            .await
+            .map_err(RecursiveError::transaction("getting session"))?;
+        let backend = self
+            .mls_provider()
+            .await
+            .map_err(RecursiveError::transaction("getting mls provider"))?;
+        for conversation in conversations {
+            // conversations never evaluated so far are compared with their current status
+            conversation.baseline_e2ei_status(&session, &backend).await;
+            conversation.reevaluate_e2ei_status(&session, &backend, at).await;
+        }
+        Ok(())
+    }
+
+    /// The active conversations, when an E2EI status observer is registered
+    pub(super) async fn e2ei_observed_conversations(&self) -> Result<Vec<MlsConversation>> {
+        let session = self
+            .session()
+            .await
+            .map_err(RecursiveError::transaction("getting session"))?;
+        if !session.has_e2ei_status_observer().await {
+            return Ok(Vec::new());
+        }
+        self.active_conversations().await
+    }
+
+    /// Every conversation persisted in the keystore, deserialized, except the ones we were removed from
+    pub(super) async fn active_conversations(&self) -> Result<Vec<MlsConversation>> {
+        let persisted_groups = self
+            .keystore()
+            .await
+            .map_err(RecursiveError::transaction("getting keystore"))?
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .map_err(KeystoreError::wrap("finding all persisted mls groups"))?;
+
+        let mut conversations = Vec::with_capacity(persisted_groups.len());
+        for persisted in &persisted_groups {
+            let conversation = MlsConversation::from_serialized_state(persisted)
+                .map_err(RecursiveError::mls_conversation("deserializing mls conversation"))?;
+            if conversation.group.is_active() {
+                conversations.push(conversation);
+            }
+        }
+        Ok(conversations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        prelude::{DeviceStatus, E2eiConversationState},
+        test_utils::*,
+    };
+
+    #[apply(all_cred_cipher)]
+    async fn reports_devices_expiring_at_a_given_time(case: TestContext) {
+        if !case.is_x509() {
+            return;
+        }
+        let [alice, mut bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let id = conversation.id().clone();
+            assert_eq!(conversation.e2ei_state().await, E2eiConversationState::Verified);
+            let observer = TestE2eiStatusObserver::new();
+            bob.session()
+                .await
+                .register_e2ei_status_observer(observer.clone())
+                .await
+                .unwrap();
+
+            // the first evaluation only records the current status
+            let now = crate::mls::unix_timestamp();
+            bob.transaction.e2ei_reevaluate_status(now).await.unwrap();
+            bob.commit_transaction().await;
+            assert!(observer.observed_events().await.is_empty());
+
+            // test certificates are valid for a day
+            let later = now + 2 * 24 * 60 * 60;
+            bob.transaction.e2ei_reevaluate_status(later).await.unwrap();
+            // changes are only reported once the transaction is committed
+            assert!(observer.observed_events().await.is_empty());
+            bob.commit_transaction().await;
+            let mut expired = [alice.get_client_id().await, bob.get_client_id().await];
+            expired.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+            let mut expected = expired
+                .into_iter()
+                .map(|client_id| E2eiStatusEvent::Device(id.clone(), client_id, DeviceStatus::Expired))
+                .collect::<Vec<_>>();
+            expected.push(E2eiStatusEvent::Conversation(
+                id.clone(),
+                E2eiConversationState::NotVerified,
+            ));
+            assert_eq!(observer.observed_events().await, expected);
+
+            // the statuses as of later were not recorded, so nothing changed as of now
+            bob.transaction.e2ei_reevaluate_status(now).await.unwrap();
+            bob.commit_transaction().await;
+            assert_eq!(observer.observed_events().await.len(), expected.len());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn does_not_report_changes_of_aborted_transactions(case: TestContext) {
+        if !case.is_x509() {
+            return;
+        }
+        let [alice, mut bob] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice, &bob]).await;
+            let id = conversation.id().clone();
+            let observer = TestE2eiStatusObserver::new();
+            bob.session()
+                .await
+                .register_e2ei_status_observer(observer.clone())
+                .await
+                .unwrap();
+
+            // the status recorded by a committed transaction is kept in the keystore
+            let now = crate::mls::unix_timestamp();
+            bob.transaction.e2ei_reevaluate_status(now).await.unwrap();
+            bob.commit_transaction().await;
+            assert!(bob.session().await.has_e2ei_status(&id).await.unwrap());
+
+            let later = now + 2 * 24 * 60 * 60;
+            bob.transaction.e2ei_reevaluate_status(later).await.unwrap();
+            bob.pretend_crash().await;
+            bob.commit_transaction().await;
+            assert!(observer.observed_events().await.is_empty());
+        })
+        .await
+    }
+
+    #[apply(all_cred_cipher)]
+    async fn forgets_status_of_wiped_conversations(case: TestContext) {
+        let [alice] = case.sessions().await;
+        Box::pin(async move {
+            let conversation = case.create_conversation([&alice]).await;
+            let id = conversation.id().clone();
+            let session = alice.session().await;
+            session
+                .register_e2ei_status_observer(TestE2eiStatusObserver::new())
+                .await
+                .unwrap();
+            alice
+                .transaction
+                .e2ei_reevaluate_status(crate::mls::unix_timestamp())
+                .await
+                .unwrap();
+            assert!(session.has_e2ei_status(&id).await.unwrap());
+
+            conversation.guard().await.wipe().await.unwrap();
+            assert!(!session.has_e2ei_status(&id).await.unwrap());
+        })
+        .await
+    }
+}
@@ -1,13 +1,15 @@
 //! This module contains all transactional behavior related to key packages
 
-use openmls::prelude::{KeyPackage, KeyPackageRef};
+use openmls::prelude::{KeyPackage, KeyPackageIn, KeyPackageRef, ProtocolVersion};
+use openmls_traits::OpenMlsCryptoProvider as _;
+use tls_codec::Deserialize as _;
 
 use crate::{
-    RecursiveError,
-    prelude::{MlsCiphersuite, MlsCredentialType},
+    MlsError, RecursiveError,
+    prelude::{MlsCiphersuite, MlsCredentialType, MlsKeyPackageInventoryEntry},
 };
 
-use super::{Result, TransactionContext};
+use super::{Error, Result, TransactionContext};
 
 impl TransactionContext {
     /// Returns `amount_requested` OpenMLS [openmls::key_packages::KeyPackage]s.
@@ -56,6 +58,101 @@ impl TransactionContext {
             .map_err(Into::into)
     }
 
+    /// Sets the lifetime of the KeyPackages generated from now on for this [MlsCiphersuite] and
+    /// [MlsCredentialType], see [crate::prelude::Session::set_keypackage_lifetime]. X509 KeyPackages never outlive their
+    /// certificate.
+    pub async fn set_key_package_lifetime(
+        &self,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+        lifetime: std::time::Duration,
+    ) -> Result<()> {
+        let session = self.session().await?;
+        session
+            .set_keypackage_lifetime(&self.mls_provider().await?, ciphersuite, credential_type, lifetime)
+            .await
+            .map_err(RecursiveError::mls_client("setting key package lifetime"))
+            .map_err(Into::into)
+    }
+
+    /// Counts the KeyPackages in store per [MlsCiphersuite], [MlsCredentialType] and expiry. Those expiring
+    /// within `expiring_within` are counted apart, so that they can be replaced before they expire.
+    pub async fn key_package_inventory(
+        &self,
+        expiring_within: std::time::Duration,
+    ) -> Result<Vec<MlsKeyPackageInventoryEntry>> {
+        let session = self.session().await?;
+        session
+            .keypackage_inventory(&self.mls_provider().await?, expiring_within)
+            .await
+            .map_err(RecursiveError::mls_client("taking key package inventory"))
+            .map_err(Into::into)
+    }
+
+    /// Marks these KeyPackages as uploaded to the backend, see [TransactionContext::key_package_inventory]
+    pub async fn mark_key_packages_uploaded(&self, refs: impl IntoIterator<Item = KeyPackageRef>) -> Result<()> {
+        let session = self.session().await?;
+        session
+            .mark_keypackages_uploaded(&self.mls_provider().await?, refs)
+            .await
+            .map_err(RecursiveError::mls_client("marking key packages as uploaded"))
+            .map_err(Into::into)
+    }
+
+    /// Marks these TLS serialized KeyPackages as uploaded to the backend, see
+    /// [TransactionContext::mark_key_packages_uploaded]
+    pub async fn mark_raw_key_packages_uploaded(
+        &self,
+        key_packages: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<()> {
+        let mls_provider = self.mls_provider().await?;
+        let mut refs = Vec::new();
+        for key_package in key_packages {
+            let key_package = KeyPackageIn::tls_deserialize(&mut key_package.as_ref())
+                .map_err(Error::tls_deserialize("keypackage"))?
+                .standalone_validate(&mls_provider, ProtocolVersion::Mls10, true)
+                .await
+                .map_err(MlsError::wrap("validating keypackage"))?;
+            let kp_ref = key_package
+                .hash_ref(mls_provider.crypto())
+                .map_err(MlsError::wrap("computing keypackage hashref"))?;
+            refs.push(kp_ref);
+        }
+        self.mark_key_packages_uploaded(refs).await
+    }
+
+    /// Returns the last resort KeyPackage of this [MlsCiphersuite] and [MlsCredentialType], generating it if
+    /// there is none. Unlike other KeyPackages, it is kept once used by a Welcome, so that the backend can
+    /// hand it out once it ran out of KeyPackages.
+    pub async fn last_resort_key_package(
+        &self,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<KeyPackage> {
+        let session = self.session().await?;
+        session
+            .last_resort_keypackage(&self.mls_provider().await?, ciphersuite, credential_type)
+            .await
+            .map_err(RecursiveError::mls_client("getting last resort key package"))
+            .map_err(Into::into)
+    }
+
+    /// Replaces the last resort KeyPackage of this [MlsCiphersuite] and [MlsCredentialType] with a new one,
+    /// see [TransactionContext::last_resort_key_package]. Welcomes sent to the previous one cannot be
+    /// processed anymore.
+    pub async fn rotate_last_resort_key_package(
+        &self,
+        ciphersuite: MlsCiphersuite,
+        credential_type: MlsCredentialType,
+    ) -> Result<KeyPackage> {
+        let session = self.session().await?;
+        session
+            .rotate_last_resort_keypackage(&self.mls_provider().await?, ciphersuite, credential_type)
+            .await
+            .map_err(RecursiveError::mls_client("rotating last resort key package"))
+            .map_err(Into::into)
+    }
+
     /// Prunes local KeyPackages after making sure they also have been deleted on the backend side
     /// You should only use this after [TransactionContext::save_x509_credential]
     pub async fn delete_keypackages(&self, refs: impl IntoIterator<Item = KeyPackageRef>) -> Result<()> {
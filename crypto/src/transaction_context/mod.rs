@@ -26,6 +26,7 @@ mod error;
 pub mod key_package;
 #[cfg(feature = "proteus")]
 pub mod proteus;
+pub(crate) mod psk;
 #[cfg(test)]
 pub mod test_utils;
 
@@ -181,7 +182,10 @@ impl TransactionContext {
     /// something is called from this object.
     pub async fn finish(&self) -> Result<()> {
         let mut guard = self.inner.write().await;
-        let TransactionContextInner::Valid { provider, .. } = guard.deref() else {
+        let TransactionContextInner::Valid {
+            provider, mls_client, ..
+        } = guard.deref()
+        else {
             return Err(Error::InvalidTransactionContext);
         };
 
@@ -191,6 +195,12 @@ impl TransactionContext {
             .await
             .map_err(KeystoreError::wrap("commiting transaction"))
             .map_err(Into::into);
+        // status changes are only reported once persisted
+        if commit_result.is_ok() {
+            mls_client.notify_e2ei_status_changes().await;
+        } else {
+            mls_client.discard_e2ei_status_changes().await;
+        }
 
         *guard = TransactionContextInner::Invalid;
         commit_result
@@ -202,10 +212,14 @@ impl TransactionContext {
     pub async fn abort(&self) -> Result<()> {
         let mut guard = self.inner.write().await;
 
-        let TransactionContextInner::Valid { provider, .. } = guard.deref() else {
+        let TransactionContextInner::Valid {
+            provider, mls_client, ..
+        } = guard.deref()
+        else {
             return Err(Error::InvalidTransactionContext);
         };
 
+        mls_client.discard_e2ei_status_changes().await;
         let result = provider
             .keystore()
             .rollback_transaction()
@@ -0,0 +1,179 @@
+//! External pre-shared keys, which bind conversations to secrets shared out-of-band.
+
+use core_crypto_keystore::{
+    CryptoKeystoreError,
+    entities::{MlsExternalPsk, MlsPskBundle},
+};
+use mls_crypto_provider::{CryptoKeystore, MlsCryptoProvider};
+use openmls::prelude::{ExternalPsk, PreSharedKeyId, Psk};
+use openmls_traits::{
+    OpenMlsCryptoProvider,
+    key_store::{MlsEntity, OpenMlsKeyStore},
+};
+
+use super::{Error, Result, TransactionContext};
+use crate::{KeystoreError, MlsError, prelude::MlsCiphersuite};
+
+/// Ids of external pre-shared keys starting with this prefix are reserved for the pre-shared keys core-crypto
+/// stores itself, e.g. to link a branch to its conversation.
+pub const RESERVED_PSK_ID_PREFIX: &[u8] = b"core-crypto/";
+
+impl TransactionContext {
+    /// Stores the secret of an external pre-shared key under the given id. Conversations of this
+    /// ciphersuite can then inject it with [crate::mls::conversation::ConversationGuard::inject_external_psk],
+    /// and commits or welcomes referencing it can be processed.
+    ///
+    /// Every member has to store the same secret under the same id before it gets injected. The id must
+    /// not start with [RESERVED_PSK_ID_PREFIX].
+    pub async fn store_external_psk(&self, ciphersuite: MlsCiphersuite, psk_id: Vec<u8>, secret: &[u8]) -> Result<()> {
+        if psk_id.is_empty() || secret.is_empty() {
+            return Err(Error::CallerError(
+                "external pre-shared key ids and secrets must not be empty",
+            ));
+        }
+        if psk_id.starts_with(RESERVED_PSK_ID_PREFIX) {
+            return Err(Error::CallerError(
+                "external pre-shared key ids starting with the reserved prefix cannot be stored",
+            ));
+        }
+        let backend = self.mls_provider().await?;
+        write_external_psk::<Error>(&backend, ciphersuite, psk_id, secret).await?;
+        Ok(())
+    }
+}
+
+/// Stores the secret of an external pre-shared key, see [TransactionContext::store_external_psk]
+pub(crate) async fn write_external_psk<E: From<MlsError> + From<KeystoreError>>(
+    backend: &MlsCryptoProvider,
+    ciphersuite: MlsCiphersuite,
+    psk_id: Vec<u8>,
+    secret: &[u8],
+) -> Result<(), E> {
+    let pre_shared_key_id = PreSharedKeyId::new(
+        ciphersuite.into(),
+        backend.rand(),
+        Psk::External(ExternalPsk::new(psk_id.clone())),
+    )
+    .map_err(MlsError::wrap("creating pre-shared key id"))?;
+    // OpenMLS decides which id the secret is stored under, we record it to be able to delete the secret
+    let recorder = RecordingProvider::new(backend);
+    pre_shared_key_id
+        .write_to_key_store(&recorder, ciphersuite.into(), secret)
+        .await
+        .map_err(MlsError::wrap("storing external pre-shared key"))?;
+    let psk_bundle_id = recorder
+        .key_store
+        .stored_ids()
+        .pop()
+        .ok_or_else(|| CryptoKeystoreError::MlsKeyStoreError("no pre-shared key bundle was stored".into()))
+        .map_err(KeystoreError::wrap("recording external pre-shared key"))?;
+    backend
+        .keystore()
+        .save(MlsExternalPsk::new(&psk_id, &psk_bundle_id))
+        .await
+        .map_err(KeystoreError::wrap("recording external pre-shared key"))?;
+    Ok(())
+}
+
+/// Deletes the secret of an external pre-shared key stored with [write_external_psk], if any
+pub(crate) async fn delete_external_psk(backend: &MlsCryptoProvider, psk_id: Vec<u8>) -> Result<(), KeystoreError> {
+    let keystore = backend.keystore();
+    let Some(external_psk) = keystore
+        .find::<MlsExternalPsk>(&psk_id)
+        .await
+        .map_err(KeystoreError::wrap("finding external pre-shared key"))?
+    else {
+        return Ok(());
+    };
+    keystore
+        .remove::<MlsPskBundle, _>(&external_psk.psk_bundle_id)
+        .await
+        .map_err(KeystoreError::wrap("deleting external pre-shared key"))?;
+    keystore
+        .remove::<MlsExternalPsk, _>(&psk_id)
+        .await
+        .map_err(KeystoreError::wrap("deleting external pre-shared key record"))
+}
+
+/// Provider recording the ids OpenMLS stores values under, and delegating everything else to the
+/// backend
+struct RecordingProvider<'a> {
+    backend: &'a MlsCryptoProvider,
+    key_store: RecordingKeyStore<'a>,
+}
+
+impl<'a> RecordingProvider<'a> {
+    fn new(backend: &'a MlsCryptoProvider) -> Self {
+        Self {
+            backend,
+            key_store: RecordingKeyStore {
+                keystore: backend.key_store(),
+                stored_ids: Default::default(),
+            },
+        }
+    }
+}
+
+impl<'a> OpenMlsCryptoProvider for RecordingProvider<'a> {
+    type CryptoProvider = <MlsCryptoProvider as OpenMlsCryptoProvider>::CryptoProvider;
+    type RandProvider = <MlsCryptoProvider as OpenMlsCryptoProvider>::RandProvider;
+    type KeyStoreProvider = RecordingKeyStore<'a>;
+    type AuthenticationServiceProvider = <MlsCryptoProvider as OpenMlsCryptoProvider>::AuthenticationServiceProvider;
+
+    fn crypto(&self) -> &Self::CryptoProvider {
+        self.backend.crypto()
+    }
+
+    fn rand(&self) -> &Self::RandProvider {
+        self.backend.rand()
+    }
+
+    fn key_store(&self) -> &Self::KeyStoreProvider {
+        &self.key_store
+    }
+
+    fn authentication_service(&self) -> &Self::AuthenticationServiceProvider {
+        self.backend.authentication_service()
+    }
+}
+
+/// Key store recording the ids values are stored under, see [RecordingProvider]
+struct RecordingKeyStore<'a> {
+    keystore: &'a CryptoKeystore,
+    stored_ids: std::sync::Mutex<Vec<Vec<u8>>>,
+}
+
+impl RecordingKeyStore<'_> {
+    fn stored_ids(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.stored_ids.lock().expect("the lock is never held across a panic"))
+    }
+}
+
+#[cfg_attr(target_family = "wasm", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_family = "wasm"), async_trait::async_trait)]
+impl OpenMlsKeyStore for RecordingKeyStore<'_> {
+    type Error = CryptoKeystoreError;
+
+    async fn store<V: MlsEntity + Sync>(&self, k: &[u8], v: &V) -> Result<(), Self::Error>
+    where
+        Self: Sized,
+    {
+        OpenMlsKeyStore::store(self.keystore, k, v).await?;
+        self.stored_ids
+            .lock()
+            .expect("the lock is never held across a panic")
+            .push(k.to_vec());
+        Ok(())
+    }
+
+    async fn read<V: MlsEntity>(&self, k: &[u8]) -> Option<V>
+    where
+        Self: Sized,
+    {
+        OpenMlsKeyStore::read(self.keystore, k).await
+    }
+
+    async fn delete<V: MlsEntity>(&self, k: &[u8]) -> Result<(), Self::Error> {
+        OpenMlsKeyStore::delete::<V>(self.keystore, k).await
+    }
+}
@@ -2,17 +2,20 @@ use super::TransactionContext;
 use core_crypto_keystore::{
     connection::FetchFromDatabase as _,
     entities::{
-        E2eiEnrollment, MlsCredential, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsHpkePrivateKey,
-        MlsKeyPackage, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
-        PersistedMlsPendingGroup,
+        E2eiEnrollment, MlsConversationReInit, MlsCredential, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair,
+        MlsExporterSecret, MlsExternalPsk, MlsHpkePrivateKey, MlsKeyPackage, MlsPendingMessage, MlsPskBundle,
+        MlsSignatureKeyPair, PersistedMlsGroup, PersistedMlsPendingGroup,
     },
 };
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct EntitiesCount {
+    pub conversation_reinit: usize,
     pub credential: usize,
     pub encryption_keypair: usize,
     pub epoch_encryption_keypair: usize,
+    pub exporter_secret: usize,
+    pub external_psk: usize,
     pub enrollment: usize,
     pub group: usize,
     pub hpke_private_key: usize,
@@ -27,9 +30,12 @@ impl TransactionContext {
     /// Count the entities
     pub async fn count_entities(&self) -> EntitiesCount {
         let keystore = self.keystore().await.unwrap();
+        let conversation_reinit = keystore.count::<MlsConversationReInit>().await.unwrap();
         let credential = keystore.count::<MlsCredential>().await.unwrap();
         let encryption_keypair = keystore.count::<MlsEncryptionKeyPair>().await.unwrap();
         let epoch_encryption_keypair = keystore.count::<MlsEpochEncryptionKeyPair>().await.unwrap();
+        let exporter_secret = keystore.count::<MlsExporterSecret>().await.unwrap();
+        let external_psk = keystore.count::<MlsExternalPsk>().await.unwrap();
         let enrollment = keystore.count::<E2eiEnrollment>().await.unwrap();
         let group = keystore.count::<PersistedMlsGroup>().await.unwrap();
         let hpke_private_key = keystore.count::<MlsHpkePrivateKey>().await.unwrap();
@@ -39,9 +45,12 @@ impl TransactionContext {
         let psk_bundle = keystore.count::<MlsPskBundle>().await.unwrap();
         let signature_keypair = keystore.count::<MlsSignatureKeyPair>().await.unwrap();
         EntitiesCount {
+            conversation_reinit,
             credential,
             encryption_keypair,
             epoch_encryption_keypair,
+            exporter_secret,
+            external_psk,
             enrollment,
             group,
             hpke_private_key,
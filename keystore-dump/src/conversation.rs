@@ -0,0 +1,313 @@
+use chrono::TimeZone as _;
+use core_crypto::prelude::{ClientId, MlsCredentialType, MlsGroup, MlsProposalType};
+use core_crypto_keystore::{
+    Database as Keystore,
+    connection::FetchFromDatabase as _,
+    entities::{MlsBufferedCommit, PersistedMlsGroup, PersistedMlsPendingGroup},
+};
+use openmls::prelude::{Credential, Sender};
+use serde::Serialize;
+
+/// What is needed to debug a conversation, decoded from its persisted group state
+#[derive(Debug, Serialize)]
+pub struct ConversationSummary {
+    id: String,
+    /// Whether the conversation is being joined by an external commit which has not been merged yet
+    pending: bool,
+    epoch: u64,
+    ciphersuite: String,
+    /// The wire format policies of the group as they are, since they may match no wire policy core-crypto sets
+    incoming_wire_format: String,
+    outgoing_wire_format: String,
+    members: Vec<Member>,
+    external_senders: Vec<ExternalSender>,
+    pending_proposals: Vec<PendingProposal>,
+    /// Types of the proposals of the own commit waiting to be merged, if any
+    pending_commit: Option<Vec<String>>,
+    /// Messages buffered for a future epoch
+    buffered_messages: Vec<Buffered>,
+    /// The commit buffered until its proposals arrive, if any
+    buffered_commit: Option<Buffered>,
+    parent_id: Option<String>,
+    child_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Member {
+    index: u32,
+    client_id: String,
+    credential_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ExternalSender {
+    identity: String,
+    credential_type: String,
+    signature_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PendingProposal {
+    proposal_ref: String,
+    proposal_type: String,
+    sender: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Buffered {
+    received_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn credential_type(credential: &Credential) -> String {
+    format!("{:?}", MlsCredentialType::from(credential.credential_type()))
+}
+
+impl Buffered {
+    fn received_at(seconds: Option<u64>) -> Self {
+        let received_at = seconds.and_then(|seconds| chrono::Utc.timestamp_opt(seconds as i64, 0).single());
+        Self { received_at }
+    }
+}
+
+impl ConversationSummary {
+    /// Summarizes one of the `groups` persisted in the keystore
+    pub async fn new(
+        keystore: &Keystore,
+        groups: &[PersistedMlsGroup],
+        persisted: &PersistedMlsGroup,
+    ) -> anyhow::Result<Self> {
+        let parent_id = persisted.parent_id.as_deref();
+        Self::summarize(keystore, groups, &persisted.id, &persisted.state, parent_id, false).await
+    }
+
+    /// Summarizes a pending group persisted in the keystore, whose children are looked up in `groups`
+    pub async fn new_pending(
+        keystore: &Keystore,
+        groups: &[PersistedMlsGroup],
+        persisted: &PersistedMlsPendingGroup,
+    ) -> anyhow::Result<Self> {
+        let parent_id = persisted.parent_id.as_deref();
+        Self::summarize(keystore, groups, &persisted.id, &persisted.state, parent_id, true).await
+    }
+
+    async fn summarize(
+        keystore: &Keystore,
+        groups: &[PersistedMlsGroup],
+        id: &[u8],
+        state: &[u8],
+        parent_id: Option<&[u8]>,
+        pending: bool,
+    ) -> anyhow::Result<Self> {
+        let group = core_crypto_keystore::deser::<MlsGroup>(state)?;
+
+        let members = group
+            .members()
+            .map(|member| Member {
+                index: member.index.u32(),
+                client_id: ClientId::from(member.credential.identity()).to_string(),
+                credential_type: credential_type(&member.credential),
+            })
+            .collect::<Vec<_>>();
+        let client_at = |index: u32| {
+            members
+                .iter()
+                .find(|member| member.index == index)
+                .map(|member| member.client_id.clone())
+        };
+
+        let external_senders = group
+            .group_context_extensions()
+            .external_senders()
+            .map(|senders| {
+                senders
+                    .iter()
+                    .map(|sender| ExternalSender {
+                        identity: hex::encode(sender.credential().identity()),
+                        credential_type: credential_type(sender.credential()),
+                        signature_key: hex::encode(sender.signature_key().as_slice()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let pending_proposals = group
+            .pending_proposals()
+            .map(|proposal| PendingProposal {
+                proposal_ref: hex::encode(proposal.proposal_reference().as_slice()),
+                proposal_type: format!("{:?}", MlsProposalType::from(proposal.proposal())),
+                sender: match proposal.sender() {
+                    Sender::Member(index) => {
+                        client_at(index.u32()).unwrap_or_else(|| format!("unknown member at index {}", index.u32()))
+                    }
+                    Sender::External(index) => format!("external sender at index {}", index.index()),
+                    Sender::NewMemberProposal | Sender::NewMemberCommit => "new member".to_string(),
+                },
+            })
+            .collect();
+        let pending_commit = group.pending_commit().map(|commit| {
+            commit
+                .queued_proposals()
+                .map(|proposal| format!("{:?}", MlsProposalType::from(proposal.proposal())))
+                .collect()
+        });
+
+        let buffered_messages = keystore
+            .find_pending_messages_by_conversation_id(id)
+            .await?
+            .iter()
+            .map(|message| Buffered::received_at(message.received_at()))
+            .collect();
+        let buffered_commit = keystore
+            .find::<MlsBufferedCommit>(id)
+            .await?
+            .map(|commit| Buffered::received_at(commit.received_at()));
+
+        let child_ids = groups
+            .iter()
+            .filter(|child| child.parent_id.as_deref() == Some(id))
+            .map(|child| hex::encode(&child.id))
+            .collect();
+
+        let wire_format_policy = group.configuration().wire_format_policy();
+        Ok(Self {
+            id: hex::encode(id),
+            pending,
+            epoch: group.epoch().as_u64(),
+            ciphersuite: format!("{:?}", group.ciphersuite()),
+            incoming_wire_format: format!("{:?}", wire_format_policy.incoming()),
+            outgoing_wire_format: format!("{:?}", wire_format_policy.outgoing()),
+            members,
+            external_senders,
+            pending_proposals,
+            pending_commit,
+            buffered_messages,
+            buffered_commit,
+            parent_id: parent_id.map(hex::encode),
+            child_ids,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_crypto::prelude::{
+        CoreCrypto, MlsCiphersuite, MlsConversationConfiguration, MlsWirePolicy, Session, SessionConfig,
+    };
+    use core_crypto_keystore::{ConnectionType, DatabaseKey, entities::MlsPendingMessage};
+    use openmls::prelude::WireFormatPolicy;
+
+    use super::*;
+
+    const CONVERSATION_ID: &[u8] = b"conversation";
+    const PENDING_ID: &[u8] = b"pending conversation";
+
+    /// A keystore holding a conversation created by alice, alone in it
+    async fn keystore_with_conversation(path: &str) -> Keystore {
+        let key = DatabaseKey::generate();
+        let config = SessionConfig::builder()
+            .persistent(path)
+            .database_key(key.clone())
+            .client_id("alice".into())
+            .ciphersuites([MlsCiphersuite::default()])
+            .build()
+            .validate()
+            .unwrap();
+        let cc = CoreCrypto::from(Session::try_new(config).await.unwrap());
+        let transaction = cc.new_transaction().await.unwrap();
+        transaction
+            .new_conversation(
+                &CONVERSATION_ID.to_vec(),
+                MlsCredentialType::Basic,
+                MlsConversationConfiguration::default(),
+            )
+            .await
+            .unwrap();
+        transaction.finish().await.unwrap();
+
+        Keystore::open(ConnectionType::Persistent(path), &key).await.unwrap()
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn summarizes_established_and_pending_conversations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.db");
+        let keystore = keystore_with_conversation(path.to_str().unwrap()).await;
+        let groups = keystore
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .unwrap();
+        let [group] = groups.as_slice() else {
+            panic!("expected a single conversation, found {}", groups.len());
+        };
+
+        let summary = ConversationSummary::new(&keystore, &groups, group).await.unwrap();
+        let wire_format_policy = WireFormatPolicy::from(MlsWirePolicy::default());
+        assert_eq!(summary.id, hex::encode(CONVERSATION_ID));
+        assert!(!summary.pending);
+        assert_eq!(summary.epoch, 0);
+        assert_eq!(
+            summary.incoming_wire_format,
+            format!("{:?}", wire_format_policy.incoming())
+        );
+        assert_eq!(
+            summary.outgoing_wire_format,
+            format!("{:?}", wire_format_policy.outgoing())
+        );
+        let [member] = summary.members.as_slice() else {
+            panic!("expected a single member, found {}", summary.members.len());
+        };
+        assert_eq!(member.client_id, ClientId::from("alice").to_string());
+        assert_eq!(member.credential_type, format!("{:?}", MlsCredentialType::Basic));
+        assert!(summary.external_senders.is_empty());
+        assert!(summary.pending_proposals.is_empty());
+        assert!(summary.pending_commit.is_none());
+        assert!(summary.buffered_messages.is_empty());
+        assert!(summary.buffered_commit.is_none());
+        assert!(summary.parent_id.is_none());
+        assert!(summary.child_ids.is_empty());
+
+        // the state of a pending group is a group too, only its external commit has not been merged
+        keystore.new_transaction().await.unwrap();
+        keystore
+            .save(PersistedMlsPendingGroup {
+                id: PENDING_ID.to_vec(),
+                state: group.state.clone(),
+                parent_id: Some(CONVERSATION_ID.to_vec()),
+                custom_configuration: Vec::new(),
+            })
+            .await
+            .unwrap();
+        keystore
+            .save(MlsPendingMessage {
+                foreign_id: PENDING_ID.to_vec(),
+                message: b"message".to_vec(),
+                received_at: Some(1_700_000_000u64.to_be_bytes().to_vec()),
+            })
+            .await
+            .unwrap();
+        keystore.commit_transaction().await.unwrap();
+
+        let pending_group = keystore
+            .find::<PersistedMlsPendingGroup>(PENDING_ID)
+            .await
+            .unwrap()
+            .unwrap();
+        let summary = ConversationSummary::new_pending(&keystore, &groups, &pending_group)
+            .await
+            .unwrap();
+        assert_eq!(summary.id, hex::encode(PENDING_ID));
+        assert!(summary.pending);
+        assert_eq!(summary.parent_id, Some(hex::encode(CONVERSATION_ID)));
+        assert_eq!(summary.members.len(), 1);
+        let [buffered] = summary.buffered_messages.as_slice() else {
+            panic!(
+                "expected a single buffered message, found {}",
+                summary.buffered_messages.len()
+            );
+        };
+        assert_eq!(
+            buffered.received_at,
+            chrono::Utc.timestamp_opt(1_700_000_000, 0).single()
+        );
+    }
+}
@@ -8,158 +8,580 @@ fn main() -> anyhow::Result<()> {
 }
 
 #[cfg(not(target_family = "wasm"))]
-#[macro_rules_attribute::apply(smol_macros::main)]
-async fn main() -> anyhow::Result<()> {
-    #[derive(Debug, clap::Parser)]
-    #[command(author, version, about, long_about = None)]
-    struct Args {
-        #[arg(short, long)]
-        /// The 256-bit database key, hex-encoded.
-        key: String,
+mod conversation;
+#[cfg(not(target_family = "wasm"))]
+mod output;
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, clap::Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(short, long)]
+    /// The 256-bit database key, hex-encoded.
+    key: String,
+
+    path: String,
+
+    #[arg(short, long = "entity", value_enum)]
+    /// Only dump the entities of this type. Can be repeated; all types are dumped by default.
+    entities: Vec<output::EntityType>,
+
+    #[arg(short, long)]
+    /// Only dump the entities of the conversation with this id, hex-encoded.
+    conversation: Option<String>,
+
+    #[arg(long)]
+    /// Replace private keys and secrets by fingerprints, which can only be compared within a single dump.
+    redact: bool,
+
+    #[arg(long, value_enum, default_value_t = output::Format::Json)]
+    format: output::Format,
+
+    #[arg(long)]
+    /// Work on a copy of the database loaded in memory, so that the database file is never written to.
+    in_memory_copy: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Lists the entities referring to ones which are not in the keystore anymore.
+    Check {
+        #[arg(long)]
+        /// Also delete them, within a single transaction. Cannot be combined with `--in-memory-copy`.
+        repair: bool,
+    },
+}
 
-        path: String,
+#[cfg(not(target_family = "wasm"))]
+impl Args {
+    /// Rejects the combinations of arguments clap cannot express
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.in_memory_copy && matches!(self.command, Some(Command::Check { repair: true })) {
+            bail!("--repair cannot be combined with --in-memory-copy, the repaired copy would be discarded");
+        }
+        Ok(())
     }
 
-    use chrono::TimeZone;
+    fn dumps(&self, entity_type: output::EntityType) -> bool {
+        (self.entities.is_empty() || self.entities.contains(&entity_type))
+            && (self.conversation.is_none() || entity_type.is_conversation_scoped())
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[macro_rules_attribute::apply(smol_macros::main)]
+async fn main() -> anyhow::Result<()> {
     use clap::Parser as _;
-    use core_crypto_keystore::{
-        ConnectionType, Database as Keystore, DatabaseKey, connection::FetchFromDatabase, entities::*,
-    };
-    use openmls::prelude::TlsDeserializeTrait;
-    use serde::ser::{SerializeMap, Serializer};
+    use core_crypto_keystore::DatabaseKey;
 
     let args = Args::parse();
+    args.validate()?;
 
     if !std::path::Path::new(&args.path).exists() {
         bail!("File not found: {}", args.path);
     }
 
     let key = DatabaseKey::try_from(hex::decode(&args.key)?.as_slice())?;
-    let keystore = Keystore::open(ConnectionType::Persistent(&args.path), &key)
-        .await
-        .map_err(|e| anyhow!("The passkey is probably wrong; [err: {e}]"))?;
+    let keystore = open_keystore(&args, &key).await?;
 
-    let mut json_serializer = serde_json::Serializer::pretty(std::io::stdout());
-    let mut json_map = json_serializer.serialize_map(None)?;
+    let mut output = output::Output::new(args.format, args.redact);
+    match &args.command {
+        Some(Command::Check { repair }) => check_integrity(&keystore, *repair, &mut output).await?,
+        None => dump(&keystore, &args, &mut output).await?,
+    }
+    output.finish()
+}
 
-    let mut credentials: Vec<serde_json::Value> = vec![];
-    for cred in keystore
-        .find_all::<MlsCredential>(Default::default())
-        .await?
-        .into_iter()
-    {
-        let mls_credential = openmls::prelude::Credential::tls_deserialize(&mut cred.credential.as_slice())?;
-        let date = chrono::Utc
-            .timestamp_opt(cred.created_at as i64, 0)
-            .single()
-            .ok_or_else(|| anyhow!("Cannot parse credential creation date"))?;
-
-        credentials.push(serde_json::json!({
-            "id": cred.id,
-            "credential": mls_credential,
-            "created_at": date
-        }));
-    }
-    json_map.serialize_entry("mls_credentials", &credentials)?;
-
-    let mut signature_keypairs: Vec<serde_json::Value> = vec![];
-    for kp in keystore
-        .find_all::<MlsSignatureKeyPair>(Default::default())
-        .await?
-        .into_iter()
-    {
-        let mls_keypair = openmls_basic_credential::SignatureKeyPair::tls_deserialize(&mut kp.keypair.as_slice())?;
-        signature_keypairs.push(serde_json::json!({
-            "signature_scheme": kp.signature_scheme,
-            "mls_keypair": mls_keypair,
-            "credential_id": kp.credential_id,
-        }));
-    }
-    json_map.serialize_entry("mls_signature_keypairs", &signature_keypairs)?;
-
-    let hpke_sks: Vec<openmls_traits::types::HpkePrivateKey> = keystore
-        .find_all::<MlsHpkePrivateKey>(Default::default())
-        .await?
-        .into_iter()
-        .map(|hpke_sk| postcard::from_bytes::<openmls_traits::types::HpkePrivateKey>(&hpke_sk.sk))
-        .collect::<postcard::Result<_>>()?;
-    json_map.serialize_entry("mls_hpke_private_keys", &hpke_sks)?;
+/// Writes the entities selected by the arguments to the output
+#[cfg(not(target_family = "wasm"))]
+async fn dump(
+    keystore: &core_crypto_keystore::Database,
+    args: &Args,
+    output: &mut output::Output,
+) -> anyhow::Result<()> {
+    use chrono::TimeZone;
+    use core_crypto_keystore::{connection::FetchFromDatabase, entities::*};
+    use openmls::prelude::TlsDeserializeTrait;
+    use output::EntityType;
 
-    let hpke_keypairs: Vec<openmls_traits::types::HpkeKeyPair> = keystore
-        .find_all::<MlsEncryptionKeyPair>(Default::default())
-        .await?
-        .into_iter()
-        .map(|hpke_kp| postcard::from_bytes::<openmls_traits::types::HpkeKeyPair>(&hpke_kp.sk))
-        .collect::<postcard::Result<_>>()?;
-    json_map.serialize_entry("mls_hpke_keypairs", &hpke_keypairs)?;
+    let conversation_id = args.conversation.as_deref().map(hex::decode).transpose()?;
+    let in_conversation = |id: &[u8]| {
+        conversation_id
+            .as_deref()
+            .is_none_or(|conversation_id| conversation_id == id)
+    };
 
-    let mut external_psks: std::collections::HashMap<String, openmls::schedule::psk::PskBundle> = Default::default();
-    for psk in keystore.find_all::<MlsPskBundle>(Default::default()).await?.into_iter() {
-        let mls_psk = postcard::from_bytes::<openmls::schedule::psk::PskBundle>(&psk.psk)?;
-        external_psks.insert(hex::encode(&psk.psk_id), mls_psk);
+    if args.dumps(EntityType::MlsCredentials) {
+        let mut credentials: Vec<serde_json::Value> = vec![];
+        for cred in keystore
+            .find_all::<MlsCredential>(Default::default())
+            .await?
+            .into_iter()
+        {
+            let mls_credential = openmls::prelude::Credential::tls_deserialize(&mut cred.credential.as_slice())?;
+            let date = chrono::Utc
+                .timestamp_opt(cred.created_at as i64, 0)
+                .single()
+                .ok_or_else(|| anyhow!("Cannot parse credential creation date"))?;
+
+            credentials.push(serde_json::json!({
+                "id": cred.id,
+                "credential": mls_credential,
+                "created_at": date
+            }));
+        }
+        output.write(EntityType::MlsCredentials, &credentials)?;
     }
 
-    json_map.serialize_entry("external_psks", &external_psks)?;
+    if args.dumps(EntityType::MlsSignatureKeypairs) {
+        let mut signature_keypairs: Vec<serde_json::Value> = vec![];
+        for kp in keystore
+            .find_all::<MlsSignatureKeyPair>(Default::default())
+            .await?
+            .into_iter()
+        {
+            let mls_keypair = openmls_basic_credential::SignatureKeyPair::tls_deserialize(&mut kp.keypair.as_slice())?;
+            signature_keypairs.push(serde_json::json!({
+                "signature_scheme": kp.signature_scheme,
+                "mls_keypair": mls_keypair,
+                "credential_id": kp.credential_id,
+            }));
+        }
+        output.write(EntityType::MlsSignatureKeypairs, &signature_keypairs)?;
+    }
 
-    let keypackages: Vec<openmls::prelude::KeyPackage> = keystore
-        .find_all::<MlsKeyPackage>(Default::default())
-        .await?
-        .into_iter()
-        .map(|kp| postcard::from_bytes::<openmls::prelude::KeyPackage>(&kp.keypackage))
-        .collect::<postcard::Result<_>>()?;
-    json_map.serialize_entry("mls_keypackages", &keypackages)?;
+    if args.dumps(EntityType::MlsHpkePrivateKeys) {
+        let hpke_sks: Vec<openmls_traits::types::HpkePrivateKey> = keystore
+            .find_all::<MlsHpkePrivateKey>(Default::default())
+            .await?
+            .into_iter()
+            .map(|hpke_sk| postcard::from_bytes::<openmls_traits::types::HpkePrivateKey>(&hpke_sk.sk))
+            .collect::<postcard::Result<_>>()?;
+        output.write(EntityType::MlsHpkePrivateKeys, &hpke_sks)?;
+    }
 
-    let e2ei_enrollments: Vec<core_crypto::prelude::E2eiEnrollment> = keystore
-        .find_all::<E2eiEnrollment>(Default::default())
-        .await?
-        .into_iter()
-        .map(|enrollment| serde_json::from_slice::<core_crypto::prelude::E2eiEnrollment>(&enrollment.content))
-        .collect::<serde_json::Result<_>>()?;
-    json_map.serialize_entry("e2ei_enrollments", &e2ei_enrollments)?;
+    if args.dumps(EntityType::MlsHpkeKeypairs) {
+        let hpke_keypairs: Vec<openmls_traits::types::HpkeKeyPair> = keystore
+            .find_all::<MlsEncryptionKeyPair>(Default::default())
+            .await?
+            .into_iter()
+            .map(|hpke_kp| postcard::from_bytes::<openmls_traits::types::HpkeKeyPair>(&hpke_kp.sk))
+            .collect::<postcard::Result<_>>()?;
+        output.write(EntityType::MlsHpkeKeypairs, &hpke_keypairs)?;
+    }
 
-    let pgroups: Vec<openmls::prelude::MlsGroup> = keystore
-        .find_all::<PersistedMlsGroup>(Default::default())
-        .await?
-        .into_iter()
-        .map(|pgroup| core_crypto_keystore::deser::<openmls::prelude::MlsGroup>(&pgroup.state))
-        .collect::<core_crypto_keystore::CryptoKeystoreResult<_>>()?;
-    json_map.serialize_entry("mls_groups", &pgroups)?;
+    if args.dumps(EntityType::ExternalPsks) {
+        let mut external_psks: Vec<serde_json::Value> = vec![];
+        for psk in keystore.find_all::<MlsPskBundle>(Default::default()).await?.into_iter() {
+            let mls_psk = postcard::from_bytes::<openmls::schedule::psk::PskBundle>(&psk.psk)?;
+            external_psks.push(serde_json::json!({
+                "id": hex::encode(&psk.psk_id),
+                "bundle": mls_psk,
+            }));
+        }
+        output.write(EntityType::ExternalPsks, &external_psks)?;
+    }
 
-    let pegroups: Vec<openmls::prelude::MlsGroup> = keystore
-        .find_all::<PersistedMlsPendingGroup>(Default::default())
-        .await?
-        .into_iter()
-        .map(|pgroup| core_crypto_keystore::deser::<openmls::prelude::MlsGroup>(&pgroup.state))
-        .collect::<core_crypto_keystore::CryptoKeystoreResult<_>>()?;
-    json_map.serialize_entry("mls_pending_groups", &pegroups)?;
+    if args.dumps(EntityType::MlsKeypackages) {
+        let keypackages: Vec<openmls::prelude::KeyPackage> = keystore
+            .find_all::<MlsKeyPackage>(Default::default())
+            .await?
+            .into_iter()
+            .map(|kp| postcard::from_bytes::<openmls::prelude::KeyPackage>(&kp.keypackage))
+            .collect::<postcard::Result<_>>()?;
+        output.write(EntityType::MlsKeypackages, &keypackages)?;
+    }
+
+    if args.dumps(EntityType::E2eiEnrollments) {
+        let e2ei_enrollments: Vec<core_crypto::prelude::E2eiEnrollment> = keystore
+            .find_all::<E2eiEnrollment>(Default::default())
+            .await?
+            .into_iter()
+            .map(|enrollment| serde_json::from_slice::<core_crypto::prelude::E2eiEnrollment>(&enrollment.content))
+            .collect::<serde_json::Result<_>>()?;
+        output.write(EntityType::E2eiEnrollments, &e2ei_enrollments)?;
+    }
+
+    if args.dumps(EntityType::MlsGroups) {
+        let pgroups: Vec<openmls::prelude::MlsGroup> = keystore
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await?
+            .into_iter()
+            .filter(|pgroup| in_conversation(&pgroup.id))
+            .map(|pgroup| core_crypto_keystore::deser::<openmls::prelude::MlsGroup>(&pgroup.state))
+            .collect::<core_crypto_keystore::CryptoKeystoreResult<_>>()?;
+        output.write(EntityType::MlsGroups, &pgroups)?;
+    }
+
+    if args.dumps(EntityType::MlsConversations) {
+        let pgroups = keystore.find_all::<PersistedMlsGroup>(Default::default()).await?;
+        let mut conversations = vec![];
+        for pgroup in pgroups.iter().filter(|pgroup| in_conversation(&pgroup.id)) {
+            conversations.push(conversation::ConversationSummary::new(keystore, &pgroups, pgroup).await?);
+        }
+        let pending_groups = keystore
+            .find_all::<PersistedMlsPendingGroup>(Default::default())
+            .await?;
+        for pending_group in pending_groups.iter().filter(|pgroup| in_conversation(&pgroup.id)) {
+            conversations
+                .push(conversation::ConversationSummary::new_pending(keystore, &pgroups, pending_group).await?);
+        }
+        output.write(EntityType::MlsConversations, &conversations)?;
+    }
 
-    if let Some(proteus_identity) = keystore.find::<ProteusIdentity>(ProteusIdentity::ID).await? {
+    if args.dumps(EntityType::MlsPendingGroups) {
+        let pegroups: Vec<openmls::prelude::MlsGroup> = keystore
+            .find_all::<PersistedMlsPendingGroup>(Default::default())
+            .await?
+            .into_iter()
+            .filter(|pgroup| in_conversation(&pgroup.id))
+            .map(|pgroup| core_crypto_keystore::deser::<openmls::prelude::MlsGroup>(&pgroup.state))
+            .collect::<core_crypto_keystore::CryptoKeystoreResult<_>>()?;
+        output.write(EntityType::MlsPendingGroups, &pegroups)?;
+    }
+
+    let dumps_proteus = [
+        EntityType::ProteusIdentity,
+        EntityType::ProteusPrekeys,
+        EntityType::ProteusSessions,
+    ]
+    .into_iter()
+    .any(|entity_type| args.dumps(entity_type));
+    if dumps_proteus && let Some(proteus_identity) = keystore.find::<ProteusIdentity>(ProteusIdentity::ID).await? {
         let identity = {
             let sk = proteus_identity.sk_raw();
             let pk = proteus_identity.pk_raw();
             proteus_wasm::keys::IdentityKeyPair::from_raw_key_pair(*sk, *pk)?
         };
-        json_map.serialize_entry("proteus_identity", &identity)?;
+        if args.dumps(EntityType::ProteusIdentity) {
+            output.write(EntityType::ProteusIdentity, &identity)?;
+        }
 
-        let prekeys: Vec<proteus_wasm::keys::PreKey> = keystore
-            .find_all::<ProteusPrekey>(Default::default())
-            .await?
-            .into_iter()
-            .map(|pk| proteus_wasm::keys::PreKey::deserialise(&pk.prekey))
-            .collect::<Result<Vec<_>, proteus_wasm::DecodeError>>()?;
-        json_map.serialize_entry("proteus_prekeys", &prekeys)?;
+        if args.dumps(EntityType::ProteusPrekeys) {
+            let prekeys: Vec<proteus_wasm::keys::PreKey> = keystore
+                .find_all::<ProteusPrekey>(Default::default())
+                .await?
+                .into_iter()
+                .map(|pk| proteus_wasm::keys::PreKey::deserialise(&pk.prekey))
+                .collect::<Result<Vec<_>, proteus_wasm::DecodeError>>()?;
+            output.write(EntityType::ProteusPrekeys, &prekeys)?;
+        }
 
-        let proteus_sessions: Vec<proteus_wasm::session::Session<proteus_wasm::keys::IdentityKeyPair>> = keystore
-            .find_all::<ProteusSession>(Default::default())
-            .await?
-            .into_iter()
-            .map(|session| proteus_wasm::session::Session::deserialise(identity.clone(), &session.session))
-            .collect::<Result<Vec<_>, proteus_wasm::DecodeError>>()?;
+        if args.dumps(EntityType::ProteusSessions) {
+            let proteus_sessions: Vec<proteus_wasm::session::Session<proteus_wasm::keys::IdentityKeyPair>> = keystore
+                .find_all::<ProteusSession>(Default::default())
+                .await?
+                .into_iter()
+                .map(|session| proteus_wasm::session::Session::deserialise(identity.clone(), &session.session))
+                .collect::<Result<Vec<_>, proteus_wasm::DecodeError>>()?;
+            output.write(EntityType::ProteusSessions, &proteus_sessions)?;
+        }
+    }
+
+    Ok(())
+}
 
-        json_map.serialize_entry("proteus_sessions", &proteus_sessions)?;
+/// Opens the keystore, or an in-memory copy of it if requested.
+///
+/// Opening a keystore may migrate it, so the copy is a snapshot of the database taken with the online
+/// backup API and migrated in memory. The snapshot is consistent even if the database is in use.
+#[cfg(not(target_family = "wasm"))]
+async fn open_keystore(
+    args: &Args,
+    key: &core_crypto_keystore::DatabaseKey,
+) -> anyhow::Result<core_crypto_keystore::Database> {
+    use core_crypto_keystore::{ConnectionType, Database as Keystore};
+
+    let wrong_key = |e: core_crypto_keystore::CryptoKeystoreError| anyhow!("The passkey is probably wrong; [err: {e}]");
+    if args.in_memory_copy {
+        Keystore::open_in_memory_copy(&args.path, key).await.map_err(wrong_key)
+    } else {
+        Keystore::open(ConnectionType::Persistent(&args.path), key)
+            .await
+            .map_err(wrong_key)
     }
+}
 
-    json_map.end()?;
+/// Writes the dangling references of the keystore to the output, removing them if requested
+#[cfg(not(target_family = "wasm"))]
+async fn check_integrity(
+    keystore: &core_crypto_keystore::Database,
+    repair: bool,
+    output: &mut output::Output,
+) -> anyhow::Result<()> {
+    use core_crypto_keystore::integrity::DanglingReference;
 
-    Ok(())
+    let dangling: Vec<serde_json::Value> = keystore
+        .check_integrity(repair)
+        .await?
+        .iter()
+        .map(|reference| {
+            let details = match reference {
+                DanglingReference::EncryptionKeyPair { pk } => serde_json::json!({ "pk": hex::encode(pk) }),
+                DanglingReference::SignatureKeyPair { pk, credential_id } => serde_json::json!({
+                    "pk": hex::encode(pk),
+                    "credential_id": hex::encode(credential_id),
+                }),
+                DanglingReference::PendingMessages { conversation_id, count } => serde_json::json!({
+                    "conversation_id": hex::encode(conversation_id),
+                    "count": count,
+                }),
+                DanglingReference::BufferedCommit { conversation_id } => {
+                    serde_json::json!({ "conversation_id": hex::encode(conversation_id) })
+                }
+                DanglingReference::ExporterSecret { id, conversation_id } => serde_json::json!({
+                    "id": hex::encode(id),
+                    "conversation_id": hex::encode(conversation_id),
+                }),
+                DanglingReference::ParentConversation { id, parent_id } => serde_json::json!({
+                    "id": hex::encode(id),
+                    "parent_id": hex::encode(parent_id),
+                }),
+            };
+            // encryption keypairs are only reported, see [DanglingReference::EncryptionKeyPair]
+            let repaired = repair && !matches!(reference, DanglingReference::EncryptionKeyPair { .. });
+            serde_json::json!({
+                "collection": reference.collection_name(),
+                "reference": details,
+                "repaired": repaired,
+            })
+        })
+        .collect();
+
+    output.write(output::EntityType::DanglingReferences, &dangling)
+}
+
+#[cfg(all(test, not(target_family = "wasm")))]
+mod tests {
+    use clap::Parser as _;
+    use core_crypto::prelude::{
+        ClientId, CoreCrypto, MlsCiphersuite, MlsConversationConfiguration, MlsCredentialType, Session, SessionConfig,
+    };
+    use core_crypto_keystore::{
+        ConnectionType, Database, DatabaseKey,
+        connection::FetchFromDatabase as _,
+        entities::{
+            E2eiEnrollment, MlsEncryptionKeyPair, MlsHpkePrivateKey, MlsPskBundle, MlsSignatureKeyPair,
+            PersistedMlsGroup, ProteusIdentity,
+        },
+    };
+    use openmls::prelude::TlsDeserializeTrait as _;
+    use serde_json::Value;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "bd4c7053-1c5a-4020-9559-cd7bf7961954:4959bc6ab12f2846@world.com";
+
+    /// Whether the bytes appear in the value, either as an array of bytes or hex-encoded in a string
+    fn contains_bytes(value: &Value, bytes: &[u8]) -> bool {
+        match value {
+            Value::String(string) => string.contains(&hex::encode(bytes)),
+            Value::Array(values) => {
+                let array = values
+                    .iter()
+                    .map(|value| value.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                    .collect::<Option<Vec<u8>>>();
+                array.is_some_and(|array| array.windows(bytes.len()).any(|window| window == bytes))
+                    || values.iter().any(|value| contains_bytes(value, bytes))
+            }
+            Value::Object(fields) => fields.values().any(|field| contains_bytes(field, bytes)),
+            _ => false,
+        }
+    }
+
+    async fn dump_document(keystore: &Database, key: &str, path: &str, redact: bool) -> Value {
+        let mut args = vec!["keystore-dump", "--key", key, path];
+        if redact {
+            args.push("--redact");
+        }
+        let args = Args::try_parse_from(args).unwrap();
+        let mut output = output::Output::new(args.format, args.redact);
+        dump(keystore, &args, &mut output).await.unwrap();
+        output.into_document()
+    }
+
+    /// Fills a keystore through a session with every kind of entity holding secrets
+    async fn fill_keystore(path: &str, key: DatabaseKey, ciphersuite: MlsCiphersuite, psk_secret: &[u8]) {
+        let config = SessionConfig::builder()
+            .persistent(path)
+            .database_key(key)
+            .client_id(ClientId::from(CLIENT_ID))
+            .ciphersuites([ciphersuite])
+            .build()
+            .validate()
+            .unwrap();
+        let cc = CoreCrypto::from(Session::try_new(config).await.unwrap());
+        let transaction = cc.new_transaction().await.unwrap();
+
+        let conversation_config = MlsConversationConfiguration {
+            ciphersuite,
+            ..Default::default()
+        };
+        transaction
+            .new_conversation(&b"conversation".to_vec(), MlsCredentialType::Basic, conversation_config)
+            .await
+            .unwrap();
+        transaction
+            .get_or_create_client_keypackages(ciphersuite, MlsCredentialType::Basic, 2)
+            .await
+            .unwrap();
+        transaction
+            .store_external_psk(ciphersuite, b"psk".to_vec(), psk_secret)
+            .await
+            .unwrap();
+
+        let enrollment = transaction
+            .e2ei_new_enrollment(
+                CLIENT_ID.into(),
+                "Alice Smith".to_string(),
+                "alice_wire".to_string(),
+                None,
+                90 * 24 * 3600,
+                ciphersuite,
+            )
+            .await
+            .unwrap();
+        transaction.e2ei_enrollment_stash(enrollment).await.unwrap();
+
+        transaction.proteus_init().await.unwrap();
+        let prekey = transaction.proteus_new_prekey(1).await.unwrap();
+        transaction
+            .proteus_session_from_prekey("session", &prekey)
+            .await
+            .unwrap();
+
+        transaction.finish().await.unwrap();
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn redaction_hides_the_secrets_of_a_real_keystore() {
+        let ciphersuite = MlsCiphersuite::default();
+        let psk_secret = (0..32).map(|i| 0xa0 ^ i).collect::<Vec<u8>>();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.db");
+        let path = path.to_str().unwrap();
+        let key = DatabaseKey::generate();
+        fill_keystore(path, key.clone(), ciphersuite, &psk_secret).await;
+
+        let keystore = Database::open(ConnectionType::Persistent(path), &key).await.unwrap();
+        let mut secrets = vec![("external psk", psk_secret)];
+        for keypair in keystore
+            .find_all::<MlsSignatureKeyPair>(Default::default())
+            .await
+            .unwrap()
+        {
+            let keypair =
+                openmls_basic_credential::SignatureKeyPair::tls_deserialize(&mut keypair.keypair.as_slice()).unwrap();
+            secrets.push(("signature private key", keypair.private().to_vec()));
+        }
+        for private_key in keystore
+            .find_all::<MlsHpkePrivateKey>(Default::default())
+            .await
+            .unwrap()
+        {
+            let private_key = postcard::from_bytes::<openmls_traits::types::HpkePrivateKey>(&private_key.sk).unwrap();
+            secrets.push(("hpke private key", private_key.to_vec()));
+        }
+        for keypair in keystore
+            .find_all::<MlsEncryptionKeyPair>(Default::default())
+            .await
+            .unwrap()
+        {
+            let keypair = postcard::from_bytes::<openmls_traits::types::HpkeKeyPair>(&keypair.sk).unwrap();
+            secrets.push(("hpke keypair", keypair.private.to_vec()));
+        }
+        for group in keystore
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .unwrap()
+        {
+            let group = core_crypto_keystore::deser::<openmls::prelude::MlsGroup>(&group.state).unwrap();
+            let resumption_psk = group.get_past_resumption_psk(group.epoch()).unwrap();
+            secrets.push(("group resumption secret", resumption_psk.as_slice().to_vec()));
+        }
+        for enrollment in keystore.find_all::<E2eiEnrollment>(Default::default()).await.unwrap() {
+            let enrollment = serde_json::from_slice::<Value>(&enrollment.content).unwrap();
+            let sign_sk = serde_json::from_value::<Vec<u8>>(enrollment["sign_sk"].clone()).unwrap();
+            secrets.push(("enrollment signature key", sign_sk));
+        }
+        let identity = keystore
+            .find::<ProteusIdentity>(ProteusIdentity::ID)
+            .await
+            .unwrap()
+            .unwrap();
+        secrets.push(("proteus identity", identity.sk_raw()[..32].to_vec()));
+
+        for name in [
+            "signature private key",
+            "hpke private key",
+            "hpke keypair",
+            "group resumption secret",
+            "enrollment signature key",
+        ] {
+            assert!(
+                secrets.iter().any(|(secret_name, _)| *secret_name == name),
+                "no {name} in the keystore"
+            );
+        }
+
+        let key = hex::encode(&key);
+        let document = dump_document(&keystore, &key, path, false).await;
+        let redacted = dump_document(&keystore, &key, path, true).await;
+        assert!(!document["proteus_sessions"].as_array().unwrap().is_empty());
+        for (name, secret) in &secrets {
+            assert!(contains_bytes(&document, secret), "{name} is not dumped");
+            assert!(!contains_bytes(&redacted, secret), "{name} is not redacted");
+        }
+    }
+
+    #[test]
+    fn repair_cannot_be_combined_with_in_memory_copy() {
+        let parse =
+            |args: &[&str]| Args::try_parse_from([["keystore-dump", "--key", "00"].as_slice(), args].concat()).unwrap();
+
+        assert!(parse(&["--in-memory-copy", "path", "check"]).validate().is_ok());
+        assert!(parse(&["path", "check", "--repair"]).validate().is_ok());
+        assert!(
+            parse(&["--in-memory-copy", "path", "check", "--repair"])
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[macro_rules_attribute::apply(smol_macros::test)]
+    async fn in_memory_copy_snapshots_a_database_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.db");
+        let path = path.to_str().unwrap();
+        let key = DatabaseKey::generate();
+        let bundle = |id: &[u8]| MlsPskBundle {
+            psk_id: id.to_vec(),
+            psk: vec![0xa0; 32],
+        };
+
+        // the database stays open, so the saved bundle may only be in its WAL
+        let keystore = Database::open(ConnectionType::Persistent(path), &key).await.unwrap();
+        keystore.new_transaction().await.unwrap();
+        keystore.save(bundle(b"before")).await.unwrap();
+        keystore.commit_transaction().await.unwrap();
+
+        let args =
+            Args::try_parse_from(["keystore-dump", "--key", &hex::encode(&key), "--in-memory-copy", path]).unwrap();
+        let copy = open_keystore(&args, &key).await.unwrap();
+        assert!(copy.find::<MlsPskBundle>(b"before").await.unwrap().is_some());
+
+        copy.new_transaction().await.unwrap();
+        copy.save(bundle(b"copy")).await.unwrap();
+        copy.commit_transaction().await.unwrap();
+        keystore.new_transaction().await.unwrap();
+        keystore.save(bundle(b"after")).await.unwrap();
+        keystore.commit_transaction().await.unwrap();
+
+        assert!(copy.find::<MlsPskBundle>(b"after").await.unwrap().is_none());
+        assert!(keystore.find::<MlsPskBundle>(b"copy").await.unwrap().is_none());
+    }
 }
@@ -0,0 +1,176 @@
+use std::io::Write as _;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Kinds of entities the keystore can be dumped as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum EntityType {
+    MlsCredentials,
+    MlsSignatureKeypairs,
+    MlsHpkePrivateKeys,
+    MlsHpkeKeypairs,
+    ExternalPsks,
+    MlsKeypackages,
+    E2eiEnrollments,
+    MlsGroups,
+    /// Summaries of the conversations, decoded from their group state
+    MlsConversations,
+    MlsPendingGroups,
+    ProteusIdentity,
+    ProteusPrekeys,
+    ProteusSessions,
+    /// Entities referring to ones which are not in the keystore anymore, written by the `check` command
+    #[value(skip)]
+    DanglingReferences,
+}
+
+impl EntityType {
+    /// Name of the entities in the output
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MlsCredentials => "mls_credentials",
+            Self::MlsSignatureKeypairs => "mls_signature_keypairs",
+            Self::MlsHpkePrivateKeys => "mls_hpke_private_keys",
+            Self::MlsHpkeKeypairs => "mls_hpke_keypairs",
+            Self::ExternalPsks => "external_psks",
+            Self::MlsKeypackages => "mls_keypackages",
+            Self::E2eiEnrollments => "e2ei_enrollments",
+            Self::MlsGroups => "mls_groups",
+            Self::MlsConversations => "mls_conversations",
+            Self::MlsPendingGroups => "mls_pending_groups",
+            Self::ProteusIdentity => "proteus_identity",
+            Self::ProteusPrekeys => "proteus_prekeys",
+            Self::ProteusSessions => "proteus_sessions",
+            Self::DanglingReferences => "dangling_references",
+        }
+    }
+
+    /// Whether the entities belong to a conversation
+    pub fn is_conversation_scoped(self) -> bool {
+        matches!(self, Self::MlsGroups | Self::MlsConversations | Self::MlsPendingGroups)
+    }
+
+    /// Whether each entity is a secret as a whole, rather than a structure holding secrets
+    fn is_secret(self) -> bool {
+        matches!(self, Self::MlsHpkePrivateKeys)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// A single pretty-printed JSON object, with the entities of each type in an array
+    Json,
+    /// One JSON object per line and per entity, tagged with its type
+    Ndjson,
+}
+
+/// Writes the dumped entities to stdout
+pub struct Output {
+    format: Format,
+    redact: bool,
+    /// Entities of each type, in the order they were dumped, when writing a single JSON object
+    document: Vec<(&'static str, Value)>,
+}
+
+impl Output {
+    pub fn new(format: Format, redact: bool) -> Self {
+        Self {
+            format,
+            redact,
+            document: Vec::new(),
+        }
+    }
+
+    /// Writes the entities of a type. A sequence is written as one entity per element.
+    pub fn write(&mut self, entity_type: EntityType, entities: impl Serialize) -> anyhow::Result<()> {
+        let mut entities = serde_json::to_value(entities)?;
+        if self.redact {
+            redact(entity_type, &mut entities);
+        }
+
+        match self.format {
+            Format::Json => self.document.push((entity_type.name(), entities)),
+            Format::Ndjson => {
+                let entities = match entities {
+                    Value::Array(entities) => entities,
+                    entity => vec![entity],
+                };
+                let mut stdout = std::io::stdout().lock();
+                for entity in entities {
+                    let line = serde_json::json!({ "type": entity_type.name(), "value": entity });
+                    serde_json::to_writer(&mut stdout, &line)?;
+                    writeln!(stdout)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        if self.format == Format::Json {
+            let mut serializer = serde_json::Serializer::pretty(std::io::stdout());
+            serde::Serializer::collect_map(&mut serializer, self.document)?;
+        }
+        Ok(())
+    }
+
+    /// The JSON object [Self::finish] would write, instead of writing it
+    #[cfg(test)]
+    pub fn into_document(self) -> Value {
+        Value::Object(
+            self.document
+                .into_iter()
+                .map(|(name, entities)| (name.to_string(), entities))
+                .collect(),
+        )
+    }
+}
+
+/// Replaces the secrets of dumped entities by fingerprints.
+///
+/// Secrets are found by the name of the fields holding them, since the entities are only known through their
+/// serialization.
+fn redact(entity_type: EntityType, entities: &mut Value) {
+    match entities {
+        Value::Array(entities) if entity_type.is_secret() => {
+            entities.iter_mut().for_each(|entity| *entity = fingerprint(entity));
+        }
+        entities => redact_fields(entities),
+    }
+}
+
+fn redact_fields(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                if is_secret_field(name) {
+                    *field = fingerprint(field);
+                } else {
+                    redact_fields(field);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_fields),
+        _ => {}
+    }
+}
+
+fn is_secret_field(name: &str) -> bool {
+    const SECRET_KEYS: [&str; 5] = ["root_key", "chain_key", "cipher_key", "mac_key", "resumption_psk"];
+
+    let name = name.to_ascii_lowercase();
+    ["secret", "private"].iter().any(|word| name.contains(word))
+        || name == "sk"
+        || name.ends_with("_sk")
+        || name.ends_with("_kp")
+        || SECRET_KEYS.contains(&name.as_str())
+}
+
+/// A hash of the value, salted for this run with [obfuscate::compute_hash]: fingerprints can be compared within
+/// a dump, but not across dumps.
+fn fingerprint(value: &Value) -> Value {
+    let hash = obfuscate::compute_hash(value.to_string().as_bytes());
+    Value::String(format!("fingerprint:{}", hex::encode(hash)))
+}
@@ -0,0 +1,265 @@
+//! Encrypted, portable backups of the whole content of a [Database].
+//!
+//! A backup holds every entity of the keystore, serialized independently of the storage backend, so
+//! that it can be restored on SQLCipher as well as on IndexedDB. Its layout is:
+//!
+//! - [BACKUP_MAGIC]
+//! - [BACKUP_FORMAT_VERSION], as a big-endian `u16`
+//! - a 12 bytes random nonce
+//! - the entities, encrypted with AES-256-GCM under the caller-supplied key, authenticating the
+//!   magic and the version as associated data
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit as _, Nonce,
+    aead::{Aead as _, Payload},
+};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+#[cfg(feature = "proteus-keystore")]
+use crate::entities::{ProteusIdentity, ProteusPrekey, ProteusSession};
+use crate::{
+    CryptoKeystoreError, CryptoKeystoreResult,
+    connection::{Database, DatabaseKey, FetchFromDatabase as _, KeystoreDatabaseConnection},
+    entities::{
+        ConsumerData, E2eiAcmeCA, E2eiCrl, E2eiEnrollment, E2eiIntermediateCert, Entity, EntityTransactionExt,
+        MlsBufferedCommit, MlsConversationReInit, MlsCredential, MlsE2eiStatus, MlsEncryptionKeyPair,
+        MlsEpochEncryptionKeyPair, MlsExporterSecret, MlsExternalPsk, MlsHistorySecret, MlsHpkePrivateKey,
+        MlsKeyPackage, MlsKeyPackageLifetime, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
+        PersistedMlsPendingGroup,
+    },
+    transaction::KeystoreTransaction,
+};
+
+/// Leading bytes of every backup
+pub const BACKUP_MAGIC: &[u8; 8] = b"CCKSBKUP";
+/// Version of the backup layout, bumped whenever an entity or the layout changes incompatibly
+pub const BACKUP_FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = BACKUP_MAGIC.len() + size_of::<u16>();
+const NONCE_LEN: usize = 12;
+
+/// The records of one entity type. They hold secrets in the clear, so they are wiped once dropped.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct BackupCollection {
+    pub(crate) name: String,
+    /// Each record, serialized on its own
+    pub(crate) records: Vec<Vec<u8>>,
+}
+
+/// Something done with the entity type of a collection, see [visit_collection]
+pub(crate) trait CollectionVisitor {
+    type Output;
+
+    fn visit<E: Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt + Sync>(
+        self,
+    ) -> CryptoKeystoreResult<Self::Output>;
+}
+
+impl BackupCollection {
+    async fn export<E: Entity<ConnectionType = KeystoreDatabaseConnection>>(
+        db: &Database,
+    ) -> CryptoKeystoreResult<Self> {
+        let records = db
+            .find_all::<E>(Default::default())
+            .await?
+            .iter()
+            .map(postcard::to_stdvec)
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: E::COLLECTION_NAME.to_string(),
+            records,
+        })
+    }
+
+    async fn restore<E: Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt + Sync>(
+        &self,
+        transaction: &KeystoreTransaction,
+    ) -> CryptoKeystoreResult<()> {
+        for record in &self.records {
+            transaction.restore(postcard::from_bytes::<E>(record)?).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the entities making up a backup. Refresh tokens only exist in IndexedDB and are not used
+/// anymore, so they are left out.
+macro_rules! backup_collections {
+    ($($(#[$meta:meta])* $entity:ty),* $(,)?) => {
+        pub(crate) async fn export_collections(db: &Database) -> CryptoKeystoreResult<Vec<BackupCollection>> {
+            let mut collections = Vec::new();
+            $(
+                $(#[$meta])*
+                collections.push(BackupCollection::export::<$entity>(db).await?);
+            )*
+            collections.retain(|collection| !collection.records.is_empty());
+            Ok(collections)
+        }
+
+        async fn restore_collection(
+            transaction: &KeystoreTransaction,
+            collection: &BackupCollection,
+        ) -> CryptoKeystoreResult<()> {
+            $(
+                $(#[$meta])*
+                if collection.name == <$entity>::COLLECTION_NAME {
+                    return collection.restore::<$entity>(transaction).await;
+                }
+            )*
+            Err(CryptoKeystoreError::InvalidBackup(format!(
+                "unknown collection \"{}\"",
+                collection.name
+            )))
+        }
+
+        /// Calls the visitor with the entity type stored in the collection having this name
+        pub(crate) fn visit_collection<V: CollectionVisitor>(name: &str, visitor: V) -> CryptoKeystoreResult<V::Output> {
+            $(
+                $(#[$meta])*
+                if name == <$entity>::COLLECTION_NAME {
+                    return visitor.visit::<$entity>();
+                }
+            )*
+            Err(CryptoKeystoreError::InvalidBackup(format!("unknown collection \"{name}\"")))
+        }
+    };
+}
+
+backup_collections!(
+    ConsumerData,
+    MlsCredential,
+    MlsSignatureKeyPair,
+    MlsHpkePrivateKey,
+    MlsEncryptionKeyPair,
+    MlsEpochEncryptionKeyPair,
+    MlsPskBundle,
+    MlsKeyPackage,
+    PersistedMlsGroup,
+    PersistedMlsPendingGroup,
+    MlsPendingMessage,
+    MlsBufferedCommit,
+    MlsExporterSecret,
+    MlsConversationReInit,
+    MlsKeyPackageLifetime,
+    MlsExternalPsk,
+    MlsE2eiStatus,
+    MlsHistorySecret,
+    E2eiEnrollment,
+    E2eiAcmeCA,
+    E2eiIntermediateCert,
+    E2eiCrl,
+    #[cfg(feature = "proteus-keystore")]
+    ProteusIdentity,
+    #[cfg(feature = "proteus-keystore")]
+    ProteusPrekey,
+    #[cfg(feature = "proteus-keystore")]
+    ProteusSession,
+);
+
+fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..BACKUP_MAGIC.len()].copy_from_slice(BACKUP_MAGIC);
+    header[BACKUP_MAGIC.len()..].copy_from_slice(&BACKUP_FORMAT_VERSION.to_be_bytes());
+    header
+}
+
+fn encrypt(collections: &[BackupCollection], key: &DatabaseKey) -> CryptoKeystoreResult<Vec<u8>> {
+    let plaintext = Zeroizing::new(postcard::to_stdvec(collections)?);
+    let header = header();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = Aes256Gcm::new(key.as_ref().into())
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| CryptoKeystoreError::BackupEncryptionFailed)?;
+
+    let mut backup = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len());
+    backup.extend_from_slice(&header);
+    backup.extend_from_slice(&nonce);
+    backup.extend_from_slice(&ciphertext);
+    Ok(backup)
+}
+
+fn decrypt(backup: &[u8], key: &DatabaseKey) -> CryptoKeystoreResult<Vec<BackupCollection>> {
+    if backup.len() < HEADER_LEN + NONCE_LEN || !backup.starts_with(BACKUP_MAGIC) {
+        return Err(CryptoKeystoreError::InvalidBackup("not a keystore backup".to_string()));
+    }
+    let (header, rest) = backup.split_at(HEADER_LEN);
+    let version = u16::from_be_bytes([header[BACKUP_MAGIC.len()], header[BACKUP_MAGIC.len() + 1]]);
+    if version != BACKUP_FORMAT_VERSION {
+        return Err(CryptoKeystoreError::UnsupportedBackupVersion(version));
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let plaintext = Aes256Gcm::new(key.as_ref().into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| CryptoKeystoreError::BackupDecryptionFailed)?;
+    Ok(postcard::from_bytes(&plaintext)?)
+}
+
+impl Database {
+    /// Exports every entity of this database into a backup encrypted under `key`, see [crate::backup]
+    /// for its layout.
+    ///
+    /// No transaction must be in progress, so that only committed records are exported.
+    pub async fn export_backup(&self, key: &DatabaseKey) -> CryptoKeystoreResult<Vec<u8>> {
+        if self.transaction.lock().await.is_some() {
+            return Err(CryptoKeystoreError::TransactionInProgress {
+                attempted_operation: "export_backup()".to_string(),
+            });
+        }
+        let collections = export_collections(self).await?;
+        encrypt(&collections, key)
+    }
+
+    /// Restores every entity of a backup made by [Database::export_backup] into this database, which
+    /// is meant to be empty. Records having the same id as a restored one are replaced.
+    ///
+    /// The backup is authenticated before anything is written, and restored within a single
+    /// transaction: either all of it or nothing ends up in the database. No transaction must be in
+    /// progress.
+    pub async fn import_backup(&self, backup: &[u8], key: &DatabaseKey) -> CryptoKeystoreResult<()> {
+        if self.transaction.lock().await.is_some() {
+            return Err(CryptoKeystoreError::TransactionInProgress {
+                attempted_operation: "import_backup()".to_string(),
+            });
+        }
+        let collections = decrypt(backup, key)?;
+        self.restore_collections(&collections).await
+    }
+
+    /// Saves these collections within a new transaction, committed only if all of them could be saved
+    pub(crate) async fn restore_collections(&self, collections: &[BackupCollection]) -> CryptoKeystoreResult<()> {
+        self.new_transaction().await?;
+        let restored = async {
+            let transaction_guard = self.transaction.lock().await;
+            let Some(transaction) = transaction_guard.as_ref() else {
+                return Err(CryptoKeystoreError::MutatingOperationWithoutTransaction);
+            };
+            for collection in collections {
+                restore_collection(transaction, collection).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        match restored {
+            Ok(()) => self.commit_transaction().await,
+            Err(err) => {
+                self.rollback_transaction().await?;
+                Err(err)
+            }
+        }
+    }
+}
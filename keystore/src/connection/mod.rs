@@ -20,7 +20,10 @@ pub mod platform {
 }
 
 pub use self::platform::*;
-use crate::entities::{Entity, EntityFindParams, MlsPendingMessage, StringEntityId};
+use crate::entities::{
+    Entity, EntityFindParams, MlsExporterSecret, MlsHistorySecret, MlsKeyPackage, MlsKeyPackageCount,
+    MlsPendingMessage, StringEntityId,
+};
 use std::ops::DerefMut;
 
 use crate::entities::{EntityTransactionExt, UniqueEntity};
@@ -180,16 +183,28 @@ pub enum ConnectionType<'a> {
 impl Database {
     pub async fn open(location: ConnectionType<'_>, key: &DatabaseKey) -> CryptoKeystoreResult<Self> {
         let conn = match location {
-            ConnectionType::Persistent(name) => KeystoreDatabaseConnection::open(name, key).await?.into(),
-            ConnectionType::InMemory => KeystoreDatabaseConnection::open_in_memory(key).await?.into(),
+            ConnectionType::Persistent(name) => KeystoreDatabaseConnection::open(name, key).await?,
+            ConnectionType::InMemory => KeystoreDatabaseConnection::open_in_memory(key).await?,
         };
+        Ok(Self::from_connection(conn))
+    }
+
+    /// Opens an in-memory copy of the database at `path`. The copy is a consistent snapshot, even if the
+    /// database is in use, and the database itself is only read.
+    #[cfg(not(target_family = "wasm"))]
+    pub async fn open_in_memory_copy(path: &str, key: &DatabaseKey) -> CryptoKeystoreResult<Self> {
+        let conn = KeystoreDatabaseConnection::open_in_memory_copy(path, key).await?;
+        Ok(Self::from_connection(conn))
+    }
+
+    fn from_connection(conn: KeystoreDatabaseConnection) -> Self {
         #[allow(clippy::arc_with_non_send_sync)] // see https://github.com/rustwasm/wasm-bindgen/pull/955
-        let conn = Arc::new(conn);
-        Ok(Self {
+        let conn = Arc::new(conn.into());
+        Self {
             conn,
             transaction: Default::default(),
             transaction_semaphore: Arc::new(Semaphore::new(ALLOWED_CONCURRENT_TRANSACTIONS_COUNT)),
-        })
+        }
     }
 
     pub async fn borrow_conn(&self) -> CryptoKeystoreResult<MutexGuard<'_, KeystoreDatabaseConnection>> {
@@ -327,6 +342,118 @@ impl Database {
             .await
     }
 
+    /// Exporter secrets retained for this conversation, ordered by epoch
+    pub async fn find_exporter_secrets_by_conversation_id(
+        &self,
+        conversation_id: &[u8],
+    ) -> CryptoKeystoreResult<Vec<MlsExporterSecret>> {
+        let mut conn = self.conn.lock().await;
+        let persisted_records = MlsExporterSecret::find_all_by_conversation_id(&mut conn, conversation_id).await?;
+
+        let transaction_guard = self.transaction.lock().await;
+        let Some(transaction) = transaction_guard.as_ref() else {
+            return Ok(persisted_records);
+        };
+        transaction
+            .find_exporter_secrets_by_conversation_id(conversation_id, persisted_records)
+            .await
+    }
+
+    /// History secrets archived for this conversation, ordered by epoch
+    pub async fn find_history_secrets_by_conversation_id(
+        &self,
+        conversation_id: &[u8],
+    ) -> CryptoKeystoreResult<Vec<MlsHistorySecret>> {
+        let mut conn = self.conn.lock().await;
+        let persisted_records = MlsHistorySecret::find_all_by_conversation_id(&mut conn, conversation_id).await?;
+
+        let transaction_guard = self.transaction.lock().await;
+        let Some(transaction) = transaction_guard.as_ref() else {
+            return Ok(persisted_records);
+        };
+        transaction
+            .find_history_secrets_by_conversation_id(conversation_id, persisted_records)
+            .await
+    }
+
+    /// Key packages of this ciphersuite and credential type, either the last resort ones or the others
+    pub async fn find_key_packages(
+        &self,
+        ciphersuite: u16,
+        credential_type: u8,
+        last_resort: bool,
+        params: EntityFindParams,
+    ) -> CryptoKeystoreResult<Vec<MlsKeyPackage>> {
+        let mut conn = self.conn.lock().await;
+        let transaction_guard = self.transaction.lock().await;
+        let Some(transaction) = transaction_guard.as_ref() else {
+            return MlsKeyPackage::find_all_by_ciphersuite_and_credential_type(
+                &mut conn,
+                ciphersuite,
+                credential_type,
+                last_resort,
+                params,
+            )
+            .await;
+        };
+
+        // key packages changed within the transaction shift the pages, which are only cut once merged
+        let persisted_records = MlsKeyPackage::find_all_by_ciphersuite_and_credential_type(
+            &mut conn,
+            ciphersuite,
+            credential_type,
+            last_resort,
+            EntityFindParams {
+                limit: None,
+                offset: None,
+                reverse: params.reverse,
+            },
+        )
+        .await?;
+        transaction
+            .find_key_packages(persisted_records, params, |kp| {
+                kp.ciphersuite() == Some(ciphersuite)
+                    && kp.credential_type() == Some(credential_type)
+                    && kp.is_last_resort() == last_resort
+            })
+            .await
+    }
+
+    /// Counts the key packages other than the last resort ones per ciphersuite, credential type and expiry,
+    /// see [MlsKeyPackage::expiry]. Key packages lacking metadata are not counted.
+    pub async fn count_key_packages(&self, now: u64, soon: u64) -> CryptoKeystoreResult<Vec<MlsKeyPackageCount>> {
+        let mut conn = self.conn.lock().await;
+        let transaction_guard = self.transaction.lock().await;
+        let Some(transaction) = transaction_guard.as_ref() else {
+            return MlsKeyPackage::count_by_ciphersuite_and_credential_type(&mut conn, now, soon, &[]).await;
+        };
+
+        // key packages changed within the transaction are counted as they are in it
+        let (changed_ids, saved_records) = transaction.changed_key_packages().await?;
+        let mut counts =
+            MlsKeyPackage::count_by_ciphersuite_and_credential_type(&mut conn, now, soon, &changed_ids).await?;
+        for key_package in &saved_records {
+            MlsKeyPackageCount::add(&mut counts, key_package, now, soon);
+        }
+        MlsKeyPackageCount::sort(&mut counts);
+        Ok(counts)
+    }
+
+    /// Key packages stored before their ciphersuite and credential type were recorded, see
+    /// [MlsKeyPackage::set_metadata]
+    pub async fn find_key_packages_without_metadata(&self) -> CryptoKeystoreResult<Vec<MlsKeyPackage>> {
+        let mut conn = self.conn.lock().await;
+        let persisted_records = MlsKeyPackage::find_all_without_metadata(&mut conn).await?;
+
+        let transaction_guard = self.transaction.lock().await;
+        let Some(transaction) = transaction_guard.as_ref() else {
+            return Ok(persisted_records);
+        };
+        transaction
+            .find_key_packages(persisted_records, Default::default(), |kp| !kp.has_metadata())
+            .await
+    }
+
     pub async fn cred_delete_by_credential(&self, cred: Vec<u8>) -> CryptoKeystoreResult<()> {
         let transaction_guard = self.transaction.lock().await;
         let Some(transaction) = transaction_guard.as_ref() else {
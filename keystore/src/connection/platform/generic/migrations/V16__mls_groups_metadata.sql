@@ -0,0 +1,2 @@
+ALTER TABLE mls_groups ADD COLUMN custom_configuration BLOB;
+ALTER TABLE mls_groups ADD COLUMN last_self_update BLOB;
@@ -0,0 +1,7 @@
+CREATE TABLE mls_exporter_secrets (
+    id_hex TEXT UNIQUE,
+    conversation_id BLOB,
+    epoch BLOB,
+    label BLOB,
+    secret BLOB
+);
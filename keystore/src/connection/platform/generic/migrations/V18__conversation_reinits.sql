@@ -0,0 +1,5 @@
+CREATE TABLE mls_conversation_reinits (
+    id_hex TEXT UNIQUE,
+    new_id BLOB,
+    ciphersuite BLOB
+);
@@ -0,0 +1,2 @@
+ALTER TABLE mls_pending_messages ADD COLUMN received_at BLOB;
+ALTER TABLE mls_buffered_commits ADD COLUMN received_at BLOB;
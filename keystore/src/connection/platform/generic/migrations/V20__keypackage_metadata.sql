@@ -0,0 +1,5 @@
+ALTER TABLE mls_keypackages ADD COLUMN ciphersuite BLOB;
+ALTER TABLE mls_keypackages ADD COLUMN credential_type BLOB;
+ALTER TABLE mls_keypackages ADD COLUMN expires_at BLOB;
+ALTER TABLE mls_keypackages ADD COLUMN uploaded_at BLOB;
+CREATE INDEX mls_keypackages_ciphersuite_credential_type ON mls_keypackages (ciphersuite, credential_type);
@@ -0,0 +1 @@
+ALTER TABLE mls_keypackages ADD COLUMN last_resort BLOB;
@@ -0,0 +1,7 @@
+CREATE TABLE mls_history_secrets (
+    id_hex TEXT UNIQUE,
+    conversation_id BLOB,
+    epoch BLOB,
+    secret BLOB,
+    welcome BLOB
+);
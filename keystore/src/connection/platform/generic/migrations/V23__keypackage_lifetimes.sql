@@ -0,0 +1,4 @@
+CREATE TABLE mls_keypackage_lifetimes (
+    id_hex TEXT UNIQUE,
+    lifetime BLOB
+);
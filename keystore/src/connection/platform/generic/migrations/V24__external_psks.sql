@@ -0,0 +1,4 @@
+CREATE TABLE mls_external_psks (
+    id_hex TEXT UNIQUE,
+    psk_bundle_id BLOB
+);
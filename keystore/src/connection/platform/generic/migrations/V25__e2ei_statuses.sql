@@ -0,0 +1,4 @@
+CREATE TABLE mls_e2ei_statuses (
+    id_hex TEXT UNIQUE,
+    status BLOB
+);
@@ -142,6 +142,43 @@ impl SqlCipherConnection {
         Ok(conn)
     }
 
+    fn init_in_memory_copy_with_key(path: &str, key: &DatabaseKey) -> CryptoKeystoreResult<Self> {
+        let mut source = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Self::set_key(&mut source, key)?;
+
+        let mut conn = rusqlite::Connection::open("")?;
+
+        #[cfg(feature = "log-queries")]
+        conn.trace_v2(TraceEventCodes::SQLITE_TRACE_STMT, Some(Self::log_query));
+
+        // The copy has to be encrypted with the same key as the source for SQLCipher to back it up
+        Self::set_key(&mut conn, key)?;
+        // The online backup API copies a consistent snapshot of the database, its WAL included, and
+        // starts over whenever the database is written to in between
+        rusqlite::backup::Backup::new(&source, &mut conn)?.run_to_completion(128, std::time::Duration::ZERO, None)?;
+        source.close().map_err(|(_, e)| e)?;
+
+        // Disable FOREIGN KEYs - The 2 step blob writing process invalidates foreign key checks unfortunately
+        conn.pragma_update(None, "foreign_keys", "OFF")?;
+
+        // The copy may be of an older version of the database
+        Self::run_migrations(&mut conn)?;
+
+        let conn = Self {
+            path: "".into(),
+            conn: Mutex::new(conn),
+        };
+
+        Ok(conn)
+    }
+
+    /// Opens an in-memory copy of the database at `path`, see [crate::Database::open_in_memory_copy]
+    pub(crate) async fn open_in_memory_copy(path: &str, key: &DatabaseKey) -> CryptoKeystoreResult<Self> {
+        let path = path.to_string();
+        let key = key.clone();
+        unblock(move || Self::init_in_memory_copy_with_key(&path, &key)).await
+    }
+
     pub async fn migrate_db_key_type_to_bytes(
         path: &str,
         old_key: &str,
@@ -53,6 +53,24 @@ impl Metabuilder {
         self
     }
 
+    /// Changes an existing object store, e.g. to add an index.
+    pub(super) fn update_object_store(
+        mut self,
+        object_store_name: &str,
+        update: impl FnOnce(ObjectStoreBuilder) -> ObjectStoreBuilder,
+    ) -> Self {
+        let object_store = self.object_stores.get_mut(object_store_name);
+        debug_assert!(
+            object_store.is_some(),
+            "we should not be updating object stores which do not exist"
+        );
+        if let Some(object_store) = object_store {
+            let previous = std::mem::replace(object_store, ObjectStoreBuilder::new(object_store_name));
+            *object_store = update(previous);
+        }
+        self
+    }
+
     /// Builds the database.
     pub(super) async fn build(self) -> Result<Database, idb::Error> {
         let mut builder = DatabaseBuilder::new(&self.name);
@@ -2,10 +2,17 @@ mod db_key_type_to_bytes;
 mod metabuilder;
 mod pre_v4;
 mod v0;
+mod v10;
+mod v11;
+mod v12;
 mod v2;
 mod v3;
 mod v4;
 mod v5;
+mod v6;
+mod v7;
+mod v8;
+mod v9;
 
 pub(super) use db_key_type_to_bytes::migrate_db_key_type_to_bytes;
 use metabuilder::Metabuilder;
@@ -28,11 +35,20 @@ const DB_VERSION_2: u32 = db_version_number(2);
 const DB_VERSION_3: u32 = db_version_number(3);
 const DB_VERSION_4: u32 = db_version_number(4);
 const DB_VERSION_5: u32 = db_version_number(5);
+const DB_VERSION_6: u32 = db_version_number(6);
+const DB_VERSION_7: u32 = db_version_number(7);
+const DB_VERSION_8: u32 = db_version_number(8);
+const DB_VERSION_9: u32 = db_version_number(9);
+const DB_VERSION_10: u32 = db_version_number(10);
+const DB_VERSION_11: u32 = db_version_number(11);
+const DB_VERSION_12: u32 = db_version_number(12);
 
 /// Open an existing idb database with the given name, and migrate it if needed.
 pub(crate) async fn open_and_migrate(name: &str, key: &DatabaseKey) -> CryptoKeystoreResult<Database> {
     /// Increment when adding a new migration.
-    const TARGET_VERSION: u32 = DB_VERSION_5;
+    const TARGET_VERSION: u32 = DB_VERSION_12;
+    // IndexedDB dumps are read and written for the current version only
+    const _: () = assert!(TARGET_VERSION == crate::idb_dump::IDB_VERSION);
     let factory = Factory::new()?;
 
     let open_existing = factory.open(name, None)?;
@@ -70,6 +86,13 @@ async fn do_migration_step(from: u32, name: &str, _key: &DatabaseKey) -> CryptoK
         // need to initialize object stores.
         1 => v4::migrate(name).await,
         DB_VERSION_4 => v5::migrate(name).await,
+        DB_VERSION_5 => v6::migrate(name).await,
+        DB_VERSION_6 => v7::migrate(name).await,
+        DB_VERSION_7 => v8::migrate(name).await,
+        DB_VERSION_8 => v9::migrate(name).await,
+        DB_VERSION_9 => v10::migrate(name).await,
+        DB_VERSION_10 => v11::migrate(name).await,
+        DB_VERSION_11 => v12::migrate(name).await,
         _ => Err(CryptoKeystoreError::MigrationNotSupported(from)),
     }
 }
@@ -0,0 +1,28 @@
+use idb::{
+    KeyPath,
+    builder::{IndexBuilder, ObjectStoreBuilder},
+};
+
+use super::{DB_VERSION_10, Metabuilder};
+use crate::{
+    CryptoKeystoreResult,
+    entities::{EntityBase as _, MlsKeyPackageLifetime},
+};
+
+/// Open IDB once with the new builder and close it, this will add the new object store.
+pub(super) async fn migrate(name: &str) -> CryptoKeystoreResult<u32> {
+    let migrated_idb = get_builder(name).build().await?;
+    let version = migrated_idb.version()?;
+    migrated_idb.close();
+    Ok(version)
+}
+
+/// Add a new object store for the MlsKeyPackageLifetime struct.
+pub(super) fn get_builder(name: &str) -> Metabuilder {
+    let previous_builder = super::v9::get_builder(name);
+    previous_builder.version(DB_VERSION_10).add_object_store(
+        ObjectStoreBuilder::new(MlsKeyPackageLifetime::COLLECTION_NAME)
+            .auto_increment(false)
+            .add_index(IndexBuilder::new("id".into(), KeyPath::new_single("id")).unique(true)),
+    )
+}
@@ -0,0 +1,32 @@
+use idb::{
+    KeyPath,
+    builder::{IndexBuilder, ObjectStoreBuilder},
+};
+
+use super::{DB_VERSION_6, Metabuilder};
+use crate::{
+    CryptoKeystoreResult,
+    entities::{EntityBase as _, MlsExporterSecret},
+};
+
+/// Open IDB once with the new builder and close it, this will add the new object store.
+pub(super) async fn migrate(name: &str) -> CryptoKeystoreResult<u32> {
+    let migrated_idb = get_builder(name).build().await?;
+    let version = migrated_idb.version()?;
+    migrated_idb.close();
+    Ok(version)
+}
+
+/// Add a new object store for the MlsExporterSecret struct.
+pub(super) fn get_builder(name: &str) -> Metabuilder {
+    let previous_builder = super::v5::get_builder(name);
+    previous_builder.version(DB_VERSION_6).add_object_store(
+        ObjectStoreBuilder::new(MlsExporterSecret::COLLECTION_NAME)
+            .auto_increment(false)
+            .add_index(IndexBuilder::new("id".into(), KeyPath::new_single("id")).unique(true))
+            .add_index(IndexBuilder::new(
+                "conversation_id".into(),
+                KeyPath::new_single("conversation_id"),
+            )),
+    )
+}
@@ -0,0 +1,28 @@
+use idb::{KeyPath, builder::IndexBuilder};
+
+use super::{DB_VERSION_8, Metabuilder};
+use crate::{
+    CryptoKeystoreResult,
+    entities::{EntityBase as _, MlsKeyPackage},
+};
+
+/// Open IDB once with the new builder and close it, this will add the new index.
+pub(super) async fn migrate(name: &str) -> CryptoKeystoreResult<u32> {
+    let migrated_idb = get_builder(name).build().await?;
+    let version = migrated_idb.version()?;
+    migrated_idb.close();
+    Ok(version)
+}
+
+/// Index key packages by ciphersuite and credential type.
+pub(super) fn get_builder(name: &str) -> Metabuilder {
+    let previous_builder = super::v7::get_builder(name);
+    previous_builder
+        .version(DB_VERSION_8)
+        .update_object_store(MlsKeyPackage::COLLECTION_NAME, |object_store| {
+            object_store.add_index(IndexBuilder::new(
+                "ciphersuite_credential_type".into(),
+                KeyPath::new_array(["ciphersuite", "credential_type"]),
+            ))
+        })
+}
@@ -6,8 +6,9 @@ use crate::{
     CryptoKeystoreError, CryptoKeystoreResult,
     connection::{DatabaseConnection, DatabaseConnectionRequirements, DatabaseKey},
     entities::{
-        E2eiAcmeCA, E2eiCrl, E2eiEnrollment, E2eiIntermediateCert, Entity as _, EntityBase as _, MlsCredential,
-        MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsHpkePrivateKey, MlsKeyPackage, MlsPendingMessage,
+        E2eiAcmeCA, E2eiCrl, E2eiEnrollment, E2eiIntermediateCert, Entity as _, EntityBase as _, MlsConversationReInit,
+        MlsCredential, MlsE2eiStatus, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsExporterSecret,
+        MlsExternalPsk, MlsHistorySecret, MlsHpkePrivateKey, MlsKeyPackage, MlsKeyPackageLifetime, MlsPendingMessage,
         MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup, PersistedMlsPendingGroup, ProteusIdentity, ProteusPrekey,
         ProteusSession,
     },
@@ -96,6 +97,12 @@ impl<'a> DatabaseConnection<'a> for WasmConnection {
                         PersistedMlsGroup,
                         PersistedMlsPendingGroup,
                         MlsPendingMessage,
+                        MlsExporterSecret,
+                        MlsConversationReInit,
+                        MlsKeyPackageLifetime,
+                        MlsExternalPsk,
+                        MlsE2eiStatus,
+                        MlsHistorySecret,
                         E2eiEnrollment,
                         E2eiAcmeCA,
                         E2eiIntermediateCert,
@@ -22,6 +22,20 @@ pub struct PersistedMlsGroup {
     pub id: Vec<u8>,
     pub state: Vec<u8>,
     pub parent_id: Option<Vec<u8>>,
+    /// Serialized configuration parameters which are not handled natively by openmls
+    pub custom_configuration: Option<Vec<u8>>,
+    /// Big-endian encoded UNIX timestamp (in seconds) of the last time we updated our own leaf node
+    pub last_self_update: Option<Vec<u8>>,
+}
+
+impl PersistedMlsGroup {
+    /// UNIX timestamp (in seconds) of the last time we updated our own leaf node, if known
+    pub fn last_self_update(&self) -> Option<u64> {
+        self.last_self_update
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+    }
 }
 
 #[cfg_attr(target_family = "wasm", async_trait::async_trait(?Send))]
@@ -75,6 +89,18 @@ pub struct MlsPendingMessage {
     #[sensitive]
     pub foreign_id: Vec<u8>,
     pub message: Vec<u8>,
+    /// Big-endian encoded UNIX timestamp (in seconds) of when the message got buffered
+    pub received_at: Option<Vec<u8>>,
+}
+
+impl MlsPendingMessage {
+    /// UNIX timestamp (in seconds) of when the message got buffered, if known
+    pub fn received_at(&self) -> Option<u64> {
+        self.received_at
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+    }
 }
 
 /// Entity representing a buffered commit.
@@ -101,14 +127,17 @@ pub struct MlsBufferedCommit {
     #[sensitive]
     conversation_id: Vec<u8>,
     commit_data: Vec<u8>,
+    received_at: Option<Vec<u8>>,
 }
 
 impl MlsBufferedCommit {
-    /// Create a new `Self` from conversation id and the commit data.
-    pub fn new(conversation_id: Vec<u8>, commit_data: Vec<u8>) -> Self {
+    /// Create a new `Self` from conversation id, the commit data and the UNIX timestamp (in seconds)
+    /// of when the commit got buffered.
+    pub fn new(conversation_id: Vec<u8>, commit_data: Vec<u8>, received_at: u64) -> Self {
         Self {
             conversation_id,
             commit_data,
+            received_at: Some(received_at.to_be_bytes().to_vec()),
         }
     }
 
@@ -123,6 +152,298 @@ impl MlsBufferedCommit {
     pub fn into_commit_data(self) -> Vec<u8> {
         self.commit_data
     }
+
+    /// UNIX timestamp (in seconds) of when the commit got buffered, if known
+    pub fn received_at(&self) -> Option<u64> {
+        self.received_at
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+    }
+}
+
+/// Entity representing an exporter secret retained after the group moved past the epoch it was exported in.
+///
+/// The id is derived from the conversation id, the epoch and the label, see [MlsExporterSecret::make_id].
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_exporter_secrets")]
+pub struct MlsExporterSecret {
+    #[id(hex, column = "id_hex")]
+    #[sensitive]
+    pub id: Vec<u8>,
+    #[sensitive]
+    pub conversation_id: Vec<u8>,
+    /// Big-endian encoded epoch
+    pub epoch: Vec<u8>,
+    /// UTF-8 encoded exporter label
+    pub label: Vec<u8>,
+    #[sensitive]
+    pub secret: Vec<u8>,
+}
+
+impl MlsExporterSecret {
+    pub fn new(conversation_id: &[u8], epoch: u64, label: &str, secret: Vec<u8>) -> Self {
+        Self {
+            id: Self::make_id(conversation_id, epoch, label),
+            conversation_id: conversation_id.to_vec(),
+            epoch: epoch.to_be_bytes().to_vec(),
+            label: label.as_bytes().to_vec(),
+            secret,
+        }
+    }
+
+    /// Unambiguous id for the secret exported with `label` in the given epoch of a conversation
+    pub fn make_id(conversation_id: &[u8], epoch: u64, label: &str) -> Vec<u8> {
+        let mut id = Vec::with_capacity(4 + conversation_id.len() + 8 + label.len());
+        id.extend_from_slice(&(conversation_id.len() as u32).to_be_bytes());
+        id.extend_from_slice(conversation_id);
+        id.extend_from_slice(&epoch.to_be_bytes());
+        id.extend_from_slice(label.as_bytes());
+        id
+    }
+
+    /// Bounds of the ids of a conversation, see [MlsExporterSecret::make_id]. They all start with the same
+    /// prefix, so they sort from it, included, up to the next prefix, excluded.
+    pub(crate) fn id_range(conversation_id: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut first_id = Vec::with_capacity(4 + conversation_id.len());
+        first_id.extend_from_slice(&(conversation_id.len() as u32).to_be_bytes());
+        first_id.extend_from_slice(conversation_id);
+        // the length prefix is never only made of 0xff bytes, so there always is a next prefix
+        let mut end_id = first_id.clone();
+        while let Some(byte) = end_id.pop() {
+            if byte < u8::MAX {
+                end_id.push(byte + 1);
+                break;
+            }
+        }
+        (first_id, end_id)
+    }
+
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch.as_slice().try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
+/// Entity linking a reinitialized conversation to the one replacing it
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_conversation_reinits")]
+pub struct MlsConversationReInit {
+    /// Id of the reinitialized conversation
+    #[id(hex, column = "id_hex")]
+    #[sensitive]
+    pub id: Vec<u8>,
+    /// Id of the conversation replacing it
+    #[sensitive]
+    pub new_id: Vec<u8>,
+    /// Big-endian encoded ciphersuite of the new conversation
+    pub ciphersuite: Vec<u8>,
+}
+
+impl MlsConversationReInit {
+    pub fn new(id: &[u8], new_id: &[u8], ciphersuite: u16) -> Self {
+        Self {
+            id: id.to_vec(),
+            new_id: new_id.to_vec(),
+            ciphersuite: ciphersuite.to_be_bytes().to_vec(),
+        }
+    }
+
+    pub fn ciphersuite(&self) -> Option<u16> {
+        self.ciphersuite.as_slice().try_into().ok().map(u16::from_be_bytes)
+    }
+}
+
+/// Entity holding the lifetime of the key packages generated for a ciphersuite and credential type,
+/// when it is not the default one
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_keypackage_lifetimes")]
+pub struct MlsKeyPackageLifetime {
+    /// Big-endian encoded ciphersuite followed by the credential type, see [MlsKeyPackageLifetime::make_id]
+    #[id(hex, column = "id_hex")]
+    pub id: Vec<u8>,
+    /// Big-endian encoded lifetime, in seconds
+    pub lifetime: Vec<u8>,
+}
+
+impl MlsKeyPackageLifetime {
+    pub fn new(ciphersuite: u16, credential_type: u8, lifetime: u64) -> Self {
+        Self {
+            id: Self::make_id(ciphersuite, credential_type),
+            lifetime: lifetime.to_be_bytes().to_vec(),
+        }
+    }
+
+    pub fn make_id(ciphersuite: u16, credential_type: u8) -> Vec<u8> {
+        [ciphersuite.to_be_bytes().as_slice(), &[credential_type]].concat()
+    }
+
+    pub fn ciphersuite(&self) -> Option<u16> {
+        self.id.get(..2)?.try_into().ok().map(u16::from_be_bytes)
+    }
+
+    pub fn credential_type(&self) -> Option<u8> {
+        match self.id.as_slice() {
+            [_, _, credential_type] => Some(*credential_type),
+            _ => None,
+        }
+    }
+
+    /// The lifetime, in seconds
+    pub fn lifetime(&self) -> Option<u64> {
+        self.lifetime.as_slice().try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
+/// Entity recording under which id OpenMLS stored the [MlsPskBundle] of an external pre-shared key
+/// stored by core-crypto, so that it can be deleted again
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_external_psks")]
+pub struct MlsExternalPsk {
+    /// Id of the external pre-shared key
+    #[id(hex, column = "id_hex")]
+    #[sensitive]
+    pub id: Vec<u8>,
+    /// Id of the [MlsPskBundle] holding its secret
+    #[sensitive]
+    pub psk_bundle_id: Vec<u8>,
+}
+
+impl MlsExternalPsk {
+    pub fn new(id: &[u8], psk_bundle_id: &[u8]) -> Self {
+        Self {
+            id: id.to_vec(),
+            psk_bundle_id: psk_bundle_id.to_vec(),
+        }
+    }
+}
+
+/// Entity holding the end-to-end identity status of a conversation and of its members as last reported to
+/// the application, so that the next evaluation is compared with it
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_e2ei_statuses")]
+pub struct MlsE2eiStatus {
+    /// Id of the conversation
+    #[id(hex, column = "id_hex")]
+    #[sensitive]
+    pub id: Vec<u8>,
+    /// Status serialized by core-crypto
+    #[sensitive]
+    pub status: Vec<u8>,
+}
+
+/// Entity archiving the history secret of a history sharing era of a conversation, along with the welcome
+/// adding its history client to the conversation.
+///
+/// The id is derived from the conversation id and the epoch starting the era, see [MlsHistorySecret::make_id].
+#[derive(
+    core_crypto_macros::Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Zeroize,
+    core_crypto_macros::Entity,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[zeroize(drop)]
+#[entity(collection_name = "mls_history_secrets")]
+pub struct MlsHistorySecret {
+    #[id(hex, column = "id_hex")]
+    #[sensitive]
+    pub id: Vec<u8>,
+    #[sensitive]
+    pub conversation_id: Vec<u8>,
+    /// Big-endian encoded epoch starting the era
+    pub epoch: Vec<u8>,
+    /// Serialized history secret
+    #[sensitive]
+    pub secret: Vec<u8>,
+    /// TLS-serialized welcome adding the history client
+    #[sensitive]
+    pub welcome: Vec<u8>,
+}
+
+impl MlsHistorySecret {
+    pub fn new(conversation_id: &[u8], epoch: u64, secret: Vec<u8>, welcome: Vec<u8>) -> Self {
+        Self {
+            id: Self::make_id(conversation_id, epoch),
+            conversation_id: conversation_id.to_vec(),
+            epoch: epoch.to_be_bytes().to_vec(),
+            secret,
+            welcome,
+        }
+    }
+
+    /// Unambiguous id for the era starting at `epoch` in a conversation
+    pub fn make_id(conversation_id: &[u8], epoch: u64) -> Vec<u8> {
+        let mut id = Vec::with_capacity(4 + conversation_id.len() + 8);
+        id.extend_from_slice(&(conversation_id.len() as u32).to_be_bytes());
+        id.extend_from_slice(conversation_id);
+        id.extend_from_slice(&epoch.to_be_bytes());
+        id
+    }
+
+    /// First and last ids of the eras of a conversation, see [MlsHistorySecret::make_id]
+    pub(crate) fn id_range(conversation_id: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        (
+            Self::make_id(conversation_id, 0),
+            Self::make_id(conversation_id, u64::MAX),
+        )
+    }
+
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch.as_slice().try_into().ok().map(u64::from_be_bytes)
+    }
 }
 
 /// Entity representing a persisted `Credential`
@@ -231,6 +552,142 @@ pub struct MlsKeyPackage {
     pub keypackage_ref: Vec<u8>,
     #[sensitive]
     pub keypackage: Vec<u8>,
+    /// Ciphersuite of the key package, big-endian encoded
+    pub ciphersuite: Option<Vec<u8>>,
+    /// Credential type of the key package, as a single byte
+    pub credential_type: Option<Vec<u8>>,
+    /// UNIX timestamp (in seconds) after which the key package is not valid anymore, big-endian encoded
+    pub expires_at: Option<Vec<u8>>,
+    /// UNIX timestamp (in seconds) of when the key package got uploaded, big-endian encoded
+    pub uploaded_at: Option<Vec<u8>>,
+    /// Non-empty for last resort key packages, which are kept once used
+    pub last_resort: Option<Vec<u8>>,
+}
+
+impl MlsKeyPackage {
+    /// Sets the metadata the key packages can be queried by. Key packages stored before they were
+    /// recorded lack it.
+    pub fn set_metadata(&mut self, ciphersuite: u16, credential_type: u8, expires_at: Option<u64>) {
+        self.ciphersuite = Some(ciphersuite.to_be_bytes().to_vec());
+        self.credential_type = Some(vec![credential_type]);
+        self.expires_at = expires_at.map(|expires_at| expires_at.to_be_bytes().to_vec());
+    }
+
+    /// Whether [Self::set_metadata] has been called on this key package
+    pub fn has_metadata(&self) -> bool {
+        self.ciphersuite().is_some() && self.credential_type().is_some()
+    }
+
+    pub fn ciphersuite(&self) -> Option<u16> {
+        self.ciphersuite
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u16::from_be_bytes)
+    }
+
+    pub fn credential_type(&self) -> Option<u8> {
+        match self.credential_type.as_deref() {
+            Some(&[credential_type]) => Some(credential_type),
+            _ => None,
+        }
+    }
+
+    /// UNIX timestamp (in seconds) after which the key package is not valid anymore, if it has a lifetime
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+    }
+
+    /// UNIX timestamp (in seconds) of when the key package got uploaded, if it has been
+    pub fn uploaded_at(&self) -> Option<u64> {
+        self.uploaded_at
+            .as_deref()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+    }
+
+    pub fn set_uploaded_at(&mut self, uploaded_at: u64) {
+        self.uploaded_at = Some(uploaded_at.to_be_bytes().to_vec());
+    }
+
+    pub fn is_last_resort(&self) -> bool {
+        self.last_resort
+            .as_ref()
+            .is_some_and(|last_resort| !last_resort.is_empty())
+    }
+
+    pub fn set_last_resort(&mut self) {
+        self.last_resort = Some(vec![1]);
+    }
+}
+
+/// How close to its expiry a key package is, see [MlsKeyPackage::expiry]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MlsKeyPackageExpiry {
+    Expired,
+    ExpiringSoon,
+    /// Valid for longer, or without lifetime
+    Valid,
+}
+
+impl MlsKeyPackage {
+    /// How close to its expiry this key package is at `now`, when expiring by `soon` counts as soon
+    pub fn expiry(&self, now: u64, soon: u64) -> MlsKeyPackageExpiry {
+        match self.expires_at() {
+            Some(expires_at) if expires_at <= now => MlsKeyPackageExpiry::Expired,
+            Some(expires_at) if expires_at <= soon => MlsKeyPackageExpiry::ExpiringSoon,
+            _ => MlsKeyPackageExpiry::Valid,
+        }
+    }
+}
+
+/// Number of key packages sharing a ciphersuite, a credential type and an expiry, see
+/// [crate::Database::count_key_packages]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MlsKeyPackageCount {
+    pub ciphersuite: u16,
+    pub credential_type: u8,
+    pub expiry: MlsKeyPackageExpiry,
+    pub count: usize,
+    /// Number of key packages with an upload time
+    pub uploaded: usize,
+}
+
+impl MlsKeyPackageCount {
+    /// Counts a key package into the entry it belongs to. Last resort key packages and the ones lacking
+    /// metadata are not counted.
+    pub(crate) fn add(counts: &mut Vec<Self>, key_package: &MlsKeyPackage, now: u64, soon: u64) {
+        let (Some(ciphersuite), Some(credential_type)) = (key_package.ciphersuite(), key_package.credential_type())
+        else {
+            return;
+        };
+        if key_package.is_last_resort() {
+            return;
+        }
+        let expiry = key_package.expiry(now, soon);
+        let uploaded = key_package.uploaded_at().is_some() as usize;
+        match counts.iter_mut().find(|entry| {
+            entry.ciphersuite == ciphersuite && entry.credential_type == credential_type && entry.expiry == expiry
+        }) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.uploaded += uploaded;
+            }
+            None => counts.push(Self {
+                ciphersuite,
+                credential_type,
+                expiry,
+                count: 1,
+                uploaded,
+            }),
+        }
+    }
+
+    pub(crate) fn sort(counts: &mut [Self]) {
+        counts.sort_by_key(|entry| (entry.ciphersuite, entry.credential_type, entry.expiry));
+    }
 }
 
 /// Entity representing an enrollment instance used to fetch a x509 certificate and persisted when
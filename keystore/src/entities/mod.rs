@@ -134,16 +134,18 @@ pub trait EntityBase:
     fn to_transaction_entity(self) -> crate::transaction::dynamic_dispatch::Entity;
 }
 
+pub(crate) const AES_GCM_256_NONCE_SIZE: usize = 12;
+
+/// Associated data of the fields encrypted in IndexedDB, see [crate::idb_dump] for its use on other
+/// platforms
+#[derive(core_crypto_macros::Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Aad {
+    pub(crate) type_name: Vec<u8>,
+    pub(crate) id: Vec<u8>,
+}
+
 cfg_if::cfg_if! {
     if #[cfg(target_family = "wasm")] {
-        const AES_GCM_256_NONCE_SIZE: usize = 12;
-
-        #[derive(core_crypto_macros::Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-        struct Aad {
-            type_name: Vec<u8>,
-            id: Vec<u8>,
-        }
-
         #[async_trait::async_trait(?Send)]
         pub trait EntityTransactionExt: Entity<ConnectionType = crate::connection::KeystoreDatabaseConnection> {
             async fn save<'a>(&'a self, tx: &crate::connection::storage::WasmStorageTransaction<'a>) -> CryptoKeystoreResult<()> {
@@ -30,20 +30,32 @@ impl PersistedMlsGroupExt for PersistedMlsGroup {
             blob.read_to_end(&mut state)?;
             blob.close()?;
 
-            let mut parent_id = None;
             // Ignore errors because null blobs cause errors on open
-            if let Ok(mut blob) =
-                transaction.blob_open(rusqlite::DatabaseName::Main, "mls_groups", "parent_id", rowid, true)
-            {
-                if !blob.is_empty() {
-                    let mut tmp = Vec::with_capacity(blob.len());
-                    blob.read_to_end(&mut tmp)?;
-                    parent_id.replace(tmp);
+            let read_optional_blob = |column| -> crate::CryptoKeystoreResult<Option<Vec<u8>>> {
+                let mut value = None;
+                if let Ok(mut blob) =
+                    transaction.blob_open(rusqlite::DatabaseName::Main, "mls_groups", column, rowid, true)
+                {
+                    if !blob.is_empty() {
+                        let mut tmp = Vec::with_capacity(blob.len());
+                        blob.read_to_end(&mut tmp)?;
+                        value.replace(tmp);
+                    }
+                    blob.close()?;
                 }
-                blob.close()?;
-            }
+                Ok(value)
+            };
+            let parent_id = read_optional_blob("parent_id")?;
+            let custom_configuration = read_optional_blob("custom_configuration")?;
+            let last_self_update = read_optional_blob("last_self_update")?;
 
-            acc.push(Self { id, parent_id, state });
+            acc.push(Self {
+                id,
+                parent_id,
+                state,
+                custom_configuration,
+                last_self_update,
+            });
             crate::CryptoKeystoreResult::Ok(acc)
         })?;
 
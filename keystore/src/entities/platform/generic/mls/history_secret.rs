@@ -0,0 +1,36 @@
+use crate::{
+    CryptoKeystoreResult,
+    connection::DatabaseConnection,
+    entities::{EntityBase, MlsHistorySecret},
+};
+
+impl MlsHistorySecret {
+    /// History secrets archived for this conversation, ordered by epoch
+    pub async fn find_all_by_conversation_id(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        conversation_id: &[u8],
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        // The ids of a conversation share its prefix, followed by the big-endian epoch
+        let (first_id, last_id) = Self::id_range(conversation_id);
+        let mut conn = conn.conn().await;
+        let transaction = conn.transaction()?;
+        let mut stmt = transaction.prepare_cached(
+            "SELECT id_hex, conversation_id, epoch, secret, welcome FROM mls_history_secrets \
+            WHERE id_hex BETWEEN ? AND ? ORDER BY id_hex",
+        )?;
+        let rows = stmt.query_map([hex::encode(first_id), hex::encode(last_id)], |r| {
+            Ok((r.get::<_, String>(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+        })?;
+        rows.map(|row| {
+            let (id, conversation_id, epoch, secret, welcome) = row?;
+            Ok(Self {
+                id: hex::decode(id)?,
+                conversation_id,
+                epoch,
+                secret,
+                welcome,
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    CryptoKeystoreResult,
+    connection::DatabaseConnection,
+    entities::{EntityBase, EntityFindParams, MlsKeyPackage, MlsKeyPackageCount, MlsKeyPackageExpiry},
+};
+
+impl MlsKeyPackage {
+    /// Key packages of this ciphersuite and credential type, either the last resort ones or the others
+    pub async fn find_all_by_ciphersuite_and_credential_type(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        ciphersuite: u16,
+        credential_type: u8,
+        last_resort: bool,
+        params: EntityFindParams,
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        let last_resort = if last_resort { ">" } else { "=" };
+        let query = format!(
+            "SELECT keypackage_ref_hex, keypackage, ciphersuite, credential_type, expires_at, uploaded_at, last_resort \
+            FROM mls_keypackages WHERE ciphersuite = ? AND credential_type = ? \
+            AND IFNULL(LENGTH(last_resort), 0) {last_resort} 0 {}",
+            params.to_sql()
+        );
+        Self::query(conn, &query, (&ciphersuite.to_be_bytes()[..], &[credential_type][..])).await
+    }
+
+    /// Key packages stored before their ciphersuite and credential type were recorded
+    pub async fn find_all_without_metadata(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        // Missing optional columns are stored as empty blobs
+        let query = "SELECT keypackage_ref_hex, keypackage, ciphersuite, credential_type, expires_at, uploaded_at, \
+            last_resort FROM mls_keypackages WHERE IFNULL(LENGTH(ciphersuite), 0) = 0 OR IFNULL(LENGTH(credential_type), 0) = 0";
+        Self::query(conn, query, ()).await
+    }
+
+    /// Counts the key packages other than the last resort ones and the `excluded` ones, per ciphersuite,
+    /// credential type and expiry, see [MlsKeyPackage::expiry]
+    pub async fn count_by_ciphersuite_and_credential_type(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        now: u64,
+        soon: u64,
+        excluded: &[Vec<u8>],
+    ) -> CryptoKeystoreResult<Vec<MlsKeyPackageCount>> {
+        use rusqlite::types::Value;
+
+        // Big-endian timestamps compare as blobs like they do as numbers
+        let excluded_params = vec!["?"; excluded.len()].join(", ");
+        let query = format!(
+            "SELECT ciphersuite, credential_type, \
+            CASE WHEN IFNULL(LENGTH(expires_at), 0) = 0 THEN 2 WHEN expires_at <= ? THEN 0 WHEN expires_at <= ? THEN 1 \
+            ELSE 2 END AS expiry, COUNT(*), SUM(IFNULL(LENGTH(uploaded_at), 0) > 0) \
+            FROM mls_keypackages WHERE IFNULL(LENGTH(last_resort), 0) = 0 \
+            AND LENGTH(ciphersuite) = 2 AND LENGTH(credential_type) = 1 \
+            AND keypackage_ref_hex NOT IN ({excluded_params}) \
+            GROUP BY ciphersuite, credential_type, expiry"
+        );
+        let params = [
+            Value::Blob(now.to_be_bytes().to_vec()),
+            Value::Blob(soon.to_be_bytes().to_vec()),
+        ]
+        .into_iter()
+        .chain(excluded.iter().map(|id| Value::Text(hex::encode(id))));
+
+        let mut conn = conn.conn().await;
+        let transaction = conn.transaction()?;
+        let mut stmt = transaction.prepare(&query)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |r| {
+            Ok((
+                r.get::<_, Vec<u8>>(0)?,
+                r.get::<_, Vec<u8>>(1)?,
+                r.get::<_, u8>(2)?,
+                r.get::<_, i64>(3)?,
+                r.get::<_, i64>(4)?,
+            ))
+        })?;
+        let mut counts = rows
+            .map(|row| {
+                let (ciphersuite, credential_type, expiry, count, uploaded) = row?;
+                let ciphersuite = u16::from_be_bytes(ciphersuite.as_slice().try_into()?);
+                let expiry = match expiry {
+                    0 => MlsKeyPackageExpiry::Expired,
+                    1 => MlsKeyPackageExpiry::ExpiringSoon,
+                    _ => MlsKeyPackageExpiry::Valid,
+                };
+                Ok(MlsKeyPackageCount {
+                    ciphersuite,
+                    credential_type: credential_type[0],
+                    expiry,
+                    count: count as usize,
+                    uploaded: uploaded as usize,
+                })
+            })
+            .collect::<CryptoKeystoreResult<Vec<_>>>()?;
+        MlsKeyPackageCount::sort(&mut counts);
+        Ok(counts)
+    }
+
+    async fn query(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        let mut conn = conn.conn().await;
+        let transaction = conn.transaction()?;
+        let mut stmt = transaction.prepare_cached(query)?;
+        let optional = |bytes: Option<Vec<u8>>| bytes.filter(|bytes| !bytes.is_empty());
+        let rows = stmt.query_map(params, |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get(1)?,
+                r.get(2)?,
+                r.get(3)?,
+                r.get(4)?,
+                r.get(5)?,
+                r.get(6)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (keypackage_ref, keypackage, ciphersuite, credential_type, expires_at, uploaded_at, last_resort) = row?;
+            Ok(Self {
+                keypackage_ref: hex::decode(keypackage_ref)?,
+                keypackage,
+                ciphersuite: optional(ciphersuite),
+                credential_type: optional(credential_type),
+                expires_at: optional(expires_at),
+                uploaded_at: optional(uploaded_at),
+                last_resort: optional(last_resort),
+            })
+        })
+        .collect()
+    }
+}
@@ -13,14 +13,14 @@ impl MlsPendingMessage {
         let mut conn = conn.conn().await;
         let transaction = conn.transaction()?;
         let query: String = format!(
-            "SELECT rowid FROM mls_pending_messages WHERE id = ? {}",
+            "SELECT rowid, received_at FROM mls_pending_messages WHERE id = ? {}",
             params.to_sql()
         );
 
         let mut stmt = transaction.prepare_cached(&query)?;
-        let rows = stmt.query_map([conversation_id], |r| r.get(0))?;
-        rows.map(|rowid_result| {
-            let rowid = rowid_result?;
+        let rows = stmt.query_map([conversation_id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.map(|row_result| {
+            let (rowid, received_at) = row_result?;
             use std::io::Read as _;
 
             let mut blob =
@@ -43,6 +43,7 @@ impl MlsPendingMessage {
             Ok(Self {
                 foreign_id: conversation_id,
                 message,
+                received_at,
             })
         })
         .collect()
@@ -71,13 +72,16 @@ impl Entity for MlsPendingMessage {
     ) -> crate::CryptoKeystoreResult<Vec<Self>> {
         let mut conn = conn.conn().await;
         let transaction = conn.transaction()?;
-        let query: String = format!("SELECT rowid FROM mls_pending_messages {}", params.to_sql());
+        let query: String = format!(
+            "SELECT rowid, received_at FROM mls_pending_messages {}",
+            params.to_sql()
+        );
 
         let mut stmt = transaction.prepare_cached(&query)?;
-        let mut rows = stmt.query_map([], |r| r.get(0))?;
-        let entities = rows.try_fold(Vec::new(), |mut acc, rowid_result| {
+        let mut rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        let entities = rows.try_fold(Vec::new(), |mut acc, row_result| {
             use std::io::Read as _;
-            let rowid = rowid_result?;
+            let (rowid, received_at) = row_result?;
 
             let mut blob =
                 transaction.blob_open(rusqlite::DatabaseName::Main, "mls_pending_messages", "id", rowid, true)?;
@@ -99,6 +103,7 @@ impl Entity for MlsPendingMessage {
             acc.push(Self {
                 foreign_id: id,
                 message,
+                received_at,
             });
             crate::CryptoKeystoreResult::Ok(acc)
         })?;
@@ -148,8 +153,8 @@ impl EntityTransactionExt for MlsPendingMessage {
 
         use rusqlite::ToSql as _;
         transaction.execute(
-            "INSERT INTO mls_pending_messages (id, message) VALUES(?, ?)",
-            [&zid.to_sql()?, &zmsg.to_sql()?],
+            "INSERT INTO mls_pending_messages (id, message, received_at) VALUES(?, ?, ?)",
+            [&zid.to_sql()?, &zmsg.to_sql()?, &self.received_at.to_sql()?],
         )?;
         let rowid = transaction.last_insert_rowid();
 
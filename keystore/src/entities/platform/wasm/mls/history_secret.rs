@@ -0,0 +1,31 @@
+use js_sys::Uint8Array;
+
+use crate::{
+    CryptoKeystoreResult,
+    entities::{EntityBase, MlsHistorySecret},
+};
+
+impl MlsHistorySecret {
+    /// History secrets archived for this conversation, ordered by epoch
+    pub async fn find_all_by_conversation_id(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        conversation_id: &[u8],
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        // The ids of a conversation share its prefix, followed by the big-endian epoch
+        let (first_id, last_id) = Self::id_range(conversation_id);
+        let range = idb::KeyRange::bound(
+            &Uint8Array::from(first_id.as_slice()).into(),
+            &Uint8Array::from(last_id.as_slice()).into(),
+            None,
+            None,
+        )?;
+        let mut secrets: Vec<Self> = conn
+            .storage()
+            .get_all_with_query(Self::COLLECTION_NAME, Some(range), None)
+            .await?;
+        // the in-memory keystore has no key ranges, and is never used in prod
+        secrets.retain(|secret| secret.conversation_id == conversation_id);
+        secrets.sort_by_key(Self::epoch);
+        Ok(secrets)
+    }
+}
@@ -0,0 +1,104 @@
+use idb::TransactionMode;
+
+use crate::{
+    CryptoKeystoreResult,
+    connection::storage::WasmStorageWrapper,
+    entities::{Entity as _, EntityBase, EntityFindParams, MlsKeyPackage, MlsKeyPackageCount},
+};
+
+impl MlsKeyPackage {
+    /// Key packages of this ciphersuite and credential type, either the last resort ones or the others
+    pub async fn find_all_by_ciphersuite_and_credential_type(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        ciphersuite: u16,
+        credential_type: u8,
+        last_resort: bool,
+        params: EntityFindParams,
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        let storage = conn.storage();
+        let mut key_packages = match storage.wrapper() {
+            WasmStorageWrapper::Persistent(idb) => {
+                let transaction = idb.transaction(&[Self::COLLECTION_NAME], TransactionMode::ReadOnly)?;
+                let index = transaction
+                    .object_store(Self::COLLECTION_NAME)?
+                    .index("ciphersuite_credential_type")?;
+                let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+                let query = serde::Serialize::serialize(
+                    &(ciphersuite.to_be_bytes().to_vec(), vec![credential_type]),
+                    &serializer,
+                )?;
+                index
+                    .get_all(Some(query.into()), None)?
+                    .await?
+                    .into_iter()
+                    .map(|value| {
+                        let mut kp = serde_wasm_bindgen::from_value::<Self>(value)?;
+                        kp.decrypt(&storage.cipher)?;
+                        Ok(kp)
+                    })
+                    .collect::<CryptoKeystoreResult<Vec<_>>>()?
+            }
+            // the in-memory keystore has no indexes, and is never used in prod
+            WasmStorageWrapper::InMemory(_) => {
+                let key_packages: Vec<Self> = storage.get_all(Self::COLLECTION_NAME, None).await?;
+                key_packages
+                    .into_iter()
+                    .filter(|kp| kp.ciphersuite() == Some(ciphersuite) && kp.credential_type() == Some(credential_type))
+                    .collect()
+            }
+        };
+
+        key_packages.retain(|kp| kp.is_last_resort() == last_resort);
+        if params.reverse {
+            key_packages.reverse();
+        }
+        Ok(key_packages
+            .into_iter()
+            .skip(params.offset.unwrap_or_default() as usize)
+            .take(params.limit.unwrap_or(u32::MAX) as usize)
+            .collect())
+    }
+
+    /// Counts the key packages other than the last resort ones and the `excluded` ones, per ciphersuite,
+    /// credential type and expiry, see [MlsKeyPackage::expiry]
+    pub async fn count_by_ciphersuite_and_credential_type(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+        now: u64,
+        soon: u64,
+        excluded: &[Vec<u8>],
+    ) -> CryptoKeystoreResult<Vec<MlsKeyPackageCount>> {
+        let storage = conn.storage();
+        let key_packages: Vec<Self> = match storage.wrapper() {
+            // only records with metadata are part of the index, and the key packages themselves are not
+            // decrypted since only their metadata is counted
+            WasmStorageWrapper::Persistent(idb) => {
+                let transaction = idb.transaction(&[Self::COLLECTION_NAME], TransactionMode::ReadOnly)?;
+                transaction
+                    .object_store(Self::COLLECTION_NAME)?
+                    .index("ciphersuite_credential_type")?
+                    .get_all(None, None)?
+                    .await?
+                    .into_iter()
+                    .map(serde_wasm_bindgen::from_value)
+                    .collect::<Result<_, serde_wasm_bindgen::Error>>()?
+            }
+            WasmStorageWrapper::InMemory(_) => storage.get_all(Self::COLLECTION_NAME, None).await?,
+        };
+
+        let mut counts = Vec::new();
+        for key_package in key_packages.iter().filter(|kp| !excluded.contains(&kp.keypackage_ref)) {
+            MlsKeyPackageCount::add(&mut counts, key_package, now, soon);
+        }
+        MlsKeyPackageCount::sort(&mut counts);
+        Ok(counts)
+    }
+
+    /// Key packages stored before their ciphersuite and credential type were recorded
+    pub async fn find_all_without_metadata(
+        conn: &mut <Self as EntityBase>::ConnectionType,
+    ) -> CryptoKeystoreResult<Vec<Self>> {
+        // records lacking the metadata are not part of the index
+        let key_packages: Vec<Self> = conn.storage().get_all(Self::COLLECTION_NAME, None).await?;
+        Ok(key_packages.into_iter().filter(|kp| !kp.has_metadata()).collect())
+    }
+}
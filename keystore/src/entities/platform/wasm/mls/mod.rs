@@ -1,8 +1,11 @@
 pub mod credential;
 pub mod e2ei_acme_ca;
 pub mod encryption_keypair;
+pub mod exporter_secret;
 pub mod group;
+pub mod history_secret;
 pub mod hpke_private_key;
+pub mod key_package;
 pub mod pending_message;
 pub mod psk_bundle;
 pub mod refresh_token;
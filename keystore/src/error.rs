@@ -19,6 +19,18 @@ pub enum MissingKeyErrorKind {
     MlsCredential,
     #[error("MLS Buffered Commit")]
     MlsBufferedCommit,
+    #[error("MLS Exporter Secret")]
+    MlsExporterSecret,
+    #[error("MLS Conversation ReInit")]
+    MlsConversationReInit,
+    #[error("MLS KeyPackage Lifetime")]
+    MlsKeyPackageLifetime,
+    #[error("MLS External PSK")]
+    MlsExternalPsk,
+    #[error("MLS E2EI Status")]
+    MlsE2eiStatus,
+    #[error("MLS History Secret")]
+    MlsHistorySecret,
     #[error("MLS Persisted Group")]
     PersistedMlsGroup,
     #[error("MLS Persisted Pending Group")]
@@ -86,7 +98,6 @@ pub enum CryptoKeystoreError {
     #[cfg(target_family = "wasm")]
     #[error("The task has been canceled")]
     WasmExecutorError,
-    #[cfg(target_family = "wasm")]
     #[error("aead::Error")]
     AesGcmError,
     #[cfg(target_family = "wasm")]
@@ -140,7 +151,18 @@ pub enum CryptoKeystoreError {
     TimestampError,
     #[error("Could not find {0} in keystore with value {1}")]
     NotFound(&'static str, String),
-    #[cfg(target_family = "wasm")]
+    #[error("Invalid keystore backup: {0}")]
+    InvalidBackup(String),
+    #[error("Unsupported keystore backup version {0}")]
+    UnsupportedBackupVersion(u16),
+    #[error("The keystore backup could not be encrypted")]
+    BackupEncryptionFailed,
+    #[error("The keystore backup could not be decrypted: either the key is wrong or the backup has been tampered with")]
+    BackupDecryptionFailed,
+    #[error("Invalid IndexedDB dump: {0}")]
+    InvalidIdbDump(String),
+    #[error("Unsupported IndexedDB version {0}, the database must be migrated first")]
+    UnsupportedIdbVersion(u32),
     #[error(transparent)]
     SerdeJsonError(#[from] serde_json::Error),
     #[cfg(target_family = "wasm")]
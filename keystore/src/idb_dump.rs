@@ -0,0 +1,271 @@
+//! Conversion between the IndexedDB keystore used on WASM and the keystores of the other platforms.
+//!
+//! An IndexedDB keystore cannot be opened outside of a browser, so it is exchanged as an [IdbDump]:
+//! the records of each object store, exactly as stored. A record is an entity serialized as JSON, whose
+//! sensitive fields are encrypted with AES-256-GCM under the database key, laid out as
+//! `[12 bytes of nonce..., ...ciphertext]`. See `docs/KEYSTORE_IMPLEMENTATION.md` for the details.
+//!
+//! Such a dump can be produced by reading every object store from JS, and loaded into any [Database]
+//! with [Database::import_idb_dump]. The other way around, [Database::export_idb_dump] produces the
+//! records to write into IndexedDB.
+
+use std::collections::BTreeMap;
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit as _, Nonce,
+    aead::{Aead as _, Payload},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "proteus-keystore")]
+use crate::entities::{ProteusIdentity, ProteusPrekey, ProteusSession};
+use crate::{
+    CryptoKeystoreError, CryptoKeystoreResult,
+    backup::{BackupCollection, CollectionVisitor, export_collections, visit_collection},
+    connection::{Database, DatabaseKey, KeystoreDatabaseConnection},
+    entities::{
+        AES_GCM_256_NONCE_SIZE, Aad, ConsumerData, E2eiAcmeCA, E2eiCrl, E2eiEnrollment, E2eiIntermediateCert, Entity,
+        EntityTransactionExt, MlsBufferedCommit, MlsConversationReInit, MlsCredential, MlsE2eiStatus,
+        MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsExporterSecret, MlsExternalPsk, MlsHistorySecret,
+        MlsHpkePrivateKey, MlsKeyPackage, MlsKeyPackageLifetime, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair,
+        PersistedMlsGroup, PersistedMlsPendingGroup,
+    },
+};
+
+/// Version of the IndexedDB database dumps are read from and written for.
+///
+/// Dumps of older databases must be migrated first, by opening them with this version of the keystore.
+pub const IDB_VERSION: u32 = 10_002_012;
+
+/// Object store of the refresh tokens, which are not used anymore
+const REFRESH_TOKEN_STORE: &str = "e2ei_refresh_token";
+
+/// The content of an IndexedDB keystore, see [crate::idb_dump]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdbDump {
+    /// Version of the IndexedDB database the records belong to, which must be [IDB_VERSION]
+    pub version: u32,
+    /// The records of each object store, by object store name
+    pub object_stores: BTreeMap<String, Vec<IdbRecord>>,
+}
+
+/// A record of an IndexedDB object store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdbRecord {
+    /// Key of the record
+    pub key: Vec<u8>,
+    /// The record as stored, i.e. with its sensitive fields encrypted
+    pub value: Value,
+}
+
+/// Fields which are encrypted in IndexedDB, see the `encrypt` implementations of the WASM entities. Those of the
+/// entities deriving [Entity] are generated along with it.
+fn encrypted_fields(collection_name: &str) -> &'static [&'static str] {
+    match collection_name {
+        ConsumerData::COLLECTION_NAME | E2eiAcmeCA::COLLECTION_NAME => &["content"],
+        E2eiEnrollment::COLLECTION_NAME => E2eiEnrollment::ENCRYPTED_FIELDS,
+        E2eiIntermediateCert::COLLECTION_NAME => E2eiIntermediateCert::ENCRYPTED_FIELDS,
+        E2eiCrl::COLLECTION_NAME => E2eiCrl::ENCRYPTED_FIELDS,
+        MlsCredential::COLLECTION_NAME => &["credential"],
+        MlsSignatureKeyPair::COLLECTION_NAME => &["keypair"],
+        MlsHpkePrivateKey::COLLECTION_NAME | MlsEncryptionKeyPair::COLLECTION_NAME => &["sk"],
+        MlsEpochEncryptionKeyPair::COLLECTION_NAME => MlsEpochEncryptionKeyPair::ENCRYPTED_FIELDS,
+        MlsPskBundle::COLLECTION_NAME => &["psk"],
+        MlsKeyPackage::COLLECTION_NAME => MlsKeyPackage::ENCRYPTED_FIELDS,
+        PersistedMlsGroup::COLLECTION_NAME => PersistedMlsGroup::ENCRYPTED_FIELDS,
+        PersistedMlsPendingGroup::COLLECTION_NAME => &["state"],
+        MlsPendingMessage::COLLECTION_NAME => &["message"],
+        MlsBufferedCommit::COLLECTION_NAME => MlsBufferedCommit::ENCRYPTED_FIELDS,
+        MlsExporterSecret::COLLECTION_NAME => MlsExporterSecret::ENCRYPTED_FIELDS,
+        MlsConversationReInit::COLLECTION_NAME => MlsConversationReInit::ENCRYPTED_FIELDS,
+        MlsKeyPackageLifetime::COLLECTION_NAME => MlsKeyPackageLifetime::ENCRYPTED_FIELDS,
+        MlsExternalPsk::COLLECTION_NAME => MlsExternalPsk::ENCRYPTED_FIELDS,
+        MlsE2eiStatus::COLLECTION_NAME => MlsE2eiStatus::ENCRYPTED_FIELDS,
+        MlsHistorySecret::COLLECTION_NAME => MlsHistorySecret::ENCRYPTED_FIELDS,
+        #[cfg(feature = "proteus-keystore")]
+        ProteusIdentity::COLLECTION_NAME => &["pk", "sk"],
+        #[cfg(feature = "proteus-keystore")]
+        ProteusPrekey::COLLECTION_NAME => &["prekey"],
+        #[cfg(feature = "proteus-keystore")]
+        ProteusSession::COLLECTION_NAME => ProteusSession::ENCRYPTED_FIELDS,
+        _ => &[],
+    }
+}
+
+/// The id of an entity in IndexedDB, i.e. the key of its record and the id authenticated along its
+/// encrypted fields. Only the Proteus identity has a different id there.
+fn idb_id<E: Entity>(entity: &E) -> &[u8] {
+    #[cfg(feature = "proteus-keystore")]
+    if entity.downcast::<ProteusIdentity>().is_some() {
+        return &[1];
+    }
+    entity.id_raw()
+}
+
+fn aad<E: Entity>(entity: &E) -> CryptoKeystoreResult<Vec<u8>> {
+    let aad = Aad {
+        type_name: E::COLLECTION_NAME.as_bytes().to_vec(),
+        id: idb_id(entity).to_vec(),
+    };
+    Ok(serde_json::to_vec(&aad)?)
+}
+
+fn field_mut<'a>(value: &'a mut Value, field: &str) -> CryptoKeystoreResult<&'a mut Value> {
+    value
+        .get_mut(field)
+        .ok_or_else(|| CryptoKeystoreError::InvalidIdbDump(format!("missing field \"{field}\"")))
+}
+
+/// Replaces the bytes of each field by the output of `transform`
+fn transform_fields(
+    value: &mut Value,
+    fields: &[&str],
+    mut transform: impl FnMut(&[u8]) -> CryptoKeystoreResult<Vec<u8>>,
+) -> CryptoKeystoreResult<()> {
+    for field in fields {
+        let field = field_mut(value, field)?;
+        let bytes = serde_json::from_value::<Vec<u8>>(field.take())?;
+        *field = serde_json::to_value(transform(&bytes)?)?;
+    }
+    Ok(())
+}
+
+/// Decrypts the records of an object store into a collection
+struct FromIdb<'a> {
+    records: &'a [IdbRecord],
+    cipher: &'a Aes256Gcm,
+}
+
+impl CollectionVisitor for FromIdb<'_> {
+    type Output = Vec<Vec<u8>>;
+
+    fn visit<E: Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt + Sync>(
+        self,
+    ) -> CryptoKeystoreResult<Self::Output> {
+        self.records
+            .iter()
+            .map(|record| {
+                let mut value = record.value.clone();
+                // ids are stored in the clear, so the encrypted record tells what was authenticated
+                let aad = aad(&serde_json::from_value::<E>(value.clone())?)?;
+                transform_fields(&mut value, encrypted_fields(E::COLLECTION_NAME), |data| {
+                    if data.len() < AES_GCM_256_NONCE_SIZE {
+                        return Err(CryptoKeystoreError::AesGcmError);
+                    }
+                    let (nonce, msg) = data.split_at(AES_GCM_256_NONCE_SIZE);
+                    self.cipher
+                        .decrypt(Nonce::from_slice(nonce), Payload { msg, aad: &aad })
+                        .map_err(|_| CryptoKeystoreError::AesGcmError)
+                })?;
+                Ok(postcard::to_stdvec(&serde_json::from_value::<E>(value)?)?)
+            })
+            .collect()
+    }
+}
+
+/// Encrypts the records of a collection as they are stored in IndexedDB
+struct ToIdb<'a> {
+    records: &'a [Vec<u8>],
+    cipher: &'a Aes256Gcm,
+}
+
+impl CollectionVisitor for ToIdb<'_> {
+    type Output = Vec<IdbRecord>;
+
+    fn visit<E: Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt + Sync>(
+        self,
+    ) -> CryptoKeystoreResult<Self::Output> {
+        self.records
+            .iter()
+            .map(|record| {
+                let entity = postcard::from_bytes::<E>(record)?;
+                let aad = aad(&entity)?;
+                let mut value = serde_json::to_value(&entity)?;
+                transform_fields(&mut value, encrypted_fields(E::COLLECTION_NAME), |data| {
+                    let nonce: [u8; AES_GCM_256_NONCE_SIZE] = rand::random();
+                    let ciphertext = self
+                        .cipher
+                        .encrypt(Nonce::from_slice(&nonce), Payload { msg: data, aad: &aad })
+                        .map_err(|_| CryptoKeystoreError::AesGcmError)?;
+                    Ok([nonce.as_slice(), &ciphertext].concat())
+                })?;
+                Ok(IdbRecord {
+                    key: idb_id(&entity).to_vec(),
+                    value,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Database {
+    /// Loads the content of an IndexedDB keystore, encrypted under `key`, into this database, which is
+    /// meant to be empty. Records having the same id as a loaded one are replaced.
+    ///
+    /// Every record is decrypted before anything is written, then all of them are saved within a single
+    /// transaction. No transaction must be in progress.
+    pub async fn import_idb_dump(&self, dump: &IdbDump, key: &DatabaseKey) -> CryptoKeystoreResult<()> {
+        if self.transaction.lock().await.is_some() {
+            return Err(CryptoKeystoreError::TransactionInProgress {
+                attempted_operation: "import_idb_dump()".to_string(),
+            });
+        }
+        if dump.version != IDB_VERSION {
+            return Err(CryptoKeystoreError::UnsupportedIdbVersion(dump.version));
+        }
+
+        let cipher = Aes256Gcm::new(key.as_ref().into());
+        let mut collections = Vec::with_capacity(dump.object_stores.len());
+        for (name, records) in &dump.object_stores {
+            if records.is_empty() || name == REFRESH_TOKEN_STORE {
+                continue;
+            }
+            let records = visit_collection(
+                name,
+                FromIdb {
+                    records,
+                    cipher: &cipher,
+                },
+            )
+            .map_err(|err| match err {
+                CryptoKeystoreError::InvalidBackup(reason) => CryptoKeystoreError::InvalidIdbDump(reason),
+                err => err,
+            })?;
+            collections.push(BackupCollection {
+                name: name.clone(),
+                records,
+            });
+        }
+        self.restore_collections(&collections).await
+    }
+
+    /// Dumps the content of this database as it would be stored in an IndexedDB keystore encrypted
+    /// under `key`, e.g. to be written into IndexedDB from JS.
+    ///
+    /// No transaction must be in progress, so that only committed records are dumped.
+    pub async fn export_idb_dump(&self, key: &DatabaseKey) -> CryptoKeystoreResult<IdbDump> {
+        if self.transaction.lock().await.is_some() {
+            return Err(CryptoKeystoreError::TransactionInProgress {
+                attempted_operation: "export_idb_dump()".to_string(),
+            });
+        }
+
+        let cipher = Aes256Gcm::new(key.as_ref().into());
+        let mut object_stores = BTreeMap::new();
+        for collection in export_collections(self).await? {
+            let records = visit_collection(
+                &collection.name,
+                ToIdb {
+                    records: &collection.records,
+                    cipher: &cipher,
+                },
+            )?;
+            object_stores.insert(collection.name.clone(), records);
+        }
+        Ok(IdbDump {
+            version: IDB_VERSION,
+            object_stores,
+        })
+    }
+}
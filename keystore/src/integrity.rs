@@ -0,0 +1,198 @@
+//! Detection and removal of entities referring to ones which are not in the keystore anymore.
+//!
+//! Such entities are left behind when a deletion is interrupted or when an older version of
+//! core-crypto forgot to cascade it. They are never used again, so they only take up space, but they
+//! make the content of a keystore harder to reason about.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    CryptoKeystoreError, CryptoKeystoreResult,
+    connection::{Database, FetchFromDatabase as _},
+    entities::{
+        EntityBase as _, MlsBufferedCommit, MlsCredential, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair,
+        MlsExporterSecret, MlsKeyPackage, MlsPendingMessage, MlsSignatureKeyPair, PersistedMlsGroup,
+        PersistedMlsPendingGroup,
+    },
+};
+
+/// An entity referring to one which is not in the keystore anymore, see [Database::check_integrity]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DanglingReference {
+    /// An encryption keypair whose public key is part of no key package, conversation or pending
+    /// conversation. Its public key is looked up in their serialized state, which is opaque to the
+    /// keystore, so this is only reported and never repaired: deleting a keypair still in use would
+    /// break the conversation or key package holding it.
+    EncryptionKeyPair { pk: Vec<u8> },
+    /// A signature keypair whose credentials have all been deleted
+    SignatureKeyPair { pk: Vec<u8>, credential_id: Vec<u8> },
+    /// Messages buffered for a conversation which is neither established nor pending
+    PendingMessages { conversation_id: Vec<u8>, count: usize },
+    /// A commit buffered for a conversation which does not exist
+    BufferedCommit { conversation_id: Vec<u8> },
+    /// An exporter secret retained for a conversation which does not exist
+    ExporterSecret { id: Vec<u8>, conversation_id: Vec<u8> },
+    /// A conversation whose parent conversation does not exist. Repairing it only unlinks the parent,
+    /// the conversation itself is kept.
+    ParentConversation { id: Vec<u8>, parent_id: Vec<u8> },
+}
+
+impl DanglingReference {
+    /// Name of the collection holding the referring entity
+    pub fn collection_name(&self) -> &'static str {
+        match self {
+            Self::EncryptionKeyPair { .. } => MlsEncryptionKeyPair::COLLECTION_NAME,
+            Self::SignatureKeyPair { .. } => MlsSignatureKeyPair::COLLECTION_NAME,
+            Self::PendingMessages { .. } => MlsPendingMessage::COLLECTION_NAME,
+            Self::BufferedCommit { .. } => MlsBufferedCommit::COLLECTION_NAME,
+            Self::ExporterSecret { .. } => MlsExporterSecret::COLLECTION_NAME,
+            Self::ParentConversation { .. } => PersistedMlsGroup::COLLECTION_NAME,
+        }
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+impl Database {
+    /// Lists the entities referring to ones which are not in the keystore anymore. When `repair` is
+    /// set, they are also removed, within a single transaction, except for encryption keypairs, see
+    /// [DanglingReference::EncryptionKeyPair].
+    ///
+    /// No transaction must be in progress, so that only committed records are checked.
+    ///
+    /// History secrets are never dangling: they are archived for conversations which are not known
+    /// locally too, so that a new device can replay their history.
+    pub async fn check_integrity(&self, repair: bool) -> CryptoKeystoreResult<Vec<DanglingReference>> {
+        if self.transaction.lock().await.is_some() {
+            return Err(CryptoKeystoreError::TransactionInProgress {
+                attempted_operation: "check_integrity()".to_string(),
+            });
+        }
+        if !repair {
+            return self.find_dangling_references().await;
+        }
+
+        self.new_transaction().await?;
+        let repaired = async {
+            let dangling = self.find_dangling_references().await?;
+            for reference in &dangling {
+                self.remove_dangling_reference(reference).await?;
+            }
+            Ok(dangling)
+        }
+        .await;
+
+        match repaired {
+            Ok(dangling) => {
+                self.commit_transaction().await?;
+                Ok(dangling)
+            }
+            Err(err) => {
+                self.rollback_transaction().await?;
+                Err(err)
+            }
+        }
+    }
+
+    async fn find_dangling_references(&self) -> CryptoKeystoreResult<Vec<DanglingReference>> {
+        let groups = self.find_all::<PersistedMlsGroup>(Default::default()).await?;
+        let pending_groups = self.find_all::<PersistedMlsPendingGroup>(Default::default()).await?;
+        let key_packages = self.find_all::<MlsKeyPackage>(Default::default()).await?;
+        let epoch_keypairs = self.find_all::<MlsEpochEncryptionKeyPair>(Default::default()).await?;
+        let group_ids = groups.iter().map(|group| group.id.as_slice()).collect::<HashSet<_>>();
+
+        let mut dangling = Vec::new();
+
+        // Epoch keypairs are serialized along with their public keys, so they are searched too
+        let referencing_states = key_packages
+            .iter()
+            .map(|kp| kp.keypackage.as_slice())
+            .chain(groups.iter().map(|group| group.state.as_slice()))
+            .chain(pending_groups.iter().map(|group| group.state.as_slice()))
+            .chain(epoch_keypairs.iter().map(|keypairs| keypairs.keypairs.as_slice()))
+            .collect::<Vec<_>>();
+        for keypair in self.find_all::<MlsEncryptionKeyPair>(Default::default()).await? {
+            if !referencing_states.iter().any(|state| contains(state, &keypair.pk)) {
+                dangling.push(DanglingReference::EncryptionKeyPair { pk: keypair.pk.clone() });
+            }
+        }
+
+        let credential_ids = self
+            .find_all::<MlsCredential>(Default::default())
+            .await?
+            .into_iter()
+            .map(|credential| credential.id.clone())
+            .collect::<HashSet<_>>();
+        for keypair in self.find_all::<MlsSignatureKeyPair>(Default::default()).await? {
+            // keypairs stored through OpenMLS are not tied to a credential
+            if !keypair.credential_id.is_empty() && !credential_ids.contains(&keypair.credential_id) {
+                dangling.push(DanglingReference::SignatureKeyPair {
+                    pk: keypair.pk.clone(),
+                    credential_id: keypair.credential_id.clone(),
+                });
+            }
+        }
+
+        let mut pending_message_counts = BTreeMap::<Vec<u8>, usize>::new();
+        for message in self.find_all::<MlsPendingMessage>(Default::default()).await? {
+            *pending_message_counts.entry(message.foreign_id.clone()).or_default() += 1;
+        }
+        for (conversation_id, count) in pending_message_counts {
+            let is_pending = pending_groups.iter().any(|group| group.id == conversation_id);
+            if !group_ids.contains(conversation_id.as_slice()) && !is_pending {
+                dangling.push(DanglingReference::PendingMessages { conversation_id, count });
+            }
+        }
+
+        for commit in self.find_all::<MlsBufferedCommit>(Default::default()).await? {
+            if !group_ids.contains(commit.conversation_id()) {
+                dangling.push(DanglingReference::BufferedCommit {
+                    conversation_id: commit.conversation_id().to_vec(),
+                });
+            }
+        }
+
+        for secret in self.find_all::<MlsExporterSecret>(Default::default()).await? {
+            if !group_ids.contains(secret.conversation_id.as_slice()) {
+                dangling.push(DanglingReference::ExporterSecret {
+                    id: secret.id.clone(),
+                    conversation_id: secret.conversation_id.clone(),
+                });
+            }
+        }
+
+        for group in &groups {
+            if let Some(parent_id) = group.parent_id.as_ref().filter(|id| !group_ids.contains(id.as_slice())) {
+                dangling.push(DanglingReference::ParentConversation {
+                    id: group.id.clone(),
+                    parent_id: parent_id.clone(),
+                });
+            }
+        }
+
+        Ok(dangling)
+    }
+
+    async fn remove_dangling_reference(&self, reference: &DanglingReference) -> CryptoKeystoreResult<()> {
+        match reference {
+            DanglingReference::EncryptionKeyPair { .. } => Ok(()),
+            DanglingReference::SignatureKeyPair { pk, .. } => self.remove::<MlsSignatureKeyPair, _>(pk).await,
+            DanglingReference::PendingMessages { conversation_id, .. } => {
+                self.remove_pending_messages_by_conversation_id(conversation_id).await
+            }
+            DanglingReference::BufferedCommit { conversation_id } => {
+                self.remove::<MlsBufferedCommit, _>(conversation_id).await
+            }
+            DanglingReference::ExporterSecret { id, .. } => self.remove::<MlsExporterSecret, _>(id).await,
+            DanglingReference::ParentConversation { id, .. } => {
+                let Some(mut group) = self.find::<PersistedMlsGroup>(id).await? else {
+                    return Ok(());
+                };
+                group.parent_id = None;
+                self.save(group).await.map(|_| ())
+            }
+        }
+    }
+}
@@ -4,9 +4,12 @@
 mod error;
 pub use error::*;
 
+pub mod backup;
 pub mod connection;
 pub use connection::{ConnectionType, Database, DatabaseKey};
 pub mod entities;
+pub mod idb_dump;
+pub mod integrity;
 pub mod transaction;
 
 pub(crate) mod mls;
@@ -33,6 +33,8 @@ pub trait CryptoKeystoreMls: Sized {
 
     /// Persists a `MlsGroup`
     ///
+    /// Metadata already persisted alongside the group (custom configuration, last self update) is kept.
+    ///
     /// # Arguments
     /// * `group_id` - group/conversation id
     /// * `state` - the group state
@@ -151,10 +153,17 @@ impl CryptoKeystoreMls for crate::Database {
         state: &[u8],
         parent_group_id: Option<&[u8]>,
     ) -> CryptoKeystoreResult<()> {
+        let (custom_configuration, last_self_update) = self
+            .find::<PersistedMlsGroup>(group_id)
+            .await?
+            .map(|group| (group.custom_configuration.clone(), group.last_self_update.clone()))
+            .unwrap_or_default();
         self.save(PersistedMlsGroup {
             id: group_id.into(),
             state: state.into(),
             parent_id: parent_group_id.map(Into::into),
+            custom_configuration,
+            last_self_update,
         })
         .await?;
 
@@ -278,9 +287,15 @@ impl openmls_traits::key_store::OpenMlsKeyStore for crate::connection::Database
                 self.save(kp).await?;
             }
             MlsEntityId::KeyPackage => {
+                // We cannot inspect the key package here, core-crypto records its metadata afterwards
                 let kp = MlsKeyPackage {
                     keypackage_ref: k.into(),
                     keypackage: data,
+                    ciphersuite: None,
+                    credential_type: None,
+                    expires_at: None,
+                    uploaded_at: None,
+                    last_resort: None,
                 };
                 self.save(kp).await?;
             }
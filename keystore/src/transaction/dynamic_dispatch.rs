@@ -10,8 +10,9 @@ use crate::{
     connection::TransactionWrapper,
     entities::{
         ConsumerData, E2eiAcmeCA, E2eiCrl, E2eiEnrollment, E2eiIntermediateCert, EntityBase, EntityTransactionExt,
-        MlsBufferedCommit, MlsCredential, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsHpkePrivateKey,
-        MlsKeyPackage, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
+        MlsBufferedCommit, MlsConversationReInit, MlsCredential, MlsE2eiStatus, MlsEncryptionKeyPair,
+        MlsEpochEncryptionKeyPair, MlsExporterSecret, MlsExternalPsk, MlsHistorySecret, MlsHpkePrivateKey,
+        MlsKeyPackage, MlsKeyPackageLifetime, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
         PersistedMlsPendingGroup, StringEntityId, UniqueEntity,
     },
 };
@@ -27,6 +28,12 @@ pub enum Entity {
     MlsEpochEncryptionKeyPair(MlsEpochEncryptionKeyPair),
     MlsCredential(MlsCredential),
     MlsBufferedCommit(MlsBufferedCommit),
+    MlsExporterSecret(MlsExporterSecret),
+    MlsConversationReInit(MlsConversationReInit),
+    MlsKeyPackageLifetime(MlsKeyPackageLifetime),
+    MlsExternalPsk(MlsExternalPsk),
+    MlsE2eiStatus(MlsE2eiStatus),
+    MlsHistorySecret(MlsHistorySecret),
     PersistedMlsGroup(PersistedMlsGroup),
     PersistedMlsPendingGroup(PersistedMlsPendingGroup),
     MlsPendingMessage(MlsPendingMessage),
@@ -54,6 +61,12 @@ pub enum EntityId {
     EpochEncryptionKeyPair(Vec<u8>),
     MlsCredential(Vec<u8>),
     MlsBufferedCommit(Vec<u8>),
+    MlsExporterSecret(Vec<u8>),
+    MlsConversationReInit(Vec<u8>),
+    MlsKeyPackageLifetime(Vec<u8>),
+    MlsExternalPsk(Vec<u8>),
+    MlsE2eiStatus(Vec<u8>),
+    MlsHistorySecret(Vec<u8>),
     PersistedMlsGroup(Vec<u8>),
     PersistedMlsPendingGroup(Vec<u8>),
     MlsPendingMessage(Vec<u8>),
@@ -82,6 +95,12 @@ impl EntityId {
             EntityId::EpochEncryptionKeyPair(vec) => vec.as_slice().into(),
             EntityId::MlsCredential(vec) => vec.as_slice().into(),
             EntityId::MlsBufferedCommit(vec) => vec.as_slice().into(),
+            EntityId::MlsExporterSecret(vec) => vec.as_slice().into(),
+            EntityId::MlsConversationReInit(vec) => vec.as_slice().into(),
+            EntityId::MlsKeyPackageLifetime(vec) => vec.as_slice().into(),
+            EntityId::MlsExternalPsk(vec) => vec.as_slice().into(),
+            EntityId::MlsE2eiStatus(vec) => vec.as_slice().into(),
+            EntityId::MlsHistorySecret(vec) => vec.as_slice().into(),
             EntityId::PersistedMlsGroup(vec) => vec.as_slice().into(),
             EntityId::PersistedMlsPendingGroup(vec) => vec.as_slice().into(),
             EntityId::MlsPendingMessage(vec) => vec.as_slice().into(),
@@ -109,6 +128,12 @@ impl EntityId {
             MlsEncryptionKeyPair::COLLECTION_NAME => Ok(Self::EncryptionKeyPair(id.into())),
             MlsEpochEncryptionKeyPair::COLLECTION_NAME => Ok(Self::EpochEncryptionKeyPair(id.into())),
             MlsBufferedCommit::COLLECTION_NAME => Ok(Self::MlsBufferedCommit(id.into())),
+            MlsExporterSecret::COLLECTION_NAME => Ok(Self::MlsExporterSecret(id.into())),
+            MlsConversationReInit::COLLECTION_NAME => Ok(Self::MlsConversationReInit(id.into())),
+            MlsKeyPackageLifetime::COLLECTION_NAME => Ok(Self::MlsKeyPackageLifetime(id.into())),
+            MlsExternalPsk::COLLECTION_NAME => Ok(Self::MlsExternalPsk(id.into())),
+            MlsE2eiStatus::COLLECTION_NAME => Ok(Self::MlsE2eiStatus(id.into())),
+            MlsHistorySecret::COLLECTION_NAME => Ok(Self::MlsHistorySecret(id.into())),
             PersistedMlsGroup::COLLECTION_NAME => Ok(Self::PersistedMlsGroup(id.into())),
             PersistedMlsPendingGroup::COLLECTION_NAME => Ok(Self::PersistedMlsPendingGroup(id.into())),
             MlsCredential::COLLECTION_NAME => Ok(Self::MlsCredential(id.into())),
@@ -138,6 +163,12 @@ impl EntityId {
             EntityId::EpochEncryptionKeyPair(_) => MlsEpochEncryptionKeyPair::COLLECTION_NAME,
             EntityId::MlsCredential(_) => MlsCredential::COLLECTION_NAME,
             EntityId::MlsBufferedCommit(_) => MlsBufferedCommit::COLLECTION_NAME,
+            EntityId::MlsExporterSecret(_) => MlsExporterSecret::COLLECTION_NAME,
+            EntityId::MlsConversationReInit(_) => MlsConversationReInit::COLLECTION_NAME,
+            EntityId::MlsKeyPackageLifetime(_) => MlsKeyPackageLifetime::COLLECTION_NAME,
+            EntityId::MlsExternalPsk(_) => MlsExternalPsk::COLLECTION_NAME,
+            EntityId::MlsE2eiStatus(_) => MlsE2eiStatus::COLLECTION_NAME,
+            EntityId::MlsHistorySecret(_) => MlsHistorySecret::COLLECTION_NAME,
             EntityId::PersistedMlsGroup(_) => PersistedMlsGroup::COLLECTION_NAME,
             EntityId::PersistedMlsPendingGroup(_) => PersistedMlsPendingGroup::COLLECTION_NAME,
             EntityId::MlsPendingMessage(_) => MlsPendingMessage::COLLECTION_NAME,
@@ -171,6 +202,12 @@ pub async fn execute_save(tx: &TransactionWrapper<'_>, entity: &Entity) -> Crypt
         }
         Entity::MlsCredential(mls_credential) => mls_credential.save(tx).await,
         Entity::MlsBufferedCommit(mls_pending_commit) => mls_pending_commit.save(tx).await,
+        Entity::MlsExporterSecret(mls_exporter_secret) => mls_exporter_secret.save(tx).await,
+        Entity::MlsConversationReInit(mls_conversation_reinit) => mls_conversation_reinit.save(tx).await,
+        Entity::MlsKeyPackageLifetime(mls_keypackage_lifetime) => mls_keypackage_lifetime.save(tx).await,
+        Entity::MlsExternalPsk(mls_external_psk) => mls_external_psk.save(tx).await,
+        Entity::MlsE2eiStatus(mls_e2ei_status) => mls_e2ei_status.save(tx).await,
+        Entity::MlsHistorySecret(mls_history_secret) => mls_history_secret.save(tx).await,
         Entity::PersistedMlsGroup(persisted_mls_group) => persisted_mls_group.save(tx).await,
         Entity::PersistedMlsPendingGroup(persisted_mls_pending_group) => persisted_mls_pending_group.save(tx).await,
         Entity::MlsPendingMessage(mls_pending_message) => mls_pending_message.save(tx).await,
@@ -199,6 +236,12 @@ pub async fn execute_delete(tx: &TransactionWrapper<'_>, entity_id: &EntityId) -
         id @ EntityId::EpochEncryptionKeyPair(_) => MlsEpochEncryptionKeyPair::delete(tx, id.as_id()).await,
         id @ EntityId::MlsCredential(_) => MlsCredential::delete(tx, id.as_id()).await,
         id @ EntityId::MlsBufferedCommit(_) => MlsBufferedCommit::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsExporterSecret(_) => MlsExporterSecret::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsConversationReInit(_) => MlsConversationReInit::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsKeyPackageLifetime(_) => MlsKeyPackageLifetime::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsExternalPsk(_) => MlsExternalPsk::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsE2eiStatus(_) => MlsE2eiStatus::delete(tx, id.as_id()).await,
+        id @ EntityId::MlsHistorySecret(_) => MlsHistorySecret::delete(tx, id.as_id()).await,
         id @ EntityId::PersistedMlsGroup(_) => PersistedMlsGroup::delete(tx, id.as_id()).await,
         id @ EntityId::PersistedMlsPendingGroup(_) => PersistedMlsPendingGroup::delete(tx, id.as_id()).await,
         id @ EntityId::MlsPendingMessage(_) => MlsPendingMessage::delete(tx, id.as_id()).await,
@@ -1,5 +1,5 @@
-use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_lock::{RwLock, SemaphoreGuardArc};
@@ -16,8 +16,48 @@ use crate::{CryptoKeystoreError, CryptoKeystoreResult, connection::Database};
 
 pub mod dynamic_dispatch;
 
-#[derive(Debug, Default, derive_more::Deref, derive_more::DerefMut)]
-struct InMemoryTable(HashMap<Vec<u8>, Zeroizing<Vec<u8>>>);
+/// Records cached by a transaction for one collection, keyed by their merge key.
+///
+/// Use [crate::entities::Entity::merge_key] because `id_raw()` is not always unique for records:
+/// for `MlsCredential`, `id_raw()` is the `ClientId`, and for `MlsPendingMessage` it's the id of the group
+/// it belongs to. The merge keys of the records are indexed by id, so that removals don't have to
+/// deserialize every cached record.
+#[derive(Debug, Default, derive_more::Deref)]
+struct InMemoryTable {
+    #[deref]
+    records: HashMap<Vec<u8>, Zeroizing<Vec<u8>>>,
+    merge_keys_by_id: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+}
+
+impl InMemoryTable {
+    fn insert<E: crate::entities::Entity>(&mut self, entity: &E) -> CryptoKeystoreResult<()> {
+        let serialized = postcard::to_stdvec(entity)?;
+        let merge_key = entity.merge_key();
+        self.merge_keys_by_id
+            .entry(entity.id_raw().to_vec())
+            .or_default()
+            .insert(merge_key.clone());
+        self.records.insert(merge_key, Zeroizing::new(serialized));
+        Ok(())
+    }
+
+    /// Removes the records with this id
+    fn remove_by_id(&mut self, id: &[u8]) {
+        for merge_key in self.merge_keys_by_id.remove(id).unwrap_or_default() {
+            self.records.remove(&merge_key);
+        }
+    }
+
+    /// Keeps the records for which `f` returns `true`
+    fn retain(&mut self, f: impl FnMut(&Vec<u8>, &mut Zeroizing<Vec<u8>>) -> bool) {
+        self.records.retain(f);
+        let records = &self.records;
+        self.merge_keys_by_id.retain(|_id, merge_keys| {
+            merge_keys.retain(|merge_key| records.contains_key(merge_key));
+            !merge_keys.is_empty()
+        });
+    }
+}
 
 type InMemoryCache = Arc<RwLock<HashMap<String, InMemoryTable>>>;
 
@@ -50,14 +90,23 @@ impl KeystoreTransaction {
         entity.pre_save().await?;
         let mut cache_guard = self.cache.write().await;
         let table = cache_guard.entry(E::COLLECTION_NAME.to_string()).or_default();
-        let serialized = postcard::to_stdvec(&entity)?;
-        // Use merge_key() because `id_raw()` is not always unique for records.
-        // For `MlsCredential`, `id_raw()` is the `CLientId`.
-        // For `MlsPendingMessage` it's the id of the group it belongs to.
-        table.insert(entity.merge_key(), Zeroizing::new(serialized));
+        table.insert(&entity)?;
         Ok(entity)
     }
 
+    /// Like [Self::save_mut], but keeps the fields usually generated when saving, e.g. the creation
+    /// date of a credential, as they are when restoring a backup.
+    pub(crate) async fn restore<
+        E: crate::entities::Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt + Sync,
+    >(
+        &self,
+        entity: E,
+    ) -> CryptoKeystoreResult<()> {
+        let mut cache_guard = self.cache.write().await;
+        let table = cache_guard.entry(E::COLLECTION_NAME.to_string()).or_default();
+        table.insert(&entity)
+    }
+
     pub(crate) async fn remove<
         E: crate::entities::Entity<ConnectionType = KeystoreDatabaseConnection> + EntityTransactionExt,
         S: AsRef<[u8]>,
@@ -66,11 +115,9 @@ impl KeystoreTransaction {
         id: S,
     ) -> CryptoKeystoreResult<()> {
         let mut cache_guard = self.cache.write().await;
-        if let Entry::Occupied(mut table) = cache_guard.entry(E::COLLECTION_NAME.to_string())
-            && let Entry::Occupied(cached_record) = table.get_mut().entry(id.as_ref().to_vec())
-        {
-            cached_record.remove_entry();
-        };
+        if let Some(table) = cache_guard.get_mut(E::COLLECTION_NAME) {
+            table.remove_by_id(id.as_ref());
+        }
 
         let mut deleted_list = self.deleted.write().await;
         deleted_list.push(EntityId::from_collection_name(E::COLLECTION_NAME, id.as_ref())?);
@@ -101,31 +148,32 @@ impl KeystoreTransaction {
     }
 
     pub(crate) async fn cred_delete_by_credential(&self, cred: Vec<u8>) -> CryptoKeystoreResult<()> {
+        // We cannot return an error from `retain()`, so we've got to do this dance with a mutable result.
+        let mut result = Ok(());
+
         let mut cache_guard = self.cache.write().await;
         if let Entry::Occupied(mut table) = cache_guard.entry(MlsCredential::COLLECTION_NAME.to_string()) {
-            table.get_mut().retain(|_, value| **value != cred);
+            table.get_mut().retain(|_key, record_bytes| {
+                postcard::from_bytes::<MlsCredential>(record_bytes)
+                    .map(|credential| credential.credential != cred)
+                    .inspect_err(|err| result = Err(err.clone()))
+                    .unwrap_or(false)
+            });
         }
 
         let mut deleted_list = self.deleted_credentials.write().await;
         deleted_list.push(cred);
-        Ok(())
+        result.map_err(Into::into)
     }
 
     pub(crate) async fn remove_pending_messages_by_conversation_id(
         &self,
         conversation_id: &[u8],
     ) -> CryptoKeystoreResult<()> {
-        // We cannot return an error from `retain()`, so we've got to do this dance with a mutable result.
-        let mut result = Ok(());
-
+        // Pending messages are identified by the id of their conversation
         let mut cache_guard = self.cache.write().await;
-        if let Entry::Occupied(mut table) = cache_guard.entry(MlsPendingMessage::COLLECTION_NAME.to_string()) {
-            table.get_mut().retain(|_key, record_bytes| {
-                postcard::from_bytes::<MlsPendingMessage>(record_bytes)
-                    .map(|pending_message| pending_message.foreign_id != conversation_id)
-                    .inspect_err(|err| result = Err(err.clone()))
-                    .unwrap_or(false)
-            });
+        if let Some(table) = cache_guard.get_mut(MlsPendingMessage::COLLECTION_NAME) {
+            table.remove_by_id(conversation_id);
         }
 
         let mut deleted_list = self.deleted.write().await;
@@ -133,7 +181,7 @@ impl KeystoreTransaction {
             MlsPendingMessage::COLLECTION_NAME,
             conversation_id,
         )?);
-        result.map_err(Into::into)
+        Ok(())
     }
 
     pub(crate) async fn find_pending_messages_by_conversation_id(
@@ -153,6 +201,85 @@ impl KeystoreTransaction {
         Ok(merged_records)
     }
 
+    pub(crate) async fn find_exporter_secrets_by_conversation_id(
+        &self,
+        conversation_id: &[u8],
+        persisted_records: Vec<MlsExporterSecret>,
+    ) -> CryptoKeystoreResult<Vec<MlsExporterSecret>> {
+        let cached_records = self
+            .find_all_in_cache::<MlsExporterSecret>()
+            .await?
+            .into_iter()
+            .filter(|secret| secret.conversation_id == conversation_id)
+            .collect();
+        let mut merged_records = self
+            .merge_records(cached_records, persisted_records, Default::default())
+            .await;
+        merged_records.sort_by_key(MlsExporterSecret::epoch);
+        Ok(merged_records)
+    }
+
+    pub(crate) async fn find_history_secrets_by_conversation_id(
+        &self,
+        conversation_id: &[u8],
+        persisted_records: Vec<MlsHistorySecret>,
+    ) -> CryptoKeystoreResult<Vec<MlsHistorySecret>> {
+        let cached_records = self
+            .find_all_in_cache::<MlsHistorySecret>()
+            .await?
+            .into_iter()
+            .filter(|secret| secret.conversation_id == conversation_id)
+            .collect();
+        let mut merged_records = self
+            .merge_records(cached_records, persisted_records, Default::default())
+            .await;
+        merged_records.sort_by_key(MlsHistorySecret::epoch);
+        Ok(merged_records)
+    }
+
+    pub(crate) async fn find_key_packages(
+        &self,
+        persisted_records: Vec<MlsKeyPackage>,
+        params: EntityFindParams,
+        matches: impl Fn(&MlsKeyPackage) -> bool,
+    ) -> CryptoKeystoreResult<Vec<MlsKeyPackage>> {
+        let cached_records = self.find_all_in_cache::<MlsKeyPackage>().await?;
+        // A key package changed within this transaction may not match anymore, hence filtering once merged
+        let merged_records = self
+            .merge_records(
+                cached_records,
+                persisted_records,
+                EntityFindParams {
+                    limit: None,
+                    offset: None,
+                    reverse: params.reverse,
+                },
+            )
+            .await;
+        Ok(merged_records
+            .into_iter()
+            .filter(matches)
+            .skip(params.offset.unwrap_or_default() as usize)
+            .take(params.limit.unwrap_or(u32::MAX) as usize)
+            .collect())
+    }
+
+    /// The ids of the key packages saved or removed within this transaction, and the saved ones
+    pub(crate) async fn changed_key_packages(&self) -> CryptoKeystoreResult<(Vec<Vec<u8>>, Vec<MlsKeyPackage>)> {
+        let saved_records = self.find_all_in_cache::<MlsKeyPackage>().await?;
+        let deleted_list = self.deleted.read().await;
+        let deleted_ids = deleted_list.iter().filter_map(|id| match id {
+            EntityId::KeyPackage(id) => Some(id.clone()),
+            _ => None,
+        });
+        let changed_ids = saved_records
+            .iter()
+            .map(|kp| kp.keypackage_ref.clone())
+            .chain(deleted_ids)
+            .collect();
+        Ok((changed_ids, saved_records))
+    }
+
     async fn find_in_cache<E>(&self, id: &[u8]) -> CryptoKeystoreResult<Option<E>>
     where
         E: crate::entities::Entity<ConnectionType = KeystoreDatabaseConnection>,
@@ -253,25 +380,26 @@ impl KeystoreTransaction {
     /// Identity from the perspective of this function is determined by the output of [crate::entities::Entity::merge_key].
     ///
     /// Further, the output list of records is built with respect to the provided [EntityFindParams]
-    /// and the deleted records cached in this [Self] instance.
+    /// and the deleted records cached in this [Self] instance. Those only apply to `records_b`: removing
+    /// a record also removes it from the cache, so cached records have been saved after any removal.
     async fn merge_records<E: crate::entities::Entity<ConnectionType = KeystoreDatabaseConnection>>(
         &self,
         records_a: Vec<E>,
         records_b: Vec<E>,
         params: EntityFindParams,
     ) -> Vec<E> {
-        let mut merged = records_a.into_iter().chain(records_b).unique_by(|e| e.merge_key());
-
         let deleted_records = self.deleted.read().await;
         let deleted_credentials = self.deleted_credentials.read().await;
 
+        let records_b = records_b.into_iter().filter(|record| {
+            !Self::record_is_in_deleted_list(record, &deleted_records)
+                && !Self::credential_is_in_deleted_list(record, &deleted_credentials)
+        });
+        let mut merged = records_a.into_iter().chain(records_b).unique_by(|e| e.merge_key());
+
         let merged: &mut dyn Iterator<Item = E> = if params.reverse { &mut merged.rev() } else { &mut merged };
 
         merged
-            .filter(|record| {
-                !Self::record_is_in_deleted_list(record, &deleted_records)
-                    && !Self::credential_is_in_deleted_list(record, &deleted_credentials)
-            })
             .skip(params.offset.unwrap_or(0) as usize)
             .take(params.limit.unwrap_or(u32::MAX) as usize)
             .collect()
@@ -370,15 +498,7 @@ macro_rules! commit_transaction {
             #[cfg(not(target_family = "wasm"))]
             let tx = conn.transaction()?.into();
 
-             $( $(
-                if !$records.is_empty() {
-                    for record in $records {
-                        dynamic_dispatch::execute_save(&tx, &record.to_transaction_entity()).await?;
-                    }
-                }
-             )* )*
-
-
+        // Removals come first: records which are still cached have been saved after them
         for deleted_id in deleted_ids.iter() {
             dynamic_dispatch::execute_delete(&tx, deleted_id).await?
         }
@@ -387,6 +507,14 @@ macro_rules! commit_transaction {
             MlsCredential::delete_by_credential(&tx, deleted_credential.to_owned()).await?;
         }
 
+             $( $(
+                if !$records.is_empty() {
+                    for record in $records {
+                        dynamic_dispatch::execute_save(&tx, &record.to_transaction_entity()).await?;
+                    }
+                }
+             )* )*
+
          tx.commit_tx().await?;
      };
 }
@@ -413,7 +541,14 @@ impl KeystoreTransaction {
                 (identifier_13, E2eiAcmeCA),
                 (identifier_14, E2eiIntermediateCert),
                 (identifier_15, E2eiCrl),
-                (identifier_16, ConsumerData)
+                (identifier_16, ConsumerData),
+                (identifier_20, MlsExporterSecret),
+                (identifier_21, MlsConversationReInit),
+                (identifier_22, MlsHistorySecret),
+                (identifier_23, MlsBufferedCommit),
+                (identifier_24, MlsKeyPackageLifetime),
+                (identifier_25, MlsExternalPsk),
+                (identifier_26, MlsE2eiStatus)
             ],
             proteus_types: [
                 (identifier_17, ProteusPrekey),
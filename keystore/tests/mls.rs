@@ -16,8 +16,9 @@ mod tests {
 
     use core_crypto_keystore::MissingKeyErrorKind;
     use core_crypto_keystore::entities::{
-        EntityBase, MlsCredential, MlsHpkePrivateKey, MlsKeyPackage, MlsPskBundle, MlsSignatureKeyPair,
-        PersistedMlsGroup, PersistedMlsPendingGroup,
+        EntityBase, MlsBufferedCommit, MlsCredential, MlsExporterSecret, MlsHistorySecret, MlsHpkePrivateKey,
+        MlsKeyPackage, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
+        PersistedMlsPendingGroup,
     };
     use openmls::prelude::TlsSerializeTrait as _;
     use openmls_traits::OpenMlsCryptoProvider as _;
@@ -61,6 +62,340 @@ mod tests {
         );
     }
 
+    #[apply(all_storage_types)]
+    pub async fn last_save_or_removal_wins_within_a_transaction(context: KeystoreTestContext) {
+        use core_crypto_keystore::connection::FetchFromDatabase as _;
+
+        let store = context.store();
+        let key = |sk: &[u8]| MlsHpkePrivateKey {
+            sk: sk.to_vec(),
+            pk: b"pk".to_vec(),
+        };
+        store.save(key(b"first")).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        // Saving after removing restores the record
+        store.new_transaction().await.unwrap();
+        store.remove::<MlsHpkePrivateKey, _>(b"pk").await.unwrap();
+        store.save(key(b"second")).await.unwrap();
+        assert_eq!(store.find(b"pk").await.unwrap(), Some(key(b"second")));
+        store.commit_transaction().await.unwrap();
+        store.new_transaction().await.unwrap();
+        assert_eq!(store.find(b"pk").await.unwrap(), Some(key(b"second")));
+
+        // Removing after saving deletes the record
+        store.save(key(b"third")).await.unwrap();
+        store.remove::<MlsHpkePrivateKey, _>(b"pk").await.unwrap();
+        assert_eq!(store.find::<MlsHpkePrivateKey>(b"pk").await.unwrap(), None);
+        store.commit_transaction().await.unwrap();
+        store.new_transaction().await.unwrap();
+        assert_eq!(store.find::<MlsHpkePrivateKey>(b"pk").await.unwrap(), None);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn buffered_commits_are_written_on_commit(context: KeystoreTestContext) {
+        use core_crypto_keystore::connection::FetchFromDatabase as _;
+
+        let store = context.store();
+        let conversation_id = b"conversation".to_vec();
+        store
+            .save(MlsBufferedCommit::new(conversation_id.clone(), b"commit".to_vec(), 1))
+            .await
+            .unwrap();
+        store.commit_transaction().await.unwrap();
+
+        // Transactions used to cache buffered commits without ever writing them
+        store.new_transaction().await.unwrap();
+        let commit = store
+            .find::<MlsBufferedCommit>(&conversation_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(commit.commit_data(), b"commit");
+        assert_eq!(commit.received_at(), Some(1));
+
+        store.remove::<MlsBufferedCommit, _>(&conversation_id).await.unwrap();
+        store.commit_transaction().await.unwrap();
+        store.new_transaction().await.unwrap();
+        assert!(
+            store
+                .find::<MlsBufferedCommit>(&conversation_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_save_pending_messages_again_after_removing_them(context: KeystoreTestContext) {
+        let store = context.store();
+        let conversation_id = b"conversation".to_vec();
+        let pending_message = |message: &[u8]| MlsPendingMessage {
+            foreign_id: conversation_id.clone(),
+            message: message.to_vec(),
+            received_at: Some(1u64.to_be_bytes().to_vec()),
+        };
+        store.save(pending_message(b"kept")).await.unwrap();
+        store.save(pending_message(b"dropped")).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        // Within a single transaction, the last operation has to win
+        store.new_transaction().await.unwrap();
+        store
+            .remove_pending_messages_by_conversation_id(&conversation_id)
+            .await
+            .unwrap();
+        store.save(pending_message(b"kept")).await.unwrap();
+        let messages = store
+            .find_pending_messages_by_conversation_id(&conversation_id)
+            .await
+            .unwrap();
+        assert_eq!(messages, vec![pending_message(b"kept")]);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let messages = store
+            .find_pending_messages_by_conversation_id(&conversation_id)
+            .await
+            .unwrap();
+        assert_eq!(messages, vec![pending_message(b"kept")]);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_find_history_secrets_by_conversation_id(context: KeystoreTestContext) {
+        let store = context.store();
+        let history_secret = |conversation_id: &[u8], epoch: u64| {
+            MlsHistorySecret::new(conversation_id, epoch, b"secret".to_vec(), vec![])
+        };
+        store.save(history_secret(b"conversation", 256)).await.unwrap();
+        store.save(history_secret(b"conv", 2)).await.unwrap();
+        store.save(history_secret(b"other", 3)).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        store.save(history_secret(b"conversation", 1)).await.unwrap();
+        let expected = vec![history_secret(b"conversation", 1), history_secret(b"conversation", 256)];
+        let found = store
+            .find_history_secrets_by_conversation_id(b"conversation")
+            .await
+            .unwrap();
+        assert_eq!(found, expected);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let found = store
+            .find_history_secrets_by_conversation_id(b"conversation")
+            .await
+            .unwrap();
+        assert_eq!(found, expected);
+        let found = store.find_history_secrets_by_conversation_id(b"conv").await.unwrap();
+        assert_eq!(found, vec![history_secret(b"conv", 2)]);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_find_exporter_secrets_by_conversation_id(context: KeystoreTestContext) {
+        let store = context.store();
+        let exporter_secret = |conversation_id: &[u8], epoch: u64, label: &str| {
+            MlsExporterSecret::new(conversation_id, epoch, label, b"secret".to_vec())
+        };
+        store
+            .save(exporter_secret(b"conversation", 256, "media"))
+            .await
+            .unwrap();
+        store.save(exporter_secret(b"conv", 2, "media")).await.unwrap();
+        store.save(exporter_secret(b"other", 3, "media")).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        store.save(exporter_secret(b"conversation", 1, "media")).await.unwrap();
+        store.save(exporter_secret(b"conversation", 1, "files")).await.unwrap();
+        let expected = vec![
+            exporter_secret(b"conversation", 1, "files"),
+            exporter_secret(b"conversation", 1, "media"),
+            exporter_secret(b"conversation", 256, "media"),
+        ];
+        let mut found = store
+            .find_exporter_secrets_by_conversation_id(b"conversation")
+            .await
+            .unwrap();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(found, expected);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let mut found = store
+            .find_exporter_secrets_by_conversation_id(b"conversation")
+            .await
+            .unwrap();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(found, expected);
+        let found = store.find_exporter_secrets_by_conversation_id(b"conv").await.unwrap();
+        assert_eq!(found, vec![exporter_secret(b"conv", 2, "media")]);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_find_key_packages_by_ciphersuite_and_credential_type(context: KeystoreTestContext) {
+        use core_crypto_keystore::entities::EntityFindParams;
+
+        let store = context.store();
+        let key_package = |keypackage_ref: &[u8], ciphersuite: Option<u16>| {
+            let mut key_package = MlsKeyPackage {
+                keypackage_ref: keypackage_ref.to_vec(),
+                keypackage: b"keypackage".to_vec(),
+                ciphersuite: None,
+                credential_type: None,
+                expires_at: None,
+                uploaded_at: None,
+                last_resort: None,
+            };
+            if let Some(ciphersuite) = ciphersuite {
+                key_package.set_metadata(ciphersuite, 1, Some(u64::MAX));
+            }
+            key_package
+        };
+        store.save(key_package(b"first", Some(1))).await.unwrap();
+        store.save(key_package(b"other", Some(2))).await.unwrap();
+        store.save(key_package(b"legacy", None)).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        store.save(key_package(b"second", Some(1))).await.unwrap();
+        // Changed within the transaction, hence not matching anymore
+        store.save(key_package(b"other", Some(3))).await.unwrap();
+
+        let mut found = store.find_key_packages(1, 1, false, Default::default()).await.unwrap();
+        found.sort_by(|a, b| a.keypackage_ref.cmp(&b.keypackage_ref));
+        assert_eq!(
+            found,
+            vec![key_package(b"first", Some(1)), key_package(b"second", Some(1))]
+        );
+        assert!(
+            store
+                .find_key_packages(2, 1, false, Default::default())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            store
+                .find_key_packages(1, 2, false, Default::default())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let without_metadata = store.find_key_packages_without_metadata().await.unwrap();
+        assert_eq!(without_metadata, vec![key_package(b"legacy", None)]);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let found = store.find_key_packages(3, 1, false, Default::default()).await.unwrap();
+        assert_eq!(found, vec![key_package(b"other", Some(3))]);
+
+        let mut last_resort = key_package(b"last resort", Some(1));
+        last_resort.set_last_resort();
+        store.save(last_resort.clone()).await.unwrap();
+        let found = store.find_key_packages(1, 1, true, Default::default()).await.unwrap();
+        assert_eq!(found, vec![last_resort.clone()]);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let found = store.find_key_packages(1, 1, true, Default::default()).await.unwrap();
+        assert_eq!(found, vec![last_resort]);
+        let found = store.find_key_packages(1, 1, false, Default::default()).await.unwrap();
+        assert_eq!(found.len(), 2);
+
+        // Pages are only cut once the records of the transaction are merged with the persisted ones
+        let page = |offset| EntityFindParams {
+            limit: Some(1),
+            offset: Some(offset),
+            reverse: false,
+        };
+        let mut paged = Vec::new();
+        for offset in 0..3 {
+            paged.extend(store.find_key_packages(1, 1, false, page(offset)).await.unwrap());
+        }
+        assert_eq!(paged, found);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_count_key_packages_by_ciphersuite_and_credential_type(context: KeystoreTestContext) {
+        use core_crypto_keystore::entities::{MlsKeyPackageCount, MlsKeyPackageExpiry};
+
+        let store = context.store();
+        let key_package = |keypackage_ref: &[u8], expires_at: Option<u64>| {
+            let mut key_package = MlsKeyPackage {
+                keypackage_ref: keypackage_ref.to_vec(),
+                keypackage: b"keypackage".to_vec(),
+                ciphersuite: None,
+                credential_type: None,
+                expires_at: None,
+                uploaded_at: None,
+                last_resort: None,
+            };
+            key_package.set_metadata(1, 1, expires_at);
+            key_package
+        };
+        let count = |expiry, count, uploaded| MlsKeyPackageCount {
+            ciphersuite: 1,
+            credential_type: 1,
+            expiry,
+            count,
+            uploaded,
+        };
+        let (now, soon) = (1000, 2000);
+
+        let mut uploaded = key_package(b"uploaded", None);
+        uploaded.set_uploaded_at(now);
+        store.save(uploaded).await.unwrap();
+        store.save(key_package(b"expired", Some(now))).await.unwrap();
+        store.save(key_package(b"expiring", Some(soon))).await.unwrap();
+        store.save(key_package(b"removed", Some(soon))).await.unwrap();
+        let mut last_resort = key_package(b"last resort", None);
+        last_resort.set_last_resort();
+        store.save(last_resort).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        // changed within the transaction
+        store.save(key_package(b"expired", Some(u64::MAX))).await.unwrap();
+        store.remove::<MlsKeyPackage, _>(b"removed").await.unwrap();
+        store.save(key_package(b"new", Some(now - 1))).await.unwrap();
+        let expected = vec![
+            count(MlsKeyPackageExpiry::Expired, 1, 0),
+            count(MlsKeyPackageExpiry::ExpiringSoon, 1, 0),
+            count(MlsKeyPackageExpiry::Valid, 2, 1),
+        ];
+        assert_eq!(store.count_key_packages(now, soon).await.unwrap(), expected);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        assert_eq!(store.count_key_packages(now, soon).await.unwrap(), expected);
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn can_delete_a_credential_saved_in_the_same_transaction(context: KeystoreTestContext) {
+        use core_crypto_keystore::connection::FetchFromDatabase as _;
+
+        let store = context.store();
+        let credential = |credential: &[u8]| MlsCredential {
+            id: credential.to_vec(),
+            credential: credential.to_vec(),
+            created_at: 0,
+        };
+        store.save(credential(b"kept")).await.unwrap();
+        store.save(credential(b"deleted")).await.unwrap();
+        store.cred_delete_by_credential(b"deleted".to_vec()).await.unwrap();
+
+        let credentials = store.find_all::<MlsCredential>(Default::default()).await.unwrap();
+        assert_eq!(credentials, vec![credential(b"kept")]);
+        store.commit_transaction().await.unwrap();
+
+        store.new_transaction().await.unwrap();
+        let credentials = store.find_all::<MlsCredential>(Default::default()).await.unwrap();
+        assert_eq!(credentials, vec![credential(b"kept")]);
+    }
+
     #[apply(all_storage_types)]
     pub async fn can_add_read_delete_credential_bundle_openmls_traits(context: KeystoreTestContext) {
         use core_crypto_keystore::connection::FetchFromDatabase;
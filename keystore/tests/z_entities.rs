@@ -132,6 +132,21 @@ mod tests_impl {
         }
     }
 
+    pub(crate) async fn can_find_restored_entities<
+        R: EntityRandomUpdateExt + Entity<ConnectionType = KeystoreDatabaseConnection> + Sync,
+    >(
+        store: &CryptoKeystore,
+        restored: &CryptoKeystore,
+    ) {
+        let entities = store.find_all::<R>(EntityFindParams::default()).await.unwrap();
+        assert!(!entities.is_empty());
+        let restored_entities = restored.find_all::<R>(EntityFindParams::default()).await.unwrap();
+        assert_eq!(entities.len(), restored_entities.len());
+        for entity in &entities {
+            assert!(restored_entities.contains(entity));
+        }
+    }
+
     pub(crate) async fn can_list_entities_with_find_all<
         R: EntityRandomUpdateExt + Entity<ConnectionType = KeystoreDatabaseConnection> + Sync,
     >(
@@ -145,12 +160,76 @@ mod tests_impl {
     }
 }
 
+macro_rules! test_backup_for_entities {
+    ($test_name:ident, [$($entity:ident),+ $(,)?]) => {
+        #[apply(all_storage_types)]
+        async fn $test_name(context: KeystoreTestContext) {
+            let store = context.store();
+            let _ = env_logger::try_init();
+            $(
+                crate::tests_impl::can_save_entity::<$entity>(&store).await;
+            )+
+            store.commit_transaction().await.unwrap();
+
+            let key = DatabaseKey::generate();
+            let backup = store.export_backup(&key).await.unwrap();
+            // It's required by cleanup to have a running transaction before finishing the test
+            store.new_transaction().await.unwrap();
+
+            let restored = CryptoKeystore::open(ConnectionType::InMemory, &TEST_ENCRYPTION_KEY)
+                .await
+                .unwrap();
+            restored.import_backup(&backup, &key).await.unwrap();
+            $(
+                crate::tests_impl::can_find_restored_entities::<$entity>(&store, &restored).await;
+            )+
+            restored.wipe().await.unwrap();
+        }
+    };
+}
+
+macro_rules! test_idb_dump_for_entities {
+    ($test_name:ident, [$($entity:ident),+ $(,)?]) => {
+        #[apply(all_storage_types)]
+        async fn $test_name(context: KeystoreTestContext) {
+            let store = context.store();
+            let _ = env_logger::try_init();
+            $(
+                crate::tests_impl::can_save_entity::<$entity>(&store).await;
+            )+
+            store.commit_transaction().await.unwrap();
+
+            let key = DatabaseKey::generate();
+            let dump = store.export_idb_dump(&key).await.unwrap();
+            // It's required by cleanup to have a running transaction before finishing the test
+            store.new_transaction().await.unwrap();
+            $(
+                assert!(!dump.object_stores[<$entity>::COLLECTION_NAME].is_empty());
+            )+
+
+            // dumps are exchanged as JSON
+            let dump: IdbDump = serde_json::from_str(&serde_json::to_string(&dump).unwrap()).unwrap();
+            let restored = CryptoKeystore::open(ConnectionType::InMemory, &TEST_ENCRYPTION_KEY)
+                .await
+                .unwrap();
+            restored.import_idb_dump(&dump, &key).await.unwrap();
+            $(
+                crate::tests_impl::can_find_restored_entities::<$entity>(&store, &restored).await;
+            )+
+            restored.wipe().await.unwrap();
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use crate::common::*;
     use crate::utils::EntityRandomExt;
     use crate::utils::EntityRandomUpdateExt;
     use core_crypto_keystore::CryptoKeystoreError;
+    use core_crypto_keystore::backup::BACKUP_MAGIC;
+    use core_crypto_keystore::connection::{ConnectionType, FetchFromDatabase as _};
+    use core_crypto_keystore::idb_dump::{IDB_VERSION, IdbDump, IdbRecord};
     use wasm_bindgen_test::*;
 
     wasm_bindgen_test_configure!(run_in_browser);
@@ -166,6 +245,12 @@ mod tests {
     test_for_entity!(test_mls_psk_bundle, MlsPskBundle);
     test_for_entity!(test_mls_encryption_keypair, MlsEncryptionKeyPair);
     test_for_entity!(test_mls_epoch_encryption_keypair, MlsEpochEncryptionKeyPair);
+    test_for_entity!(test_mls_exporter_secret, MlsExporterSecret);
+    test_for_entity!(test_mls_conversation_reinit, MlsConversationReInit);
+    test_for_entity!(test_mls_keypackage_lifetime, MlsKeyPackageLifetime);
+    test_for_entity!(test_mls_external_psk, MlsExternalPsk);
+    test_for_entity!(test_mls_e2ei_status, MlsE2eiStatus);
+    test_for_entity!(test_mls_history_secret, MlsHistorySecret);
     test_for_entity!(test_mls_hpke_private_key, MlsHpkePrivateKey);
     test_for_entity!(test_e2ei_intermediate_cert, E2eiIntermediateCert);
     test_for_entity!(test_e2ei_crl, E2eiCrl);
@@ -179,6 +264,293 @@ mod tests {
         }
     }
 
+    test_backup_for_entities!(
+        test_backup_mls_entities,
+        [
+            PersistedMlsGroup,
+            PersistedMlsPendingGroup,
+            MlsPendingMessage,
+            MlsCredential,
+            MlsKeyPackage,
+            MlsSignatureKeyPair,
+            MlsPskBundle,
+            MlsEncryptionKeyPair,
+            MlsEpochEncryptionKeyPair,
+            MlsExporterSecret,
+            MlsConversationReInit,
+            MlsKeyPackageLifetime,
+            MlsExternalPsk,
+            MlsE2eiStatus,
+            MlsHistorySecret,
+            MlsHpkePrivateKey,
+            E2eiIntermediateCert,
+            E2eiCrl,
+            E2eiEnrollment,
+        ]
+    );
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "proteus-keystore")] {
+            test_backup_for_entities!(test_backup_proteus_entities, [ProteusIdentity, ProteusPrekey, ProteusSession]);
+        }
+    }
+
+    test_idb_dump_for_entities!(
+        test_idb_dump_mls_entities,
+        [
+            PersistedMlsGroup,
+            PersistedMlsPendingGroup,
+            MlsPendingMessage,
+            MlsCredential,
+            MlsKeyPackage,
+            MlsSignatureKeyPair,
+            MlsPskBundle,
+            MlsEncryptionKeyPair,
+            MlsEpochEncryptionKeyPair,
+            MlsExporterSecret,
+            MlsConversationReInit,
+            MlsKeyPackageLifetime,
+            MlsExternalPsk,
+            MlsE2eiStatus,
+            MlsHistorySecret,
+            MlsHpkePrivateKey,
+            E2eiIntermediateCert,
+            E2eiCrl,
+            E2eiEnrollment,
+        ]
+    );
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "proteus-keystore")] {
+            test_idb_dump_for_entities!(test_idb_dump_proteus_entities, [ProteusIdentity, ProteusPrekey, ProteusSession]);
+        }
+    }
+
+    /// Emulates what an exporter reading the object stores of IndexedDB would produce
+    #[apply(all_storage_types)]
+    pub async fn can_import_idb_records(context: KeystoreTestContext) {
+        use aes_gcm::{
+            Aes256Gcm, KeyInit as _, Nonce,
+            aead::{Aead as _, Payload},
+        };
+
+        let store = context.store();
+        let key = DatabaseKey::generate();
+        let group = PersistedMlsGroup::random();
+
+        #[derive(serde::Serialize)]
+        struct Aad<'a> {
+            type_name: &'a [u8],
+            id: &'a [u8],
+        }
+
+        let nonce: [u8; 12] = rand::random();
+        let aad = Aad {
+            type_name: PersistedMlsGroup::COLLECTION_NAME.as_bytes(),
+            id: &group.id,
+        };
+        let ciphertext = Aes256Gcm::new(key.as_ref().into())
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &group.state,
+                    aad: &serde_json::to_vec(&aad).unwrap(),
+                },
+            )
+            .unwrap();
+        let record = IdbRecord {
+            key: group.id.clone(),
+            value: serde_json::json!({
+                "id": group.id,
+                "state": [nonce.as_slice(), &ciphertext].concat(),
+                "parent_id": null,
+                "custom_configuration": null,
+                "last_self_update": null,
+            }),
+        };
+        let mut dump = IdbDump {
+            version: IDB_VERSION - 1,
+            object_stores: [
+                (PersistedMlsGroup::COLLECTION_NAME.to_string(), vec![record]),
+                ("e2ei_refresh_token".to_string(), vec![]),
+            ]
+            .into(),
+        };
+
+        store.rollback_transaction().await.unwrap();
+        let error = store.import_idb_dump(&dump, &key).await.unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::UnsupportedIdbVersion(_)));
+
+        dump.version = IDB_VERSION;
+        let error = store
+            .import_idb_dump(&dump, &DatabaseKey::generate())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::AesGcmError));
+
+        store.import_idb_dump(&dump, &key).await.unwrap();
+        let restored = store.find::<PersistedMlsGroup>(&group.id).await.unwrap().unwrap();
+        assert_eq!(restored, group);
+        // It's required by cleanup to have a running transaction before finishing the test
+        store.new_transaction().await.unwrap();
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn backup_is_authenticated(context: KeystoreTestContext) {
+        let store = context.store();
+        let key = DatabaseKey::generate();
+        assert!(matches!(
+            store.export_backup(&key).await.unwrap_err(),
+            CryptoKeystoreError::TransactionInProgress { .. }
+        ));
+
+        store.save(PersistedMlsGroup::random()).await.unwrap();
+        store.commit_transaction().await.unwrap();
+        let backup = store.export_backup(&key).await.unwrap();
+        store.new_transaction().await.unwrap();
+
+        let restored = CryptoKeystore::open(ConnectionType::InMemory, &TEST_ENCRYPTION_KEY)
+            .await
+            .unwrap();
+        let error = restored
+            .import_backup(&backup, &DatabaseKey::generate())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::BackupDecryptionFailed));
+
+        let mut tampered = backup.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let error = restored.import_backup(&tampered, &key).await.unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::BackupDecryptionFailed));
+
+        let mut newer = backup.clone();
+        newer[BACKUP_MAGIC.len() + 1] += 1;
+        let error = restored.import_backup(&newer, &key).await.unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::UnsupportedBackupVersion(2)));
+
+        let error = restored.import_backup(&backup[1..], &key).await.unwrap_err();
+        assert!(matches!(error, CryptoKeystoreError::InvalidBackup(_)));
+
+        let groups = restored
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .unwrap();
+        assert!(groups.is_empty());
+
+        restored.import_backup(&backup, &key).await.unwrap();
+        let groups = restored
+            .find_all::<PersistedMlsGroup>(Default::default())
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        restored.wipe().await.unwrap();
+    }
+
+    #[apply(all_storage_types)]
+    pub async fn check_integrity_repairs_dangling_references(context: KeystoreTestContext) {
+        use core_crypto_keystore::integrity::DanglingReference;
+
+        let store = context.store();
+        assert!(matches!(
+            store.check_integrity(false).await.unwrap_err(),
+            CryptoKeystoreError::TransactionInProgress { .. }
+        ));
+
+        let gone_id = b"deleted conversation".to_vec();
+        let used_keypair = MlsEncryptionKeyPair::random();
+        let mut group = PersistedMlsGroup::random();
+        group.state = [b"leaf".as_slice(), &used_keypair.pk, b"tree"].concat();
+        let mut child = PersistedMlsGroup::random();
+        child.parent_id = Some(gone_id.clone());
+        let unused_keypair = MlsEncryptionKeyPair::random();
+
+        let credential = MlsCredential::random();
+        let mut signature_keypair = MlsSignatureKeyPair::random();
+        signature_keypair.credential_id = credential.id.clone();
+        let mut openmls_signature_keypair = MlsSignatureKeyPair::random();
+        openmls_signature_keypair.credential_id = vec![];
+        let dangling_signature_keypair = MlsSignatureKeyPair::random();
+
+        let mut pending_message = MlsPendingMessage::random();
+        pending_message.foreign_id = group.id.clone();
+        let dangling_pending_messages =
+            [MlsPendingMessage::random(), MlsPendingMessage::random()].map(|mut message| {
+                message.foreign_id = gone_id.clone();
+                message
+            });
+        let exporter_secret = MlsExporterSecret::new(&group.id, 1, "label", vec![1; 32]);
+        let dangling_exporter_secret = MlsExporterSecret::new(&gone_id, 1, "label", vec![1; 32]);
+        // history secrets are also archived for conversations which are not known locally
+        let mut unknown_history_secret = MlsHistorySecret::random();
+        unknown_history_secret.conversation_id = gone_id.clone();
+
+        store.save(group.clone()).await.unwrap();
+        store.save(child.clone()).await.unwrap();
+        store.save(used_keypair.clone()).await.unwrap();
+        store.save(unused_keypair.clone()).await.unwrap();
+        store.save(credential).await.unwrap();
+        store.save(signature_keypair).await.unwrap();
+        store.save(openmls_signature_keypair).await.unwrap();
+        store.save(dangling_signature_keypair.clone()).await.unwrap();
+        store.save(pending_message).await.unwrap();
+        for message in dangling_pending_messages {
+            store.save(message).await.unwrap();
+        }
+        store
+            .save(MlsBufferedCommit::new(gone_id.clone(), vec![1; 32], 1))
+            .await
+            .unwrap();
+        store.save(exporter_secret).await.unwrap();
+        store.save(dangling_exporter_secret.clone()).await.unwrap();
+        store.save(unknown_history_secret).await.unwrap();
+        store.commit_transaction().await.unwrap();
+
+        let dangling = store.check_integrity(false).await.unwrap();
+        let expected = [
+            DanglingReference::EncryptionKeyPair {
+                pk: unused_keypair.pk.clone(),
+            },
+            DanglingReference::SignatureKeyPair {
+                pk: dangling_signature_keypair.pk.clone(),
+                credential_id: dangling_signature_keypair.credential_id.clone(),
+            },
+            DanglingReference::PendingMessages {
+                conversation_id: gone_id.clone(),
+                count: 2,
+            },
+            DanglingReference::BufferedCommit {
+                conversation_id: gone_id.clone(),
+            },
+            DanglingReference::ExporterSecret {
+                id: dangling_exporter_secret.id.clone(),
+                conversation_id: gone_id.clone(),
+            },
+            DanglingReference::ParentConversation {
+                id: child.id.clone(),
+                parent_id: gone_id.clone(),
+            },
+        ];
+        assert_eq!(dangling, expected);
+        // checking alone does not remove anything
+        assert_eq!(store.check_integrity(false).await.unwrap(), expected);
+
+        assert_eq!(store.check_integrity(true).await.unwrap(), expected);
+        // encryption keypairs are only reported
+        assert_eq!(store.check_integrity(false).await.unwrap(), expected[..1]);
+
+        assert_eq!(store.count::<MlsEncryptionKeyPair>().await.unwrap(), 2);
+        assert_eq!(store.count::<MlsSignatureKeyPair>().await.unwrap(), 2);
+        assert_eq!(store.count::<MlsPendingMessage>().await.unwrap(), 1);
+        assert_eq!(store.count::<MlsBufferedCommit>().await.unwrap(), 0);
+        assert_eq!(store.count::<MlsExporterSecret>().await.unwrap(), 1);
+        assert_eq!(store.count::<MlsHistorySecret>().await.unwrap(), 1);
+        let child = store.find::<PersistedMlsGroup>(&child.id).await.unwrap().unwrap();
+        assert_eq!(child.parent_id, None);
+
+        // It's required by cleanup to have a running transaction before finishing the test
+        store.new_transaction().await.unwrap();
+    }
+
     // This test cannot pass on WASM: if you grep through the codebase, you'll note that
     // `CoreCryptoKeystore::AlreadyExists` is only produced in one place: in the entity derive macro,
     // in the non-wasm branch of the derive implementation.
@@ -212,8 +584,9 @@ mod tests {
 #[cfg(test)]
 pub mod utils {
     use core_crypto_keystore::entities::{
-        E2eiEnrollment, MlsCredential, MlsEncryptionKeyPair, MlsEpochEncryptionKeyPair, MlsHpkePrivateKey,
-        MlsKeyPackage, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
+        E2eiEnrollment, MlsConversationReInit, MlsCredential, MlsE2eiStatus, MlsEncryptionKeyPair,
+        MlsEpochEncryptionKeyPair, MlsExporterSecret, MlsExternalPsk, MlsHistorySecret, MlsHpkePrivateKey,
+        MlsKeyPackage, MlsKeyPackageLifetime, MlsPendingMessage, MlsPskBundle, MlsSignatureKeyPair, PersistedMlsGroup,
         PersistedMlsPendingGroup, ProteusSession,
     };
     use rand::Rng as _;
@@ -322,17 +695,23 @@ pub mod utils {
                 };
             }
 
-    impl_entity_random_update_ext!(MlsKeyPackage, blob_fields=[keypackage,], additional_fields=[(keypackage_ref: uuid::Uuid::new_v4().hyphenated().to_string().into()),]);
+    impl_entity_random_update_ext!(MlsKeyPackage, blob_fields=[keypackage,], additional_fields=[(keypackage_ref: uuid::Uuid::new_v4().hyphenated().to_string().into()),(ciphersuite: Some(1u16.to_be_bytes().to_vec())),(credential_type: Some(vec![1])),(expires_at: Some(1u64.to_be_bytes().to_vec())),(uploaded_at: None),(last_resort: None),]);
     impl_entity_random_update_ext!(MlsCredential, blob_fields=[credential,], additional_fields=[(id: uuid::Uuid::new_v4().hyphenated().to_string().into()),(created_at: 0; auto-generated:true),]);
     impl_entity_random_update_ext!(MlsSignatureKeyPair, blob_fields=[pk,keypair,credential_id,], additional_fields=[(signature_scheme: rand::random()),]);
     impl_entity_random_update_ext!(MlsHpkePrivateKey, blob_fields=[pk id_like:true,sk,]);
     impl_entity_random_update_ext!(MlsEncryptionKeyPair, blob_fields=[pk id_like:true,sk,]);
     impl_entity_random_update_ext!(MlsPskBundle, blob_fields=[psk,psk_id id_like:true,]);
-    impl_entity_random_update_ext!(PersistedMlsGroup, id_field=id, blob_fields=[state,], additional_fields=[(parent_id: None),]);
+    impl_entity_random_update_ext!(PersistedMlsGroup, id_field=id, blob_fields=[state,], additional_fields=[(parent_id: None),(custom_configuration: None),(last_self_update: None),]);
     impl_entity_random_update_ext!(PersistedMlsPendingGroup, id_field=id, blob_fields=[state,custom_configuration,], additional_fields=[(parent_id: None),]);
-    impl_entity_random_update_ext!(MlsPendingMessage, id_field = foreign_id, blob_fields = [message,]);
+    impl_entity_random_update_ext!(MlsPendingMessage, id_field = foreign_id, blob_fields = [message,], additional_fields = [(received_at: Some(1u64.to_be_bytes().to_vec())),]);
     impl_entity_random_update_ext!(E2eiEnrollment, id_field = id, blob_fields = [content,]);
     impl_entity_random_update_ext!(MlsEpochEncryptionKeyPair, id_field = id, blob_fields = [keypairs,]);
+    impl_entity_random_update_ext!(MlsConversationReInit, id_field = id, blob_fields = [new_id,], additional_fields = [(ciphersuite: 1u16.to_be_bytes().to_vec()),]);
+    impl_entity_random_update_ext!(MlsKeyPackageLifetime, id_field = id, blob_fields = [lifetime,]);
+    impl_entity_random_update_ext!(MlsExternalPsk, id_field = id, blob_fields = [psk_bundle_id,]);
+    impl_entity_random_update_ext!(MlsE2eiStatus, id_field = id, blob_fields = [status,]);
+    impl_entity_random_update_ext!(MlsHistorySecret, id_field = id, blob_fields = [conversation_id id_like:true, secret, welcome,], additional_fields = [(epoch: 1u64.to_be_bytes().to_vec()),]);
+    impl_entity_random_update_ext!(MlsExporterSecret, id_field = id, blob_fields = [conversation_id id_like:true, secret,], additional_fields = [(epoch: 1u64.to_be_bytes().to_vec()),(label: b"label".to_vec()),]);
 
     impl EntityRandomExt for core_crypto_keystore::entities::E2eiIntermediateCert {
         fn random() -> Self {